@@ -0,0 +1,864 @@
+//! The Unicode bidirectional algorithm: the embedding levels of the characters in a paragraph,
+//! and the visual order of the runs in a line.
+//! ref. https://www.unicode.org/reports/tr9/
+//! ref. https://www.w3.org/TR/css-writing-modes-3/#text-direction
+
+/// The bidirectional character types.
+/// ref. https://www.unicode.org/reports/tr9/#Table_Bidirectional_Character_Types
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BidiClass {
+    LeftToRight,           // L
+    RightToLeft,           // R
+    ArabicLetter,          // AL
+    EuropeanNumber,        // EN
+    EuropeanSeparator,     // ES
+    EuropeanTerminator,    // ET
+    ArabicNumber,          // AN
+    CommonSeparator,       // CS
+    NonspacingMark,        // NSM
+    BoundaryNeutral,       // BN
+    ParagraphSeparator,    // B
+    SegmentSeparator,      // S
+    WhiteSpace,            // WS
+    OtherNeutral,          // ON
+    LeftToRightEmbedding,  // LRE
+    LeftToRightOverride,   // LRO
+    RightToLeftEmbedding,  // RLE
+    RightToLeftOverride,   // RLO
+    PopDirectionalFormat,  // PDF
+    LeftToRightIsolate,    // LRI
+    RightToLeftIsolate,    // RLI
+    FirstStrongIsolate,    // FSI
+    PopDirectionalIsolate, // PDI
+}
+
+use self::BidiClass::*;
+
+pub const LRE: char = '\u{202A}';
+pub const RLE: char = '\u{202B}';
+pub const PDF: char = '\u{202C}';
+pub const LRO: char = '\u{202D}';
+pub const RLO: char = '\u{202E}';
+pub const LRI: char = '\u{2066}';
+pub const RLI: char = '\u{2067}';
+pub const FSI: char = '\u{2068}';
+pub const PDI: char = '\u{2069}';
+
+// The deepest embedding level.
+const MAX_DEPTH: u8 = 125;
+
+/// The bidirectional character type of `c`.
+pub fn bidi_class(c: char) -> BidiClass {
+    match c {
+        LRE => LeftToRightEmbedding,
+        RLE => RightToLeftEmbedding,
+        PDF => PopDirectionalFormat,
+        LRO => LeftToRightOverride,
+        RLO => RightToLeftOverride,
+        LRI => LeftToRightIsolate,
+        RLI => RightToLeftIsolate,
+        FSI => FirstStrongIsolate,
+        PDI => PopDirectionalIsolate,
+        '\u{200E}' => LeftToRight,
+        '\u{200F}' => RightToLeft,
+        '\u{061C}' => ArabicLetter,
+        '\n' | '\r' | '\u{1C}'...'\u{1E}' | '\u{85}' | '\u{2029}' => ParagraphSeparator,
+        '\t' | '\u{0B}' | '\u{1F}' => SegmentSeparator,
+        '\u{0C}' | ' ' | '\u{1680}' | '\u{2000}'...'\u{200A}' | '\u{2028}' | '\u{205F}' => {
+            WhiteSpace
+        }
+        '\u{3000}' => WhiteSpace,
+        '\u{00}'...'\u{08}' | '\u{0E}'...'\u{1B}' | '\u{7F}'...'\u{84}' | '\u{86}'...'\u{9F}' => {
+            BoundaryNeutral
+        }
+        '\u{AD}' | '\u{200B}'...'\u{200D}' | '\u{2060}'...'\u{2065}' | '\u{FEFF}' => {
+            BoundaryNeutral
+        }
+        '0'...'9' | '\u{B2}' | '\u{B3}' | '\u{B9}' | '\u{06F0}'...'\u{06F9}' => EuropeanNumber,
+        '\u{2070}'...'\u{2079}' | '\u{2080}'...'\u{2089}' | '\u{FF10}'...'\u{FF19}' => {
+            EuropeanNumber
+        }
+        '+' | '-' | '\u{207A}' | '\u{207B}' | '\u{FB29}' | '\u{FE62}' | '\u{FE63}' | '\u{FF0B}' => {
+            EuropeanSeparator
+        }
+        '\u{FF0D}' => EuropeanSeparator,
+        '#' | '$' | '%' | '\u{A2}'...'\u{A5}' | '\u{B0}' | '\u{B1}' | '\u{066A}' => {
+            EuropeanTerminator
+        }
+        '\u{2030}'...'\u{2034}' | '\u{20A0}'...'\u{20CF}' => EuropeanTerminator,
+        '\u{0600}'...'\u{0605}' | '\u{0660}'...'\u{0669}' | '\u{066B}' | '\u{066C}' => ArabicNumber,
+        ',' | '.' | '/' | ':' | '\u{A0}' | '\u{060C}' | '\u{202F}' | '\u{2044}' => CommonSeparator,
+        '\u{FE50}' | '\u{FE52}' | '\u{FE55}' | '\u{FF0C}' | '\u{FF0E}' | '\u{FF0F}'
+        | '\u{FF1A}' => CommonSeparator,
+        '\u{0300}'...'\u{036F}' | '\u{0483}'...'\u{0489}' | '\u{0591}'...'\u{05BD}' => {
+            NonspacingMark
+        }
+        '\u{05BF}' | '\u{05C1}' | '\u{05C2}' | '\u{05C4}' | '\u{05C5}' | '\u{05C7}' => {
+            NonspacingMark
+        }
+        '\u{0610}'...'\u{061A}'
+        | '\u{064B}'...'\u{065F}'
+        | '\u{0670}'
+        | '\u{06D6}'...'\u{06DC}' => NonspacingMark,
+        '\u{06DF}'...'\u{06E4}' | '\u{06E7}' | '\u{06E8}' | '\u{06EA}'...'\u{06ED}' => {
+            NonspacingMark
+        }
+        '\u{20D0}'...'\u{20FF}' | '\u{FE00}'...'\u{FE0F}' | '\u{FE20}'...'\u{FE2F}' => {
+            NonspacingMark
+        }
+        '\u{0590}'...'\u{05FF}' | '\u{07C0}'...'\u{085F}' | '\u{FB1D}'...'\u{FB4F}' => RightToLeft,
+        '\u{10800}'...'\u{10FFF}' | '\u{1E800}'...'\u{1EDFF}' => RightToLeft,
+        '\u{0600}'...'\u{07BF}' | '\u{0860}'...'\u{08FF}' | '\u{FB50}'...'\u{FDFF}' => ArabicLetter,
+        '\u{FE70}'...'\u{FEFE}' | '\u{1EE00}'...'\u{1EEFF}' => ArabicLetter,
+        '!' | '"' | '&' | '\'' | '(' | ')' | '*' | ';' | '<' | '=' | '>' | '?' | '@' => {
+            OtherNeutral
+        }
+        '[' | '\\' | ']' | '^' | '_' | '`' | '{' | '|' | '}' | '~' => OtherNeutral,
+        '\u{A1}' | '\u{A6}'...'\u{A9}' | '\u{AB}' | '\u{AC}' | '\u{AE}' | '\u{AF}' | '\u{B4}' => {
+            OtherNeutral
+        }
+        '\u{B6}'...'\u{B8}' | '\u{BB}'...'\u{BF}' | '\u{D7}' | '\u{F7}' => OtherNeutral,
+        '\u{2010}'...'\u{2027}' | '\u{2035}'...'\u{2043}' | '\u{2045}'...'\u{205E}' => OtherNeutral,
+        '\u{2190}'...'\u{2BFF}' | '\u{3001}'...'\u{3004}' | '\u{3008}'...'\u{3020}' => OtherNeutral,
+        '\u{FE59}'...'\u{FE61}' | '\u{FF08}' | '\u{FF09}' | '\u{FF3B}' | '\u{FF3D}' => OtherNeutral,
+        '\u{FF5B}' | '\u{FF5D}' | '\u{FFFC}' | '\u{FFFD}' => OtherNeutral,
+        _ => LeftToRight,
+    }
+}
+
+/// The character whose glyph mirrors that of `c`, e.g. `)` for `(`. Characters in right-to-left
+/// runs are displayed with the mirrored glyphs, and the paired brackets are found with it.
+/// ref. https://www.unicode.org/reports/tr9/#L4
+pub fn mirror(c: char) -> Option<char> {
+    const PAIRS: &[(char, char)] = &[
+        ('(', ')'),
+        ('<', '>'),
+        ('[', ']'),
+        ('{', '}'),
+        ('\u{AB}', '\u{BB}'),
+        ('\u{2039}', '\u{203A}'),
+        ('\u{2045}', '\u{2046}'),
+        ('\u{207D}', '\u{207E}'),
+        ('\u{208D}', '\u{208E}'),
+        ('\u{2264}', '\u{2265}'),
+        ('\u{2329}', '\u{232A}'),
+        ('\u{3008}', '\u{3009}'),
+        ('\u{300A}', '\u{300B}'),
+        ('\u{300C}', '\u{300D}'),
+        ('\u{300E}', '\u{300F}'),
+        ('\u{3010}', '\u{3011}'),
+        ('\u{3014}', '\u{3015}'),
+        ('\u{FF08}', '\u{FF09}'),
+        ('\u{FF1C}', '\u{FF1E}'),
+        ('\u{FF3B}', '\u{FF3D}'),
+        ('\u{FF5B}', '\u{FF5D}'),
+    ];
+    PAIRS
+        .iter()
+        .filter_map(|&(open, close)| {
+            if c == open {
+                Some(close)
+            } else if c == close {
+                Some(open)
+            } else {
+                None
+            }
+        })
+        .next()
+}
+
+/// Whether `c` is an opening or a closing paired bracket. None if it's neither.
+/// ref. https://www.unicode.org/Public/UCD/latest/ucd/BidiBrackets.txt
+fn bracket_type(c: char) -> Option<bool> {
+    match c {
+        '(' | '[' | '{' | '\u{2045}' | '\u{207D}' | '\u{208D}' | '\u{2329}' => Some(true),
+        '\u{3008}' | '\u{300A}' | '\u{300C}' | '\u{300E}' | '\u{3010}' | '\u{3014}' => Some(true),
+        '\u{FF08}' | '\u{FF3B}' | '\u{FF5B}' => Some(true),
+        ')' | ']' | '}' | '\u{2046}' | '\u{207E}' | '\u{208E}' | '\u{232A}' => Some(false),
+        '\u{3009}' | '\u{300B}' | '\u{300D}' | '\u{300F}' | '\u{3011}' | '\u{3015}' => Some(false),
+        '\u{FF09}' | '\u{FF3D}' | '\u{FF5D}' => Some(false),
+        _ => None,
+    }
+}
+
+fn is_isolate_initiator(class: BidiClass) -> bool {
+    class == LeftToRightIsolate || class == RightToLeftIsolate || class == FirstStrongIsolate
+}
+
+/// The indices of the PDIs matching the isolate initiators by the rule BD9.
+fn matching_pdis(classes: &[BidiClass]) -> Vec<Option<usize>> {
+    let mut matching_pdi = vec![None; classes.len()];
+    let mut open_isolates = vec![];
+    for (i, &class) in classes.iter().enumerate() {
+        match class {
+            _ if is_isolate_initiator(class) => open_isolates.push(i),
+            PopDirectionalIsolate => {
+                if let Some(initiator) = open_isolates.pop() {
+                    matching_pdi[initiator] = Some(i);
+                }
+            }
+            ParagraphSeparator => open_isolates.clear(),
+            _ => {}
+        }
+    }
+    matching_pdi
+}
+
+// The characters removed by the rule X9.
+fn is_removed(class: BidiClass) -> bool {
+    match class {
+        LeftToRightEmbedding | RightToLeftEmbedding | LeftToRightOverride | RightToLeftOverride
+        | PopDirectionalFormat | BoundaryNeutral => true,
+        _ => false,
+    }
+}
+
+// The neutral and isolate formatting characters for the rules N1 and N2.
+fn is_neutral(class: BidiClass) -> bool {
+    match class {
+        ParagraphSeparator
+        | SegmentSeparator
+        | WhiteSpace
+        | OtherNeutral
+        | LeftToRightIsolate
+        | RightToLeftIsolate
+        | FirstStrongIsolate
+        | PopDirectionalIsolate => true,
+        _ => false,
+    }
+}
+
+/// The direction of a strong type, with numbers counting as right-to-left as in the rules N0
+/// and N1.
+fn strong_direction(class: BidiClass) -> Option<BidiClass> {
+    match class {
+        LeftToRight => Some(LeftToRight),
+        RightToLeft | ArabicLetter | EuropeanNumber | ArabicNumber => Some(RightToLeft),
+        _ => None,
+    }
+}
+
+fn direction_of_level(level: u8) -> BidiClass {
+    if level % 2 == 0 {
+        LeftToRight
+    } else {
+        RightToLeft
+    }
+}
+
+/// Whether `text` has characters that make a left-to-right paragraph other than all
+/// left-to-right.
+pub fn has_right_to_left(text: &str) -> bool {
+    text.chars().any(|c| match bidi_class(c) {
+        RightToLeft | ArabicLetter | ArabicNumber | RightToLeftEmbedding | RightToLeftOverride
+        | RightToLeftIsolate | FirstStrongIsolate => true,
+        _ => false,
+    })
+}
+
+/// The level of a paragraph whose first strong character is found by the rules P2 and P3.
+/// Characters between isolate initiators and their matching PDIs are skipped. None if there's
+/// no strong character.
+pub fn first_strong_level(text: &str) -> Option<u8> {
+    let classes: Vec<BidiClass> = text.chars().map(bidi_class).collect();
+    first_strong_class_level(&classes)
+}
+
+/// The level of the first strong class in `classes` like `first_strong_level`.
+fn first_strong_class_level(classes: &[BidiClass]) -> Option<u8> {
+    let mut isolates = 0;
+    for &class in classes {
+        match class {
+            LeftToRightIsolate | RightToLeftIsolate | FirstStrongIsolate => isolates += 1,
+            PopDirectionalIsolate if isolates > 0 => isolates -= 1,
+            LeftToRight if isolates == 0 => return Some(0),
+            RightToLeft | ArabicLetter if isolates == 0 => return Some(1),
+            ParagraphSeparator => break,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The embedding levels of the bytes of the paragraph `text` whose level is `base_level`.
+/// The levels of the whitespace at the end of lines are reset by `reset_whitespace_levels`.
+/// ref. https://www.unicode.org/reports/tr9/#Basic_Display_Algorithm
+pub fn resolve_levels(text: &str, base_level: u8) -> Vec<u8> {
+    let chars: Vec<char> = text.chars().collect();
+    let original: Vec<BidiClass> = chars.iter().map(|&c| bidi_class(c)).collect();
+    let (mut levels, mut classes, matching_pdi) = explicit_levels(&chars, &original, base_level);
+
+    for sequence in isolating_run_sequences(&original, &levels, &matching_pdi, base_level) {
+        resolve_sequence(&sequence, &chars, &original, &mut classes, &levels);
+    }
+
+    // I1, I2
+    for i in 0..chars.len() {
+        if is_removed(original[i]) {
+            continue;
+        }
+        levels[i] = match (levels[i] % 2, classes[i]) {
+            (0, RightToLeft) => levels[i] + 1,
+            (0, ArabicNumber) | (0, EuropeanNumber) => levels[i] + 2,
+            (1, LeftToRight) | (1, ArabicNumber) | (1, EuropeanNumber) => levels[i] + 1,
+            _ => levels[i],
+        };
+    }
+
+    // The characters removed by X9 take the level of the preceding character.
+    let mut prev_level = base_level;
+    for i in 0..chars.len() {
+        if is_removed(original[i]) {
+            levels[i] = prev_level;
+        }
+        prev_level = levels[i];
+    }
+
+    chars
+        .iter()
+        .zip(levels)
+        .flat_map(|(c, level)| vec![level; c.len_utf8()])
+        .collect()
+}
+
+struct DirectionalStatus {
+    level: u8,
+    override_class: Option<BidiClass>,
+    isolate: bool,
+}
+
+/// The rules X1 to X8: the explicit embedding levels, the types changed by the overrides, and
+/// the index of the PDI matching each isolate initiator.
+fn explicit_levels(
+    chars: &[char],
+    original: &[BidiClass],
+    base_level: u8,
+) -> (Vec<u8>, Vec<BidiClass>, Vec<Option<usize>>) {
+    let mut levels = vec![base_level; chars.len()];
+    let mut classes = original.to_vec();
+    let matching_pdi = matching_pdis(original);
+
+    let mut stack = vec![DirectionalStatus {
+        level: base_level,
+        override_class: None,
+        isolate: false,
+    }];
+    let mut overflow_isolates = 0;
+    let mut overflow_embeddings = 0;
+    let mut valid_isolates = 0;
+
+    for i in 0..chars.len() {
+        let class = original[i];
+        let (level, override_class) = {
+            let last = stack.last().unwrap();
+            (last.level, last.override_class)
+        };
+        let next_level = |rtl: bool| match (rtl, level % 2) {
+            (true, 0) | (false, 1) => level + 1,
+            _ => level + 2,
+        };
+        levels[i] = level;
+
+        match class {
+            RightToLeftEmbedding | LeftToRightEmbedding | RightToLeftOverride
+            | LeftToRightOverride => {
+                let rtl = class == RightToLeftEmbedding || class == RightToLeftOverride;
+                let new_level = next_level(rtl);
+                if new_level <= MAX_DEPTH && overflow_isolates == 0 && overflow_embeddings == 0 {
+                    stack.push(DirectionalStatus {
+                        level: new_level,
+                        override_class: match class {
+                            RightToLeftOverride => Some(RightToLeft),
+                            LeftToRightOverride => Some(LeftToRight),
+                            _ => None,
+                        },
+                        isolate: false,
+                    });
+                } else if overflow_isolates == 0 {
+                    overflow_embeddings += 1;
+                }
+            }
+            RightToLeftIsolate | LeftToRightIsolate | FirstStrongIsolate => {
+                if let Some(override_class) = override_class {
+                    classes[i] = override_class;
+                }
+                let rtl = match class {
+                    RightToLeftIsolate => true,
+                    LeftToRightIsolate => false,
+                    // The direction of the isolate comes from the text up to its matching PDI.
+                    _ => {
+                        let end = matching_pdi[i].unwrap_or(chars.len());
+                        first_strong_class_level(&original[i + 1..end]) == Some(1)
+                    }
+                };
+                let new_level = next_level(rtl);
+                if new_level <= MAX_DEPTH && overflow_isolates == 0 && overflow_embeddings == 0 {
+                    valid_isolates += 1;
+                    stack.push(DirectionalStatus {
+                        level: new_level,
+                        override_class: None,
+                        isolate: true,
+                    });
+                } else {
+                    overflow_isolates += 1;
+                }
+            }
+            PopDirectionalIsolate => {
+                if overflow_isolates > 0 {
+                    overflow_isolates -= 1;
+                } else if valid_isolates > 0 {
+                    overflow_embeddings = 0;
+                    while !stack.last().unwrap().isolate {
+                        stack.pop();
+                    }
+                    stack.pop();
+                    valid_isolates -= 1;
+                }
+                let last = stack.last().unwrap();
+                levels[i] = last.level;
+                if let Some(override_class) = last.override_class {
+                    classes[i] = override_class;
+                }
+            }
+            PopDirectionalFormat => {
+                if overflow_isolates == 0 {
+                    if overflow_embeddings > 0 {
+                        overflow_embeddings -= 1;
+                    } else if !stack.last().unwrap().isolate && stack.len() >= 2 {
+                        stack.pop();
+                    }
+                }
+            }
+            ParagraphSeparator => levels[i] = base_level,
+            BoundaryNeutral => {}
+            _ => {
+                if let Some(override_class) = override_class {
+                    classes[i] = override_class;
+                }
+            }
+        }
+    }
+    (levels, classes, matching_pdi)
+}
+
+/// A sequence of level runs connected by isolates, whose weak and neutral types are resolved
+/// together, and the types at its start and end.
+/// ref. https://www.unicode.org/reports/tr9/#BD13
+struct IsolatingRunSequence {
+    indices: Vec<usize>,
+    sos: BidiClass,
+    eos: BidiClass,
+}
+
+/// The rule X10.
+fn isolating_run_sequences(
+    original: &[BidiClass],
+    levels: &[u8],
+    matching_pdi: &[Option<usize>],
+    base_level: u8,
+) -> Vec<IsolatingRunSequence> {
+    // The level runs, without the characters removed by X9.
+    let mut runs: Vec<Vec<usize>> = vec![];
+    let mut run_level = None;
+    for i in (0..original.len()).filter(|&i| !is_removed(original[i])) {
+        if run_level == Some(levels[i]) {
+            runs.last_mut().unwrap().push(i);
+        } else {
+            runs.push(vec![i]);
+            run_level = Some(levels[i]);
+        }
+    }
+
+    let matched_pdis: Vec<usize> = matching_pdi.iter().filter_map(|&pdi| pdi).collect();
+    let mut sequences = vec![];
+    for run in &runs {
+        if matched_pdis.contains(&run[0]) {
+            continue;
+        }
+        let mut indices = run.clone();
+        loop {
+            let last = *indices.last().unwrap();
+            let next = match matching_pdi[last] {
+                Some(pdi) if is_isolate_initiator(original[last]) => {
+                    runs.iter().find(|run| run[0] == pdi)
+                }
+                _ => None,
+            };
+            match next {
+                Some(next) => indices.extend(next),
+                None => break,
+            }
+        }
+
+        let (first, last) = (indices[0], *indices.last().unwrap());
+        let level = levels[first];
+        let prev_level = (0..first)
+            .rev()
+            .find(|&i| !is_removed(original[i]))
+            .map_or(base_level, |i| levels[i]);
+        let next_level = if is_isolate_initiator(original[last]) {
+            base_level
+        } else {
+            (last + 1..original.len())
+                .find(|&i| !is_removed(original[i]))
+                .map_or(base_level, |i| levels[i])
+        };
+        sequences.push(IsolatingRunSequence {
+            indices: indices,
+            sos: direction_of_level(::std::cmp::max(level, prev_level)),
+            eos: direction_of_level(::std::cmp::max(levels[last], next_level)),
+        });
+    }
+    sequences
+}
+
+/// The rules W1 to W7, N0 to N2 for an isolating run sequence.
+fn resolve_sequence(
+    sequence: &IsolatingRunSequence,
+    chars: &[char],
+    original: &[BidiClass],
+    classes: &mut [BidiClass],
+    levels: &[u8],
+) {
+    let indices = &sequence.indices;
+    let len = indices.len();
+    let mut types: Vec<BidiClass> = indices.iter().map(|&i| classes[i]).collect();
+    let embedding_direction = direction_of_level(levels[indices[0]]);
+
+    // W1
+    for k in 0..len {
+        if types[k] == NonspacingMark {
+            types[k] = if k == 0 {
+                sequence.sos
+            } else if is_isolate_initiator(types[k - 1]) || types[k - 1] == PopDirectionalIsolate {
+                OtherNeutral
+            } else {
+                types[k - 1]
+            };
+        }
+    }
+
+    // W2, W3
+    let mut last_strong = sequence.sos;
+    for k in 0..len {
+        match types[k] {
+            LeftToRight | RightToLeft | ArabicLetter => last_strong = types[k],
+            EuropeanNumber if last_strong == ArabicLetter => types[k] = ArabicNumber,
+            _ => {}
+        }
+    }
+    for t in types.iter_mut().filter(|t| **t == ArabicLetter) {
+        *t = RightToLeft;
+    }
+
+    // W4
+    for k in 1..len.saturating_sub(1) {
+        match (types[k - 1], types[k], types[k + 1]) {
+            (EuropeanNumber, EuropeanSeparator, EuropeanNumber)
+            | (EuropeanNumber, CommonSeparator, EuropeanNumber) => types[k] = EuropeanNumber,
+            (ArabicNumber, CommonSeparator, ArabicNumber) => types[k] = ArabicNumber,
+            _ => {}
+        }
+    }
+
+    // W5
+    let mut k = 0;
+    while k < len {
+        if types[k] != EuropeanTerminator {
+            k += 1;
+            continue;
+        }
+        let end = (k..len)
+            .find(|&j| types[j] != EuropeanTerminator)
+            .unwrap_or(len);
+        let adjacent_to_number = (k > 0 && types[k - 1] == EuropeanNumber)
+            || (end < len && types[end] == EuropeanNumber);
+        if adjacent_to_number {
+            for t in &mut types[k..end] {
+                *t = EuropeanNumber;
+            }
+        }
+        k = end;
+    }
+
+    // W6
+    for t in types.iter_mut() {
+        if *t == EuropeanSeparator || *t == EuropeanTerminator || *t == CommonSeparator {
+            *t = OtherNeutral;
+        }
+    }
+
+    // W7
+    let mut last_strong = sequence.sos;
+    for t in types.iter_mut() {
+        match *t {
+            LeftToRight | RightToLeft => last_strong = *t,
+            EuropeanNumber if last_strong == LeftToRight => *t = LeftToRight,
+            _ => {}
+        }
+    }
+
+    // N0: Paired brackets take the direction of the text they enclose.
+    for (open, close) in bracket_pairs(indices, chars, original, &types) {
+        let inside = |direction| {
+            types[open + 1..close]
+                .iter()
+                .any(|&t| strong_direction(t) == Some(direction))
+        };
+        let direction = if inside(embedding_direction) {
+            Some(embedding_direction)
+        } else {
+            let opposite = if embedding_direction == LeftToRight {
+                RightToLeft
+            } else {
+                LeftToRight
+            };
+            if inside(opposite) {
+                let context = types[..open]
+                    .iter()
+                    .rev()
+                    .filter_map(|&t| strong_direction(t))
+                    .next()
+                    .unwrap_or(sequence.sos);
+                Some(if context == opposite {
+                    opposite
+                } else {
+                    embedding_direction
+                })
+            } else {
+                None
+            }
+        };
+        if let Some(direction) = direction {
+            for &k in &[open, close] {
+                types[k] = direction;
+                // The marks following a bracket take its direction.
+                for j in k + 1..len {
+                    if original[indices[j]] != NonspacingMark {
+                        break;
+                    }
+                    types[j] = direction;
+                }
+            }
+        }
+    }
+
+    // N1, N2
+    let mut k = 0;
+    while k < len {
+        if !is_neutral(types[k]) {
+            k += 1;
+            continue;
+        }
+        let end = (k..len).find(|&j| !is_neutral(types[j])).unwrap_or(len);
+        let before = if k == 0 {
+            sequence.sos
+        } else {
+            strong_direction(types[k - 1]).unwrap_or(embedding_direction)
+        };
+        let after = if end == len {
+            sequence.eos
+        } else {
+            strong_direction(types[end]).unwrap_or(embedding_direction)
+        };
+        let direction = if before == after {
+            before
+        } else {
+            embedding_direction
+        };
+        for t in &mut types[k..end] {
+            *t = direction;
+        }
+        k = end;
+    }
+
+    for (k, &i) in indices.iter().enumerate() {
+        classes[i] = types[k];
+    }
+}
+
+/// The pairs of brackets in an isolating run sequence, as positions in the sequence sorted by
+/// the opening brackets.
+/// ref. https://www.unicode.org/reports/tr9/#BD16
+fn bracket_pairs(
+    indices: &[usize],
+    chars: &[char],
+    original: &[BidiClass],
+    types: &[BidiClass],
+) -> Vec<(usize, usize)> {
+    let mut stack: Vec<(char, usize)> = vec![];
+    let mut pairs = vec![];
+    for (k, &i) in indices.iter().enumerate() {
+        if types[k] != OtherNeutral || original[i] != OtherNeutral {
+            continue;
+        }
+        match bracket_type(chars[i]) {
+            Some(true) => {
+                if stack.len() == 63 {
+                    break;
+                }
+                stack.push((mirror(chars[i]).unwrap(), k));
+            }
+            Some(false) => {
+                if let Some(depth) = stack.iter().rposition(|&(close, _)| close == chars[i]) {
+                    pairs.push((stack[depth].1, k));
+                    stack.truncate(depth);
+                }
+            }
+            None => {}
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+/// The rule L1: the whitespace and isolate formatting characters at the end of the line
+/// `text`, and before its segment separators, get the paragraph level `base_level`.
+/// `levels` are the levels of the bytes of `text`.
+pub fn reset_whitespace_levels(text: &str, levels: &mut [u8], base_level: u8) {
+    let mut trailing: Vec<usize> = vec![];
+    for (offset, c) in text.char_indices() {
+        match bidi_class(c) {
+            WhiteSpace
+            | BoundaryNeutral
+            | LeftToRightIsolate
+            | RightToLeftIsolate
+            | FirstStrongIsolate
+            | PopDirectionalIsolate => trailing.push(offset),
+            SegmentSeparator | ParagraphSeparator => {
+                trailing.push(offset);
+                for offset in trailing.drain(..) {
+                    reset_char_level(text, levels, offset, base_level);
+                }
+            }
+            _ => trailing.clear(),
+        }
+    }
+    for offset in trailing {
+        reset_char_level(text, levels, offset, base_level);
+    }
+}
+
+fn reset_char_level(text: &str, levels: &mut [u8], offset: usize, level: u8) {
+    let len = text[offset..].chars().next().map_or(0, char::len_utf8);
+    for l in &mut levels[offset..offset + len] {
+        *l = level;
+    }
+}
+
+/// The rule L2: the indices of the runs of `levels` in visual order, from left to right.
+pub fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let highest = levels.iter().cloned().max().unwrap_or(0);
+    let lowest_odd = levels.iter().cloned().min().unwrap_or(0) | 1;
+    let mut level = highest;
+    while level >= lowest_odd && level > 0 {
+        let mut i = 0;
+        while i < order.len() {
+            if levels[order[i]] < level {
+                i += 1;
+                continue;
+            }
+            let end = (i..order.len())
+                .find(|&j| levels[order[j]] < level)
+                .unwrap_or(order.len());
+            order[i..end].reverse();
+            i = end;
+        }
+        level -= 1;
+    }
+    order
+}
+
+#[test]
+fn test_bidi() {
+    // The levels of the characters of `text`, with the paragraph level `base_level`.
+    fn levels(text: &str, base_level: u8) -> Vec<u8> {
+        let levels = resolve_levels(text, base_level);
+        text.char_indices()
+            .map(|(offset, _)| levels[offset])
+            .collect()
+    }
+
+    assert_eq!(levels("abc", 0), vec![0, 0, 0]);
+    assert_eq!(levels("abc", 1), vec![2, 2, 2]);
+    assert_eq!(levels("אב ג", 0), vec![1, 1, 1, 1]);
+    // Neutrals between different directions take the embedding direction.
+    assert_eq!(levels("a אב", 0), vec![0, 0, 1, 1]);
+    assert_eq!(levels("a אב", 1), vec![2, 1, 1, 1]);
+    // Numbers in right-to-left text.
+    assert_eq!(levels("א 12.5", 0), vec![1, 1, 2, 2, 2, 2]);
+    assert_eq!(levels("\u{627} 12", 0), vec![1, 1, 2, 2]);
+    // Brackets are resolved in pairs.
+    assert_eq!(levels("א(b)ג", 1), vec![1, 1, 2, 1, 1]);
+    assert_eq!(levels("a(ב)c", 0), vec![0, 0, 1, 0, 0]);
+    // Mirrored characters which aren't brackets are plain neutrals.
+    assert_eq!(levels("א<ב«ג≤ד", 1), vec![1; 7]);
+    assert_eq!(bracket_type('>'), None);
+    assert_eq!(bracket_type(']'), Some(false));
+    // Explicit embeddings and isolates.
+    assert_eq!(levels("a\u{202E}bc\u{202C}d", 0), vec![0, 0, 1, 1, 1, 0]);
+    assert_eq!(levels("א\u{2066}b\u{2069}!", 1), vec![1, 1, 2, 1, 1]);
+    // An FSI takes its direction from the text up to its matching PDI only.
+    assert_eq!(levels("a\u{2068}!\u{2069}א", 0), vec![0, 0, 2, 0, 1]);
+
+    assert_eq!(first_strong_level("1 אa"), Some(1));
+    assert_eq!(first_strong_level("\u{2067}א\u{2069} a"), Some(0));
+    assert_eq!(first_strong_level("12"), None);
+
+    let text = "אב  ";
+    let mut line_levels = resolve_levels(text, 0);
+    reset_whitespace_levels(text, &mut line_levels, 0);
+    assert_eq!(line_levels, vec![1, 1, 1, 1, 0, 0]);
+
+    assert_eq!(visual_order(&[0, 1, 1, 0]), vec![0, 2, 1, 3]);
+    assert_eq!(visual_order(&[1, 2, 2, 1, 1]), vec![4, 3, 1, 2, 0]);
+    assert_eq!(mirror('('), Some(')'));
+    assert_eq!(mirror('»'), Some('«'));
+    assert_eq!(mirror('a'), None);
+}
+
+#[test]
+fn test_bidi_reordering() {
+    use dom::NodeType;
+    use layout::{layout_html, BoxType};
+
+    let css = "div { display: block; width: 400px; }
+               .rtl { direction: rtl; }";
+    let tree = layout_html(
+        "<div><div>abc אבג דהו def</div>\
+         <div class='rtl'>אבג abc</div></div>",
+        css,
+        400,
+    );
+    let runs = |i: usize| {
+        tree.children[i].children[0]
+            .children
+            .iter()
+            .filter_map(|item| match (&item.box_type, &item.node.data) {
+                (&BoxType::TextNode(ref text), &NodeType::Text(ref s)) => Some((
+                    s[text.range.clone()].trim().to_string(),
+                    text.bidi_level,
+                    item.dimensions.content.x,
+                )),
+                _ => None,
+            })
+            .filter(|&(ref text, _, _)| !text.is_empty())
+            .collect::<Vec<_>>()
+    };
+
+    // The right-to-left run keeps its place between the left-to-right words.
+    let line = runs(0);
+    let texts: Vec<&str> = line.iter().map(|&(ref text, _, _)| text.as_str()).collect();
+    assert_eq!(texts, vec!["abc", "אבג דהו", "def"]);
+    let levels: Vec<u8> = line.iter().map(|run| run.1).collect();
+    assert_eq!(levels, vec![0, 1, 0]);
+    assert!(line[0].2 < line[1].2 && line[1].2 < line[2].2);
+
+    // In a right-to-left paragraph, the first word is on the right.
+    let line = runs(1);
+    let hebrew = line.iter().find(|run| run.0 == "אבג").unwrap();
+    let latin = line.iter().find(|run| run.0 == "abc").unwrap();
+    assert_eq!((hebrew.1, latin.1), (1, 2));
+    assert!(latin.2 < hebrew.2);
+}
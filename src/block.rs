@@ -1,8 +1,9 @@
-use css::{Unit, Value};
+use dom::NodeType;
 use float::Floats;
-use layout::{Dimensions, LayoutBox};
+use layout::{BoxType, Dimensions, LayoutBox};
+use style::{BoxSizing, ClearType, Display, LengthOrPercentage, LengthOrPercentageOrAuto};
 
-use std::cmp::max;
+use std::cmp::{max, min};
 
 use app_units::Au;
 
@@ -11,210 +12,172 @@ impl LayoutBox {
     pub fn layout_block(
         &mut self,
         floats: &mut Floats,
-        last_margin_bottom: Au,
+        margins: &mut CollapsibleMargins,
         containing_block: Dimensions,
-        _saved_block: Dimensions,
+        saved_block: Dimensions,
         viewport: Dimensions,
     ) {
         self.load_image(containing_block);
 
-        self.floats = floats.clone();
-
-        let margin = self.property.margin();
-        let padding = self.property.padding();
-        let border = self.property.border_width();
+        // Floats outside a new block formatting context don't affect its content.
+        self.floats = if self.establishes_block_formatting_context() {
+            Floats::new()
+        } else {
+            floats.clone()
+        };
 
         // Child width can depend on parent width, so we need to calculate this box's width before
         // laying out its children.
-        self.calculate_block_width(containing_block, &margin, &padding, &border);
+        self.calculate_block_width(containing_block);
 
-        self.calculate_block_position(
-            last_margin_bottom,
-            containing_block,
-            margin,
-            padding,
-            border,
-        );
+        self.calculate_block_position(margins, containing_block);
 
         if self.floats.is_present() {
             self.floats.translate(self.dimensions.offset());
         }
 
-        self.layout_block_children(viewport);
+        let definite_block = self.definite_block(saved_block);
+        if self.establishes_block_formatting_context() {
+            self.layout_children(definite_block, viewport);
+            // A block formatting context root contains its floats.
+            // ref. https://www.w3.org/TR/CSS2/visudet.html#root-height
+            self.dimensions.content.height = max(
+                self.dimensions.content.height,
+                self.floats.clearance(ClearType::Both),
+            );
+            *margins = CollapsibleMargins::default();
+        } else {
+            self.layout_block_children(Some(margins), definite_block, viewport);
+        }
+        margins.adjoin(self.dimensions.margin.bottom);
 
         self.assign_replaced_width_if_necessary();
         self.assign_replaced_height_if_necessary();
 
         // Parent height can depend on child height, so `calculate_height` must be called after the
         // children are laid out.
-        self.calculate_block_height();
+        self.calculate_block_height(saved_block);
     }
 
     /// Calculate the width of a block-level non-replaced element in normal flow.
     /// Sets the horizontal margin/padding/border dimensions, and the `width`.
     /// ref. http://www.w3.org/TR/CSS2/visudet.html#blockwidth
-    pub fn calculate_block_width(
-        &mut self,
-        containing_block: Dimensions,
-        margin: &(Value, Value, Value, Value),
-        padding: &(Value, Value, Value, Value),
-        border: &(Value, Value, Value, Value),
-    ) {
-        let cb_width = containing_block.content.width.to_f64_px();
+    pub fn calculate_block_width(&mut self, containing_block: Dimensions) {
+        let cb_width = containing_block.content.width;
+
+        // `width` has initial value `auto`. `None` means `auto` below.
+        let mut used = self.solve_block_width(self.specified_width(cb_width), cb_width);
+
+        // If the width is out of `min-width` and `max-width`, the rules are applied again with the
+        // limit as the specified width.
+        // ref. https://www.w3.org/TR/CSS2/visudet.html#min-max-widths
+        let clamped = self.clamp_width(used.0, cb_width);
+        if clamped != used.0 {
+            used = self.solve_block_width(Some(clamped), cb_width);
+        }
+        let (width, margin_left, margin_right) = used;
 
-        // `width` has initial value `auto`.
-        let auto = Value::Keyword("auto".to_string());
-        let mut width = if let Some(x) = self.property.value("width") {
-            x[0].clone()
-        } else {
-            auto.clone()
-        };
+        let style = &self.property.computed;
+        let d = &mut self.dimensions;
+        d.content.width = width;
+
+        d.padding.left = style.padding.left.to_au(cb_width);
+        d.padding.right = style.padding.right.to_au(cb_width);
+
+        d.border.left = style.border_width.left;
+        d.border.right = style.border_width.right;
+
+        d.margin.left = margin_left;
+        d.margin.right = margin_right;
+    }
 
-        let mut margin_left = margin.3.clone();
-        let mut margin_right = margin.1.clone();
+    /// The used content width and horizontal margins for the content width `width`, or `auto` if
+    /// `width` is None.
+    fn solve_block_width(&self, width: Option<Au>, cb_width: Au) -> (Au, Au, Au) {
+        let style = &self.property.computed;
 
-        let border_left = &border.3;
-        let border_right = &border.1;
+        let mut margin_left = style.margin.left.to_au(cb_width);
+        let mut margin_right = style.margin.right.to_au(cb_width);
 
-        let padding_left = &padding.3;
-        let padding_right = &padding.1;
+        let border_left = style.border_width.left;
+        let border_right = style.border_width.right;
 
-        let total = sum([
-            &margin_left,
-            &margin_right,
-            border_left,
-            border_right,
-            padding_left,
-            padding_right,
-            &width,
-        ].iter()
-            .map(|v| v.maybe_percent_to_px(cb_width).unwrap_or(0.0)));
+        let padding_left = style.padding.left.to_au(cb_width);
+        let padding_right = style.padding.right.to_au(cb_width);
+
+        let total = margin_left.unwrap_or(Au(0)) + margin_right.unwrap_or(Au(0)) + border_left
+            + border_right + padding_left + padding_right
+            + width.unwrap_or(Au(0));
 
         // If width is not auto and the total is wider than the container, treat auto margins as 0.
-        if width != auto && total > containing_block.content.width.to_f64_px() {
-            if margin_left == auto {
-                margin_left = Value::Length(0.0, Unit::Px);
-            }
-            if margin_right == auto {
-                margin_right = Value::Length(0.0, Unit::Px);
-            }
+        if width.is_some() && total > cb_width {
+            margin_left.get_or_insert(Au(0));
+            margin_right.get_or_insert(Au(0));
         }
 
         // Adjust used values so that the above sum equals `containing_block.width`.
-        // Each arm of the `match` should increase the total width by exactly `underflow`,
-        // and afterward all values should be absolute lengths in px.
-        let underflow = containing_block.content.width - Au::from_f64_px(total);
+        // Each arm of the `match` should increase the total width by exactly `underflow`.
+        let underflow = cb_width - total;
 
-        match (width == auto, margin_left == auto, margin_right == auto) {
+        match (width, margin_left, margin_right) {
             // If the values are overconstrained, calculate margin_right.
-            (false, false, false) => {
-                margin_right = Value::Length(
-                    margin_right.maybe_percent_to_px(cb_width).unwrap() + underflow.to_f64_px(),
-                    Unit::Px,
-                );
+            (Some(width), Some(margin_left), Some(margin_right)) => {
+                (width, margin_left, margin_right + underflow)
             }
 
             // If exactly one size is auto, its used value follows from the equality.
-            (false, false, true) => {
-                margin_right = Value::Length(underflow.to_f64_px(), Unit::Px);
-            }
-            (false, true, false) => {
-                margin_left = Value::Length(underflow.to_f64_px(), Unit::Px);
-            }
+            (Some(width), Some(margin_left), None) => (width, margin_left, underflow),
+            (Some(width), None, Some(margin_right)) => (width, underflow, margin_right),
 
             // If width is set to auto, any other auto values become 0.
-            (true, _, _) => {
-                if margin_left == auto {
-                    margin_left = Value::Length(0.0, Unit::Px);
-                }
-                if margin_right == auto {
-                    margin_right = Value::Length(0.0, Unit::Px);
-                }
-
-                if underflow.to_f64_px() >= 0.0 {
+            (None, margin_left, margin_right) => {
+                let margin_left = margin_left.unwrap_or(Au(0));
+                let margin_right = margin_right.unwrap_or(Au(0));
+                if underflow >= Au(0) {
                     // Expand width to fill the underflow.
-                    width = Value::Length(underflow.to_f64_px(), Unit::Px);
+                    (underflow, margin_left, margin_right)
                 } else {
                     // Width can't be negative. Adjust the right margin instead.
-                    width = Value::Length(0.0, Unit::Px);
-                    margin_right = Value::Length(
-                        margin_right.maybe_percent_to_px(cb_width).unwrap() + underflow.to_f64_px(),
-                        Unit::Px,
-                    );
+                    (Au(0), margin_left, margin_right + underflow)
                 }
             }
 
             // If margin-left and margin-right are both auto, their used values are equal.
-            (false, true, true) => {
-                margin_left = Value::Length(underflow.to_f64_px() / 2.0, Unit::Px);
-                margin_right = Value::Length(underflow.to_f64_px() / 2.0, Unit::Px);
-            }
-        }
-
-        let d = &mut self.dimensions;
-        if let Some(width) = width.maybe_percent_to_px(cb_width) {
-            d.content.width = Au::from_f64_px(width)
-        }
-
-        if let Some(padding_left) = padding_left.maybe_percent_to_px(cb_width) {
-            d.padding.left = Au::from_f64_px(padding_left)
-        }
-        if let Some(padding_right) = padding_right.maybe_percent_to_px(cb_width) {
-            d.padding.right = Au::from_f64_px(padding_right)
-        }
-
-        if let Some(border_left) = border_left.maybe_percent_to_px(cb_width) {
-            d.border.left = Au::from_f64_px(border_left)
-        }
-        if let Some(border_right) = border_right.maybe_percent_to_px(cb_width) {
-            d.border.right = Au::from_f64_px(border_right)
-        }
-
-        if let Some(margin_left) = margin_left.maybe_percent_to_px(cb_width) {
-            d.margin.left = Au::from_f64_px(margin_left)
-        }
-        if let Some(margin_right) = margin_right.maybe_percent_to_px(cb_width) {
-            d.margin.right = Au::from_f64_px(margin_right)
+            (Some(width), None, None) => (width, underflow / 2, underflow / 2),
         }
     }
 
     /// Finish calculating the block's edge sizes, and position it within its containing block.
     /// http://www.w3.org/TR/CSS2/visudet.html#normal-block
     /// Sets the vertical margin/padding/border dimensions, and the `x`, `y` values.
+    /// `margins` are the margins above the box which collapse with its top margin, and the top
+    /// margin is added to them. The used `margin.top` is the collapsed margin, which may include
+    /// the top margins of the first children.
     pub fn calculate_block_position(
         &mut self,
-        last_margin_bottom: Au,
+        margins: &mut CollapsibleMargins,
         containing_block: Dimensions,
-        margin: (Value, Value, Value, Value),
-        padding: (Value, Value, Value, Value),
-        border: (Value, Value, Value, Value),
     ) {
-        let cb_width = containing_block.content.width.to_f64_px();
-        let d = &mut self.dimensions;
-
-        // margin, border, and padding have initial value 0.
-        let zero = Value::Length(0.0, Unit::Px);
+        let cb_width = containing_block.content.width;
+        // Vertical percentages refer to the width of the containing block, and auto margins are 0.
+        margins.adjoin(self.property.computed.margin.top.to_au(cb_width).unwrap_or(Au(0)));
 
-        d.margin.top = Au::from_f64_px(margin.0.maybe_percent_to_px(cb_width).unwrap_or(0f64));
-        d.margin.bottom = Au::from_f64_px(margin.2.maybe_percent_to_px(cb_width).unwrap_or(0f64));
-
-        // Margin collapse
-        // TODO: Is this implementation correct?
-        if last_margin_bottom >= d.margin.top {
-            d.margin.top = Au(0);
-        } else {
-            d.margin.top = d.margin.top - last_margin_bottom;
+        let mut collapsed = *margins;
+        if self.collapses_with_first_child(cb_width) {
+            collapsed.adjoin_margins(self.first_children_margins(self.dimensions.content.width));
         }
 
-        d.border.top = Au::from_f64_px(border.0.maybe_percent_to_px(cb_width).unwrap());
-        d.border.bottom = Au::from_f64_px(border.2.maybe_percent_to_px(cb_width).unwrap());
+        let style = &self.property.computed;
+        let d = &mut self.dimensions;
+
+        d.margin.top = collapsed.collapse();
+        d.margin.bottom = style.margin.bottom.to_au(cb_width).unwrap_or(Au(0));
 
-        d.padding.top = Au::from_f64_px(padding.0.maybe_percent_to_px(cb_width).unwrap());
-        d.padding.bottom = Au::from_f64_px(padding.2.maybe_percent_to_px(cb_width).unwrap());
+        d.border.top = style.border_width.top;
+        d.border.bottom = style.border_width.bottom;
 
-        self.z_index = self.property.lookup("z-index", "z-index", &vec![zero])[0]
-            .clone()
-            .to_num() as i32;
+        d.padding.top = style.padding.top.to_au(cb_width);
+        d.padding.bottom = style.padding.bottom.to_au(cb_width);
 
         d.content.x = d.margin.left + d.border.left + d.padding.left;
 
@@ -222,51 +185,471 @@ impl LayoutBox {
         d.content.y = containing_block.content.height + d.margin.top + d.border.top + d.padding.top;
     }
 
+    /// Lay out the children in the formatting context this box establishes, whose margins don't
+    /// collapse with the margins of this box.
+    /// `saved_block` is the block percentage heights of the children refer to. See
+    /// `definite_block`.
+    pub fn layout_children(&mut self, saved_block: Dimensions, viewport: Dimensions) {
+        let display = self.property.computed.display;
+        if display.is_flex() {
+            self.layout_flex_children(viewport);
+        } else if display.is_grid() {
+            self.layout_grid_children(viewport);
+        } else if display.is_table() {
+            self.layout_table_children(viewport);
+        } else {
+            self.layout_block_children(None, saved_block, viewport);
+        }
+    }
+
     /// Lay out the block's children within its content area.
     /// Sets `self.dimensions.height` to the total content height.
-    pub fn layout_block_children(&mut self, viewport: Dimensions) {
+    /// If the margins of the children collapse with the margins of this box, `margins` has the
+    /// margins adjoining the top margin of this box, including the top margin itself, and it gets
+    /// the margins of the last children that collapse with the bottom margin.
+    /// ref. https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+    pub fn layout_block_children(
+        &mut self,
+        margins: Option<&mut CollapsibleMargins>,
+        saved_block: Dimensions,
+        viewport: Dimensions,
+    ) {
+        let collapses_with_last_child = self.property.computed.height
+            == LengthOrPercentageOrAuto::Auto
+            && self.property
+                .computed
+                .min_height
+                .map_or(true, |min_height| min_height.is_zero())
+            && self.dimensions.border.bottom == Au(0)
+            && self.dimensions.padding.bottom == Au(0);
+        let writing_mode = self.property.computed.writing_mode;
         let d = &mut self.dimensions;
-        let mut last_margin_bottom = Au(0);
         let mut floats = &mut self.floats;
+        // The margins between the bottom of the previous child and the top of the next child.
+        let mut adjoining = CollapsibleMargins::default();
 
         d.content.height = Au(0);
 
-        // TODO: Consider a better way to position children.
+        if let Some(ref margins) = margins {
+            if d.border.top == Au(0) && d.padding.top == Au(0) {
+                // The margins of the first children are already in `margin.top` of this box, so
+                // the content starts at the top margin edge.
+                adjoining = **margins;
+                d.content.height = -d.margin.top;
+            }
+        }
+
         for child in &mut self.children {
-            if let Some(clear) = child.property.clear() {
+            if let Some(clear) = child.property.computed.clear {
                 let clearance = floats.clearance(clear);
-                d.content.height += clearance;
+                if clearance > Au(0) {
+                    // Clearance keeps the margins above from collapsing with the child's.
+                    d.content.height += adjoining.collapse() + clearance;
+                    adjoining = CollapsibleMargins::default();
+                }
             }
 
             if floats.is_present() {
                 floats.ceiling = max(floats.ceiling, d.content.height);
             }
 
-            child.layout(&mut floats, last_margin_bottom, *d, *d, viewport);
+            let collapses_through =
+                child.in_normal_flow() && child.collapses_through(d.content.width);
+            let mut child_margins = adjoining;
+            if child.starts_vertical_flow(writing_mode) {
+                child.layout_vertical_flow(&mut child_margins, *d, saved_block, viewport);
+            } else {
+                child.layout(&mut floats, &mut child_margins, *d, saved_block, viewport);
+            }
 
             if child.in_normal_flow() {
-                last_margin_bottom = child.dimensions.margin.bottom;
+                adjoining = child_margins;
                 // Increment the height so each child is laid out below the previous one.
-                d.content.height += child.dimensions.margin_box().height;
+                if !collapses_through {
+                    d.content.height +=
+                        child.dimensions.margin.top + child.dimensions.border_box().height;
+                }
             }
         }
+
+        match margins {
+            Some(margins) if collapses_with_last_child => *margins = adjoining,
+            Some(margins) => {
+                d.content.height += adjoining.collapse();
+                *margins = CollapsibleMargins::default();
+            }
+            None => d.content.height += adjoining.collapse(),
+        }
+        d.content.height = max(d.content.height, Au(0));
     }
 
-    /// Height of a block-level non-replaced element in normal flow with overflow visible.
-    pub fn calculate_block_height(&mut self) {
-        // If the height is set to an explicit length, use that exact length.
+    /// Height of a block-level non-replaced element in normal flow.
+    /// `saved_block` is the block percentage heights refer to. See `definite_block`.
+    pub fn calculate_block_height(&mut self, saved_block: Dimensions) {
+        // If the height is specified, use that exact height.
         // Otherwise, just keep the value set by `layout_block_children`.
-        if let Some(val) = self.get_style_node().value("height") {
-            if let Value::Length(h, Unit::Px) = val[0] {
-                self.dimensions.content.height = Au::from_f64_px(h);
+        let height = self.specified_height(saved_block).unwrap_or(self.dimensions.content.height);
+        self.dimensions.content.height = self.clamp_height(height, saved_block);
+    }
+}
+
+impl LayoutBox {
+    /// The content width `width` specifies in a containing block of the width `cb_width`, or
+    /// None if `auto`.
+    pub fn specified_width(&self, cb_width: Au) -> Option<Au> {
+        self.property
+            .computed
+            .width
+            .to_au(cb_width)
+            .map(|width| self.content_width(width, cb_width))
+    }
+
+    /// Limits the content width `width` by `min-width` and `max-width`. `min-width` wins if they
+    /// conflict.
+    pub fn clamp_width(&self, width: Au, cb_width: Au) -> Au {
+        let (min_width, max_width) = self.min_max_width(cb_width);
+        let width = match max_width {
+            Some(max_width) => min(width, max_width),
+            None => width,
+        };
+        max(width, min_width.unwrap_or(Au(0)))
+    }
+
+    /// The content widths `min-width` and `max-width` specify, or None if `auto` and `none`.
+    pub fn min_max_width(&self, cb_width: Au) -> (Option<Au>, Option<Au>) {
+        let style = &self.property.computed;
+        let resolve = |size: LengthOrPercentage| self.content_width(size.to_au(cb_width), cb_width);
+        (style.min_width.map(&resolve), style.max_width.map(&resolve))
+    }
+
+    /// The content width of the box whose `box-sizing` width is `width`.
+    fn content_width(&self, width: Au, cb_width: Au) -> Au {
+        let style = &self.property.computed;
+        match style.box_sizing {
+            BoxSizing::ContentBox => width,
+            BoxSizing::BorderBox => max(
+                Au(0),
+                width - style.border_width.left - style.border_width.right
+                    - style.padding.left.to_au(cb_width)
+                    - style.padding.right.to_au(cb_width),
+            ),
+        }
+    }
+
+    /// The content height `height` specifies, or None if `auto`. A percentage refers to the
+    /// height of `saved_block`, and is treated as `auto` if the height isn't definite.
+    /// The vertical padding and borders must be calculated before.
+    pub fn specified_height(&self, saved_block: Dimensions) -> Option<Au> {
+        let height = match self.property.computed.height {
+            LengthOrPercentageOrAuto::Length(height) => height,
+            LengthOrPercentageOrAuto::Percentage(percentage) => {
+                percentage_of_height(percentage, saved_block)?
             }
+            LengthOrPercentageOrAuto::Auto => return None,
+        };
+        Some(self.content_height(height))
+    }
+
+    /// Limits the content height `height` by `min-height` and `max-height`. Percentages are
+    /// ignored if the height of `saved_block` isn't definite.
+    pub fn clamp_height(&self, height: Au, saved_block: Dimensions) -> Au {
+        let (min_height, max_height) = self.min_max_height(saved_block);
+        let height = match max_height {
+            Some(max_height) => min(height, max_height),
+            None => height,
+        };
+        max(height, min_height.unwrap_or(Au(0)))
+    }
+
+    /// The content heights `min-height` and `max-height` specify, or None if `auto`, `none` or
+    /// a percentage of an indefinite height. The vertical padding and borders must be calculated
+    /// before.
+    pub fn min_max_height(&self, saved_block: Dimensions) -> (Option<Au>, Option<Au>) {
+        let style = &self.property.computed;
+        let resolve = |size: LengthOrPercentage| {
+            let size = match size {
+                LengthOrPercentage::Length(size) => size,
+                LengthOrPercentage::Percentage(percentage) => {
+                    percentage_of_height(percentage, saved_block)?
+                }
+            };
+            Some(self.content_height(size))
+        };
+        (
+            style.min_height.and_then(&resolve),
+            style.max_height.and_then(&resolve),
+        )
+    }
+
+    /// The content height of the box whose `box-sizing` height is `height`.
+    fn content_height(&self, height: Au) -> Au {
+        let d = &self.dimensions;
+        match self.property.computed.box_sizing {
+            BoxSizing::ContentBox => height,
+            BoxSizing::BorderBox => max(
+                Au(0),
+                height - d.border.top - d.border.bottom - d.padding.top - d.padding.bottom,
+            ),
         }
     }
+
+    /// The content area of this box as the block percentage heights of the children refer to.
+    /// Its height is negative if it isn't definite, i.e. depends on the content.
+    /// ref. https://www.w3.org/TR/CSS2/visudet.html#the-height-property
+    pub fn definite_block(&self, saved_block: Dimensions) -> Dimensions {
+        let mut block = self.dimensions;
+        block.content.height = match self.specified_height(saved_block) {
+            Some(height) => self.clamp_height(height, saved_block),
+            None => Au(-1),
+        };
+        block
+    }
+}
+
+/// `percentage` of the height of `saved_block`, or None if the height isn't definite.
+fn percentage_of_height(percentage: f64, saved_block: Dimensions) -> Option<Au> {
+    let height = saved_block.content.height;
+    if height < Au(0) {
+        return None;
+    }
+    Some(Au::from_f64_px(height.to_f64_px() * percentage / 100.0))
+}
+
+/// `block` with an indefinite height, for the boxes whose percentage heights behave as `auto`.
+pub fn indefinite_block(mut block: Dimensions) -> Dimensions {
+    block.content.height = Au(-1);
+    block
+}
+
+impl LayoutBox {
+    /// Whether this box establishes a new block formatting context, where the margins of the
+    /// children don't collapse with the margins of this box.
+    /// ref. https://www.w3.org/TR/CSS2/visuren.html#block-formatting
+    pub fn establishes_block_formatting_context(&self) -> bool {
+        match self.box_type {
+            BoxType::BlockNode => match self.property.computed.display {
+                Display::Block | Display::ListItem => self.property.computed.is_scroll_container(),
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+
+    /// Whether the top margin of this box collapses with the top margin of its first in-flow
+    /// child, i.e. there is no border, padding or formatting context between them.
+    fn collapses_with_first_child(&self, cb_width: Au) -> bool {
+        let style = &self.property.computed;
+        !self.establishes_block_formatting_context() && style.border_width.top == Au(0)
+            && style.padding.top.to_au(cb_width) == Au(0)
+    }
+
+    /// Whether the top and bottom margins of this box collapse, which happens when the box is
+    /// empty: no height, border, padding, or content except boxes the margins collapse through.
+    pub fn collapses_through(&self, cb_width: Au) -> bool {
+        let style = &self.property.computed;
+        match self.box_type {
+            // Whitespace alone makes no line boxes.
+            BoxType::AnonymousBlock => self.children.iter().all(|child| match child.node.data {
+                NodeType::Text(ref text) => text.chars().all(char::is_whitespace),
+                _ => false,
+            }),
+            BoxType::BlockNode => {
+                self.collapses_with_first_child(cb_width) && !self.is_replaced()
+                    && style.height == LengthOrPercentageOrAuto::Auto
+                    && style.min_height.map_or(true, |min_height| min_height.is_zero())
+                    && style.border_width.bottom == Au(0)
+                    && style.padding.bottom.to_au(cb_width) == Au(0)
+                    && self.children
+                        .iter()
+                        .filter(|child| child.in_normal_flow())
+                        .all(|child| child.collapses_through(cb_width))
+            }
+            _ => false,
+        }
+    }
+
+    /// The top margins of the first in-flow children in the content area of the width `width`,
+    /// with all the margins of the children the margins collapse through.
+    fn first_children_margins(&self, width: Au) -> CollapsibleMargins {
+        let mut margins = CollapsibleMargins::default();
+        for child in self.children.iter().filter(|child| child.in_normal_flow()) {
+            if child.box_type == BoxType::AnonymousBlock {
+                if !child.collapses_through(width) {
+                    break;
+                }
+                continue;
+            }
+            let style = &child.property.computed;
+            margins.adjoin(style.margin.top.to_au(width).unwrap_or(Au(0)));
+            // The width of the grandchildren's containing block isn't known yet, so the width
+            // of the child is estimated from its style.
+            let child_width = style.width.to_au(width).unwrap_or(width);
+            if child.collapses_with_first_child(width) {
+                margins.adjoin_margins(child.first_children_margins(child_width));
+            }
+            if !child.collapses_through(width) {
+                break;
+            }
+            margins.adjoin(style.margin.bottom.to_au(width).unwrap_or(Au(0)));
+        }
+        margins
+    }
+}
+
+/// The adjoining vertical margins, which collapse into a single margin: the sum of the largest
+/// positive margin and the most negative margin.
+/// ref. https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CollapsibleMargins {
+    positive: Au,
+    negative: Au,
+}
+
+impl CollapsibleMargins {
+    pub fn new(margin: Au) -> CollapsibleMargins {
+        let mut margins = CollapsibleMargins::default();
+        margins.adjoin(margin);
+        margins
+    }
+
+    pub fn adjoin(&mut self, margin: Au) {
+        self.positive = max(self.positive, margin);
+        self.negative = min(self.negative, margin);
+    }
+
+    pub fn adjoin_margins(&mut self, margins: CollapsibleMargins) {
+        self.adjoin(margins.positive);
+        self.adjoin(margins.negative);
+    }
+
+    /// The width of the collapsed margin.
+    pub fn collapse(&self) -> Au {
+        self.positive + self.negative
+    }
+}
+
+#[test]
+fn test_margin_collapsing() {
+    use layout::layout_html;
+
+    let css = "div { display: block; }
+               .a { margin: 10px 0 20px; height: 5px; }
+               .b { margin-top: 15px; height: 5px; }
+               .neg { margin-top: -30px; height: 5px; }
+               .first { margin-top: 25px; height: 5px; }
+               .border { border-top: 1px solid; margin-top: 5px; }
+               .empty { margin: 12px 0 18px; }
+               .flex { display: flex; margin-bottom: 7px; }
+               .last { margin-bottom: 40px; height: 5px; }";
+    let tree = layout_html(
+        "<div style='padding: 1px 0'><div class='a'></div><div class='b'></div>\
+         <div class='a'></div><div class='neg'></div>\
+         <div class='b'><div class='first'></div></div>\
+         <div><div class='a first'><div class='a'></div></div></div>\
+         <div class='border'><div class='a'></div></div>\
+         <div class='empty'></div><div class='a'></div>\
+         <div><div class='last'></div></div><div class='b'></div>\
+         <div class='flex'><div class='a'></div></div><div class='b'></div>\
+         </div>",
+        css,
+        400,
+    );
+    let y = |layout_box: &LayoutBox| layout_box.dimensions.content.y.to_px();
+    let root = &tree;
+
+    // Adjoining margins of siblings collapse into the largest one.
+    assert_eq!(y(&root.children[0]), 10);
+    assert_eq!(y(&root.children[1]), 35);
+
+    // A negative margin is subtracted from the largest positive one.
+    assert_eq!(y(&root.children[2]), 50);
+    assert_eq!(y(&root.children[3]), 45);
+
+    // The top margin of the first child collapses with its parent's, so the larger margin of the
+    // child pushes the parent down.
+    let parent = &root.children[4];
+    assert_eq!(parent.dimensions.margin.top.to_px(), 25);
+    assert_eq!(y(parent), 75);
+    assert_eq!(y(&parent.children[0]), 0);
+
+    // The margins collapse through more than one level.
+    let parent = &root.children[5];
+    assert_eq!(parent.dimensions.margin.top.to_px(), 25);
+    assert_eq!(y(parent), 105);
+    assert_eq!(y(&parent.children[0]), 0);
+    assert_eq!(y(&parent.children[0].children[0]), 0);
+    // The bottom margins of the last children collapse too.
+    assert_eq!(parent.dimensions.content.height.to_px(), 5);
+
+    // The border separates the margins of the parent and the child.
+    let parent = &root.children[6];
+    assert_eq!(y(parent), 131);
+    assert_eq!(y(&parent.children[0]), 10);
+
+    // The margins collapse through an empty block.
+    assert_eq!(root.children[7].dimensions.content.height.to_px(), 0);
+    assert_eq!(y(&root.children[8]), 166);
+
+    // The bottom margin of the last child collapses with the sibling's top margin.
+    let parent = &root.children[9];
+    assert_eq!(parent.dimensions.content.height.to_px(), 5);
+    assert_eq!(y(&root.children[10]), 236);
+
+    // A flex container establishes a new formatting context.
+    let parent = &root.children[11];
+    assert_eq!(y(parent), 241);
+    assert_eq!(y(&parent.children[0]), 10);
+    assert_eq!(parent.dimensions.content.height.to_px(), 35);
+    assert_eq!(y(&root.children[12]), 291);
 }
 
-fn sum<I>(iter: I) -> f64
-where
-    I: Iterator<Item = f64>,
-{
-    iter.fold(0., |a, b| a + b)
+#[test]
+fn test_sizing() {
+    use layout::layout_html;
+
+    let css = "div { display: block; }
+               .max { max-width: 100px; margin: 0 auto; }
+               .min { width: 50px; min-width: 80px; }
+               .border { box-sizing: border-box; width: 100px; height: 50px; padding: 10px;
+                         border: 5px solid; }
+               .fixed { height: 200px; }
+               .half { height: 50%; }
+               .em { height: 2em; max-height: 50%; }
+               .min-height { min-height: 30px; }
+               .max-height { max-height: 10px; }
+               .float { float: left; max-width: 20px; }";
+    let tree = layout_html(
+        "<div><div class='max'></div><div class='min'></div><div class='border'></div>\
+         <div class='fixed'><div class='half'></div><div class='em'></div></div>\
+         <div><div class='half'></div></div>\
+         <div class='min-height'></div><div class='max-height'>a</div>\
+         <div class='float'>aaaa</div></div>",
+        css,
+        400,
+    );
+    let size = |layout_box: &LayoutBox| {
+        let content = layout_box.dimensions.content;
+        (content.width.to_px(), content.height.to_px())
+    };
+
+    // `max-width` limits the width, and the auto margins center the box again.
+    assert_eq!(size(&tree.children[0]), (100, 0));
+    assert_eq!(tree.children[0].dimensions.margin.left.to_px(), 150);
+    assert_eq!(size(&tree.children[1]), (80, 0));
+
+    // The specified sizes include the padding and the border.
+    assert_eq!(size(&tree.children[2]), (70, 20));
+
+    // Percentage heights refer to the definite height of the containing block.
+    let fixed = &tree.children[3];
+    assert_eq!(size(&fixed.children[0]), (400, 100));
+    assert_eq!(size(&fixed.children[1]), (400, 32));
+    // They're treated as `auto` if the height of the containing block depends on the content.
+    assert_eq!(size(&tree.children[4].children[0]), (400, 0));
+
+    assert_eq!(size(&tree.children[5]), (400, 30));
+    assert_eq!(size(&tree.children[6]), (400, 10));
+
+    // The shrink-to-fit width of a float is limited too.
+    assert_eq!(size(&tree.children[7]).0, 20);
 }
@@ -0,0 +1,489 @@
+//! Generated content: the `content` property of `::before` and `::after`, CSS counters and
+//! quotes.
+//! ref. https://www.w3.org/TR/css-content-3/
+//! ref. https://www.w3.org/TR/css-lists-3/#auto-numbering
+
+use css::Value;
+use dom::ElementData;
+use html;
+use style::{Display, Style};
+
+/// An instance of a counter. It's created by the element at `depth`, and is visible to the
+/// element, its descendants, its following siblings and their descendants.
+#[derive(Clone, Debug, PartialEq)]
+struct Counter {
+    name: String,
+    depth: usize,
+    value: i32,
+}
+
+/// The counters and the nesting level of quotes, updated in document order while the layout
+/// tree is built.
+#[derive(Clone, Debug, Default)]
+pub struct ContentContext {
+    counters: Vec<Counter>,
+    quote_depth: usize,
+}
+
+/// A piece of generated content whose counters, quotes and `attr()` are resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContentItem {
+    Text(String),
+    Image(String),
+}
+
+impl ContentContext {
+    pub fn new() -> ContentContext {
+        Default::default()
+    }
+
+    /// Applies `counter-reset`, `counter-increment` and then `counter-set` of an element at
+    /// `depth`. List items also increment the `list-item` counter.
+    pub fn update_counters(&mut self, style: &Style, depth: usize) {
+        for (name, value) in counter_list(style.value("counter-reset"), 0) {
+            self.reset_counter(name, value, depth);
+        }
+        let mut increments = counter_list(style.value("counter-increment"), 1);
+        if style.computed.display == Display::ListItem
+            && increments.iter().all(|&(ref name, _)| name != "list-item")
+        {
+            increments.push(("list-item".to_string(), 1));
+        }
+        for (name, value) in increments {
+            self.increment_counter(name, value, depth);
+        }
+        for (name, value) in counter_list(style.value("counter-set"), 0) {
+            // Setting a counter not in scope instantiates it.
+            if self.innermost_counter(name.as_str()).is_none() {
+                self.reset_counter(name.clone(), 0, depth);
+            }
+            self.innermost_counter(name.as_str()).unwrap().value = value;
+        }
+    }
+
+    /// Drops the counters created by the children of the element at `depth`, whose scopes end
+    /// with the element.
+    pub fn leave_children(&mut self, depth: usize) {
+        self.counters.retain(|counter| counter.depth <= depth);
+    }
+
+    fn reset_counter(&mut self, name: String, value: i32, depth: usize) {
+        // Resetting a counter created by a preceding sibling replaces it instead of nesting.
+        match self.innermost_counter(name.as_str()) {
+            Some(counter) if counter.depth == depth => {
+                counter.value = value;
+                return;
+            }
+            _ => {}
+        }
+        self.counters.push(Counter {
+            name: name,
+            depth: depth,
+            value: value,
+        });
+    }
+
+    fn increment_counter(&mut self, name: String, value: i32, depth: usize) {
+        // Incrementing a counter not in scope instantiates it first.
+        if self.innermost_counter(name.as_str()).is_none() {
+            self.reset_counter(name.clone(), 0, depth);
+        }
+        let counter = self.innermost_counter(name.as_str()).unwrap();
+        counter.value = counter.value.saturating_add(value);
+    }
+
+    fn innermost_counter(&mut self, name: &str) -> Option<&mut Counter> {
+        self.counters
+            .iter_mut()
+            .rev()
+            .find(|counter| counter.name == name)
+    }
+
+    /// Returns the values of the counter `name` from the outermost to the innermost.
+    fn counter_values(&self, name: &str) -> Vec<i32> {
+        let values: Vec<i32> = self.counters
+            .iter()
+            .filter(|counter| counter.name == name)
+            .map(|counter| counter.value)
+            .collect();
+        if values.is_empty() {
+            vec![0]
+        } else {
+            values
+        }
+    }
+
+    /// Resolves the `content` property of a `::before` or `::after` with `style` generated by
+    /// `elem`. Returns None if no box should be generated, i.e. for `none` and `normal`.
+    pub fn content_items(&mut self, style: &Style, elem: &ElementData) -> Option<Vec<ContentItem>> {
+        let content = style.value("content")?;
+        match content.first() {
+            Some(&Value::Keyword(ref k)) if k == "none" || k == "normal" => return None,
+            _ => {}
+        }
+
+        let quotes = quote_pairs(style.value("quotes"));
+        let mut items = vec![];
+        for value in &content {
+            let text = match *value {
+                Value::Str(ref string) => string.clone(),
+                Value::Url(ref url) => {
+                    items.push(ContentItem::Image(html::resolve_url(url)));
+                    continue;
+                }
+                Value::Function(ref name, ref arguments) => {
+                    self.resolve_function(name.as_str(), arguments, elem)
+                }
+                Value::Keyword(ref k) => self.resolve_quote(k.as_str(), &quotes),
+                _ => "".to_string(),
+            };
+            // Adjacent strings go in the same text box.
+            match items.last_mut() {
+                Some(&mut ContentItem::Text(ref mut last)) => {
+                    last.push_str(text.as_str());
+                    continue;
+                }
+                _ => {}
+            }
+            items.push(ContentItem::Text(text));
+        }
+        Some(items)
+    }
+
+    /// Resolves the content of the `::marker` with `style` of a list item `elem`. Unless
+    /// `content` is given, it's `list-style-image`, or the `list-item` counter formatted in
+    /// `list-style-type`. Returns None if there's no marker.
+    pub fn marker_items(&mut self, style: &Style, elem: &ElementData) -> Option<Vec<ContentItem>> {
+        match style.value("content") {
+            Some(ref content) if content.first() != Some(&Value::Keyword("normal".to_string())) => {
+                return self.content_items(style, elem)
+            }
+            _ => {}
+        }
+
+        if let Some(Value::Url(url)) = style
+            .value("list-style-image")
+            .and_then(|image| image.into_iter().next())
+        {
+            return Some(vec![ContentItem::Image(html::resolve_url(url.as_str()))]);
+        }
+
+        let list_style_type = style
+            .value("list-style-type")
+            .and_then(|list_style_type| list_style_type.into_iter().next());
+        let marker = match list_style_type {
+            Some(Value::Keyword(ref k)) if k == "none" => return None,
+            Some(Value::Keyword(ref k)) => {
+                let value = *self.counter_values("list-item").last().unwrap();
+                format_marker(value, k.as_str())
+            }
+            // `list-style-type: "-"`
+            Some(Value::Str(string)) => string,
+            _ => format_marker(0, "disc"),
+        };
+        Some(vec![ContentItem::Text(marker)])
+    }
+
+    fn resolve_function(&self, name: &str, arguments: &[Value], elem: &ElementData) -> String {
+        let keyword = |i: usize| match arguments.get(i) {
+            Some(&Value::Keyword(ref k)) => Some(k.as_str()),
+            _ => None,
+        };
+        match name {
+            "attr" => keyword(0)
+                .and_then(|attr| elem.attrs.get(attr))
+                .cloned()
+                .unwrap_or_default(),
+            // counter(<name>, <counter-style>?)
+            "counter" => keyword(0).map_or("".to_string(), |counter| {
+                let value = *self.counter_values(counter).last().unwrap();
+                format_counter(value, keyword(1).unwrap_or("decimal"))
+            }),
+            // counters(<name>, <string>, <counter-style>?)
+            "counters" => keyword(0).map_or("".to_string(), |counter| {
+                let separator = match arguments.get(1) {
+                    Some(&Value::Str(ref separator)) => separator.as_str(),
+                    _ => "",
+                };
+                let style = keyword(2).unwrap_or("decimal");
+                self.counter_values(counter)
+                    .into_iter()
+                    .map(|value| format_counter(value, style))
+                    .collect::<Vec<String>>()
+                    .join(separator)
+            }),
+            _ => "".to_string(),
+        }
+    }
+
+    fn resolve_quote(&mut self, keyword: &str, quotes: &[(String, String)]) -> String {
+        let quote = |depth: usize| quotes.get(depth).or_else(|| quotes.last());
+        match keyword {
+            "open-quote" => {
+                let open = quote(self.quote_depth).map_or("".to_string(), |q| q.0.clone());
+                self.quote_depth += 1;
+                open
+            }
+            // A close-quote without a matching open-quote is ignored.
+            "close-quote" if self.quote_depth > 0 => {
+                self.quote_depth -= 1;
+                quote(self.quote_depth).map_or("".to_string(), |q| q.1.clone())
+            }
+            "no-open-quote" => {
+                self.quote_depth += 1;
+                "".to_string()
+            }
+            "no-close-quote" if self.quote_depth > 0 => {
+                self.quote_depth -= 1;
+                "".to_string()
+            }
+            _ => "".to_string(),
+        }
+    }
+}
+
+/// Returns the pairs of `counter-reset` or `counter-increment`, e.g. `[("a", 1), ("b", 0)]` for
+/// `a 1 b`. `default` is used for the names without an integer.
+fn counter_list(values: Option<Vec<Value>>, default: i32) -> Vec<(String, i32)> {
+    let mut list: Vec<(String, i32)> = vec![];
+    for value in values.unwrap_or_default() {
+        match value {
+            Value::Keyword(ref k) if k == "none" => {}
+            Value::Keyword(name) => list.push((name, default)),
+            Value::Num(n) => {
+                if let Some(last) = list.last_mut() {
+                    last.1 = n as i32;
+                }
+            }
+            _ => {}
+        }
+    }
+    list
+}
+
+/// Returns the pairs of open and close quotes of the `quotes` property.
+fn quote_pairs(values: Option<Vec<Value>>) -> Vec<(String, String)> {
+    let values = values.unwrap_or_default();
+    match values.first() {
+        Some(&Value::Keyword(ref k)) if k == "none" => return vec![],
+        Some(&Value::Str(_)) => {}
+        // `auto`
+        _ => {
+            return vec![
+                ("\u{201c}".to_string(), "\u{201d}".to_string()),
+                ("\u{2018}".to_string(), "\u{2019}".to_string()),
+            ]
+        }
+    }
+    let strings: Vec<String> = values
+        .into_iter()
+        .filter_map(|value| match value {
+            Value::Str(string) => Some(string),
+            _ => None,
+        })
+        .collect();
+    strings
+        .chunks(2)
+        .filter(|pair| pair.len() == 2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect()
+}
+
+/// Formats a counter value in the counter style `style`. Values out of the range of the style
+/// are formatted in `decimal`.
+/// ref. https://www.w3.org/TR/css-counter-styles-3/#predefined-counters
+pub fn format_counter(value: i32, style: &str) -> String {
+    match style {
+        "none" => Some("".to_string()),
+        "disc" => Some("\u{2022}".to_string()),
+        "circle" => Some("\u{25e6}".to_string()),
+        "square" => Some("\u{25aa}".to_string()),
+        "decimal-leading-zero" if value >= 0 => Some(format!("{:02}", value)),
+        "lower-alpha" | "lower-latin" => alphabetic(value, "abcdefghijklmnopqrstuvwxyz"),
+        "upper-alpha" | "upper-latin" => alphabetic(value, "ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+        "lower-greek" => alphabetic(value, "αβγδεζηθικλμνξοπρστυφχψω"),
+        "lower-roman" => roman(value).map(|roman| roman.to_lowercase()),
+        "upper-roman" => roman(value),
+        "cjk-ideographic" => Some(cjk_ideographic(value)),
+        _ => None,
+    }.unwrap_or_else(|| value.to_string())
+}
+
+/// Formats the marker of a list item whose `list-item` counter is `value`.
+fn format_marker(value: i32, list_style_type: &str) -> String {
+    let suffix = match list_style_type {
+        "disc" | "circle" | "square" => " ",
+        "cjk-ideographic" => "\u{3001}",
+        _ => ". ",
+    };
+    format_counter(value, list_style_type) + suffix
+}
+
+/// e.g. a, b, ..., z, aa, ab, ... for `abcdefghijklmnopqrstuvwxyz`.
+fn alphabetic(value: i32, letters: &str) -> Option<String> {
+    if value < 1 {
+        return None;
+    }
+    let letters: Vec<char> = letters.chars().collect();
+    let mut n = value as usize;
+    let mut string = vec![];
+    while n > 0 {
+        n -= 1;
+        string.push(letters[n % letters.len()]);
+        n /= letters.len();
+    }
+    Some(string.into_iter().rev().collect())
+}
+
+fn roman(value: i32) -> Option<String> {
+    if value < 1 || value > 3999 {
+        return None;
+    }
+    let mut n = value;
+    let mut string = "".to_string();
+    for &(weight, symbol) in &[
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ] {
+        while n >= weight {
+            string.push_str(symbol);
+            n -= weight;
+        }
+    }
+    Some(string)
+}
+
+/// Formats `value` in informal traditional Chinese, e.g. 十二 for 12 and 一百零一 for 101.
+fn cjk_ideographic(value: i32) -> String {
+    const DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+    let mut n = (value as i64).abs();
+    if n == 0 {
+        return DIGITS[0].to_string();
+    }
+
+    let mut string = "".to_string();
+    // Whether a 零 is needed before the next non-zero digit.
+    let mut zero = false;
+    for &(group_weight, group_name) in &[(100_000_000, "億"), (10_000, "萬"), (1, "")] {
+        let group = n / group_weight;
+        n %= group_weight;
+        for &(weight, name) in &[(1000, "千"), (100, "百"), (10, "十"), (1, "")] {
+            let digit = group / weight % 10;
+            if digit == 0 {
+                zero = !string.is_empty();
+                continue;
+            }
+            if zero {
+                string.push(DIGITS[0]);
+                zero = false;
+            }
+            string.push(DIGITS[digit as usize]);
+            string.push_str(name);
+        }
+        if group > 0 {
+            string.push_str(group_name);
+        }
+    }
+
+    // 一十 at the beginning is written as 十.
+    if string.starts_with("一十") {
+        string.remove(0);
+    }
+    if value < 0 {
+        string.insert(0, '負');
+    }
+    string
+}
+
+#[test]
+fn test_counters() {
+    use css::parse_attr_style;
+    use dom::{Node, NodeType};
+
+    let style = |src: &str| {
+        Style::new_with(
+            parse_attr_style(src.to_string())
+                .into_iter()
+                .map(|decl| (decl.name, decl.values))
+                .collect(),
+        )
+    };
+    let node = Node::elem("p".to_string(), Default::default(), vec![]);
+    let elem = match node.data {
+        NodeType::Element(ref elem) => elem,
+        NodeType::Text(_) => unreachable!(),
+    };
+    let text = |text: &str| Some(vec![ContentItem::Text(text.to_string())]);
+
+    let mut ctx = ContentContext::new();
+    // <ol> at depth 1 with items at depth 2, the second of which contains another <ol>.
+    ctx.update_counters(&style("counter-reset: item"), 1);
+    ctx.update_counters(&style("counter-increment: item"), 2);
+    ctx.update_counters(&style("counter-increment: item"), 2);
+    ctx.update_counters(&style("counter-reset: item 4"), 3);
+    ctx.update_counters(&style("counter-increment: item 2"), 4);
+    assert_eq!(
+        ctx.content_items(
+            &style("content: counters(item, \".\") \" \" counter(item)"),
+            elem
+        ),
+        text("2.6 6")
+    );
+    ctx.leave_children(3);
+    ctx.leave_children(2);
+    assert_eq!(
+        ctx.content_items(&style("content: counter(item)"), elem),
+        text("2")
+    );
+
+    let quotes = "content: open-quote open-quote close-quote close-quote close-quote";
+    assert_eq!(
+        ctx.content_items(&style(quotes), elem),
+        text("\u{201c}\u{2018}\u{2019}\u{201d}")
+    );
+    assert_eq!(
+        ctx.content_items(
+            &style("quotes: '<' '>'; content: open-quote open-quote close-quote"),
+            elem
+        ),
+        text("<<>")
+    );
+    assert_eq!(ctx.content_items(&style("content: none"), elem), None);
+}
+
+#[test]
+fn test_counter_styles() {
+    let format = |values: &[i32], style: &str| {
+        values
+            .iter()
+            .map(|&value| format_counter(value, style))
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(format(&[1, 26, 27, 702, 0], "lower-alpha"), ["a", "z", "aa", "zz", "0"]);
+    assert_eq!(format(&[2, 24, 25], "lower-greek"), ["β", "ω", "αα"]);
+    assert_eq!(
+        format(&[4, 1994, 4000], "upper-roman"),
+        ["IV", "MCMXCIV", "4000"]
+    );
+    assert_eq!(format(&[9, -1], "decimal-leading-zero"), ["09", "-1"]);
+    assert_eq!(
+        format(
+            &[0, 10, 12, 101, 110, 10010, 100000, 100000000, -20],
+            "cjk-ideographic"
+        ),
+        ["零", "十", "十二", "一百零一", "一百一十", "一萬零一十", "十萬", "一億", "負二十"]
+    );
+    assert_eq!(format_marker(3, "lower-roman"), "iii. ");
+    assert_eq!(format_marker(3, "square"), "\u{25aa} ");
+}
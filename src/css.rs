@@ -1,10 +1,27 @@
-use std::{fmt, collections::HashSet};
+use std::{fmt, collections::{HashMap, HashSet}};
 
 use html::remove_comments;
+use style::DEFAULT_FONT_SIZE;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Stylesheet {
     pub rules: Vec<Rule>,
+    pub buckets: RuleBuckets,
+    /// The pseudo-elements of the rightmost compound selectors, so that the cascade of a
+    /// pseudo-element is skipped when no rule can match it.
+    pub pseudo_elements: HashSet<PseudoElement>,
+}
+
+/// The indices of rules bucketed by the rightmost compound selector of their selectors, so that
+/// only the rules which may match an element are checked.
+/// A selector is put in the id bucket if its rightmost compound has an id, else in the bucket of
+/// one of its classes, else in the tag bucket, else in the universal bucket.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RuleBuckets {
+    id: HashMap<String, Vec<usize>>,
+    class: HashMap<String, Vec<usize>>,
+    tag: HashMap<String, Vec<usize>>,
+    universal: Vec<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -25,12 +42,24 @@ pub struct SimpleSelector {
     pub tag_name: Option<String>,
     pub id: Option<String>,
     pub class: HashSet<String>,
+    // Only the rightmost compound selector may have a pseudo-element.
+    pub pseudo_element: Option<PseudoElement>,
+}
+
+/// The supported pseudo-elements. Selectors with other pseudo-elements are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PseudoElement {
+    Before,
+    After,
+    Marker,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Declaration {
     pub name: String,
     pub values: Vec<Value>,
+    // `!important`
+    pub important: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -39,14 +68,40 @@ pub enum Value {
     Length(f64, Unit),
     Num(f64),
     Color(Color),
+    Str(String),
+    Url(String),
+    // e.g. `attr(title)` and `counter(item, upper-roman)`.
+    Function(String, Vec<Value>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Unit {
     Px,
     Pt,
+    Pc,
+    In,
+    Cm,
+    Mm,
     Percent,
     Em,
+    Rem,
+    Ex,
+    Ch,
+    Vw,
+    Vh,
+    Vmin,
+    Vmax,
+    // The flexible length of grid tracks.
+    Fr,
+}
+
+/// The measures that font- and viewport-relative lengths are resolved against. All in px.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthContext {
+    pub font_size: f64,
+    pub root_font_size: f64,
+    pub viewport_width: f64,
+    pub viewport_height: f64,
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
@@ -87,43 +142,271 @@ color!(BLUE, 0x00, 0x00, 0xff);
 color!(TEAL, 0x00, 0x80, 0x80);
 color!(AQUA, 0x00, 0xff, 0xff);
 
+pub const TRANSPARENT: Color = Color {
+    r: 0x00,
+    g: 0x00,
+    b: 0x00,
+    a: 0x00,
+};
+
 impl Copy for Color {}
 
+impl Color {
+    fn from_rgb_hex(hex: u32) -> Color {
+        Color {
+            r: (hex >> 16) as u8,
+            g: (hex >> 8) as u8,
+            b: hex as u8,
+            a: 0xff,
+        }
+    }
+
+    /// Converts HSL into RGB. `h` is in degrees, and `s`, `l` and `a` are in [0, 1].
+    /// ref. https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+    pub fn from_hsla(h: f64, s: f64, l: f64, a: f64) -> Color {
+        let h = ((h % 360.0) + 360.0) % 360.0 / 360.0;
+        let s = s.max(0.0).min(1.0);
+        let l = l.max(0.0).min(1.0);
+        let t2 = if l <= 0.5 {
+            l * (s + 1.0)
+        } else {
+            l + s - l * s
+        };
+        let t1 = l * 2.0 - t2;
+        fn hue_to_rgb(t1: f64, t2: f64, h: f64) -> f64 {
+            let h = if h < 0.0 {
+                h + 1.0
+            } else if h > 1.0 {
+                h - 1.0
+            } else {
+                h
+            };
+            if h * 6.0 < 1.0 {
+                t1 + (t2 - t1) * h * 6.0
+            } else if h * 2.0 < 1.0 {
+                t2
+            } else if h * 3.0 < 2.0 {
+                t1 + (t2 - t1) * (2.0 / 3.0 - h) * 6.0
+            } else {
+                t1
+            }
+        }
+        Color {
+            r: to_color_component(hue_to_rgb(t1, t2, h + 1.0 / 3.0)),
+            g: to_color_component(hue_to_rgb(t1, t2, h)),
+            b: to_color_component(hue_to_rgb(t1, t2, h - 1.0 / 3.0)),
+            a: to_color_component(a),
+        }
+    }
+}
+
+/// Converts a color component in [0, 1] into [0, 255].
+fn to_color_component(f: f64) -> u8 {
+    (f.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+// ref. https://www.w3.org/TR/css-color-4/#named-colors
+// Sorted by name for binary search.
+static NAMED_COLORS: &'static [(&'static str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+/// Returns the color named `name`, which must be in lowercase.
+pub fn named_color(name: &str) -> Option<Color> {
+    NAMED_COLORS
+        .binary_search_by(|&(color_name, _)| color_name.cmp(name))
+        .ok()
+        .map(|i| Color::from_rgb_hex(NAMED_COLORS[i].1))
+}
+
 impl Value {
     pub fn to_px(&self) -> Option<f64> {
         match *self {
-            Value::Length(f, Unit::Px) | Value::Num(f) => Some(f),
-            Value::Length(f, Unit::Pt) => Some(pt2px(f)),
-            Value::Length(_, Unit::Em) => {
-                println!("The unit 'em' is currently unsupported. Treated as '16px'");
-                Some(16.0)
-            }
+            Value::Num(f) => Some(f),
+            Value::Length(_, Unit::Percent) => None,
+            Value::Length(f, ref unit) => unit.to_px(f),
             _ => None,
         }
     }
 
     pub fn maybe_percent_to_px(&self, len: f64) -> Option<f64> {
         match *self {
-            Value::Length(f, Unit::Px) | Value::Num(f) => Some(f),
-            Value::Length(f, Unit::Pt) => Some(pt2px(f)),
             Value::Length(f, Unit::Percent) => Some(len * (f / 100.0)),
-            Value::Length(_, Unit::Em) => {
-                println!("The unit 'em' is currently unsupported. Treated as '16px'");
-                Some(16.0)
-            }
-            _ => None,
+            _ => self.to_px(),
         }
     }
 
     pub fn to_pt(&self) -> Option<f64> {
         match *self {
-            Value::Length(f, Unit::Pt) | Value::Num(f) => Some(f),
-            Value::Length(f, Unit::Px) => Some(px2pt(f)),
-            Value::Length(_, Unit::Em) => {
-                println!("The unit 'em' is currently unsupported. Treated as '12pt'");
-                Some(12.0)
-            }
-            _ => None,
+            Value::Num(f) => Some(f),
+            _ => self.to_px().map(px2pt),
+        }
+    }
+
+    /// Converts a font- or viewport-relative length into an absolute length in px.
+    /// Any other value is returned as it is.
+    pub fn resolve_relative_length(&self, ctx: &LengthContext) -> Value {
+        match *self {
+            Value::Length(f, ref unit) => match ctx.relative_unit_to_px(f, unit) {
+                Some(px) => Value::Length(px, Unit::Px),
+                None => self.clone(),
+            },
+            // e.g. `minmax(10em, 1fr)`
+            Value::Function(ref name, ref arguments) => Value::Function(
+                name.clone(),
+                arguments
+                    .iter()
+                    .map(|argument| argument.resolve_relative_length(ctx))
+                    .collect(),
+            ),
+            _ => self.clone(),
         }
     }
 
@@ -134,32 +417,26 @@ impl Value {
         }
     }
 
+    /// Returns the color this value represents. `currentColor` depends on the element, so it's
+    /// not resolved here (see `is_current_color`).
     pub fn to_color(&self) -> Option<Color> {
         match *self {
             Value::Color(color) => Some(color),
             Value::Keyword(ref color_name) => match color_name.as_str() {
-                "black" => Some(BLACK),
-                "silver" => Some(SILVER),
-                "gray" => Some(GRAY),
-                "white" => Some(WHITE),
-                "red" => Some(RED),
-                "maroon" => Some(MAROON),
-                "purple" => Some(PURPLE),
-                "fuchsia" => Some(FUCHSIA),
-                "green" => Some(GREEN),
-                "lime" => Some(LIME),
-                "olive" => Some(OLIVE),
-                "yellow" => Some(YELLOW),
-                "navy" => Some(NAVY),
-                "blue" => Some(BLUE),
-                "teal" => Some(TEAL),
-                "aqua" => Some(AQUA),
-                _ => None,
+                "transparent" => Some(TRANSPARENT),
+                name => named_color(name),
             },
             _ => None,
         }
     }
 
+    pub fn is_current_color(&self) -> bool {
+        match *self {
+            Value::Keyword(ref k) => k == "currentcolor",
+            _ => false,
+        }
+    }
+
     pub fn to_text_decoration(&self) -> Option<TextDecoration> {
         match *self {
             Value::Keyword(ref name) => match name.to_lowercase().as_str() {
@@ -176,6 +453,76 @@ impl Value {
 
 const DPI: f64 = 96.0;
 
+impl Unit {
+    /// Converts `f` in this unit into px. Returns None for percentages and viewport-relative
+    /// units, which can't be resolved without knowing the context.
+    pub fn to_px(&self, f: f64) -> Option<f64> {
+        match *self {
+            Unit::Px => Some(f),
+            Unit::Pt => Some(pt2px(f)),
+            Unit::Pc => Some(pt2px(f * 12.0)),
+            Unit::In => Some(f * DPI),
+            Unit::Cm => Some(f * DPI / 2.54),
+            Unit::Mm => Some(f * DPI / 25.4),
+            // Font-relative units that weren't resolved by the cascade are relative to the
+            // initial font size.
+            _ => LengthContext::default().relative_unit_to_px(f, self),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Unit::Px => "px",
+            Unit::Pt => "pt",
+            Unit::Pc => "pc",
+            Unit::In => "in",
+            Unit::Cm => "cm",
+            Unit::Mm => "mm",
+            Unit::Percent => "%",
+            Unit::Em => "em",
+            Unit::Rem => "rem",
+            Unit::Ex => "ex",
+            Unit::Ch => "ch",
+            Unit::Vw => "vw",
+            Unit::Vh => "vh",
+            Unit::Vmin => "vmin",
+            Unit::Vmax => "vmax",
+            Unit::Fr => "fr",
+        }
+    }
+}
+
+impl Default for LengthContext {
+    fn default() -> LengthContext {
+        LengthContext {
+            font_size: DEFAULT_FONT_SIZE,
+            root_font_size: DEFAULT_FONT_SIZE,
+            viewport_width: 0.0,
+            viewport_height: 0.0,
+        }
+    }
+}
+
+impl LengthContext {
+    /// Converts `f` in a font- or viewport-relative unit into px.
+    pub fn relative_unit_to_px(&self, f: f64, unit: &Unit) -> Option<f64> {
+        let viewport_min = self.viewport_width.min(self.viewport_height);
+        let viewport_max = self.viewport_width.max(self.viewport_height);
+        match *unit {
+            Unit::Em => Some(f * self.font_size),
+            Unit::Rem => Some(f * self.root_font_size),
+            // We don't have the x-height and the advance of '0' at hand, so use 0.5em for both
+            // as the spec allows when the font metrics are unavailable.
+            Unit::Ex | Unit::Ch => Some(f * self.font_size / 2.0),
+            Unit::Vw if self.viewport_width > 0.0 => Some(f * self.viewport_width / 100.0),
+            Unit::Vh if self.viewport_height > 0.0 => Some(f * self.viewport_height / 100.0),
+            Unit::Vmin if viewport_min > 0.0 => Some(f * viewport_min / 100.0),
+            Unit::Vmax if viewport_max > 0.0 => Some(f * viewport_max / 100.0),
+            _ => None,
+        }
+    }
+}
+
 // TODO: any other better way?
 pub fn px2pt(f: f64) -> f64 {
     f / ((1.0 / 72.0) * DPI)
@@ -189,12 +536,20 @@ pub fn pt2px(f: f64) -> f64 {
 pub type Specificity = (usize, usize, usize);
 
 impl Selector {
+    /// Returns the rightmost compound selector, which is matched against the element itself.
+    pub fn rightmost(&self) -> &SimpleSelector {
+        match *self {
+            Selector::Simple(ref simple) => simple,
+            Selector::Descendant(_, ref b) | Selector::Child(_, ref b) => b.rightmost(),
+        }
+    }
+
     // ref: http://www.w3.org/TR/selectors/#specificity
     pub fn specificity(&self) -> Specificity {
         fn specificity_simple(simple: &SimpleSelector) -> Specificity {
             let a = simple.id.iter().count();
             let b = simple.class.len();
-            let c = simple.tag_name.iter().count();
+            let c = simple.tag_name.iter().count() + simple.pseudo_element.iter().count();
             (a, b, c)
         }
 
@@ -215,12 +570,92 @@ impl Selector {
 }
 
 pub fn parse(source: String) -> Stylesheet {
-    Stylesheet {
-        rules: Parser::new(source).parse_rules(),
+    Stylesheet::new(Parser::new(source).parse_rules())
+}
+
+impl Stylesheet {
+    pub fn new(rules: Vec<Rule>) -> Stylesheet {
+        Stylesheet {
+            buckets: RuleBuckets::new(&rules),
+            pseudo_elements: rules
+                .iter()
+                .flat_map(|rule| &rule.selectors)
+                .filter_map(|selector| selector.rightmost().pseudo_element)
+                .collect(),
+            rules: rules,
+        }
+    }
+
+    /// Returns the rules which may match an element with `tag_name`, `id` and `classes`, in
+    /// source order. The selectors of the returned rules still need to be matched.
+    pub fn candidate_rules(
+        &self,
+        tag_name: &str,
+        id: Option<&String>,
+        classes: &HashSet<&str>,
+    ) -> Vec<&Rule> {
+        self.buckets
+            .candidates(tag_name, id, classes)
+            .into_iter()
+            .map(|i| &self.rules[i])
+            .collect()
+    }
+}
+
+impl RuleBuckets {
+    pub fn new(rules: &[Rule]) -> RuleBuckets {
+        let mut buckets = RuleBuckets::default();
+        for (i, rule) in rules.iter().enumerate() {
+            for selector in &rule.selectors {
+                let rightmost = selector.rightmost();
+                let bucket = if let Some(ref id) = rightmost.id {
+                    buckets.id.entry(id.clone()).or_insert_with(Vec::new)
+                } else if let Some(class) = rightmost.class.iter().next() {
+                    buckets.class.entry(class.clone()).or_insert_with(Vec::new)
+                } else if let Some(ref tag_name) = rightmost.tag_name {
+                    buckets.tag.entry(tag_name.clone()).or_insert_with(Vec::new)
+                } else {
+                    &mut buckets.universal
+                };
+                // A rule with several selectors in the same bucket is registered once.
+                if bucket.last() != Some(&i) {
+                    bucket.push(i);
+                }
+            }
+        }
+        buckets
+    }
+
+    /// Returns the indices of the rules which may match, in ascending order.
+    pub fn candidates(
+        &self,
+        tag_name: &str,
+        id: Option<&String>,
+        classes: &HashSet<&str>,
+    ) -> Vec<usize> {
+        let mut candidates = self.universal.clone();
+        if let Some(rules) = self.tag.get(tag_name) {
+            candidates.extend(rules);
+        }
+        if let Some(rules) = id.and_then(|id| self.id.get(id)) {
+            candidates.extend(rules);
+        }
+        for class in classes {
+            if let Some(rules) = self.class.get(*class) {
+                candidates.extend(rules);
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+        candidates
     }
 }
 
 pub fn parse_attr_style(source: String) -> Vec<Declaration> {
+    expand_shorthands(parse_attr_declarations(source))
+}
+
+fn parse_attr_declarations(source: String) -> Vec<Declaration> {
     let mut decls = Vec::new();
     let mut parser = Parser::new(source);
     loop {
@@ -243,6 +678,514 @@ pub fn parse_value(source: String) -> Value {
     }
 }
 
+/// Expands shorthand declarations into their longhands, so that the cascade and `style::Style`
+/// only ever deal with longhands. Other declarations are kept as they are, and invalid shorthand
+/// declarations and declarations without values are dropped.
+/// ref. https://www.w3.org/TR/css-cascade-4/#shorthand
+pub fn expand_shorthands(declarations: Vec<Declaration>) -> Vec<Declaration> {
+    let mut expanded = Vec::with_capacity(declarations.len());
+    for declaration in declarations {
+        // A declaration without a value, e.g. `color:;`, is invalid.
+        if declaration.values.is_empty() {
+            continue;
+        }
+        let longhands = match longhands_of(declaration.name.as_str()) {
+            Some(longhands) => longhands,
+            None => {
+                expanded.push(declaration);
+                continue;
+            }
+        };
+
+        // `inherit`, `initial`, `unset` and `revert` are applied to every longhand.
+        if declaration.values.len() == 1 && is_css_wide_keyword(&declaration.values[0]) {
+            for longhand in longhands {
+                expanded.push(Declaration {
+                    name: longhand,
+                    values: declaration.values.clone(),
+                    important: declaration.important,
+                });
+            }
+            continue;
+        }
+
+        if let Some(values) = expand_shorthand(declaration.name.as_str(), &declaration.values) {
+            for (name, values) in longhands.into_iter().zip(values) {
+                expanded.push(Declaration {
+                    name: name,
+                    values: values,
+                    important: declaration.important,
+                });
+            }
+        }
+    }
+    expanded
+}
+
+pub fn is_css_wide_keyword(value: &Value) -> bool {
+    match *value {
+        Value::Keyword(ref k) => match k.as_str() {
+            "inherit" | "initial" | "unset" | "revert" => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+const SIDES: [&str; 4] = ["top", "right", "bottom", "left"];
+const CORNERS: [&str; 4] = ["top-left", "top-right", "bottom-right", "bottom-left"];
+
+const FONT_SIZE_KEYWORDS: &[&str] = &[
+    "xx-small",
+    "x-small",
+    "small",
+    "medium",
+    "large",
+    "x-large",
+    "xx-large",
+    "xxx-large",
+    "larger",
+    "smaller",
+];
+
+const BACKGROUND_REPEAT_KEYWORDS: &[&str] = &[
+    "repeat",
+    "repeat-x",
+    "repeat-y",
+    "no-repeat",
+    "space",
+    "round",
+];
+
+/// Returns the longhands of the shorthand property `name`, or None if `name` isn't a shorthand.
+/// The order matches the values returned by `expand_shorthand`.
+fn longhands_of(name: &str) -> Option<Vec<String>> {
+    let sides = |f: &Fn(&str) -> String| Some(SIDES.iter().map(|side| f(side)).collect());
+    match name {
+        "margin" | "padding" => sides(&|side| format!("{}-{}", name, side)),
+        "border-width" | "border-style" | "border-color" => {
+            let suffix = &name["border-".len()..];
+            sides(&|side| format!("border-{}-{}", side, suffix))
+        }
+        "border-top" | "border-right" | "border-bottom" | "border-left" => Some(
+            ["width", "style", "color"]
+                .iter()
+                .map(|suffix| format!("{}-{}", name, suffix))
+                .collect(),
+        ),
+        "border" => Some(
+            ["width", "style", "color"]
+                .iter()
+                .flat_map(|suffix| {
+                    SIDES
+                        .iter()
+                        .map(move |side| format!("border-{}-{}", side, suffix))
+                })
+                .collect(),
+        ),
+        "border-radius" => Some(
+            CORNERS
+                .iter()
+                .map(|corner| format!("border-{}-radius", corner))
+                .collect(),
+        ),
+        "font" => Some(to_strings(&[
+            "font-style",
+            "font-variant",
+            "font-weight",
+            "font-size",
+            "line-height",
+            "font-family",
+        ])),
+        "background" => Some(to_strings(&[
+            "background-color",
+            "background-image",
+            "background-repeat",
+            "background-attachment",
+            "background-position",
+            "background-size",
+        ])),
+        "list-style" => Some(to_strings(&[
+            "list-style-type",
+            "list-style-position",
+            "list-style-image",
+        ])),
+        "flex" => Some(to_strings(&["flex-grow", "flex-shrink", "flex-basis"])),
+        "flex-flow" => Some(to_strings(&["flex-direction", "flex-wrap"])),
+        "gap" | "grid-gap" => Some(to_strings(&["row-gap", "column-gap"])),
+        "overflow" => Some(to_strings(&["overflow-x", "overflow-y"])),
+        // The legacy name of `overflow-wrap`.
+        "word-wrap" => Some(to_strings(&["overflow-wrap"])),
+        "grid-row" | "grid-column" => {
+            Some(vec![format!("{}-start", name), format!("{}-end", name)])
+        }
+        "grid-area" => Some(to_strings(&[
+            "grid-row-start",
+            "grid-column-start",
+            "grid-row-end",
+            "grid-column-end",
+        ])),
+        _ => None,
+    }
+}
+
+fn to_strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Returns the values of the longhands of the shorthand `name` in the order of `longhands_of`,
+/// or None if `values` is invalid for the shorthand.
+/// Longhands omitted in the shorthand are set to their initial values.
+fn expand_shorthand(name: &str, values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    match name {
+        "margin" | "padding" => expand_sides(values),
+        "border-width" if values.iter().all(is_border_width) => expand_sides(values),
+        "border-style" if values.iter().all(is_border_style) => expand_sides(values),
+        "border-color" if values.iter().all(is_color) => expand_sides(values),
+        "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            expand_border_side(values)
+        }
+        "border" => expand_border_side(values)
+            .map(|side| side.into_iter().flat_map(|value| vec![value; 4]).collect()),
+        "border-radius" => expand_border_radius(values),
+        "font" => expand_font(values),
+        "background" => expand_background(values),
+        "list-style" => expand_list_style(values),
+        "flex" => expand_flex(values),
+        "flex-flow" => expand_flex_flow(values),
+        "grid-row" | "grid-column" | "grid-area" => expand_grid_placement(name, values),
+        "word-wrap" => Some(vec![values.to_vec()]),
+        "gap" | "grid-gap" | "overflow" => match values.len() {
+            1 => Some(vec![values.to_vec(), values.to_vec()]),
+            2 => Some(vec![vec![values[0].clone()], vec![values[1].clone()]]),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Expands 1 to 4 values into (top, right, bottom, left).
+fn expand_sides(values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    let (top, right, bottom, left) = match values.len() {
+        1 => (0, 0, 0, 0),
+        2 => (0, 1, 0, 1),
+        3 => (0, 1, 2, 1),
+        4 => (0, 1, 2, 3),
+        _ => return None,
+    };
+    Some(
+        [top, right, bottom, left]
+            .iter()
+            .map(|&i| vec![values[i].clone()])
+            .collect(),
+    )
+}
+
+fn keyword(k: &str) -> Value {
+    Value::Keyword(k.to_string())
+}
+
+fn is_keyword_of(value: &Value, keywords: &[&str]) -> bool {
+    match *value {
+        Value::Keyword(ref k) => keywords.contains(&k.as_str()),
+        _ => false,
+    }
+}
+
+pub fn is_border_style(value: &Value) -> bool {
+    is_keyword_of(
+        value,
+        &[
+            "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset",
+            "outset",
+        ],
+    )
+}
+
+pub fn is_border_width(value: &Value) -> bool {
+    match *value {
+        Value::Length(_, _) | Value::Num(_) => true,
+        _ => is_keyword_of(value, &["thin", "medium", "thick"]),
+    }
+}
+
+pub fn is_color(value: &Value) -> bool {
+    value.to_color().is_some() || value.is_current_color()
+}
+
+/// Expands `[ <line-width> || <line-style> || <color> ]` into (width, style, color).
+fn expand_border_side(values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    let (mut width, mut style, mut color) = (None, None, None);
+    for value in values {
+        match *value {
+            _ if is_border_width(value) && width.is_none() => width = Some(value.clone()),
+            _ if is_border_style(value) && style.is_none() => style = Some(value.clone()),
+            _ if is_color(value) && color.is_none() => color = Some(value.clone()),
+            _ => return None,
+        }
+    }
+    Some(vec![
+        vec![width.unwrap_or_else(|| keyword("medium"))],
+        vec![style.unwrap_or_else(|| keyword("none"))],
+        vec![color.unwrap_or_else(|| keyword("currentcolor"))],
+    ])
+}
+
+/// Expands `border-radius`. Each corner gets [horizontal radius, vertical radius].
+fn expand_border_radius(values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    let mut radii = values.splitn(2, |value| *value == keyword("/"));
+    let horizontal = expand_sides(radii.next()?)?;
+    let vertical = match radii.next() {
+        Some(vertical) => expand_sides(vertical)?,
+        None => horizontal.clone(),
+    };
+    Some(
+        horizontal
+            .into_iter()
+            .zip(vertical)
+            .map(|(h, v)| vec![h[0].clone(), v[0].clone()])
+            .collect(),
+    )
+}
+
+/// Expands `font: [ <font-style> || <font-variant> || <font-weight> ]? <font-size>
+/// [ / <line-height> ]? <font-family>`.
+fn expand_font(values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    let (mut style, mut variant, mut weight) = (None, None, None);
+    let mut iter = values.iter().peekable();
+
+    // Optional style, variant and weight, which come before the mandatory size.
+    let size = loop {
+        let value = iter.next()?;
+        match *value {
+            _ if is_keyword_of(value, &["normal"]) => {}
+            _ if is_keyword_of(value, &["italic", "oblique"]) && style.is_none() => {
+                style = Some(value.clone())
+            }
+            _ if is_keyword_of(value, &["small-caps"]) && variant.is_none() => {
+                variant = Some(value.clone())
+            }
+            _ if is_keyword_of(value, &["bold", "bolder", "lighter"]) && weight.is_none() => {
+                weight = Some(value.clone())
+            }
+            Value::Num(n) if n >= 1.0 && weight.is_none() => weight = Some(value.clone()),
+            Value::Length(_, _) | Value::Num(_) => break value.clone(),
+            _ if is_keyword_of(value, FONT_SIZE_KEYWORDS) => break value.clone(),
+            _ => return None,
+        }
+    };
+
+    let line_height = if iter.peek() == Some(&&keyword("/")) {
+        iter.next();
+        iter.next()?.clone()
+    } else {
+        keyword("normal")
+    };
+
+    let family: Vec<Value> = iter.cloned().collect();
+    if family.is_empty() {
+        return None;
+    }
+
+    Some(vec![
+        vec![style.unwrap_or_else(|| keyword("normal"))],
+        vec![variant.unwrap_or_else(|| keyword("normal"))],
+        vec![weight.unwrap_or_else(|| keyword("normal"))],
+        vec![size],
+        vec![line_height],
+        family,
+    ])
+}
+
+/// Expands the final layer of `background`. Only a single layer is supported, and values which
+/// aren't understood, e.g. gradients and the values of the other layers, are ignored so that
+/// the color is still painted.
+fn expand_background(values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    let (mut color, mut image, mut repeat, mut attachment) = (None, None, vec![], None);
+    let mut position = vec![];
+    let mut size = vec![];
+    let mut after_slash = false;
+    for value in values {
+        match *value {
+            _ if *value == keyword("/") && !position.is_empty() => after_slash = true,
+            _ if after_slash => size.push(value.clone()),
+            // The color and the image of the final layer are the last ones.
+            _ if is_color(value) => color = Some(value.clone()),
+            _ if is_keyword_of(value, &["none"]) => image = Some(value.clone()),
+            Value::Url(_) | Value::Function(_, _) => image = Some(value.clone()),
+            _ if is_keyword_of(value, BACKGROUND_REPEAT_KEYWORDS) => repeat.push(value.clone()),
+            _ if is_keyword_of(value, &["scroll", "fixed", "local"]) => {
+                attachment = Some(value.clone())
+            }
+            Value::Length(_, _) | Value::Num(_) => position.push(value.clone()),
+            _ if is_keyword_of(value, &["left", "right", "top", "bottom", "center"]) => {
+                position.push(value.clone())
+            }
+            // The parser drops the arguments of unsupported functions like `linear-gradient()`.
+            Value::Keyword(_) if !is_css_wide_keyword(value) => image = Some(value.clone()),
+            _ => {}
+        }
+    }
+
+    if repeat.is_empty() {
+        repeat.push(keyword("repeat"));
+    }
+    if position.is_empty() {
+        position = vec![
+            Value::Length(0.0, Unit::Percent),
+            Value::Length(0.0, Unit::Percent),
+        ];
+    }
+    if size.is_empty() {
+        size.push(keyword("auto"));
+    }
+
+    Some(vec![
+        vec![color.unwrap_or_else(|| keyword("transparent"))],
+        vec![image.unwrap_or_else(|| keyword("none"))],
+        repeat,
+        vec![attachment.unwrap_or_else(|| keyword("scroll"))],
+        position,
+        size,
+    ])
+}
+
+/// Expands `list-style: <list-style-type> || <list-style-position> || <list-style-image>`.
+fn expand_list_style(values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    let (mut type_, mut position, mut image) = (None, None, None);
+    let mut nones = 0;
+    for value in values {
+        match *value {
+            _ if is_keyword_of(value, &["none"]) => nones += 1,
+            _ if is_keyword_of(value, &["inside", "outside"]) && position.is_none() => {
+                position = Some(value.clone())
+            }
+            Value::Keyword(_) if type_.is_none() => type_ = Some(value.clone()),
+            _ if image.is_none() => image = Some(value.clone()),
+            _ => return None,
+        }
+    }
+
+    // `none` sets whichever of type and image is omitted.
+    if nones > 0 {
+        if type_.is_none() {
+            type_ = Some(keyword("none"));
+            nones -= 1;
+        }
+        if image.is_none() && nones > 0 {
+            image = Some(keyword("none"));
+            nones -= 1;
+        }
+        if nones > 0 {
+            return None;
+        }
+    }
+
+    Some(vec![
+        vec![type_.unwrap_or_else(|| keyword("disc"))],
+        vec![position.unwrap_or_else(|| keyword("outside"))],
+        vec![image.unwrap_or_else(|| keyword("none"))],
+    ])
+}
+
+/// Expands `flex: none | [ <flex-grow> <flex-shrink>? || <flex-basis> ]`.
+/// The omitted `flex-basis` is 0, unlike its initial value.
+fn expand_flex(values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    match values {
+        [Value::Keyword(ref k)] if k == "none" => {
+            return Some(vec![
+                vec![Value::Num(0.0)],
+                vec![Value::Num(0.0)],
+                vec![keyword("auto")],
+            ])
+        }
+        [Value::Keyword(ref k)] if k == "auto" => {
+            return Some(vec![
+                vec![Value::Num(1.0)],
+                vec![Value::Num(1.0)],
+                vec![keyword("auto")],
+            ])
+        }
+        _ => {}
+    }
+
+    let (mut factors, mut basis) = (vec![], None);
+    for value in values {
+        match *value {
+            // A unitless 0 after the factors is the basis.
+            Value::Num(_) if factors.len() < 2 && basis.is_none() => factors.push(value.clone()),
+            Value::Length(_, _) | Value::Num(_) if basis.is_none() => basis = Some(value.clone()),
+            _ if is_keyword_of(value, &["auto", "content"]) && basis.is_none() => {
+                basis = Some(value.clone())
+            }
+            _ => return None,
+        }
+    }
+    if factors.is_empty() && basis.is_none() {
+        return None;
+    }
+
+    let mut factors = factors.into_iter();
+    Some(vec![
+        vec![factors.next().unwrap_or(Value::Num(1.0))],
+        vec![factors.next().unwrap_or(Value::Num(1.0))],
+        vec![basis.unwrap_or(Value::Length(0.0, Unit::Percent))],
+    ])
+}
+
+/// Expands `grid-row`, `grid-column` and `grid-area`, whose lines are separated by `/`.
+/// An omitted line is the same name as the corresponding start line if it's a name, else `auto`.
+/// ref. https://www.w3.org/TR/css-grid-1/#placement-shorthands
+fn expand_grid_placement(name: &str, values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    let mut lines: Vec<Vec<Value>> = values
+        .split(|value| *value == keyword("/"))
+        .map(|line| line.to_vec())
+        .collect();
+    let count = if name == "grid-area" { 4 } else { 2 };
+    if lines.len() > count || lines.iter().any(|line| line.is_empty()) {
+        return None;
+    }
+
+    let is_name = |line: &Vec<Value>| match line.as_slice() {
+        [Value::Keyword(ref k)] => k != "auto" && k != "span",
+        _ => false,
+    };
+    while lines.len() < count {
+        // An end line takes after its start line, which is two before it in `grid-area`. So does
+        // the column start line after the row start line.
+        let start = &lines[lines.len().saturating_sub(2)];
+        let omitted = if is_name(start) {
+            start.clone()
+        } else {
+            vec![keyword("auto")]
+        };
+        lines.push(omitted);
+    }
+    Some(lines)
+}
+
+/// Expands `flex-flow: <flex-direction> || <flex-wrap>`.
+fn expand_flex_flow(values: &[Value]) -> Option<Vec<Vec<Value>>> {
+    let (mut direction, mut wrap) = (None, None);
+    for value in values {
+        match *value {
+            _ if is_keyword_of(value, &["row", "row-reverse", "column", "column-reverse"])
+                && direction.is_none() =>
+            {
+                direction = Some(value.clone())
+            }
+            _ if is_keyword_of(value, &["nowrap", "wrap", "wrap-reverse"]) && wrap.is_none() => {
+                wrap = Some(value.clone())
+            }
+            _ => return None,
+        }
+    }
+    Some(vec![
+        vec![direction.unwrap_or_else(|| keyword("row"))],
+        vec![wrap.unwrap_or_else(|| keyword("nowrap"))],
+    ])
+}
+
 fn valid_ident_char(c: char) -> bool {
     // TODO: other char codes?
     c.is_alphanumeric() || c == '-' || c == '_'
@@ -258,6 +1201,15 @@ fn valid_alpha_percent_char(c: char) -> bool {
     c.is_alphanumeric() || c == '%'
 }
 
+/// Converts an alpha value, which is a number in [0, 1] or a percentage, into [0, 1].
+fn alpha_value(&(f, ref unit): &(f64, String)) -> f64 {
+    if unit == "%" {
+        f / 100.0
+    } else {
+        f
+    }
+}
+
 fn valid_hex_char(c: char) -> bool {
     // TODO: other char codes?
     match c.to_ascii_lowercase() {
@@ -395,6 +1347,7 @@ impl Parser {
             tag_name: None,
             id: None,
             class: HashSet::new(),
+            pseudo_element: None,
         };
         while !self.eof() {
             match self.next_char()? {
@@ -410,9 +1363,11 @@ impl Parser {
                     // universal selector
                     self.consume_char()?;
                 }
-                ':' => {
-                    self.parse_pseudo_class_or_element()?;
-                }
+                ':' => match self.parse_pseudo_class_or_element()? {
+                    Some(Ok(pseudo_element)) => selector.pseudo_element = Some(pseudo_element),
+                    Some(Err(())) => unsupported_feature = true,
+                    None => {}
+                },
                 '[' => {
                     unsupported_feature = self.parse_attribute().is_err();
                 }
@@ -429,18 +1384,27 @@ impl Parser {
         }
     }
 
-    // TODO: Implement correctly
-    fn parse_pseudo_class_or_element(&mut self) -> Result<(), ()> {
+    /// Returns None for a pseudo-class, and the pseudo-element, or Err if it's unsupported.
+    // TODO: Pseudo-classes are ignored, i.e. treated as always matching.
+    fn parse_pseudo_class_or_element(&mut self) -> Result<Option<Result<PseudoElement, ()>>, ()> {
         assert_eq!(self.skip_char_if_any(':')?, true); // pseudo-class
-        self.skip_char_if_any(':')?; //pseudo-element
+        let double_colon = self.skip_char_if_any(':')?; //pseudo-element
         self.consume_whitespace()?;
-        self.parse_identifier()?;
+        let name = self.parse_identifier()?.to_lowercase();
         self.consume_whitespace()?;
         if self.skip_char_if_any('(')? {
             self.consume_while(|c| c != ')')?;
             assert_eq!(self.consume_char()?, ')');
         }
-        Ok(())
+        Ok(match name.as_str() {
+            // CSS 2 pseudo-elements can be written with a single colon.
+            "before" => Some(Ok(PseudoElement::Before)),
+            "after" => Some(Ok(PseudoElement::After)),
+            "marker" if double_colon => Some(Ok(PseudoElement::Marker)),
+            "first-line" | "first-letter" => Some(Err(())),
+            _ if double_colon => Some(Err(())),
+            _ => None,
+        })
     }
 
     // TODO: Implement correctly
@@ -464,7 +1428,7 @@ impl Parser {
             }
             declarations.push(self.parse_declaration()?);
         }
-        Ok(declarations)
+        Ok(expand_shorthands(declarations))
     }
 
     fn parse_declaration(&mut self) -> Result<Declaration, ()> {
@@ -472,12 +1436,18 @@ impl Parser {
         self.consume_whitespace()?;
         assert_eq!(self.consume_char()?, ':');
         self.consume_whitespace()?;
-        let values = self.parse_values()?;
+        let mut values = self.parse_values()?;
         self.consume_whitespace()?;
 
+        let important = values.last() == Some(&Value::Keyword("!important".to_string()));
+        if important {
+            values.pop();
+        }
+
         Ok(Declaration {
             name: property_name,
             values: values,
+            important: important,
         })
     }
 
@@ -519,16 +1489,39 @@ impl Parser {
             '-' if self.next2_char()?.is_numeric() => self.parse_length(),
             '.' | '0'...'9' => self.parse_length(),
             '#' => self.parse_color(),
-            '\"' | '\'' => self.parse_string(),
+            '\"' | '\'' => Ok(Value::Str(self.parse_string()?)),
+            // Separates values in some shorthands, e.g. `font: 12px/1.5 serif`.
+            '/' => {
+                self.consume_char()?;
+                Ok(Value::Keyword("/".to_string()))
+            }
             _ => {
-                self.skip_char_if_any('!')?; // TODO: Is this correct?
+                let bang = self.skip_char_if_any('!')?;
+                if bang {
+                    self.consume_whitespace()?;
+                }
                 self.skip_char_if_any('\\')?; // TODO: Is this correct?
 
                 let ident = self.parse_identifier()?;
+                if bang {
+                    return if ident.eq_ignore_ascii_case("important") {
+                        Ok(Value::Keyword("!important".to_string()))
+                    } else {
+                        Err(())
+                    };
+                }
+                if ident.is_empty() && self.next_char()? != '(' {
+                    // Skip the unexpected character not to loop forever.
+                    self.consume_char()?;
+                    return Err(());
+                }
                 match ident.as_str() {
-                    "rgb" => self.parse_rgb_color(),
-                    "rgba" => self.parse_rgba_color(),
+                    "rgb" | "rgba" => self.parse_rgb_color(),
+                    "hsl" | "hsla" => self.parse_hsl_color(),
                     "url" => self.parse_url(),
+                    "attr" | "counter" | "counters" | "repeat" | "minmax" | "fit-content" => {
+                        self.parse_function_arguments(ident)
+                    }
                     _ if !self.eof() && self.next_char()? == '(' => {
                         // TODO: Unsupported functions are ignored.
                         let mut nest = 0;
@@ -574,20 +1567,78 @@ impl Parser {
         n
     }
 
-    fn parse_string(&mut self) -> Result<Value, ()> {
+    fn parse_string(&mut self) -> Result<String, ()> {
         let quote = self.consume_char()?;
-        self.consume_while(|c| c != quote)?;
-        assert_eq!(self.consume_char()?, quote);
-        // TODO: Implement correctly
-        Ok(Value::Num(0.0))
+        let mut string = "".to_string();
+        loop {
+            match self.consume_char()? {
+                c if c == quote => break,
+                '\\' => match self.next_char()? {
+                    // An escaped newline continues the string.
+                    '\n' => {
+                        self.consume_char()?;
+                    }
+                    c if c.is_digit(16) => string.push(self.parse_hex_escape()?),
+                    _ => string.push(self.consume_char()?),
+                },
+                c => string.push(c),
+            }
+        }
+        Ok(string)
+    }
+
+    /// Parses the code point of an escape like `\f101`, which is followed by an optional space.
+    /// ref. https://www.w3.org/TR/css-syntax-3/#consume-escaped-code-point
+    fn parse_hex_escape(&mut self) -> Result<char, ()> {
+        let mut hex = "".to_string();
+        while hex.len() < 6 && !self.eof() && self.next_char()?.is_digit(16) {
+            hex.push(self.consume_char()?);
+        }
+        if !self.eof() && self.next_char()?.is_whitespace() {
+            self.consume_char()?;
+        }
+        Ok(u32::from_str_radix(hex.as_str(), 16)
+            .ok()
+            .and_then(::std::char::from_u32)
+            .filter(|&c| c != '\0')
+            .unwrap_or('\u{fffd}'))
+    }
+
+    /// Parses the arguments of a function, e.g. `(item, ". ")` of `counters(item, ". ")`.
+    fn parse_function_arguments(&mut self, name: String) -> Result<Value, ()> {
+        assert_eq!(self.consume_char()?, '(');
+        let mut arguments = vec![];
+        loop {
+            self.consume_whitespace()?;
+            if self.skip_char_if_any(')')? {
+                break;
+            }
+            if self.skip_char_if_any(',')? {
+                continue;
+            }
+            arguments.push(self.parse_value()?);
+        }
+        Ok(Value::Function(name, arguments))
     }
 
     fn parse_unit(&mut self) -> Result<Unit, ()> {
         match &*self.parse_identifier_percent()? {
             "px" => Ok(Unit::Px),
             "pt" => Ok(Unit::Pt),
+            "pc" => Ok(Unit::Pc),
+            "in" => Ok(Unit::In),
+            "cm" => Ok(Unit::Cm),
+            "mm" => Ok(Unit::Mm),
             "%" => Ok(Unit::Percent),
             "em" => Ok(Unit::Em),
+            "rem" => Ok(Unit::Rem),
+            "ex" => Ok(Unit::Ex),
+            "ch" => Ok(Unit::Ch),
+            "vw" => Ok(Unit::Vw),
+            "vh" => Ok(Unit::Vh),
+            "vmin" => Ok(Unit::Vmin),
+            "vmax" => Ok(Unit::Vmax),
+            "fr" => Ok(Unit::Fr),
             u => {
                 println!("unrecognized unit: {}", u);
                 // Unrecognized units are treated as Px
@@ -596,70 +1647,148 @@ impl Parser {
         }
     }
 
-    fn parse_rgb_color(&mut self) -> Result<Value, ()> {
+    /// Parses the arguments of `rgb()`, `rgba()`, `hsl()` and `hsla()`.
+    /// Both the legacy comma-separated syntax and the space-separated syntax with `/ alpha` are
+    /// accepted. Returns the components with their units (e.g. "%", "deg") and the alpha.
+    fn parse_color_function_args(
+        &mut self,
+    ) -> Result<(Vec<(f64, String)>, Option<(f64, String)>), ()> {
         assert_eq!(self.consume_char_ignore_whitescape()?, '(');
-        let r = self.parse_float()?;
-        assert_eq!(self.consume_char_ignore_whitescape()?, ',');
-        let g = self.parse_float()?;
-        assert_eq!(self.consume_char_ignore_whitescape()?, ',');
-        let b = self.parse_float()?;
-        assert_eq!(self.consume_char_ignore_whitescape()?, ')');
-        Ok(Value::Color(Color {
-            r: r as u8,
-            g: g as u8,
-            b: b as u8,
-            a: 255,
-        }))
+        let mut components = vec![];
+        let mut alpha = None;
+        let mut slash_appeared = false;
+        loop {
+            self.consume_whitespace()?;
+            match self.next_char()? {
+                ')' => {
+                    self.consume_char()?;
+                    break;
+                }
+                ',' => {
+                    self.consume_char()?;
+                }
+                '/' => {
+                    self.consume_char()?;
+                    slash_appeared = true;
+                }
+                c if c == '-' || c == '+' || c == '.' || c.is_digit(10) => {
+                    let num = self
+                        .consume_while(|c| c == '-' || c == '+' || c == '.' || c.is_digit(10))?
+                        .parse()
+                        .or_else(|_| Err(()))?;
+                    let unit = self.parse_identifier_percent()?;
+                    if slash_appeared {
+                        alpha = Some((num, unit));
+                    } else {
+                        components.push((num, unit));
+                    }
+                }
+                _ => {
+                    // Unsupported arguments such as `none` or `var()`. Skip the whole function.
+                    let mut nest = 1;
+                    self.consume_while(|c| {
+                        match c {
+                            '(' => nest += 1,
+                            ')' => nest -= 1,
+                            _ => {}
+                        }
+                        nest > 0
+                    })?;
+                    self.skip_char_if_any(')')?;
+                    return Err(());
+                }
+            }
+        }
+        // The legacy syntax puts the alpha as the fourth component.
+        if alpha.is_none() && components.len() == 4 {
+            alpha = components.pop();
+        }
+        if components.len() != 3 {
+            return Err(());
+        }
+        Ok((components, alpha))
     }
 
-    fn parse_rgba_color(&mut self) -> Result<Value, ()> {
-        assert_eq!(self.consume_char_ignore_whitescape()?, '(');
-        let r = self.parse_float()?;
-        assert_eq!(self.consume_char_ignore_whitescape()?, ',');
-        let g = self.parse_float()?;
-        assert_eq!(self.consume_char_ignore_whitescape()?, ',');
-        let b = self.parse_float()?;
-        assert_eq!(self.consume_char_ignore_whitescape()?, ',');
-        let a = self.parse_float()?;
-        assert_eq!(self.consume_char_ignore_whitescape()?, ')');
+    fn parse_rgb_color(&mut self) -> Result<Value, ()> {
+        let (components, alpha) = self.parse_color_function_args()?;
+        let to_u8 = |&(f, ref unit): &(f64, String)| {
+            if unit == "%" {
+                to_color_component(f / 100.0)
+            } else {
+                f.max(0.0).min(255.0).round() as u8
+            }
+        };
         Ok(Value::Color(Color {
-            r: r as u8,
-            g: g as u8,
-            b: b as u8,
-            a: (255.0 * a) as u8,
+            r: to_u8(&components[0]),
+            g: to_u8(&components[1]),
+            b: to_u8(&components[2]),
+            a: alpha.map_or(255, |alpha| to_color_component(alpha_value(&alpha))),
         }))
     }
 
+    fn parse_hsl_color(&mut self) -> Result<Value, ()> {
+        let (components, alpha) = self.parse_color_function_args()?;
+        let (h, ref h_unit) = components[0];
+        let hue = match h_unit.as_str() {
+            "" | "deg" => h,
+            "rad" => h.to_degrees(),
+            "grad" => h * 360.0 / 400.0,
+            "turn" => h * 360.0,
+            _ => return Err(()),
+        };
+        Ok(Value::Color(Color::from_hsla(
+            hue,
+            components[1].0 / 100.0,
+            components[2].0 / 100.0,
+            alpha.map_or(1.0, |alpha| alpha_value(&alpha)),
+        )))
+    }
+
     fn parse_url(&mut self) -> Result<Value, ()> {
-        // TODO: Implement correctly
         assert_eq!(self.consume_char_ignore_whitescape()?, '(');
-        self.consume_while(|c| c != ')')?;
+        self.consume_whitespace()?;
+        let url = match self.next_char()? {
+            '\"' | '\'' => self.parse_string()?,
+            _ => self.consume_while(|c| c != ')' && !c.is_whitespace())?,
+        };
         assert_eq!(self.consume_char_ignore_whitescape()?, ')');
-        Ok(Value::Num(0.0))
+        Ok(Value::Url(url))
     }
 
     fn parse_color(&mut self) -> Result<Value, ()> {
         assert_eq!(self.consume_char()?, '#');
         let hex_str = self.parse_hex_num()?;
-        let (r, g, b) = match hex_str.len() {
-            3 => {
-                let r = u8::from_str_radix(&hex_str[0..1], 16).unwrap();
-                let g = u8::from_str_radix(&hex_str[1..2], 16).unwrap();
-                let b = u8::from_str_radix(&hex_str[2..3], 16).unwrap();
-                (r * 16 + r, g * 16 + g, b * 16 + b)
-            }
-            6 => (
-                u8::from_str_radix(&hex_str[0..2], 16).unwrap(),
-                u8::from_str_radix(&hex_str[2..4], 16).unwrap(),
-                u8::from_str_radix(&hex_str[4..6], 16).unwrap(),
+        let digit = |i: usize, len: usize| u8::from_str_radix(&hex_str[i..i + len], 16).unwrap();
+        let (r, g, b, a) = match hex_str.len() {
+            // #rgb and #rgba
+            3 | 4 => (
+                digit(0, 1) * 0x11,
+                digit(1, 1) * 0x11,
+                digit(2, 1) * 0x11,
+                if hex_str.len() == 4 {
+                    digit(3, 1) * 0x11
+                } else {
+                    0xff
+                },
+            ),
+            // #rrggbb and #rrggbbaa
+            6 | 8 => (
+                digit(0, 2),
+                digit(2, 2),
+                digit(4, 2),
+                if hex_str.len() == 8 {
+                    digit(6, 2)
+                } else {
+                    0xff
+                },
             ),
-            _ => panic!(),
+            _ => return Err(()),
         };
         Ok(Value::Color(Color {
             r: r,
             g: g,
             b: b,
-            a: 255,
+            a: a,
         }))
     }
 
@@ -734,6 +1863,33 @@ impl Parser {
     }
 }
 
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Value::Keyword(ref kw) => write!(f, "{}", kw),
+            &Value::Length(ref num, ref unit) => write!(f, "{}{}", num, unit.as_str()),
+            &Value::Num(ref num) => write!(f, "{}", num),
+            &Value::Color(ref color) => write!(
+                f,
+                "rgba({}, {}, {}, {})",
+                color.r, color.g, color.b, color.a
+            ),
+            &Value::Str(ref string) => write!(f, "\"{}\"", string.replace('"', "\\\"")),
+            &Value::Url(ref url) => write!(f, "url(\"{}\")", url.replace('"', "\\\"")),
+            &Value::Function(ref name, ref arguments) => {
+                try!(write!(f, "{}(", name));
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, ", "));
+                    }
+                    try!(write!(f, "{}", argument));
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
 impl fmt::Display for Stylesheet {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for rule in &self.rules {
@@ -761,6 +1917,12 @@ impl fmt::Display for Stylesheet {
                         if universal {
                             try!(write!(f, "*"))
                         }
+                        match selector.pseudo_element {
+                            Some(PseudoElement::Before) => try!(write!(f, "::before")),
+                            Some(PseudoElement::After) => try!(write!(f, "::after")),
+                            Some(PseudoElement::Marker) => try!(write!(f, "::marker")),
+                            None => {}
+                        }
                         Ok(())
                     }
 
@@ -788,21 +1950,7 @@ impl fmt::Display for Stylesheet {
             for decl in &rule.declarations {
                 try!(write!(f, "  {}:", decl.name,));
                 for value in &decl.values {
-                    try!(write!(
-                        f,
-                        " {}",
-                        match value {
-                            &Value::Keyword(ref kw) => kw.clone(),
-                            &Value::Length(ref f, Unit::Px) => format!("{}px", f),
-                            &Value::Length(ref f, Unit::Pt) => format!("{}pt", f),
-                            &Value::Length(ref f, Unit::Percent) => format!("{}%", f),
-                            &Value::Length(ref f, Unit::Em) => format!("{}em", f),
-                            &Value::Num(ref f) => format!("{}", f),
-                            &Value::Color(ref color) => {
-                                format!("rgba({}, {}, {}, {})", color.r, color.g, color.b, color.a)
-                            }
-                        }
-                    ))
+                    try!(write!(f, " {}", value));
                 }
                 try!(writeln!(f));
             }
@@ -836,6 +1984,7 @@ fn test1() {
                     tag_name: None,
                     id: Some("id".to_string()),
                     class: HashSet::new(),
+                    pseudo_element: None,
                 }),
                 Selector::Simple(SimpleSelector {
                     tag_name: None,
@@ -845,17 +1994,20 @@ fn test1() {
                         h.insert("class".to_string());
                         h
                     },
+                    pseudo_element: None,
                 }),
                 Selector::Child(
                     SimpleSelector {
                         tag_name: Some("p".to_string()),
                         id: None,
                         class: HashSet::new(),
+                        pseudo_element: None,
                     },
                     Box::new(Selector::Simple(SimpleSelector {
                         tag_name: Some("a".to_string()),
                         id: None,
                         class: HashSet::new(),
+                        pseudo_element: None,
                     })),
                 ),
                 Selector::Descendant(
@@ -863,49 +2015,59 @@ fn test1() {
                         tag_name: Some("div".to_string()),
                         id: None,
                         class: HashSet::new(),
+                        pseudo_element: None,
                     },
                     Box::new(Selector::Simple(SimpleSelector {
                         tag_name: Some("p".to_string()),
                         id: None,
                         class: HashSet::new(),
+                        pseudo_element: None,
                     })),
                 ),
                 Selector::Simple(SimpleSelector {
                     tag_name: Some("div".to_string()),
                     id: None,
                     class: HashSet::new(),
+                    pseudo_element: None,
                 }),
                 Selector::Simple(SimpleSelector {
                     tag_name: Some("h1".to_string()),
                     id: None,
                     class: HashSet::new(),
+                    pseudo_element: None,
                 }),
                 Selector::Simple(SimpleSelector {
                     tag_name: None,
                     id: None,
                     class: HashSet::new(),
+                    pseudo_element: None,
                 }),
             ],
             declarations: vec![
                 Declaration {
                     name: "width".to_string(),
                     values: vec![Value::Length(70.0, Unit::Percent)],
+                    important: false,
                 },
                 Declaration {
                     name: "height".to_string(),
                     values: vec![Value::Length(50.0, Unit::Px)],
+                    important: false,
                 },
                 Declaration {
                     name: "font-weight".to_string(),
                     values: vec![Value::Keyword("bold".to_string())],
+                    important: false,
                 },
                 Declaration {
                     name: "z-index".to_string(),
                     values: vec![Value::Num(2.0)],
+                    important: false,
                 },
                 Declaration {
                     name: "font-size".to_string(),
                     values: vec![Value::Length(10.0, Unit::Pt)],
+                    important: false,
                 },
                 Declaration {
                     name: "color".to_string(),
@@ -917,6 +2079,7 @@ fn test1() {
                             a: 0xff,
                         }),
                     ],
+                    important: false,
                 },
                 Declaration {
                     name: "background-color".to_string(),
@@ -928,28 +2091,32 @@ fn test1() {
                             a: 0xff,
                         }),
                     ],
+                    important: false,
                 },
                 Declaration {
                     name: "content".to_string(),
-                    values: vec![Value::Num(0.0)],
+                    values: vec![Value::Str("aa".to_string())],
+                    important: false,
                 },
                 Declaration {
                     name: "background-image".to_string(),
-                    values: vec![Value::Num(0.0)],
+                    values: vec![Value::Url("aaa".to_string())],
+                    important: false,
                 },
                 Declaration {
                     name: "unknown".to_string(),
                     values: vec![Value::Keyword("unknown".to_string())],
+                    important: false,
                 },
             ],
         },
     ];
-    assert_eq!(stylesheet, Stylesheet { rules: rules });
+    assert_eq!(stylesheet, Stylesheet::new(rules));
 }
 
 #[test]
 fn test2() {
-    let src = "color: black; background: white; ";
+    let src = "color: black; background-color: white; ";
     let decls = parse_attr_style(src.to_string());
 
     assert_eq!(
@@ -958,10 +2125,12 @@ fn test2() {
             Declaration {
                 name: "color".to_string(),
                 values: vec![Value::Keyword("black".to_string())],
+                important: false,
             },
             Declaration {
-                name: "background".to_string(),
+                name: "background-color".to_string(),
                 values: vec![Value::Keyword("white".to_string())],
+                important: false,
             },
         ]
     );
@@ -969,7 +2138,7 @@ fn test2() {
 
 #[test]
 fn test_rgb_rgba() {
-    let src = "color: rgb(1, 2, 3); background: rgba(250, 1, 250, 0.3); ";
+    let src = "color: rgb(1, 2, 3); background-color: rgba(250, 1, 250, 0.3); ";
     let decls = parse_attr_style(src.to_string());
 
     assert_eq!(
@@ -985,17 +2154,19 @@ fn test_rgb_rgba() {
                         a: 255,
                     }),
                 ],
+                important: false,
             },
             Declaration {
-                name: "background".to_string(),
+                name: "background-color".to_string(),
                 values: vec![
                     Value::Color(Color {
                         r: 250,
                         g: 1,
                         b: 250,
-                        a: (255.0 * 0.3) as u8,
+                        a: (255.0 * 0.3_f64).round() as u8,
                     }),
                 ],
+                important: false,
             },
         ]
     );
@@ -1026,15 +2197,44 @@ fn test_at_mark_rules() {
 
 #[test]
 fn test_pseudo() {
-    parse(
+    let stylesheet = parse(
         "
         div:hover { 
         }
         div::first-line {
         }
+        p::before, a:after {
+          content: \"\\f101 x\\\"\" attr(title) counters(item, \". \") url(a.png);
+        }
         "
             .to_string(),
     );
+    assert!(stylesheet.rules[1].selectors.is_empty());
+    let pseudo_elements: Vec<Option<PseudoElement>> = stylesheet.rules[2]
+        .selectors
+        .iter()
+        .map(|selector| selector.rightmost().pseudo_element)
+        .collect();
+    assert_eq!(
+        pseudo_elements,
+        vec![Some(PseudoElement::Before), Some(PseudoElement::After)]
+    );
+    assert!(stylesheet.pseudo_elements.contains(&PseudoElement::After));
+    assert!(!stylesheet.pseudo_elements.contains(&PseudoElement::Marker));
+    assert_eq!(stylesheet.rules[2].selectors[0].specificity(), (0, 0, 2));
+    let kw = |k: &str| Value::Keyword(k.to_string());
+    assert_eq!(
+        stylesheet.rules[2].declarations[0].values,
+        vec![
+            Value::Str("\u{f101}x\"".to_string()),
+            Value::Function("attr".to_string(), vec![kw("title")]),
+            Value::Function(
+                "counters".to_string(),
+                vec![kw("item"), Value::Str(". ".to_string())],
+            ),
+            Value::Url("a.png".to_string()),
+        ]
+    );
 }
 
 #[test]
@@ -1047,3 +2247,199 @@ fn test_attribute() {
             .to_string(),
     );
 }
+
+#[test]
+fn test_units() {
+    let decls = parse_attr_style(
+        "a: 1in; b: 2.54cm; c: 10mm; d: 1pc; e: 2em; f: 1rem; g: 50vw; h: 3vmin;".to_string(),
+    );
+    let values: Vec<Value> = decls.into_iter().map(|d| d.values[0].clone()).collect();
+    assert_eq!(
+        values,
+        vec![
+            Value::Length(1.0, Unit::In),
+            Value::Length(2.54, Unit::Cm),
+            Value::Length(10.0, Unit::Mm),
+            Value::Length(1.0, Unit::Pc),
+            Value::Length(2.0, Unit::Em),
+            Value::Length(1.0, Unit::Rem),
+            Value::Length(50.0, Unit::Vw),
+            Value::Length(3.0, Unit::Vmin),
+        ]
+    );
+
+    assert_eq!(values[0].to_px(), Some(96.0));
+    assert_eq!(values[1].to_px(), Some(96.0));
+    assert_eq!(values[3].to_px(), Some(16.0));
+    assert_eq!(values[6].to_px(), None);
+
+    let ctx = LengthContext {
+        font_size: 10.0,
+        root_font_size: 20.0,
+        viewport_width: 800.0,
+        viewport_height: 600.0,
+    };
+    assert_eq!(
+        values[4].resolve_relative_length(&ctx),
+        Value::Length(20.0, Unit::Px)
+    );
+    assert_eq!(
+        values[5].resolve_relative_length(&ctx),
+        Value::Length(20.0, Unit::Px)
+    );
+    assert_eq!(
+        values[6].resolve_relative_length(&ctx),
+        Value::Length(400.0, Unit::Px)
+    );
+    assert_eq!(
+        values[7].resolve_relative_length(&ctx),
+        Value::Length(18.0, Unit::Px)
+    );
+}
+
+#[test]
+fn test_colors() {
+    let src = "a: #1234; b: #11223344; c: hsl(120, 100%, 25%); d: hsla(0.5turn 100% 50% / 50%);
+               e: rgb(255 0 0 / 0.5); f: rgb(100%, 0%, 0%); g: rebeccapurple; h: transparent;
+               i: currentColor; j: #12345;";
+    let decls = parse_attr_style(src.to_string());
+    let colors: Vec<Option<(u8, u8, u8, u8)>> = decls
+        .iter()
+        .map(|d| d.values.get(0).and_then(|v| v.to_color()))
+        .map(|c| c.map(|c| (c.r, c.g, c.b, c.a)))
+        .collect();
+    assert_eq!(
+        colors,
+        vec![
+            Some((0x11, 0x22, 0x33, 0x44)),
+            Some((0x11, 0x22, 0x33, 0x44)),
+            Some((0x00, 0x80, 0x00, 0xff)),
+            Some((0x00, 0xff, 0xff, 0x80)),
+            Some((0xff, 0x00, 0x00, 0x80)),
+            Some((0xff, 0x00, 0x00, 0xff)),
+            Some((0x66, 0x33, 0x99, 0xff)),
+            Some((0x00, 0x00, 0x00, 0x00)),
+            None,
+        ]
+    );
+    // The invalid color leaves `j` without a value, so it's dropped.
+    assert!(decls.iter().all(|d| d.name != "j"));
+    assert!(decls[8].values[0].is_current_color());
+}
+
+#[test]
+fn test_shorthands() {
+    let decls = parse_attr_style(
+        "margin: 1px 2px; border: 3px solid red; font: bold 12px/1.5 serif; \
+         background: blue; list-style: none; border-radius: 1px / 2px; border-color: 10px"
+            .to_string(),
+    );
+    let find = |name: &str| {
+        decls
+            .iter()
+            .find(|decl| decl.name == name)
+            .map(|decl| decl.values.clone())
+    };
+    let px = |f| vec![Value::Length(f, Unit::Px)];
+    let kw = |k: &str| vec![Value::Keyword(k.to_string())];
+
+    assert_eq!(find("margin"), None);
+    assert_eq!(find("margin-top"), Some(px(1.0)));
+    assert_eq!(find("margin-right"), Some(px(2.0)));
+    assert_eq!(find("margin-bottom"), Some(px(1.0)));
+    assert_eq!(find("margin-left"), Some(px(2.0)));
+
+    assert_eq!(find("border-left-width"), Some(px(3.0)));
+    assert_eq!(find("border-bottom-style"), Some(kw("solid")));
+    assert_eq!(find("border-top-color"), Some(kw("red")));
+
+    assert_eq!(find("font-weight"), Some(kw("bold")));
+    assert_eq!(find("font-style"), Some(kw("normal")));
+    assert_eq!(find("font-size"), Some(px(12.0)));
+    assert_eq!(find("line-height"), Some(vec![Value::Num(1.5)]));
+    assert_eq!(find("font-family"), Some(kw("serif")));
+
+    assert_eq!(find("background-color"), Some(kw("blue")));
+    assert_eq!(find("background-image"), Some(kw("none")));
+    let background = |source: &str| {
+        parse_attr_style(source.to_string())
+            .into_iter()
+            .map(|decl| (decl.name, decl.values))
+            .collect::<HashMap<String, Vec<Value>>>()
+    };
+    let gradient = background("background: #fff linear-gradient(to right, red, blue) no-repeat");
+    assert_eq!(gradient["background-color"], vec![Value::Color(WHITE)]);
+    assert_eq!(gradient["background-image"], kw("linear-gradient"));
+    assert_eq!(gradient["background-repeat"], kw("no-repeat"));
+    let layers = background("background: url(a.png) inherit, url(b.png) blue");
+    assert_eq!(layers["background-color"], kw("blue"));
+    assert_eq!(layers["background-image"], vec![Value::Url("b.png".to_string())]);
+
+    assert_eq!(find("list-style-type"), Some(kw("none")));
+    assert_eq!(find("list-style-image"), Some(kw("none")));
+    assert_eq!(find("list-style-position"), Some(kw("outside")));
+
+    assert_eq!(
+        find("border-top-left-radius"),
+        Some(vec![
+            Value::Length(1.0, Unit::Px),
+            Value::Length(2.0, Unit::Px)
+        ])
+    );
+
+    // `border-color` with a non-color value is invalid and dropped, keeping the colors of `border`.
+    assert_eq!(find("border-right-color"), Some(kw("red")));
+    assert_eq!(
+        decls
+            .iter()
+            .filter(|decl| decl.name == "border-right-color")
+            .count(),
+        1
+    );
+
+    let decls = parse_attr_style(
+        "flex: 2; flex-flow: wrap column; gap: 1px 2%; flex: none 3; flex: auto; overflow: clip; \
+         word-wrap: break-word"
+            .to_string(),
+    );
+    let find = |name: &str| {
+        decls
+            .iter()
+            .filter(|decl| decl.name == name)
+            .map(|decl| decl.values.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        find("flex-grow"),
+        vec![vec![Value::Num(2.0)], vec![Value::Num(1.0)]]
+    );
+    assert_eq!(
+        find("flex-basis"),
+        vec![vec![Value::Length(0.0, Unit::Percent)], kw("auto")]
+    );
+    assert_eq!(find("flex-direction"), vec![kw("column")]);
+    assert_eq!(find("flex-wrap"), vec![kw("wrap")]);
+    assert_eq!(find("row-gap"), vec![px(1.0)]);
+    assert_eq!(find("column-gap"), vec![vec![Value::Length(2.0, Unit::Percent)]]);
+    assert_eq!(find("overflow-x"), vec![kw("clip")]);
+    assert_eq!(find("overflow-y"), vec![kw("clip")]);
+    assert_eq!(find("overflow-wrap"), vec![kw("break-word")]);
+
+    let decls = parse_attr_style(
+        "grid-area: a; grid-row: 2 / span 3; grid-column: b; grid-area: 1 / 2".to_string(),
+    );
+    let find = |name: &str| {
+        decls
+            .iter()
+            .filter(|decl| decl.name == name)
+            .map(|decl| decl.values.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(find("grid-row-start"), vec![kw("a"), vec![Value::Num(2.0)], vec![Value::Num(1.0)]]);
+    assert_eq!(
+        find("grid-row-end"),
+        vec![kw("a"), vec![keyword("span"), Value::Num(3.0)], kw("auto")]
+    );
+    assert_eq!(find("grid-column-start"), vec![kw("a"), kw("b"), vec![Value::Num(2.0)]]);
+    assert_eq!(find("grid-column-end"), vec![kw("a"), kw("b"), kw("auto")]);
+}
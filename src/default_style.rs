@@ -1,198 +1,62 @@
 use css::*;
 
-use std::collections::HashSet;
-use std::cell::RefCell;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::sync::Mutex;
+use std::sync::RwLock;
 
 pub fn default_style() -> Stylesheet {
-    Stylesheet {
-        rules: DEFAULT_RULES.with(|default_rules| default_rules.borrow().clone()),
-    }
+    Stylesheet::new(DEFAULT_RULES.read().unwrap().clone())
 }
 
-thread_local!(
-    pub static DEFAULT_RULES: RefCell<Vec<Rule>> = {
-        let mut rules = vec![];
-        rule_universal(&mut rules);
-        rule_html(&mut rules);
-        // rule_body(&mut rules);
-        rule_span(&mut rules);
-        rule_h1(&mut rules);
-        rule_h2(&mut rules);
-        rule_h3(&mut rules);
-        rule_a(&mut rules);
-        rule_img(&mut rules);
-        rule_b(&mut rules);
-        rule_i(&mut rules);
-        rule_button(&mut rules);
-        rule_style(&mut rules);
-        rule_title(&mut rules);
-        rule_script(&mut rules);
-        RefCell::new(rules)
-    }
-);
-
-macro_rules! tag_name { ($name:expr) => {
-    Selector::Simple(SimpleSelector {
-        tag_name: Some($name.to_string()), id: None, class: HashSet::new() })
-}}
-
-macro_rules! decl { ($name:expr, $( $val:expr ),*) => {
-    Declaration {
-        name: $name.to_string(),
-        values: vec![$($val)*],
-    }
-}}
-
-macro_rules! keyword { ($str:expr) => { Value::Keyword($str.to_string()) }}
-macro_rules! len_px  { ($val:expr) => { Value::Length($val, Unit::Px) }}
-// macro_rules! num     { ($val:expr) => { Value::Num($val) }}
-macro_rules! color   { ($clr:expr) => { Value::Color($clr) }}
-
-fn rule_universal(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![
-            Selector::Simple(SimpleSelector {
-                tag_name: None,
-                id: None,
-                class: HashSet::new(),
-            }),
-        ],
-        declarations: vec![decl!("display", keyword!("block"))],
-    });
-}
-
-fn rule_html(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("html")],
-        declarations: vec![
-            // decl!("width", keyword!("auto")),
-            // decl!("padding", len_px!(0f64)),
-            // decl!("margin", len_px!(0f64)),
-            decl!("background", color!(WHITE)),
-        ],
-    });
-}
-
-// fn rule_body(rules: &mut Vec<Rule>) {
-//     rules.push(Rule {
-//         selectors: vec![tag_name!("body")],
-//         declarations: vec![
-//             decl!("padding", len_px!(0f64)),
-//             decl!("margin", len_px!(0f64)),
-//         ],
-//     });
-// }
-
-fn rule_span(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("span")],
-        declarations: vec![decl!("display", keyword!("inline"))],
-    });
-}
-
-fn rule_h1(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("h1")],
-        declarations: vec![
-            decl!("font-size", len_px!(30f64)),
-            decl!("font-weight", keyword!("bold")),
-            decl!("padding", len_px!(10f64)),
-        ],
-    });
-}
-
-fn rule_h2(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("h2")],
-        declarations: vec![
-            decl!("font-size", len_px!(24f64)),
-            decl!("font-weight", keyword!("bold")),
-            decl!("padding", len_px!(10f64)),
-        ],
-    });
-}
-
-fn rule_h3(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("h3")],
-        declarations: vec![
-            decl!("font-size", len_px!(19f64)),
-            decl!("font-weight", keyword!("bold")),
-            decl!("padding", len_px!(10f64)),
-        ],
-    });
-}
-
-fn rule_a(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("a")],
-        declarations: vec![
-            decl!("display", keyword!("inline")),
-            decl!(
-                "color",
-                color!(Color {
-                    r: 0,
-                    g: 0,
-                    b: 0xee,
-                    a: 0xff,
-                })
-            ),
-            decl!("text-decoration", keyword!("underline")),
-        ],
-    });
-}
-
-fn rule_img(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("img")],
-        declarations: vec![decl!("display", keyword!("inline"))],
-    });
+/// Returns the user stylesheet, which is empty unless `load_user_style` is called.
+pub fn user_style() -> Stylesheet {
+    Stylesheet::new(USER_RULES.read().unwrap().clone())
 }
 
-fn rule_b(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("b")],
-        declarations: vec![
-            decl!("display", keyword!("inline")),
-            decl!("font-weight", keyword!("bold")),
-        ],
-    });
+/// Replaces the built-in user agent stylesheet with the CSS file at `path`.
+pub fn load_default_style(path: &Path) -> io::Result<()> {
+    let rules = parse(read_file(path)?).rules;
+    *DEFAULT_RULES.write().unwrap() = rules;
+    Ok(())
 }
 
-fn rule_i(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("i")],
-        declarations: vec![
-            decl!("display", keyword!("inline")),
-            decl!("font-style", keyword!("italic")),
-        ],
-    });
+/// Loads the user stylesheet from the CSS file at `path`.
+pub fn load_user_style(path: &Path) -> io::Result<()> {
+    let rules = parse(read_file(path)?).rules;
+    *USER_RULES.write().unwrap() = rules;
+    Ok(())
 }
 
-fn rule_button(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("button")],
-        declarations: vec![decl!("display", keyword!("inline"))],
-    });
+/// The directory where `ua.css` and `user.css` are looked up when no path is given on the
+/// command line, i.e. `$XDG_CONFIG_HOME/naglfar` or `$HOME/.config/naglfar`.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|config| config.join("naglfar"))
 }
 
-fn rule_style(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("style")],
-        declarations: vec![decl!("display", keyword!("none"))],
-    });
+fn read_file(path: &Path) -> io::Result<String> {
+    let mut source = String::new();
+    File::open(path)?.read_to_string(&mut source)?;
+    Ok(source)
 }
 
-fn rule_title(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("title")],
-        declarations: vec![decl!("display", keyword!("none"))],
-    });
+// The stylesheets are loaded on the main thread before the browser thread lays out the pages, so
+// they are shared by the whole process.
+lazy_static! {
+    pub static ref DEFAULT_RULES: RwLock<Vec<Rule>> =
+        RwLock::new(parse(include_str!("default_style.css").to_string()).rules);
+    pub static ref USER_RULES: RwLock<Vec<Rule>> = RwLock::new(vec![]);
 }
 
-fn rule_script(rules: &mut Vec<Rule>) {
-    rules.push(Rule {
-        selectors: vec![tag_name!("script")],
-        declarations: vec![decl!("display", keyword!("none"))],
-    });
+// The tests run in parallel, so the ones which load or read the user stylesheet hold this lock
+// not to see each other's rules.
+#[cfg(test)]
+lazy_static! {
+    pub static ref USER_STYLE_TEST_LOCK: Mutex<()> = Mutex::new(());
 }
@@ -0,0 +1,794 @@
+use block::{indefinite_block, CollapsibleMargins};
+use dom::NodeType;
+use float::Floats;
+use inline::IntrinsicWidths;
+use layout::{BoxType, Dimensions, LayoutBox};
+use style::{Alignment, ComputedStyle, FlexDirection, FlexWrap, LengthOrPercentageOrAuto};
+
+use std::cmp::{max, min};
+
+use app_units::Au;
+
+/// A flex item while its container is laid out. Sizes are in the main axis unless noted.
+/// ref. https://www.w3.org/TR/css-flexbox-1/#layout-algorithm
+struct FlexItem {
+    // The index in the children of the container.
+    index: usize,
+    // The flex base size and the hypothetical main size of the content box.
+    base_size: Au,
+    hypothetical_size: Au,
+    // The minimum and maximum sizes of the content box. The minimum size is the automatic
+    // minimum size if `min-width` or `min-height` is `auto`.
+    min_size: Au,
+    max_size: Option<Au>,
+    // The sum of the margins, borders and paddings. Auto margins are 0.
+    offset: Au,
+    grow: f64,
+    shrink: f64,
+    target_size: Au,
+    frozen: bool,
+    // The width used to lay out the item in a column container.
+    width: Au,
+    // The outer cross size.
+    cross_size: Au,
+}
+
+impl FlexItem {
+    fn outer_hypothetical_size(&self) -> Au {
+        self.hypothetical_size + self.offset
+    }
+
+    fn outer_target_size(&self) -> Au {
+        self.target_size + self.offset
+    }
+
+    /// Limits the main size `size` by the minimum and maximum sizes. The minimum size wins if
+    /// they conflict.
+    fn clamp(&self, size: Au) -> Au {
+        let size = match self.max_size {
+            Some(max_size) => min(size, max_size),
+            None => size,
+        };
+        max(size, self.min_size)
+    }
+}
+
+struct FlexLine {
+    items: Vec<FlexItem>,
+    cross_size: Au,
+    // The position of the cross start edge in the container.
+    cross_start: Au,
+}
+
+impl LayoutBox {
+    /// Lay out the children of a flex container within its content area. The width of the
+    /// container must be calculated before.
+    /// Sets `self.dimensions.height` to the total content height like `layout_block_children`.
+    /// ref. https://www.w3.org/TR/css-flexbox-1/#layout-algorithm
+    pub fn layout_flex_children(&mut self, viewport: Dimensions) {
+        let style = self.property.computed.clone();
+        let is_row = is_row(&style);
+        let definite_height = match style.height {
+            LengthOrPercentageOrAuto::Length(height) => Some(height),
+            _ => None,
+        };
+
+        let mut containing_block: Dimensions = Default::default();
+        containing_block.content.width = self.dimensions.content.width;
+        containing_block.content.height = definite_height.unwrap_or(Au(0));
+
+        let main_size = if is_row {
+            Some(self.dimensions.content.width)
+        } else {
+            definite_height
+        };
+        let cross_size = if is_row {
+            definite_height
+        } else {
+            Some(self.dimensions.content.width)
+        };
+        let column_gap = style.column_gap.to_au(containing_block.content.width);
+        let row_gap = style.row_gap.to_au(containing_block.content.height);
+        let (main_gap, cross_gap) = if is_row {
+            (column_gap, row_gap)
+        } else {
+            (row_gap, column_gap)
+        };
+        let single_line = style.flex_wrap == FlexWrap::NoWrap || main_size.is_none();
+
+        // A flex container establishes a new formatting context.
+        self.floats = Floats::new();
+
+        // Collect the flex items in `order`. `sort_by_key` is stable, so the items with the same
+        // `order` stay in the document order.
+        let mut indices = vec![];
+        for (i, child) in self.children.iter_mut().enumerate() {
+            match child.box_type {
+                BoxType::Absolute => {
+                    // The static position is the content box origin of the container.
+                    child.dimensions.content.x = Au(0);
+                    child.dimensions.content.y = Au(0);
+                }
+                BoxType::AnonymousBlock if child.is_collapsible_whitespace() => {
+                    child.dimensions = Default::default();
+                }
+                BoxType::BlockNode
+                | BoxType::GridNode
+                | BoxType::TableNode
+                | BoxType::InlineBlockNode
+                | BoxType::Float
+                | BoxType::AnonymousBlock => indices.push(i),
+                _ => {}
+            }
+        }
+        indices.sort_by_key(|&i| self.children[i].property.computed.order);
+
+        // Determine the flex base size and the hypothetical main size of each item.
+        let mut items = vec![];
+        for i in indices {
+            items.push(self.flex_item(i, containing_block, main_size, single_line, viewport));
+        }
+
+        // Collect the items into flex lines.
+        let mut lines = vec![];
+        let mut line: Vec<FlexItem> = vec![];
+        let mut line_size = Au(0);
+        for item in items {
+            let size = item.outer_hypothetical_size();
+            if let Some(main_size) = main_size {
+                if !single_line && !line.is_empty() && line_size + main_gap + size > main_size {
+                    lines.push(line);
+                    line = vec![];
+                }
+            }
+            line_size = if line.is_empty() {
+                size
+            } else {
+                line_size + main_gap + size
+            };
+            line.push(item);
+        }
+        lines.push(line);
+
+        // Resolve the flexible lengths, and lay out the items to find their cross sizes.
+        let mut used_main_size = Au(0);
+        for line in &mut lines {
+            let gaps = main_gap * max(line.len() as i32 - 1, 0);
+            let hypothetical = line.iter()
+                .fold(gaps, |acc, item| acc + item.outer_hypothetical_size());
+            resolve_flexible_lengths(line, main_size.unwrap_or(hypothetical) - gaps);
+            let used = line.iter()
+                .fold(gaps, |acc, item| acc + item.outer_target_size());
+            used_main_size = max(used_main_size, used);
+
+            for item in line.iter_mut() {
+                let child = &mut self.children[item.index];
+                if is_row {
+                    child.layout_flex_item(containing_block, item.target_size, viewport);
+                    item.cross_size = child.dimensions.margin_box().height;
+                } else {
+                    item.cross_size =
+                        item.width + child.horizontal_offset(containing_block.content.width);
+                }
+            }
+        }
+        let main_size = main_size.unwrap_or(used_main_size);
+
+        let mut lines: Vec<FlexLine> = lines
+            .into_iter()
+            .map(|items| FlexLine {
+                cross_size: match cross_size {
+                    Some(cross_size) if single_line => cross_size,
+                    _ => items.iter().fold(Au(0), |acc, item| max(acc, item.cross_size)),
+                },
+                cross_start: Au(0),
+                items: items,
+            })
+            .collect();
+
+        // Align the lines with `align-content`.
+        let cross_gaps = cross_gap * (lines.len() as i32 - 1);
+        let lines_size = lines.iter().fold(cross_gaps, |acc, line| acc + line.cross_size);
+        let cross_size = cross_size.unwrap_or(lines_size);
+        let free_space = cross_size - lines_size;
+        let (mut position, between) = match style.align_content {
+            Alignment::Normal | Alignment::Stretch if free_space > Au(0) && !single_line => {
+                let extra = free_space / lines.len() as i32;
+                for line in &mut lines {
+                    line.cross_size += extra;
+                }
+                (Au(0), Au(0))
+            }
+            alignment => distribute_space(alignment, free_space, lines.len()),
+        };
+        for line in &mut lines {
+            line.cross_start = position;
+            position += line.cross_size + cross_gap + between;
+        }
+
+        // Stretch, lay out and position the items.
+        // Whether the main start, main end, cross start and cross end margins are auto.
+        let auto_margins = |child: &LayoutBox| {
+            let margin = child.property.computed.margin.map(|m| m.is_auto());
+            if is_row {
+                (margin.left, margin.right, margin.top, margin.bottom)
+            } else {
+                (margin.top, margin.bottom, margin.left, margin.right)
+            }
+        };
+        for line in &mut lines {
+            for item in &mut line.items {
+                let child = &mut self.children[item.index];
+                let (_, _, cross_start_auto, cross_end_auto) = auto_margins(child);
+                let alignment = child.property
+                    .computed
+                    .align_self
+                    .unwrap_or(style.align_items);
+                let stretch = (alignment == Alignment::Normal || alignment == Alignment::Stretch)
+                    && !cross_start_auto && !cross_end_auto;
+
+                if is_row {
+                    if stretch && child.property.computed.height.is_auto() {
+                        let d = &mut child.dimensions;
+                        let offset = d.margin_box().height - d.content.height;
+                        d.content.height = max(Au(0), line.cross_size - offset);
+                        item.cross_size = line.cross_size;
+                    }
+                } else {
+                    if stretch && child.property.computed.width.is_auto() {
+                        let offset = item.cross_size - item.width;
+                        item.width = max(Au(0), line.cross_size - offset);
+                        item.cross_size = line.cross_size;
+                    }
+                    child.layout_flex_item(containing_block, item.width, viewport);
+                    child.dimensions.content.height = item.target_size;
+                }
+            }
+
+            // Distribute the free space in the main axis to the auto margins, or by
+            // `justify-content`.
+            let gaps = main_gap * max(line.items.len() as i32 - 1, 0);
+            let free_space = line.items
+                .iter()
+                .fold(main_size - gaps, |acc, item| acc - item.outer_target_size());
+            let auto_margin_count = line.items.iter().fold(0, |acc, item| {
+                let (start, end, _, _) = auto_margins(&self.children[item.index]);
+                acc + start as i32 + end as i32
+            });
+            let (mut position, between) = if auto_margin_count > 0 && free_space > Au(0) {
+                (Au(0), Au(0))
+            } else {
+                distribute_space(style.justify_content, free_space, line.items.len())
+            };
+            let auto_margin = if auto_margin_count > 0 {
+                max(Au(0), free_space / auto_margin_count)
+            } else {
+                Au(0)
+            };
+
+            for item in &line.items {
+                let child = &mut self.children[item.index];
+                let (main_start_auto, main_end_auto, cross_start_auto, cross_end_auto) =
+                    auto_margins(child);
+                let alignment = child.property
+                    .computed
+                    .align_self
+                    .unwrap_or(style.align_items);
+
+                let cross_free_space = line.cross_size - item.cross_size;
+                let cross_position = match (cross_start_auto, cross_end_auto) {
+                    _ if cross_free_space < Au(0) => Au(0),
+                    (true, true) => cross_free_space / 2,
+                    (true, false) => cross_free_space,
+                    (false, true) => Au(0),
+                    (false, false) => match alignment {
+                        Alignment::End => cross_free_space,
+                        Alignment::Center => cross_free_space / 2,
+                        _ => Au(0),
+                    },
+                };
+
+                let d = &mut child.dimensions;
+                {
+                    let (margin_start, margin_end) = if is_row {
+                        (&mut d.margin.left, &mut d.margin.right)
+                    } else {
+                        (&mut d.margin.top, &mut d.margin.bottom)
+                    };
+                    if main_start_auto {
+                        *margin_start = auto_margin;
+                    }
+                    if main_end_auto {
+                        *margin_end = auto_margin;
+                    }
+                }
+                let mut margin_box = d.margin_box();
+                let (main_position, cross_position) = (position, line.cross_start + cross_position);
+                position += item.outer_target_size()
+                    + auto_margin * (main_start_auto as i32 + main_end_auto as i32)
+                    + main_gap + between;
+
+                // Reversed directions are mirrored.
+                let (outer_main_size, outer_cross_size) = if is_row {
+                    (margin_box.width, margin_box.height)
+                } else {
+                    (margin_box.height, margin_box.width)
+                };
+                let main_position = match style.flex_direction {
+                    FlexDirection::RowReverse | FlexDirection::ColumnReverse => {
+                        main_size - main_position - outer_main_size
+                    }
+                    _ => main_position,
+                };
+                let cross_position = match style.flex_wrap {
+                    FlexWrap::WrapReverse => cross_size - cross_position - outer_cross_size,
+                    _ => cross_position,
+                };
+                if is_row {
+                    margin_box.x = main_position;
+                    margin_box.y = cross_position;
+                } else {
+                    margin_box.x = cross_position;
+                    margin_box.y = main_position;
+                }
+                d.content.x = margin_box.x + d.margin.left + d.border.left + d.padding.left;
+                d.content.y = margin_box.y + d.margin.top + d.border.top + d.padding.top;
+            }
+        }
+
+        self.dimensions.content.height = if is_row { cross_size } else { main_size };
+
+        let container = self.dimensions;
+        let mut saved_block = container;
+        saved_block.content.height = definite_height.unwrap_or(Au(-1));
+        for child in &mut self.children {
+            if child.box_type == BoxType::Absolute || child.box_type == BoxType::AnonymousBlock {
+                continue;
+            }
+            child.apply_relative_offset(container, saved_block);
+            if child.is_positioned() {
+                child.layout_absolute_descendants(viewport);
+            }
+        }
+    }
+
+    /// Resolve the sizes of the flex item `self.children[index]` before the flexible lengths
+    /// are resolved. `main_size` is the inner main size of this container if definite.
+    fn flex_item(
+        &mut self,
+        index: usize,
+        containing_block: Dimensions,
+        main_size: Option<Au>,
+        single_line: bool,
+        viewport: Dimensions,
+    ) -> FlexItem {
+        let cb_width = containing_block.content.width;
+        let is_row = is_row(&self.property.computed);
+        let align_items = self.property.computed.align_items;
+        let child = &mut self.children[index];
+
+        child.load_image(containing_block);
+        let style = child.property.computed.clone();
+        let offset = if is_row {
+            child.horizontal_offset(cb_width)
+        } else {
+            style.margin.top.to_au(cb_width).unwrap_or(Au(0))
+                + style.margin.bottom.to_au(cb_width).unwrap_or(Au(0))
+                + style.border_width.top + style.border_width.bottom
+                + style.padding.top.to_au(cb_width) + style.padding.bottom.to_au(cb_width)
+        };
+
+        // The main size property, which is used as the flex basis if it is `auto`.
+        let specified_size = if is_row {
+            child.replaced_width().or_else(|| style.width.to_au(cb_width))
+        } else {
+            child.replaced_height().or_else(|| match style.height {
+                LengthOrPercentageOrAuto::Length(height) => Some(height),
+                _ => None,
+            })
+        };
+        let basis = match style.flex_basis {
+            LengthOrPercentageOrAuto::Percentage(_) if main_size.is_none() => None,
+            basis => basis.to_au(main_size.unwrap_or(Au(0))),
+        };
+
+        // In a column container, the content size is the height at the width the item would
+        // have. Stretched items are as wide as the container if it has a single line.
+        let mut width = Au(0);
+        let (min_size, max_size);
+        let content_size = if is_row {
+            let (min_width, max_width) = child.min_max_width(cb_width);
+            min_size = min_width;
+            max_size = max_width;
+            match child.replaced_width() {
+                Some(width) => IntrinsicWidths {
+                    preferred: width,
+                    minimum: width,
+                },
+                None => child.intrinsic_widths(containing_block),
+            }
+        } else {
+            let alignment = style.align_self.unwrap_or(align_items);
+            let stretch = (alignment == Alignment::Normal || alignment == Alignment::Stretch)
+                && !style.margin.left.is_auto() && !style.margin.right.is_auto();
+            width = match child.replaced_width().or_else(|| style.width.to_au(cb_width)) {
+                Some(width) => width,
+                None if single_line && stretch => {
+                    max(Au(0), cb_width - child.horizontal_offset(cb_width))
+                }
+                None => child.shrink_to_fit_width(containing_block),
+            };
+            let mut probe = child.clone();
+            probe.layout_flex_item(containing_block, width, viewport);
+            let height = probe.dimensions.content.height;
+            let mut saved_block = containing_block;
+            saved_block.content.height = main_size.unwrap_or(Au(-1));
+            let (min_height, max_height) = probe.min_max_height(saved_block);
+            min_size = min_height;
+            max_size = max_height;
+            IntrinsicWidths {
+                preferred: height,
+                minimum: height,
+            }
+        };
+
+        let base_size = basis
+            .or(specified_size)
+            .unwrap_or(content_size.preferred);
+        // The automatic minimum size is the smaller of the content size and the specified size,
+        // limited by the maximum size.
+        // ref. https://www.w3.org/TR/css-flexbox-1/#min-size-auto
+        let min_size = min_size.unwrap_or_else(|| {
+            let size = match specified_size {
+                Some(size) => min(size, content_size.minimum),
+                None => content_size.minimum,
+            };
+            max_size.map_or(size, |max_size| min(size, max_size))
+        });
+
+        let mut item = FlexItem {
+            index: index,
+            base_size: base_size,
+            hypothetical_size: Au(0),
+            min_size: min_size,
+            max_size: max_size,
+            offset: offset,
+            grow: style.flex_grow,
+            shrink: style.flex_shrink,
+            target_size: Au(0),
+            frozen: false,
+            width: width,
+            cross_size: Au(0),
+        };
+        item.hypothetical_size = item.clamp(base_size);
+        item
+    }
+
+    /// Lay out this flex or grid item and its descendants with the content width `width`.
+    /// The position is set by the container later.
+    pub fn layout_flex_item(
+        &mut self,
+        containing_block: Dimensions,
+        width: Au,
+        viewport: Dimensions,
+    ) {
+        if self.box_type == BoxType::AnonymousBlock {
+            let mut cb = containing_block;
+            cb.content.width = width;
+            cb.content.height = Au(0);
+            self.layout(
+                &mut Floats::new(),
+                &mut CollapsibleMargins::default(),
+                cb,
+                cb,
+                viewport,
+            );
+            self.dimensions.content.width = width;
+            return;
+        }
+
+        self.load_image(containing_block);
+        self.assign_padding(containing_block);
+        self.assign_border_width();
+        self.assign_margin(containing_block);
+        self.dimensions.content.width = width;
+
+        // Percentage heights of the items are treated as `auto`.
+        let saved_block = indefinite_block(containing_block);
+        self.floats = Floats::new();
+        let definite_block = self.definite_block(saved_block);
+        self.layout_children(definite_block, viewport);
+
+        self.assign_replaced_height_if_necessary();
+        self.calculate_block_height(saved_block);
+    }
+
+    /// Calculate the preferred and preferred minimum widths of the content box of a flex
+    /// container. The items of a single-line row container are placed side by side.
+    pub fn flex_intrinsic_widths(&self, containing_block: Dimensions) -> IntrinsicWidths {
+        let style = &self.property.computed;
+        let side_by_side = is_row(style) && style.flex_wrap == FlexWrap::NoWrap;
+        let gap = if is_row(style) {
+            style.column_gap.to_au(containing_block.content.width)
+        } else {
+            Au(0)
+        };
+
+        let mut widths = IntrinsicWidths::default();
+        let mut count = 0;
+        for child in &self.children {
+            match child.box_type {
+                BoxType::AnonymousBlock if child.is_collapsible_whitespace() => continue,
+                BoxType::BlockNode
+                | BoxType::GridNode
+                | BoxType::TableNode
+                | BoxType::InlineBlockNode
+                | BoxType::Float
+                | BoxType::AnonymousBlock => {}
+                _ => continue,
+            }
+            let child_widths = child.outer_intrinsic_widths(containing_block);
+            if is_row(style) {
+                widths.preferred += child_widths.preferred;
+            } else {
+                widths.preferred = max(widths.preferred, child_widths.preferred);
+            }
+            if side_by_side {
+                widths.minimum += child_widths.minimum;
+            } else {
+                widths.minimum = max(widths.minimum, child_widths.minimum);
+            }
+            count += 1;
+        }
+
+        let gaps = gap * max(count - 1, 0);
+        widths.preferred += gaps;
+        if side_by_side {
+            widths.minimum += gaps;
+        }
+        widths
+    }
+
+    /// Whether this is an anonymous block that contains only white space, which doesn't
+    /// become a flex or grid item.
+    /// ref. https://www.w3.org/TR/css-flexbox-1/#flex-items
+    pub fn is_collapsible_whitespace(&self) -> bool {
+        self.box_type == BoxType::AnonymousBlock
+            && self.children.iter().all(|child| match child.node.data {
+                NodeType::Text(ref text) if child.children.is_empty() => {
+                    text.trim().is_empty()
+                }
+                _ => false,
+            })
+    }
+}
+
+fn is_row(style: &ComputedStyle) -> bool {
+    match style.flex_direction {
+        FlexDirection::Row | FlexDirection::RowReverse => true,
+        FlexDirection::Column | FlexDirection::ColumnReverse => false,
+    }
+}
+
+/// Resolve the target main sizes of the items in a line so that the sum of their outer sizes
+/// fills `available_size`.
+/// ref. https://www.w3.org/TR/css-flexbox-1/#resolve-flexible-lengths
+fn resolve_flexible_lengths(items: &mut Vec<FlexItem>, available_size: Au) {
+    let hypothetical = items
+        .iter()
+        .fold(Au(0), |acc, item| acc + item.outer_hypothetical_size());
+    let growing = hypothetical < available_size;
+
+    // Size inflexible items.
+    for item in items.iter_mut() {
+        item.target_size = item.hypothetical_size;
+        let factor = if growing { item.grow } else { item.shrink };
+        item.frozen = factor == 0.0 || (growing && item.base_size > item.hypothetical_size)
+            || (!growing && item.base_size < item.hypothetical_size);
+    }
+
+    let free_space = |items: &Vec<FlexItem>| {
+        items.iter().fold(available_size, |acc, item| {
+            if item.frozen {
+                acc - item.outer_target_size()
+            } else {
+                acc - item.base_size - item.offset
+            }
+        })
+    };
+    let initial_free_space = free_space(items).to_f64_px();
+
+    while items.iter().any(|item| !item.frozen) {
+        let mut free_space = free_space(items).to_f64_px();
+        let factors = items.iter().filter(|item| !item.frozen).fold(0.0, |acc, item| {
+            acc + if growing { item.grow } else { item.shrink }
+        });
+        if factors < 1.0 {
+            let scaled = initial_free_space * factors;
+            if scaled.abs() < free_space.abs() {
+                free_space = scaled;
+            }
+        }
+
+        // Distribute the free space in proportion to the flex factors.
+        let scaled_shrink_factors = items
+            .iter()
+            .filter(|item| !item.frozen)
+            .fold(0.0, |acc, item| {
+                acc + item.shrink * item.base_size.to_f64_px()
+            });
+        let mut violation = Au(0);
+        let mut violations = vec![];
+        for item in items.iter_mut().filter(|item| !item.frozen) {
+            let base_size = item.base_size.to_f64_px();
+            let size = if growing {
+                base_size + free_space * item.grow / factors
+            } else if scaled_shrink_factors > 0.0 {
+                base_size + free_space * item.shrink * base_size / scaled_shrink_factors
+            } else {
+                base_size
+            };
+            let size = Au::from_f64_px(size);
+            // Fix min and max violations.
+            item.target_size = item.clamp(size);
+            violation += item.target_size - size;
+            violations.push(item.target_size - size);
+        }
+
+        // Freeze all the items if the total violation is zero, the items that violate the
+        // minimum if it is positive, and the items that violate the maximum if it is negative.
+        for (item, item_violation) in items.iter_mut().filter(|item| !item.frozen).zip(violations) {
+            if violation == Au(0) || (violation > Au(0) && item_violation > Au(0))
+                || (violation < Au(0) && item_violation < Au(0))
+            {
+                item.frozen = true;
+            }
+        }
+    }
+}
+
+/// Returns the offset of the first box and the extra space between boxes to distribute
+/// `free_space` to `count` boxes by `alignment`.
+/// ref. https://www.w3.org/TR/css-align-3/#distribution-values
+pub fn distribute_space(alignment: Alignment, free_space: Au, count: usize) -> (Au, Au) {
+    let count = count as i32;
+    match alignment {
+        Alignment::End => (free_space, Au(0)),
+        Alignment::Center => (free_space / 2, Au(0)),
+        Alignment::SpaceBetween if free_space > Au(0) && count > 1 => {
+            (Au(0), free_space / (count - 1))
+        }
+        Alignment::SpaceAround if free_space > Au(0) => {
+            let between = free_space / count;
+            (between / 2, between)
+        }
+        Alignment::SpaceEvenly if free_space > Au(0) => {
+            let between = free_space / (count + 1);
+            (between, between)
+        }
+        // Negative free space falls back to `center` for space around and space evenly.
+        Alignment::SpaceAround | Alignment::SpaceEvenly => (free_space / 2, Au(0)),
+        _ => (Au(0), Au(0)),
+    }
+}
+
+#[test]
+fn test_flexbox() {
+    use layout::{layout_html, rect};
+
+    let css = "div { display: block; }
+               .row { display: flex; column-gap: 10px; justify-content: center; }
+               .grow { flex: 1; }
+               .fixed { width: 50px; height: 30px; }
+               .first { order: -1; }
+               .wrap { display: flex; flex-wrap: wrap; width: 100px; align-items: flex-end; }
+               .item { width: 40px; height: 10px; }
+               .tall { height: 20px; }
+               .column { display: flex; flex-direction: column-reverse; height: 100px; }
+               .inline { display: inline-flex; column-gap: 2px; }";
+    let tree = layout_html(
+        "<div><div class='row'><div class='grow'>aa</div><div class='fixed'></div></div>\
+         <div class='row'><div class='fixed'></div><div class='fixed first'></div></div>\
+         <div class='wrap'><div class='item'></div><div class='item tall'></div>\
+         <div class='item'></div></div>\
+         <div class='column'><div>a</div><div class='grow'>b</div></div>\
+         <span class='inline'><span>aa</span><span>bbb</span></span></div>",
+        css,
+        200,
+    );
+    // The item grows into the free space, and is stretched to the height of the line.
+    let row = &tree.children[0];
+    assert_eq!(rect(row), (0, 0, 200, 30));
+    assert_eq!(rect(&row.children[0]), (0, 0, 140, 30));
+    assert_eq!(rect(&row.children[1]), (150, 0, 50, 30));
+
+    // `order` changes the order of the items, which are centered.
+    let row = &tree.children[1];
+    assert_eq!(rect(&row.children[1]), (45, 0, 50, 30));
+    assert_eq!(rect(&row.children[0]), (105, 0, 50, 30));
+
+    // The third item wraps, and the first item is aligned to the end of the first line.
+    let wrap = &tree.children[2];
+    assert_eq!(rect(wrap), (0, 60, 100, 30));
+    assert_eq!(rect(&wrap.children[0]), (0, 10, 40, 10));
+    assert_eq!(rect(&wrap.children[1]), (40, 0, 40, 20));
+    assert_eq!(rect(&wrap.children[2]), (0, 20, 40, 10));
+
+    // The items are laid out from the bottom, and stretched to the width of the container.
+    let column = &tree.children[3];
+    assert_eq!(rect(column), (0, 90, 200, 100));
+    assert_eq!(rect(&column.children[0]), (0, 80, 200, 19));
+    assert_eq!(rect(&column.children[1]), (0, 0, 200, 80));
+
+    // The width of an inline flex container is the sum of the items and the gap.
+    let inline = &tree.children[4].children[0];
+    assert_eq!(inline.box_type, BoxType::InlineBlockNode);
+    assert_eq!(inline.dimensions.content.width.to_px(), 42);
+}
+
+#[test]
+fn test_flex_min_max_sizes() {
+    use layout::{layout_html, rect};
+
+    let css = "div { display: block; }
+               .row { display: flex; width: 200px; }
+               .narrow { width: 100px; }
+               .grow { flex: 1; }
+               .zero { min-width: 0; }
+               .max { max-width: 50px; }
+               .min { min-width: 120px; }
+               .column { display: flex; flex-direction: column; height: 100px; }
+               .short { max-height: 30px; }";
+    let tree = layout_html(
+        "<div><div class='row narrow'><div>aaaaaaaaaaaaaaaaaaaa</div>\
+         <div class='zero'>aaaaaaaaaaaaaaaaaaaa</div></div>\
+         <div class='row'><div class='grow max'></div><div class='grow'></div></div>\
+         <div class='row'><div class='grow min'></div><div class='grow'></div></div>\
+         <div class='column'><div class='grow short'></div><div class='grow'></div></div></div>",
+        css,
+        200,
+    );
+    // The automatic minimum size keeps the first item from shrinking below its content, and
+    // `min-width: 0` lets the second item shrink.
+    let row = &tree.children[0];
+    assert_eq!(rect(&row.children[0]).2, 160);
+    assert_eq!(rect(&row.children[1]).2, 0);
+
+    // The items that violate the maximum or the minimum are frozen, and the other item takes
+    // the rest of the free space.
+    let row = &tree.children[1];
+    assert_eq!(rect(&row.children[0]), (0, 0, 50, 0));
+    assert_eq!(rect(&row.children[1]), (50, 0, 150, 0));
+    let row = &tree.children[2];
+    assert_eq!(rect(&row.children[0]), (0, 0, 120, 0));
+    assert_eq!(rect(&row.children[1]), (120, 0, 80, 0));
+
+    let column = &tree.children[3];
+    assert_eq!(rect(&column.children[0]), (0, 0, 200, 30));
+    assert_eq!(rect(&column.children[1]), (0, 30, 200, 70));
+}
+
+#[test]
+fn test_computed_flex_style() {
+    use style::{computed_style, Display, LengthOrPercentage};
+
+    let computed = &computed_style(
+        "display: inline-flex; flex: 2 3 10px; flex-flow: column wrap; order: -1; \
+         justify-content: space-between; align-items: flex-end; align-self: auto; gap: 5%",
+    );
+    assert!(computed.display.is_flex());
+    assert_eq!(computed.display.blockify(), Display::Flex);
+    assert_eq!(computed.flex_grow, 2.0);
+    assert_eq!(computed.flex_shrink, 3.0);
+    assert_eq!(
+        computed.flex_basis,
+        LengthOrPercentageOrAuto::Length(Au::from_f64_px(10.0))
+    );
+    assert_eq!(computed.flex_direction, FlexDirection::Column);
+    assert_eq!(computed.flex_wrap, FlexWrap::Wrap);
+    assert_eq!(computed.order, -1);
+    assert_eq!(computed.justify_content, Alignment::SpaceBetween);
+    assert_eq!(computed.align_items, Alignment::End);
+    assert_eq!(computed.align_self, None);
+    assert_eq!(computed.row_gap, LengthOrPercentage::Percentage(5.0));
+}
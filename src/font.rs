@@ -26,6 +26,7 @@ pub struct Font {
     pub size: Au,
     pub weight: FontWeight,
     pub slant: FontSlant,
+    pub orientation: GlyphOrientation,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -40,6 +41,18 @@ pub enum FontSlant {
     Italic,
 }
 
+/// How the glyphs are set in a line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlyphOrientation {
+    Horizontal,
+    // In a vertical line, CJK characters are upright and the others are turned clockwise.
+    Mixed,
+    Upright,
+    Sideways,
+    // Horizontal text squeezed into a single character space of a vertical line (tate-chu-yoko).
+    Combined,
+}
+
 impl Font {
     pub fn new(size: Au, weight: FontWeight, slant: FontSlant) -> Font {
         FONT_DESC.with(|font_desc| {
@@ -52,22 +65,41 @@ impl Font {
             })
         });
 
+        set_pango_gravity(GlyphOrientation::Horizontal);
+
         Font {
             size: size,
             weight: weight,
             slant: slant,
+            orientation: GlyphOrientation::Horizontal,
         }
     }
 
+    /// This font with the glyphs set in `orientation`. The text is measured along the line.
+    pub fn with_orientation(mut self, orientation: GlyphOrientation) -> Font {
+        set_pango_gravity(orientation);
+        self.orientation = orientation;
+        self
+    }
+
     pub fn new_empty() -> Font {
         Font {
             size: Au(0),
             weight: FontWeight::Normal,
             slant: FontSlant::Normal,
+            orientation: GlyphOrientation::Horizontal,
         }
     }
 
     pub fn text_width(&self, text: &str) -> f64 {
+        // Combined text takes up a square of the font size however long it is.
+        if self.orientation == GlyphOrientation::Combined {
+            return if text.trim().is_empty() {
+                0.0
+            } else {
+                self.size.to_f64_px()
+            };
+        }
         PANGO_LAYOUT.with(|layout| {
             let layout = layout.borrow_mut();
             layout.set_text(text);
@@ -91,44 +123,66 @@ impl Font {
         })
     }
 
-    pub fn compute_max_chars_and_width(&self, s: &str, max_width: f64) -> (usize, f64) {
-        if max_width < 0f64 {
-            return (0, 0.0);
+    /// Returns the widths of the pieces of `s` that can't be broken, i.e. the widths of the lines
+    /// of `s` with a line break at each of `breaks`. The whitespace at the end of a line doesn't
+    /// count.
+    pub fn unbreakable_widths(&self, s: &str, breaks: &[usize]) -> Vec<f64> {
+        let mut widths = vec![];
+        let mut start = 0;
+        for &end in breaks.iter().chain(Some(s.len()).iter()) {
+            widths.push(self.text_width(s[start..end].trim_right()));
+            start = end;
         }
+        widths
+    }
 
-        PANGO_LAYOUT.with(|layout| {
-            let layout = layout.borrow_mut();
-            // TODO: Inefficient implementation!
-            let mut text_width = 0.0;
-            let mut last_splittable_pos = None;
-            let mut last_pos = 0;
-            for (pos, c) in s.char_indices() {
-                if c.is_whitespace() || c.is_ascii_punctuation() {
-                    last_splittable_pos = Some(pos);
-                }
-
-                layout.set_text(c.to_string().as_str());
-                let c_width = pango::units_to_double(layout.get_size().0);
-                text_width += c_width;
-
-                if text_width > max_width {
-                    if let Some(pos) = last_splittable_pos {
-                        return (pos + 1, text_width - c_width); // '1' means whitespace or punctuation.
-                    } else {
-                        if pos == 0 {
-                            break;
-                        }
-                        if pos - last_pos > 1 {
-                            // if c is multi-byte character
-                            return (pos, text_width - c_width);
-                        }
-                    }
-                }
-
-                last_pos = pos;
+    /// Returns the length of the longest piece at the start of `s` that ends at one of `breaks`
+    /// and fits in `max_width`, and its width. The whitespace at the end of the piece hangs, so
+    /// it may overflow. (0, 0.0) if no piece fits.
+    pub fn compute_max_chars_and_width(
+        &self,
+        s: &str,
+        breaks: &[usize],
+        max_width: f64,
+    ) -> (usize, f64) {
+        let mut fitting = (0, 0.0);
+        for &end in breaks.iter().filter(|&&end| end > 0) {
+            if self.text_width(s[..end].trim_right()) > max_width {
+                break;
             }
+            fitting = (end, self.text_width(&s[..end]));
+        }
+        fitting
+    }
+}
 
-            (if s.is_empty() { 0 } else { 1 }, text_width)
-        })
+impl GlyphOrientation {
+    pub fn is_vertical(self) -> bool {
+        match self {
+            GlyphOrientation::Horizontal | GlyphOrientation::Combined => false,
+            _ => true,
+        }
     }
+
+    /// The base gravity and the gravity hint Pango sets the glyphs with. Vertical text is drawn
+    /// in a context turned clockwise, so sideways glyphs have the usual gravity.
+    pub fn to_pango_gravity(self) -> (pango::Gravity, pango::GravityHint) {
+        match self {
+            GlyphOrientation::Mixed => (pango::Gravity::East, pango::GravityHint::Natural),
+            GlyphOrientation::Upright => (pango::Gravity::East, pango::GravityHint::Strong),
+            _ => (pango::Gravity::South, pango::GravityHint::Natural),
+        }
+    }
+}
+
+/// Sets the gravity of the layout the text is measured with.
+fn set_pango_gravity(orientation: GlyphOrientation) {
+    let (gravity, hint) = orientation.to_pango_gravity();
+    PANGO_LAYOUT.with(|layout| {
+        let layout = layout.borrow_mut();
+        let ctx = layout.get_context().unwrap();
+        ctx.set_base_gravity(gravity);
+        ctx.set_gravity_hint(hint);
+        layout.context_changed();
+    })
 }
@@ -0,0 +1,982 @@
+use block::CollapsibleMargins;
+use float::Floats;
+use flex::distribute_space;
+use inline::IntrinsicWidths;
+use layout::{BoxType, Dimensions, LayoutBox};
+use style::{Alignment, GridAutoFlow, GridLine, GridTemplateAreas, LengthOrPercentage,
+            LengthOrPercentageOrAuto, TrackBreadth, TrackList, TrackSize};
+
+use std::cmp::{max, min};
+
+use app_units::Au;
+
+/// The placement of a grid item in an axis before auto-placement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Placement {
+    // The start and end lines, which are 0-based.
+    Definite(usize, usize),
+    // The number of tracks an automatically placed item spans.
+    Auto(usize),
+}
+
+/// The area of a grid item in lines, which are 0-based.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct GridArea {
+    rows: (usize, usize),
+    columns: (usize, usize),
+}
+
+/// The grid after the items are placed.
+struct Grid {
+    // The areas of the items in the order of the items.
+    areas: Vec<GridArea>,
+    // The explicit and implicit tracks.
+    rows: Vec<TrackSize>,
+    columns: Vec<TrackSize>,
+}
+
+/// A grid track being sized.
+/// ref. https://www.w3.org/TR/css-grid-1/#algo-terms
+#[derive(Clone, Debug)]
+struct Track {
+    size: TrackSize,
+    base_size: Au,
+    // None if infinite.
+    growth_limit: Option<Au>,
+}
+
+/// The outer sizes of a grid item in an axis.
+struct Contribution {
+    // The lines the item spans.
+    lines: (usize, usize),
+    minimum: Au,
+    maximum: Au,
+}
+
+/// The grid cells occupied by the items placed so far, indexed by the tracks in the
+/// auto-placement direction (major) and then the other tracks (minor).
+#[derive(Default)]
+struct Occupancy(Vec<Vec<bool>>);
+
+impl Occupancy {
+    fn is_free(&self, major: (usize, usize), minor: (usize, usize)) -> bool {
+        (major.0..major.1).all(|i| {
+            self.0.get(i).map_or(true, |cells| {
+                (minor.0..minor.1).all(|j| !cells.get(j).cloned().unwrap_or(false))
+            })
+        })
+    }
+
+    fn occupy(&mut self, major: (usize, usize), minor: (usize, usize)) {
+        if self.0.len() < major.1 {
+            self.0.resize(major.1, vec![]);
+        }
+        for cells in &mut self.0[major.0..major.1] {
+            if cells.len() < minor.1 {
+                cells.resize(minor.1, false);
+            }
+            for cell in &mut cells[minor.0..minor.1] {
+                *cell = true;
+            }
+        }
+    }
+}
+
+impl LayoutBox {
+    /// Lay out a block-level grid container and its descendants.
+    /// A grid container establishes a new formatting context, so floats outside don't affect
+    /// its content.
+    pub fn layout_grid(
+        &mut self,
+        _floats: &mut Floats,
+        margins: &mut CollapsibleMargins,
+        containing_block: Dimensions,
+        saved_block: Dimensions,
+        viewport: Dimensions,
+    ) {
+        self.calculate_block_width(containing_block);
+        self.calculate_block_position(margins, containing_block);
+
+        self.layout_grid_children(viewport);
+
+        self.calculate_block_height(saved_block);
+
+        // The margins of the items don't collapse with the margins of the container.
+        *margins = CollapsibleMargins::new(self.dimensions.margin.bottom);
+    }
+
+    /// Lay out the children of a grid container within its content area. The width of the
+    /// container must be calculated before.
+    /// Sets `self.dimensions.height` to the total content height like `layout_block_children`.
+    /// ref. https://www.w3.org/TR/css-grid-1/#layout-algorithm
+    pub fn layout_grid_children(&mut self, viewport: Dimensions) {
+        let style = self.property.computed.clone();
+        let width = self.dimensions.content.width;
+        let definite_height = match style.height {
+            LengthOrPercentageOrAuto::Length(height) => Some(height),
+            _ => None,
+        };
+        let mut containing_block: Dimensions = Default::default();
+        containing_block.content.width = width;
+        containing_block.content.height = definite_height.unwrap_or(Au(0));
+        let column_gap = style.column_gap.to_au(width);
+        let row_gap = style.row_gap.to_au(definite_height.unwrap_or(Au(0)));
+
+        // A grid container establishes a new formatting context.
+        self.floats = Floats::new();
+
+        for child in &mut self.children {
+            match child.box_type {
+                BoxType::Absolute => {
+                    // The static position is the content box origin of the container.
+                    child.dimensions.content.x = Au(0);
+                    child.dimensions.content.y = Au(0);
+                }
+                BoxType::AnonymousBlock if child.is_collapsible_whitespace() => {
+                    child.dimensions = Default::default();
+                }
+                _ => {}
+            }
+        }
+
+        let items = self.grid_items();
+        let grid = self.place_grid_items(&items, Some(width), definite_height);
+
+        // Size the columns with the intrinsic widths of the items.
+        let contributions: Vec<Contribution> = items
+            .iter()
+            .zip(&grid.areas)
+            .map(|(&i, area)| {
+                let widths = self.children[i].outer_intrinsic_widths(containing_block);
+                Contribution {
+                    lines: area.columns,
+                    minimum: widths.minimum,
+                    maximum: widths.preferred,
+                }
+            })
+            .collect();
+        let columns = size_tracks(
+            &grid.columns,
+            &contributions,
+            Some(width),
+            column_gap,
+            is_stretch(style.justify_content),
+        );
+        let columns = track_positions(&columns, column_gap, style.justify_content, width);
+
+        // Lay out the items in the widths of their areas, and size the rows with their heights.
+        let mut contributions = vec![];
+        for (&i, area) in items.iter().zip(&grid.areas) {
+            let child = &mut self.children[i];
+            let mut area_block = containing_block;
+            area_block.content.width = columns[area.columns.1 - 1].1 - columns[area.columns.0].0;
+
+            let area_width = area_block.content.width;
+            let justify = child
+                .property
+                .computed
+                .justify_self
+                .unwrap_or(style.justify_items);
+            let margin = child.property.computed.margin;
+            let specified_width = child
+                .replaced_width()
+                .or_else(|| child.property.computed.width.to_au(area_width));
+            let item_width = match specified_width {
+                Some(width) => width,
+                None if is_stretch(justify) && !margin.left.is_auto()
+                    && !margin.right.is_auto() =>
+                {
+                    max(Au(0), area_width - child.horizontal_offset(area_width))
+                }
+                None => child.shrink_to_fit_width(area_block),
+            };
+            child.layout_flex_item(area_block, item_width, viewport);
+
+            let height = child.dimensions.margin_box().height;
+            contributions.push(Contribution {
+                lines: area.rows,
+                minimum: height,
+                maximum: height,
+            });
+        }
+        let rows = size_tracks(
+            &grid.rows,
+            &contributions,
+            definite_height,
+            row_gap,
+            is_stretch(style.align_content),
+        );
+        let height = definite_height.unwrap_or_else(|| {
+            rows.iter().fold(Au(0), |acc, &size| acc + size)
+                + row_gap * max(rows.len() as i32 - 1, 0)
+        });
+        let rows = track_positions(&rows, row_gap, style.align_content, height);
+
+        // Align the items in their areas.
+        for (&i, area) in items.iter().zip(&grid.areas) {
+            let child = &mut self.children[i];
+            let x = columns[area.columns.0].0;
+            let y = rows[area.rows.0].0;
+            let area_width = columns[area.columns.1 - 1].1 - x;
+            let area_height = rows[area.rows.1 - 1].1 - y;
+
+            let item_style = child.property.computed.clone();
+            let is_replaced = child.is_replaced();
+            let margin = item_style.margin.map(|m| m.is_auto());
+            let align = item_style.align_self.unwrap_or(style.align_items);
+            let justify = item_style.justify_self.unwrap_or(style.justify_items);
+
+            let d = &mut child.dimensions;
+            if is_stretch(align) && item_style.height.is_auto() && !margin.top && !margin.bottom
+                && !is_replaced
+            {
+                let offset = d.margin_box().height - d.content.height;
+                d.content.height = max(Au(0), area_height - offset);
+            }
+
+            let margin_box = d.margin_box();
+            let x = x + align_in_area(
+                justify,
+                (margin.left, margin.right),
+                area_width - margin_box.width,
+            );
+            let y = y + align_in_area(
+                align,
+                (margin.top, margin.bottom),
+                area_height - margin_box.height,
+            );
+            d.content.x = x + d.margin.left + d.border.left + d.padding.left;
+            d.content.y = y + d.margin.top + d.border.top + d.padding.top;
+        }
+
+        self.dimensions.content.height = height;
+
+        let container = self.dimensions;
+        let mut saved_block = container;
+        saved_block.content.height = definite_height.unwrap_or(Au(-1));
+        for child in &mut self.children {
+            if child.box_type == BoxType::Absolute || child.box_type == BoxType::AnonymousBlock {
+                continue;
+            }
+            child.apply_relative_offset(container, saved_block);
+            if child.is_positioned() {
+                child.layout_absolute_descendants(viewport);
+            }
+        }
+    }
+
+    /// Calculate the preferred and preferred minimum widths of the content box of a grid
+    /// container, i.e. the sums of the columns sized with the intrinsic widths of the items.
+    pub fn grid_intrinsic_widths(&self, containing_block: Dimensions) -> IntrinsicWidths {
+        let style = &self.property.computed;
+        let gap = style.column_gap.to_au(containing_block.content.width);
+        let items = self.grid_items();
+        let grid = self.place_grid_items(&items, None, None);
+
+        let mut contributions: Vec<Contribution> = items
+            .iter()
+            .zip(&grid.areas)
+            .map(|(&i, area)| {
+                let widths = self.children[i].outer_intrinsic_widths(containing_block);
+                Contribution {
+                    lines: area.columns,
+                    minimum: widths.minimum,
+                    maximum: widths.preferred,
+                }
+            })
+            .collect();
+        let gaps = gap * max(grid.columns.len() as i32 - 1, 0);
+        let sum = |sizes: Vec<Au>| sizes.into_iter().fold(gaps, |acc, size| acc + size);
+
+        let preferred = sum(size_tracks(&grid.columns, &contributions, None, gap, false));
+        for contribution in &mut contributions {
+            contribution.maximum = contribution.minimum;
+        }
+        let minimum = sum(size_tracks(&grid.columns, &contributions, None, gap, false));
+        IntrinsicWidths {
+            preferred: max(preferred, minimum),
+            minimum: minimum,
+        }
+    }
+
+    /// The indices of the children that are grid items, in `order`.
+    fn grid_items(&self) -> Vec<usize> {
+        let mut items: Vec<usize> = (0..self.children.len())
+            .filter(|&i| {
+                let child = &self.children[i];
+                match child.box_type {
+                    BoxType::AnonymousBlock => !child.is_collapsible_whitespace(),
+                    BoxType::BlockNode
+                    | BoxType::GridNode
+                    | BoxType::TableNode
+                    | BoxType::InlineBlockNode
+                    | BoxType::Float => true,
+                    _ => false,
+                }
+            })
+            .collect();
+        // `sort_by_key` is stable, so the items with the same `order` stay in the document order.
+        items.sort_by_key(|&i| self.children[i].property.computed.order);
+        items
+    }
+
+    /// Place `items` in the grid, and determine the tracks of the implicit grid.
+    /// `width` and `height` are the inner sizes of this container if definite, which are used to
+    /// repeat `repeat(auto-fill, ...)`.
+    /// ref. https://www.w3.org/TR/css-grid-1/#auto-placement-algo
+    fn place_grid_items(&self, items: &[usize], width: Option<Au>, height: Option<Au>) -> Grid {
+        let style = &self.property.computed;
+        let areas = &style.grid_template_areas;
+        let explicit_columns = explicit_tracks(
+            &style.grid_template_columns,
+            width,
+            style.column_gap.to_au(width.unwrap_or(Au(0))),
+        );
+        let explicit_rows = explicit_tracks(
+            &style.grid_template_rows,
+            height,
+            style.row_gap.to_au(height.unwrap_or(Au(0))),
+        );
+        let explicit_column_count = max(explicit_columns.len(), areas.columns);
+        let explicit_row_count = max(explicit_rows.len(), areas.rows);
+
+        // Auto-placement fills the minor tracks of a major track before moving on to the next
+        // major track. The major tracks are rows unless `grid-auto-flow` is `column`.
+        let column_flow = style.grid_auto_flow == GridAutoFlow::Column;
+        let dense = style.grid_auto_flow_dense;
+        let placements: Vec<(Placement, Placement)> = items
+            .iter()
+            .map(|&i| {
+                let item_style = &self.children[i].property.computed;
+                let row = resolve_placement(&item_style.grid_row, explicit_row_count, areas, true);
+                let column = resolve_placement(
+                    &item_style.grid_column,
+                    explicit_column_count,
+                    areas,
+                    false,
+                );
+                if column_flow {
+                    (column, row)
+                } else {
+                    (row, column)
+                }
+            })
+            .collect();
+        let mut minor_count = placements.iter().fold(
+            if column_flow {
+                explicit_row_count
+            } else {
+                explicit_column_count
+            },
+            |acc, &(_, minor)| match minor {
+                Placement::Definite(_, end) => max(acc, end),
+                Placement::Auto(span) => max(acc, span),
+            },
+        );
+
+        let mut occupancy = Occupancy::default();
+        let mut placed: Vec<Option<((usize, usize), (usize, usize))>> = vec![None; items.len()];
+
+        // Place the items that are positioned in both axes.
+        for (i, &placement) in placements.iter().enumerate() {
+            if let (Placement::Definite(a, b), Placement::Definite(c, d)) = placement {
+                occupancy.occupy((a, b), (c, d));
+                placed[i] = Some(((a, b), (c, d)));
+            }
+        }
+
+        // Place the items locked to a major track.
+        let mut cursors: Vec<usize> = vec![];
+        for (i, &placement) in placements.iter().enumerate() {
+            if let (Placement::Definite(a, b), Placement::Auto(span)) = placement {
+                let mut start = if dense {
+                    0
+                } else {
+                    cursors.get(a).cloned().unwrap_or(0)
+                };
+                while !occupancy.is_free((a, b), (start, start + span)) {
+                    start += 1;
+                }
+                occupancy.occupy((a, b), (start, start + span));
+                placed[i] = Some(((a, b), (start, start + span)));
+                if cursors.len() <= a {
+                    cursors.resize(a + 1, 0);
+                }
+                cursors[a] = start + span;
+                minor_count = max(minor_count, start + span);
+            }
+        }
+
+        // Place the remaining items with the auto-placement cursor.
+        let mut cursor = (0, 0);
+        for (i, &placement) in placements.iter().enumerate() {
+            let major_span = match placement {
+                (Placement::Auto(span), _) => span,
+                _ => continue,
+            };
+            if dense {
+                cursor = (0, 0);
+            }
+            let minor = match placement.1 {
+                Placement::Definite(start, end) => {
+                    if start < cursor.1 {
+                        cursor.0 += 1;
+                    }
+                    cursor.1 = start;
+                    while !occupancy.is_free((cursor.0, cursor.0 + major_span), (start, end)) {
+                        cursor.0 += 1;
+                    }
+                    (start, end)
+                }
+                Placement::Auto(span) => {
+                    loop {
+                        if cursor.1 + span > minor_count {
+                            cursor = (cursor.0 + 1, 0);
+                        } else if occupancy
+                            .is_free((cursor.0, cursor.0 + major_span), (cursor.1, cursor.1 + span))
+                        {
+                            break;
+                        } else {
+                            cursor.1 += 1;
+                        }
+                    }
+                    (cursor.1, cursor.1 + span)
+                }
+            };
+            let major = (cursor.0, cursor.0 + major_span);
+            occupancy.occupy(major, minor);
+            placed[i] = Some((major, minor));
+        }
+
+        let areas: Vec<GridArea> = placed
+            .into_iter()
+            .map(|area| {
+                let (major, minor) = area.unwrap();
+                if column_flow {
+                    GridArea {
+                        rows: minor,
+                        columns: major,
+                    }
+                } else {
+                    GridArea {
+                        rows: major,
+                        columns: minor,
+                    }
+                }
+            })
+            .collect();
+
+        // The implicit tracks are sized by `grid-auto-rows` and `grid-auto-columns`.
+        let implicit_tracks = |explicit: Vec<TrackSize>, count: usize, auto: &Vec<TrackSize>| {
+            let explicit_count = explicit.len();
+            let mut tracks = explicit;
+            for i in explicit_count..count {
+                tracks.push(auto[(i - explicit_count) % auto.len()]);
+            }
+            tracks
+        };
+        let row_count = areas.iter().fold(explicit_row_count, |acc, a| max(acc, a.rows.1));
+        let column_count = areas
+            .iter()
+            .fold(explicit_column_count, |acc, a| max(acc, a.columns.1));
+        Grid {
+            rows: implicit_tracks(explicit_rows, row_count, &style.grid_auto_rows),
+            columns: implicit_tracks(explicit_columns, column_count, &style.grid_auto_columns),
+            areas: areas,
+        }
+    }
+}
+
+/// Resolve the lines of a grid item in an axis. `explicit_tracks` is the number of the
+/// explicit tracks in the axis.
+/// ref. https://www.w3.org/TR/css-grid-1/#line-placement
+fn resolve_placement(
+    lines: &(GridLine, GridLine),
+    explicit_tracks: usize,
+    areas: &GridTemplateAreas,
+    is_row: bool,
+) -> Placement {
+    let line = |line: &GridLine, is_start: bool| match *line {
+        GridLine::Line(n) if n > 0 => Some(n as usize - 1),
+        // -1 is the last line of the explicit grid. Lines before the first line are clamped.
+        GridLine::Line(n) => Some(max(explicit_tracks as i32 + 1 + n, 0) as usize),
+        // A name refers to the start or end line of the area. `<name>-start` and `<name>-end`
+        // are the implicitly named lines of the area.
+        GridLine::Name(ref name) => {
+            let (name, is_start) = if name.ends_with("-start") {
+                (&name[..name.len() - "-start".len()], true)
+            } else if name.ends_with("-end") {
+                (&name[..name.len() - "-end".len()], false)
+            } else {
+                (name.as_str(), is_start)
+            };
+            areas.areas.iter().find(|area| area.0 == name).map(|area| {
+                let lines = if is_row { area.1 } else { area.2 };
+                if is_start {
+                    lines.0
+                } else {
+                    lines.1
+                }
+            })
+        }
+        GridLine::Auto | GridLine::Span(_) => None,
+    };
+    let span = |line: &GridLine| match *line {
+        GridLine::Span(span) => span,
+        _ => 1,
+    };
+
+    match (line(&lines.0, true), line(&lines.1, false)) {
+        (Some(start), Some(end)) if start < end => Placement::Definite(start, end),
+        (Some(start), Some(end)) if start > end => Placement::Definite(end, start),
+        (Some(start), Some(_)) => Placement::Definite(start, start + 1),
+        (Some(start), None) => Placement::Definite(start, start + span(&lines.1)),
+        (None, Some(end)) => {
+            let start = end.saturating_sub(span(&lines.0));
+            Placement::Definite(start, max(end, start + 1))
+        }
+        (None, None) => Placement::Auto(max(span(&lines.0), span(&lines.1))),
+    }
+}
+
+/// The explicit tracks of `list`. `repeat(auto-fill, ...)` is repeated as many times as the
+/// tracks fit in `available`, or once if it's indefinite.
+/// ref. https://www.w3.org/TR/css-grid-1/#auto-repeat
+fn explicit_tracks(list: &TrackList, available: Option<Au>, gap: Au) -> Vec<TrackSize> {
+    let (index, ref repeated) = match list.auto_repeat {
+        Some(ref auto_repeat) if !auto_repeat.1.is_empty() => auto_repeat.clone(),
+        _ => return list.tracks.clone(),
+    };
+
+    let basis = available.unwrap_or(Au(0));
+    let fixed_size = |size: &TrackSize| match (size.max, size.min) {
+        (TrackBreadth::Length(l), _) | (_, TrackBreadth::Length(l)) => Some(l.to_au(basis)),
+        _ => None,
+    };
+    let repeated_size = repeated
+        .iter()
+        .map(&fixed_size)
+        .fold(Some(Au(0)), |acc, size| Some(acc? + size?));
+    let count = match (available, repeated_size) {
+        (Some(available), Some(repeated_size)) => {
+            let others = list.tracks
+                .iter()
+                .fold(Au(0), |acc, size| acc + fixed_size(size).unwrap_or(Au(0)) + gap);
+            let repetition = repeated_size + gap * repeated.len() as i32;
+            if repetition > Au(0) {
+                max(1, (available - others + gap).0 / repetition.0)
+            } else {
+                1
+            }
+        }
+        _ => 1,
+    };
+
+    let mut tracks = list.tracks[..index].to_vec();
+    for _ in 0..count {
+        tracks.extend(repeated.iter().cloned());
+    }
+    tracks.extend(list.tracks[index..].iter().cloned());
+    tracks
+}
+
+/// Size the tracks of an axis to fit `contributions`. `available` is the inner size of the
+/// container in the axis if definite. If `stretch` is true, the `auto` tracks are stretched to
+/// fill the container.
+/// Returns the used sizes of the tracks.
+/// ref. https://www.w3.org/TR/css-grid-1/#algo-track-sizing
+fn size_tracks(
+    sizes: &[TrackSize],
+    contributions: &[Contribution],
+    available: Option<Au>,
+    gap: Au,
+    stretch: bool,
+) -> Vec<Au> {
+    // Percentages of an indefinite size are treated as `auto`.
+    let resolve = |breadth| match (breadth, available) {
+        (TrackBreadth::Length(LengthOrPercentage::Percentage(_)), None) => TrackBreadth::Auto,
+        (breadth, _) => breadth,
+    };
+    let fixed = |breadth| match breadth {
+        TrackBreadth::Length(l) => Some(l.to_au(available.unwrap_or(Au(0)))),
+        _ => None,
+    };
+    let is_intrinsic = |breadth| match breadth {
+        TrackBreadth::Auto | TrackBreadth::MinContent | TrackBreadth::MaxContent => true,
+        _ => false,
+    };
+    let flex_factor = |track: &Track| match track.size.max {
+        TrackBreadth::Flex(f) => Some(f),
+        _ => None,
+    };
+    let gaps = gap * max(sizes.len() as i32 - 1, 0);
+
+    let mut tracks: Vec<Track> = sizes
+        .iter()
+        .map(|size| {
+            let size = TrackSize {
+                min: resolve(size.min),
+                max: resolve(size.max),
+            };
+            let base_size = fixed(size.min).unwrap_or(Au(0));
+            Track {
+                size: size,
+                base_size: base_size,
+                growth_limit: fixed(size.max).map(|limit| max(limit, base_size)),
+            }
+        })
+        .collect();
+
+    // Resolve the intrinsic track sizes with the items spanning fewer tracks first.
+    let mut contributions: Vec<&Contribution> = contributions.iter().collect();
+    contributions.sort_by_key(|c| c.lines.1 - c.lines.0);
+    for &contribution in &contributions {
+        let (start, end) = contribution.lines;
+        let spanned_gaps = gap * (end - start - 1) as i32;
+        let spans_flexible = tracks[start..end].iter().any(|t| flex_factor(t).is_some());
+
+        // Increase the base sizes of the tracks with an intrinsic minimum. The items spanning
+        // flexible tracks only increase the flexible tracks.
+        let targets: Vec<usize> = (start..end)
+            .filter(|&i| {
+                is_intrinsic(tracks[i].size.min)
+                    && (!spans_flexible || flex_factor(&tracks[i]).is_some())
+            })
+            .collect();
+        if end - start == 1 && !targets.is_empty() {
+            let track = &mut tracks[start];
+            let size = match track.size.min {
+                TrackBreadth::MaxContent => contribution.maximum,
+                _ => contribution.minimum,
+            };
+            track.base_size = max(track.base_size, size);
+        } else if !targets.is_empty() {
+            let sum = tracks[start..end]
+                .iter()
+                .fold(spanned_gaps, |acc, t| acc + t.base_size);
+            let extra = contribution.minimum - sum;
+            if extra > Au(0) {
+                for &i in &targets {
+                    tracks[i].base_size += extra / targets.len() as i32;
+                }
+            }
+        }
+
+        // Increase the growth limits of the tracks with an intrinsic maximum.
+        if spans_flexible {
+            continue;
+        }
+        let targets: Vec<usize> = (start..end)
+            .filter(|&i| is_intrinsic(tracks[i].size.max))
+            .collect();
+        if end - start == 1 && !targets.is_empty() {
+            let track = &mut tracks[start];
+            let size = match track.size.max {
+                TrackBreadth::MinContent => contribution.minimum,
+                _ => contribution.maximum,
+            };
+            track.growth_limit = Some(max(track.growth_limit.unwrap_or(Au(0)), size));
+        } else if !targets.is_empty() {
+            let sum = tracks[start..end].iter().fold(spanned_gaps, |acc, t| {
+                acc + t.growth_limit.unwrap_or(t.base_size)
+            });
+            let extra = contribution.maximum - sum;
+            for &i in &targets {
+                let track = &mut tracks[i];
+                let limit = track.growth_limit.unwrap_or(track.base_size);
+                track.growth_limit = Some(limit + max(extra, Au(0)) / targets.len() as i32);
+            }
+        }
+    }
+    // Infinite growth limits are set to the base sizes.
+    for track in &mut tracks {
+        track.growth_limit = Some(max(
+            track.growth_limit.unwrap_or(track.base_size),
+            track.base_size,
+        ));
+    }
+
+    // Maximize the tracks, i.e. distribute the free space up to the growth limits.
+    // ref. https://www.w3.org/TR/css-grid-1/#algo-grow-tracks
+    match available {
+        Some(available) => {
+            let mut free_space = tracks
+                .iter()
+                .fold(available - gaps, |acc, t| acc - t.base_size);
+            loop {
+                let growable: Vec<usize> = (0..tracks.len())
+                    .filter(|&i| tracks[i].growth_limit.unwrap() > tracks[i].base_size)
+                    .collect();
+                if growable.is_empty() || free_space / growable.len() as i32 <= Au(0) {
+                    break;
+                }
+                let share = free_space / growable.len() as i32;
+                for i in growable {
+                    let track = &mut tracks[i];
+                    let increase = min(share, track.growth_limit.unwrap() - track.base_size);
+                    track.base_size += increase;
+                    free_space -= increase;
+                }
+            }
+        }
+        // The max-content size of the grid.
+        None => for track in &mut tracks {
+            track.base_size = track.growth_limit.unwrap();
+        },
+    }
+
+    // Expand the flexible tracks.
+    // ref. https://www.w3.org/TR/css-grid-1/#algo-flex-tracks
+    if tracks.iter().any(|t| flex_factor(t).is_some()) {
+        let fr = match available {
+            Some(available) => find_fr_size(&tracks, available - gaps),
+            None => {
+                // Each flexible track is at least its base size, and each item spanning
+                // flexible tracks fits in them.
+                let mut fr = tracks.iter().fold(0.0f64, |fr, t| match flex_factor(t) {
+                    Some(f) => fr.max(t.base_size.to_f64_px() / f.max(1.0)),
+                    None => fr,
+                });
+                for contribution in &contributions_spanning_flexible(&tracks, &contributions) {
+                    let (start, end) = contribution.lines;
+                    let space = contribution.maximum - gap * (end - start - 1) as i32;
+                    fr = fr.max(find_fr_size(&tracks[start..end], space));
+                }
+                fr
+            }
+        };
+        for track in &mut tracks {
+            if let Some(f) = flex_factor(track) {
+                track.base_size = max(track.base_size, Au::from_f64_px(fr * f));
+            }
+        }
+    }
+
+    // Stretch the `auto` tracks.
+    // ref. https://www.w3.org/TR/css-grid-1/#algo-stretch
+    if let (true, Some(available)) = (stretch, available) {
+        let free_space = tracks
+            .iter()
+            .fold(available - gaps, |acc, t| acc - t.base_size);
+        let auto_tracks = tracks
+            .iter()
+            .filter(|t| t.size.max == TrackBreadth::Auto)
+            .count();
+        if free_space > Au(0) && auto_tracks > 0 {
+            for track in tracks.iter_mut().filter(|t| t.size.max == TrackBreadth::Auto) {
+                track.base_size += free_space / auto_tracks as i32;
+            }
+        }
+    }
+
+    tracks.into_iter().map(|t| t.base_size).collect()
+}
+
+/// The contributions of the items that span flexible tracks.
+fn contributions_spanning_flexible<'a>(
+    tracks: &[Track],
+    contributions: &[&'a Contribution],
+) -> Vec<&'a Contribution> {
+    contributions
+        .iter()
+        .filter(|c| {
+            tracks[c.lines.0..c.lines.1].iter().any(|t| match t.size.max {
+                TrackBreadth::Flex(_) => true,
+                _ => false,
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Find the size of `1fr` in px with which `tracks` fill `space`.
+/// ref. https://www.w3.org/TR/css-grid-1/#algo-find-fr-size
+fn find_fr_size(tracks: &[Track], space: Au) -> f64 {
+    let mut inflexible: Vec<bool> = tracks
+        .iter()
+        .map(|t| match t.size.max {
+            TrackBreadth::Flex(_) => false,
+            _ => true,
+        })
+        .collect();
+    loop {
+        let (mut leftover, mut factors) = (space.to_f64_px(), 0.0);
+        for (track, &inflexible) in tracks.iter().zip(&inflexible) {
+            match track.size.max {
+                TrackBreadth::Flex(f) if !inflexible => factors += f,
+                _ => leftover -= track.base_size.to_f64_px(),
+            }
+        }
+        let fr = leftover.max(0.0) / factors.max(1.0);
+
+        // The tracks whose base sizes are larger than their flexible sizes are inflexible.
+        let mut changed = false;
+        for (track, inflexible) in tracks.iter().zip(inflexible.iter_mut()) {
+            if let TrackBreadth::Flex(f) = track.size.max {
+                if !*inflexible && track.base_size.to_f64_px() > fr * f {
+                    *inflexible = true;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return fr;
+        }
+    }
+}
+
+/// Returns the start and end positions of the tracks of `sizes` in a container of `size`,
+/// distributing the free space by `alignment` (`justify-content` or `align-content`).
+fn track_positions(sizes: &[Au], gap: Au, alignment: Alignment, size: Au) -> Vec<(Au, Au)> {
+    let gaps = gap * max(sizes.len() as i32 - 1, 0);
+    let free_space = sizes.iter().fold(size - gaps, |acc, &s| acc - s);
+    let (mut position, between) = if is_stretch(alignment) {
+        (Au(0), Au(0))
+    } else {
+        distribute_space(alignment, free_space, sizes.len())
+    };
+    let mut positions = vec![];
+    for &size in sizes {
+        positions.push((position, position + size));
+        position += size + gap + between;
+    }
+    positions
+}
+
+/// The offset of the margin box of an item in its grid area, which has `free_space` left.
+/// `auto_margins` are whether the start and end margins are auto, which take precedence over
+/// `alignment`.
+fn align_in_area(alignment: Alignment, auto_margins: (bool, bool), free_space: Au) -> Au {
+    match auto_margins {
+        (true, true) => max(Au(0), free_space / 2),
+        (true, false) => max(Au(0), free_space),
+        (false, true) => Au(0),
+        (false, false) => match alignment {
+            Alignment::End => free_space,
+            Alignment::Center => free_space / 2,
+            _ => Au(0),
+        },
+    }
+}
+
+/// Whether `alignment` is `normal` or `stretch`, which stretches items or `auto` tracks.
+fn is_stretch(alignment: Alignment) -> bool {
+    alignment == Alignment::Normal || alignment == Alignment::Stretch
+}
+
+#[test]
+fn test_grid() {
+    use layout::{layout_html, rect};
+
+    let css = "div { display: block; }
+               .fr { display: grid; grid-template-columns: 50px 1fr 2fr; gap: 5px 15px; }
+               .item { height: 10px; }
+               .lines { display: grid; grid-template-columns: repeat(4, 50px);
+                        grid-auto-rows: 20px; }
+               .span { grid-column: span 2; }
+               .placed { grid-column: 1; grid-row: 2 / 4; }
+               .center { justify-self: center; }
+               .wide { grid-column: span 3; }
+               .areas { display: grid; grid-template-areas: 'head head' 'side main';
+                        grid-template-columns: 60px auto; }
+               .head { grid-area: head; height: 10px; }
+               .main { grid-area: main; height: 30px; }
+               .inline { display: inline-grid; grid-template-columns: auto auto;
+                         column-gap: 2px; }";
+    let tree = layout_html(
+        "<div><div class='fr'><div class='item'></div><div class='item'></div>\
+         <div class='item'></div><div class='item'></div></div>\
+         <div class='lines'><div class='span'></div><div class='placed'></div>\
+         <div class='center'>a</div><div class='wide'></div></div>\
+         <div class='areas'><div class='main'></div><div class='head'></div></div>\
+         <span class='inline'><span>aa</span><span>bbb</span></span></div>",
+        css,
+        200,
+    );
+    // The flexible columns share the space left by the fixed column and the gaps.
+    let fr = &tree.children[0];
+    assert_eq!(fr.box_type, BoxType::GridNode);
+    assert_eq!(rect(fr), (0, 0, 200, 25));
+    assert_eq!(rect(&fr.children[0]), (0, 0, 50, 10));
+    assert_eq!(rect(&fr.children[1]), (65, 0, 40, 10));
+    assert_eq!(rect(&fr.children[2]), (120, 0, 80, 10));
+    assert_eq!(rect(&fr.children[3]), (0, 15, 50, 10));
+
+    // The auto-placed items skip the cells taken by the explicitly placed item.
+    let lines = &tree.children[1];
+    assert_eq!(rect(lines), (0, 25, 200, 60));
+    assert_eq!(rect(&lines.children[0]), (0, 0, 100, 20));
+    assert_eq!(rect(&lines.children[1]), (0, 20, 50, 40));
+    assert_eq!(rect(&lines.children[2]), (121, 0, 8, 20));
+    assert_eq!(rect(&lines.children[3]), (50, 20, 150, 20));
+
+    // The items are placed in the named areas, and the auto column fills the container.
+    let areas = &tree.children[2];
+    assert_eq!(rect(areas), (0, 85, 200, 40));
+    assert_eq!(rect(&areas.children[0]), (60, 10, 140, 30));
+    assert_eq!(rect(&areas.children[1]), (0, 0, 200, 10));
+
+    // The width of an inline grid container is the sum of the columns and the gap.
+    let inline = &tree.children[3].children[0];
+    assert_eq!(inline.box_type, BoxType::InlineBlockNode);
+    assert_eq!(inline.dimensions.content.width.to_px(), 42);
+}
+
+#[test]
+fn test_computed_grid_style() {
+    use style::computed_style;
+
+    let computed = &computed_style(
+        "display: grid; grid-template-columns: [a] 10px repeat(2, 1fr minmax(5px, auto)); \
+         grid-template-rows: repeat(auto-fill, 20%); grid-template-areas: 'x x .' 'x x y'; \
+         grid-auto-flow: column dense; grid-area: x / 2 / span 2",
+    );
+    let px = |f| TrackBreadth::Length(LengthOrPercentage::Length(Au::from_f64_px(f)));
+    let fr = TrackSize {
+        min: TrackBreadth::Auto,
+        max: TrackBreadth::Flex(1.0),
+    };
+    let minmax = TrackSize {
+        min: px(5.0),
+        max: TrackBreadth::Auto,
+    };
+    assert!(computed.display.is_grid());
+    assert_eq!(
+        computed.grid_template_columns.tracks,
+        vec![
+            TrackSize {
+                min: px(10.0),
+                max: px(10.0),
+            },
+            fr,
+            minmax,
+            fr,
+            minmax,
+        ]
+    );
+    let percent = TrackBreadth::Length(LengthOrPercentage::Percentage(20.0));
+    assert_eq!(
+        computed.grid_template_rows.auto_repeat,
+        Some((
+            0,
+            vec![TrackSize {
+                min: percent,
+                max: percent,
+            }]
+        ))
+    );
+    assert_eq!(
+        computed.grid_template_areas.areas,
+        vec![
+            ("x".to_string(), (0, 2), (0, 2)),
+            ("y".to_string(), (1, 2), (2, 3)),
+        ]
+    );
+    assert_eq!(computed.grid_auto_flow, GridAutoFlow::Column);
+    assert!(computed.grid_auto_flow_dense);
+    assert_eq!(
+        computed.grid_row,
+        (GridLine::Name("x".to_string()), GridLine::Span(2))
+    );
+    assert_eq!(computed.grid_column, (GridLine::Line(2), GridLine::Auto));
+}
@@ -294,24 +294,27 @@ impl Parser {
 fn url_conv(attr: (String, String)) -> (String, String) {
     match attr.0.to_lowercase().as_str() {
         "src" | "href" => {
-            if attr.1.len() > 0 && attr.1.chars().next().unwrap() == '#' {
-                // URL Fragment
-                (attr.0.clone(), attr.1.clone())
-            } else if attr.1.starts_with("http") || attr.1.starts_with("file") {
-                // Absolute URL
-                (attr.0.clone(), attr.1.clone())
-            } else {
-                // Normal URL
-                (
-                    attr.0.clone(),
-                    CUR_DIR.with(|dir| dir.borrow().join(attr.1).to_str().unwrap().to_string()),
-                )
-            }
+            let url = resolve_url(attr.1.as_str());
+            (attr.0, url)
         }
         _ => (attr.0, attr.1),
     }
 }
 
+/// Resolves `url` relative to the directory of the current document.
+pub fn resolve_url(url: &str) -> String {
+    if url.starts_with('#') {
+        // URL Fragment
+        url.to_string()
+    } else if url.starts_with("http") || url.starts_with("file") {
+        // Absolute URL
+        url.to_string()
+    } else {
+        // Normal URL
+        CUR_DIR.with(|dir| dir.borrow().join(url).to_str().unwrap().to_string())
+    }
+}
+
 #[test]
 fn test1() {
     use std::path::Path;
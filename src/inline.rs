@@ -1,12 +1,16 @@
-use css::Value;
+use bidi;
+use block::{indefinite_block, CollapsibleMargins};
 use dom::NodeType;
-use font::Font;
-use layout::{BoxType, Dimensions, ImageData, LayoutBox, LayoutInfo, Rect, Text};
+use font::{Font, GlyphOrientation};
+use layout::{BoxType, Dimensions, ImageData, LayoutBox, LayoutInfo, Rect};
 use float::Floats;
+use line_break::{break_opportunities, cluster_boundaries};
+use style::{ComputedStyle, Direction, Display, OverflowWrap, TextAlign, UnicodeBidi,
+            VerticalAlign};
 
 use std::ops::Range;
 use std::collections::{HashMap, VecDeque};
-use std::cmp::max;
+use std::cmp::{max, min};
 
 use gdk_pixbuf::PixbufExt;
 use gdk_pixbuf;
@@ -34,10 +38,6 @@ impl LineMetrics {
             under_baseline: under_baseline,
         }
     }
-    pub fn reset(&mut self) {
-        self.above_baseline = Au(0);
-        self.under_baseline = Au(0);
-    }
     pub fn calculate_line_height(&self) -> Au {
         self.above_baseline + self.under_baseline
     }
@@ -54,11 +54,21 @@ pub struct LineMaker {
     pub end: usize,
     pub cur_width: Au,
     pub cur_height: Au,
-    pub cur_metrics: LineMetrics,
+    // The style of the root inline box, i.e. of the block container.
+    pub root: ComputedStyle,
+    // Each line box starts with a zero-width inline box with the font and `line-height` of the
+    // root inline box.
+    // ref. https://www.w3.org/TR/CSS2/visudet.html#strut
+    pub strut: LineMetrics,
+    // The baselines of the lines, set by `assign_position`.
+    pub baselines: Vec<Au>,
+    // The last character laid out, which decides if a line can break before the next text.
+    // Atomic inlines count as U+FFFC OBJECT REPLACEMENT CHARACTER.
+    pub last_char: Option<char>,
 }
 
 impl LineMaker {
-    pub fn new(boxes: Vec<LayoutBox>, floats: Floats) -> LineMaker {
+    pub fn new(boxes: Vec<LayoutBox>, floats: Floats, root: &ComputedStyle) -> LineMaker {
         LineMaker {
             pending: Line {
                 range: 0..0,
@@ -74,7 +84,10 @@ impl LineMaker {
             end: 0,
             cur_width: Au(0),
             cur_height: Au(0),
-            cur_metrics: LineMetrics::new(Au(0), Au(0)),
+            root: root.clone(),
+            strut: leading_box(root),
+            baselines: vec![],
+            last_char: None,
         }
     }
 
@@ -126,6 +139,12 @@ impl LineMaker {
                     max_width_considered_float,
                     containing_block,
                 ),
+                BoxType::Absolute => {
+                    // An absolutely positioned box takes no space in the line, and
+                    // `assign_position` gives its static position.
+                    self.end += 1;
+                    self.new_boxes.push(layoutbox);
+                }
                 _ => {
                     println!("unimplemented: {}:{}", file!(), line!());
                 }
@@ -142,16 +161,19 @@ impl LineMaker {
     }
 
     pub fn flush_cur_line(&mut self) {
+        // The inline boxes still being laid out don't wrap their fragments yet, so the metrics
+        // are refined by `assign_position`.
+        let metrics = self.line_metrics(&self.new_boxes[self.start..self.end]);
         // Push remainings to `lines`.
         self.lines.push(Line {
             range: self.start..self.end,
-            metrics: self.cur_metrics,
+            metrics: metrics,
             width: self.new_boxes[self.start..self.end]
                 .iter()
                 .fold(Au(0), |acc, lbox| acc + lbox.dimensions.margin_box().width),
             zone: self.pending.zone,
         });
-        self.cur_height += self.cur_metrics.calculate_line_height();
+        self.cur_height += metrics.calculate_line_height();
         self.start = self.end;
     }
 
@@ -159,47 +181,105 @@ impl LineMaker {
         self.flush_cur_line()
     }
 
+    /// The height of a line box and its baseline: the boxes aligned to the baseline of the root
+    /// inline box, and the strut, are fit first. Then the boxes aligned by `top` and `bottom`
+    /// extend it if they don't fit.
+    /// Lines without text or other in-flow content have no height.
+    /// ref. https://www.w3.org/TR/CSS2/visudet.html#line-height
+    fn line_metrics(&self, boxes: &[LayoutBox]) -> LineMetrics {
+        if !boxes.iter().any(|new_box| new_box.has_inline_content()) {
+            return LineMetrics::new(Au(0), Au(0));
+        }
+
+        let mut metrics = self.strut;
+        let mut line_aligned = vec![];
+        for new_box in boxes {
+            match new_box.baseline_shift(&self.root) {
+                Some(shift) => metrics.add(new_box.inline_extent(), shift),
+                None => line_aligned.push(new_box),
+            }
+            new_box.collect_line_aligned_descendants(&mut line_aligned);
+        }
+
+        for new_box in line_aligned {
+            let height = new_box.inline_extent().calculate_line_height();
+            if new_box.property.computed.vertical_align == VerticalAlign::Top {
+                metrics.under_baseline =
+                    max(metrics.under_baseline, height - metrics.above_baseline);
+            } else {
+                metrics.above_baseline =
+                    max(metrics.above_baseline, height - metrics.under_baseline);
+            }
+        }
+        metrics
+    }
+
     pub fn assign_position(&mut self) {
-        self.cur_height = Au(0);
+        self.reorder_lines();
 
-        for line in &self.lines {
+        self.cur_height = Au(0);
+        self.baselines.clear();
+
+        for i in 0..self.lines.len() {
+            let metrics = self.line_metrics(&self.new_boxes[self.lines[i].range.clone()]);
+            self.lines[i].metrics = metrics;
+            let line = &self.lines[i];
+            let line_top = self.cur_height;
+            let line_bottom = self.cur_height + metrics.calculate_line_height();
+            let baseline = self.cur_height + metrics.above_baseline;
+            self.baselines.push(baseline);
             self.cur_width = Au(0);
 
-            for new_box in &mut self.new_boxes[line.range.clone()] {
-                let (left_floats_width, mut max_width_considered_float) =
-                    (line.zone.x, line.zone.width);
+            let (left_floats_width, mut max_width_considered_float) =
+                (line.zone.x, line.zone.width);
 
-                if max_width_considered_float < Au(0) {
-                    max_width_considered_float = line.width;
-                }
+            if max_width_considered_float < Au(0) {
+                max_width_considered_float = line.width;
+            }
+
+            let spare_width = max_width_considered_float - line.width;
+            let mut text_align = self.used_text_align(i + 1 == self.lines.len());
+            if text_align == TextAlign::Justify
+                && !justify_line(&mut self.new_boxes[line.range.clone()], spare_width)
+            {
+                text_align = physical_text_align(TextAlign::Start, self.root.direction);
+            }
 
-                // TODO: Refine
-                let text_align = new_box.property.text_align();
-                let init_width = match text_align {
-                    Value::Keyword(ref k) => match k.as_str() {
-                        "center" => (max_width_considered_float - line.width) / 2,
-                        "right" => max_width_considered_float - line.width,
-                        "left" | _ => Au(0),
-                    },
-                    _ => Au(0),
-                } + left_floats_width;
+            let init_width = match text_align {
+                TextAlign::Center => spare_width / 2,
+                TextAlign::Right => spare_width,
+                _ => Au(0),
+            } + left_floats_width;
 
+            for new_box in &mut self.new_boxes[line.range.clone()] {
                 new_box.dimensions.content.x = init_width + self.cur_width
                     + new_box.dimensions.padding.left
                     + new_box.dimensions.border.left
                     + new_box.dimensions.margin.left;
 
-                // TODO: Refine
-                let ascent = new_box.content_inline_ascent();
+                let shift = new_box.baseline_shift(&self.root);
+                let box_baseline = new_box.aligned_baseline(shift, baseline, line_top, line_bottom);
                 new_box.dimensions.content.y =
-                    self.cur_height + (line.metrics.above_baseline - ascent);
+                    new_box.align_inline_box(box_baseline, line_top, line_bottom);
 
                 self.cur_width += new_box.dimensions.margin_box().width;
             }
-            self.cur_height += line.metrics.calculate_line_height();
+            self.cur_height = line_bottom;
         }
     }
 
+    /// The `text-align` of a line, resolved to left, right, center or justify. `text-align-last`
+    /// applies to the last line, which `text-align: justify` alone leaves start-aligned.
+    /// ref. https://www.w3.org/TR/css-text-3/#text-align-last-property
+    fn used_text_align(&self, last_line: bool) -> TextAlign {
+        let text_align = match (last_line, self.root.text_align_last) {
+            (true, Some(text_align_last)) => text_align_last,
+            (true, None) if self.root.text_align == TextAlign::Justify => TextAlign::Start,
+            _ => self.root.text_align,
+        };
+        physical_text_align(text_align, self.root.direction)
+    }
+
     fn run_on_inline_node(
         &mut self,
         layoutbox: &LayoutBox,
@@ -219,9 +299,9 @@ impl LineMaker {
                 .push_back(VecDeque::from(layoutbox.children.clone()));
             layoutbox.children.clear();
 
-            layoutbox.assign_padding();
+            layoutbox.assign_padding(containing_block);
             layoutbox.assign_border_width();
-            layoutbox.assign_margin();
+            layoutbox.assign_margin(containing_block);
 
             let start = linemaker.end;
 
@@ -290,26 +370,22 @@ impl LineMaker {
             }
             LayoutInfo::Image(_) => {
                 // Replaced Inline Element (<img>)
-                let width;
-                let height;
                 let mut layoutbox = layoutbox.clone();
                 layoutbox.layout_inline(&mut self.floats, containing_block);
-                width = layoutbox.dimensions.border_box().width;
-                height = layoutbox.dimensions.border_box().height;
+                let width = layoutbox.dimensions.border_box().width;
 
                 if !shrink_to_fit && self.cur_width + width > max_width {
                     self.flush_cur_line();
                     self.end += 1;
 
                     self.cur_width = width;
-                    self.cur_metrics.above_baseline = height;
                 } else {
                     self.end += 1;
                     self.cur_width += width;
-                    self.cur_metrics.above_baseline = max(self.cur_metrics.above_baseline, height);
                 }
 
                 self.new_boxes.push(layoutbox);
+                self.last_char = Some('\u{FFFC}');
             }
             LayoutInfo::Button(_, _) => {
                 // let btn_text = text(&layoutbox);
@@ -424,105 +500,134 @@ impl LineMaker {
     }
 
     fn run_on_inline_block_node(&mut self, mut layoutbox: LayoutBox, max_width: Au) {
+        let shrink_to_fit = max_width < Au(0);
+
+        // A negative width makes the inline-block shrink to its preferred width.
         let mut containing_block: Dimensions = ::std::default::Default::default();
-        containing_block.content.width = max_width - self.cur_width;
+        containing_block.content.width = max_width;
+        // The height of the line isn't known, so percentage heights are treated as `auto`.
         layoutbox.layout(
             &mut self.floats,
-            Au(0),
-            containing_block,
+            &mut CollapsibleMargins::default(),
             containing_block,
+            indefinite_block(containing_block),
             containing_block,
         );
 
         let box_width = layoutbox.dimensions.margin_box().width;
 
-        if self.cur_width + box_width > max_width {
+        if !shrink_to_fit && self.cur_width + box_width > max_width {
             self.flush_cur_line();
             self.end += 1;
 
             self.cur_width = box_width;
-            self.cur_metrics.above_baseline = max(
-                self.cur_metrics.above_baseline,
-                layoutbox.dimensions.margin_box().height,
-            );
-
-            self.new_boxes.push(layoutbox);
         } else {
             self.end += 1;
             self.cur_width += box_width;
-            self.cur_metrics.above_baseline = max(
-                self.cur_metrics.above_baseline,
-                layoutbox.dimensions.margin_box().height,
-            );
-
-            self.new_boxes.push(layoutbox);
         }
+        self.new_boxes.push(layoutbox);
+        self.last_char = Some('\u{FFFC}');
     }
 
     fn run_on_text_node(&mut self, layoutbox: &LayoutBox, max_width: Au) {
         let shrink_to_fit = max_width < Au(0);
 
-        let text = if let NodeType::Text(ref text) = layoutbox.node.data {
-            &text[self.pending.range.clone()]
+        let (whole_text, range) = if let NodeType::Text(ref text) = layoutbox.node.data {
+            (text.as_str(), self.pending.range.clone())
         } else {
             return;
         };
+        let text = &whole_text[range.clone()];
 
         let mut new_layoutbox = layoutbox.clone();
 
-        let font_size = new_layoutbox.property.font_size();
-        let line_height = new_layoutbox.property.line_height();
-        let font_weight = new_layoutbox.property.font_weight();
-        let font_slant = new_layoutbox.property.font_style();
-
-        let my_font = Font::new(font_size, font_weight, font_slant);
+        let style = &layoutbox.property.computed;
+        let my_font = text_font(style);
         let text_width = Au::from_f64_px(my_font.text_width(text));
         let (ascent, descent) = my_font.get_ascent_descent();
 
-        self.end += 1;
+        // The whitespace at the end of a line hangs.
+        let fits = shrink_to_fit
+            || self.cur_width + Au::from_f64_px(my_font.text_width(text.trim_right()))
+                <= max_width;
 
-        self.cur_metrics.above_baseline = max(
-            self.cur_metrics.above_baseline,
-            ascent + (line_height - (ascent + descent)) / 2,
-        );
-        self.cur_metrics.under_baseline = max(
-            self.cur_metrics.under_baseline,
-            (line_height - (ascent + descent)) / 2 + descent,
-        );
+        // Where to break the text. None if the rest of the text goes on this line.
+        let break_at = if fits {
+            None
+        } else {
+            let remaining_width = (max_width - self.cur_width).to_f64_px();
+            let line_is_empty = self.start == self.end;
+            let breaks: Vec<usize> = break_opportunities(
+                whole_text,
+                self.last_char,
+                style.word_break,
+                style.line_break,
+            ).into_iter()
+                .filter(|&offset| range.start <= offset && offset < range.end)
+                .map(|offset| offset - range.start)
+                // Combined text is set as a single character.
+                .filter(|&offset| offset == 0 || my_font.orientation != GlyphOrientation::Combined)
+                .collect();
+            let can_break_before = breaks.first() == Some(&0);
+
+            let (max_chars, _) =
+                my_font.compute_max_chars_and_width(text, &breaks, remaining_width);
+            if max_chars > 0 {
+                Some(max_chars)
+            } else if !line_is_empty && can_break_before {
+                // Move the whole text to the next line.
+                self.flush_cur_line();
+                self.cur_width = Au(0);
+                return;
+            } else if style.overflow_wrap != OverflowWrap::Normal {
+                // An otherwise unbreakable word can be broken at any character if it doesn't fit.
+                // ref. https://www.w3.org/TR/css-text-3/#overflow-wrap-property
+                let clusters = cluster_boundaries(text);
+                let (max_chars, _) =
+                    my_font.compute_max_chars_and_width(text, &clusters, remaining_width);
+                if max_chars == 0 && !line_is_empty {
+                    self.flush_cur_line();
+                    self.cur_width = Au(0);
+                    return;
+                }
+                if max_chars > 0 {
+                    Some(max_chars)
+                } else {
+                    // At least a character goes on an empty line.
+                    Some(clusters.first().cloned().unwrap_or(text.len()))
+                }
+            } else {
+                // Nothing fits, so the text overflows up to the first break opportunity.
+                breaks.into_iter().find(|&offset| offset > 0)
+            }
+        };
+
+        self.end += 1;
 
-        if !shrink_to_fit && self.cur_width + text_width > max_width {
-            let remaining_width = max_width - self.cur_width; // Is this correc?
-            let (max_chars, text_width) =
-                my_font.compute_max_chars_and_width(text, remaining_width.to_f64_px());
+        new_layoutbox.dimensions.content.height = ascent + descent;
 
-            new_layoutbox.dimensions.content.width = Au::from_f64_px(text_width);
-            new_layoutbox.dimensions.content.height = ascent + descent;
+        if let Some(max_chars) = break_at {
+            new_layoutbox.dimensions.content.width =
+                Au::from_f64_px(my_font.text_width(&text[..max_chars]));
 
-            new_layoutbox.set_text_info(
-                Font::new(font_size, font_weight, font_slant),
-                self.pending.range.start..self.pending.range.start + max_chars,
-            );
+            new_layoutbox.set_text_info(my_font, range.start..range.start + max_chars);
             self.new_boxes.push(new_layoutbox);
 
-            self.pending.range = self.pending.range.start + max_chars..self.pending.range.end;
+            self.pending.range = range.start + max_chars..range.end;
 
             self.flush_cur_line();
 
             self.cur_width = Au(0);
-            self.cur_metrics.reset();
         } else {
             new_layoutbox.dimensions.content.width = text_width;
-            new_layoutbox.dimensions.content.height = ascent + descent;
 
-            new_layoutbox.set_text_info(
-                Font::new(font_size, font_weight, font_slant),
-                self.pending.range.start..text.len() + self.pending.range.start,
-            );
+            new_layoutbox.set_text_info(my_font, range);
             self.new_boxes.push(new_layoutbox);
 
             self.pending.range = 0..0;
 
             self.cur_width += text_width;
+            self.last_char = text.chars().last();
         }
     }
 }
@@ -532,38 +637,567 @@ impl LayoutBox {
     pub fn layout_inline(&mut self, _floats: &mut Floats, containing_block: Dimensions) {
         self.load_image(containing_block);
 
-        self.assign_padding();
+        self.assign_padding(containing_block);
         self.assign_border_width();
-        self.assign_margin();
+        self.assign_margin(containing_block);
 
         self.assign_replaced_width_if_necessary();
         self.assign_replaced_height_if_necessary();
     }
 }
 
+/// Resolve `start` and `end` to the side of a line they are on in `direction`.
+fn physical_text_align(text_align: TextAlign, direction: Direction) -> TextAlign {
+    match (text_align, direction) {
+        (TextAlign::Start, Direction::Ltr) | (TextAlign::End, Direction::Rtl) => TextAlign::Left,
+        (TextAlign::Start, Direction::Rtl) | (TextAlign::End, Direction::Ltr) => TextAlign::Right,
+        (text_align, _) => text_align,
+    }
+}
+
+/// Distribute `spare_width` evenly to the justification opportunities in the line of `boxes`.
+/// The whitespace at the end of the line hangs, so its width is distributed as well. Return
+/// false if there is nothing to distribute or nowhere to put it.
+/// ref. https://www.w3.org/TR/css-text-3/#justify-algos
+fn justify_line(boxes: &mut [LayoutBox], spare_width: Au) -> bool {
+    let last = match boxes.len() {
+        0 => return false,
+        len => len - 1,
+    };
+    let spare_width = spare_width + boxes[last].trailing_whitespace_width();
+    let count: usize = boxes
+        .iter()
+        .enumerate()
+        .map(|(i, new_box)| new_box.justification_opportunities(i == last))
+        .sum();
+    if count == 0 || spare_width <= Au(0) {
+        return false;
+    }
+    let spacing = spare_width / count as i32;
+    for (i, new_box) in boxes.iter_mut().enumerate() {
+        new_box.justify(spacing, i == last);
+    }
+    true
+}
+
+/// The byte offsets in `text` where justification adds space: after each word separator, and
+/// between the letters of the scripts that don't separate words, i.e. Chinese and Japanese.
+/// The whitespace at the end of `text` is ignored if `trim_end`.
+/// ref. https://www.w3.org/TR/css-text-3/#justification-opportunity
+pub fn justification_opportunities(text: &str, trim_end: bool) -> Vec<usize> {
+    fn is_cjk(c: char) -> bool {
+        match c as u32 {
+            0x3000...0x30FF | 0x3400...0x4DBF | 0x4E00...0x9FFF | 0xFF00...0xFFEF => true,
+            _ => false,
+        }
+    }
+
+    let text = if trim_end { text.trim_right() } else { text };
+    let mut opportunities = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = match chars.peek() {
+            Some(&(_, next)) => Some(next),
+            None => None,
+        };
+        let between_cjk = next.map_or(false, |next| {
+            !next.is_whitespace() && (is_cjk(c) || is_cjk(next))
+        });
+        if c.is_whitespace() || between_cjk {
+            opportunities.push(i + c.len_utf8());
+        }
+    }
+    opportunities
+}
+
 impl LayoutBox {
-    fn get_first_text_node(&self) -> Option<&LayoutBox> {
+    /// The number of justification opportunities in this fragment. `last` tells whether it ends
+    /// the line.
+    fn justification_opportunities(&self, last: bool) -> usize {
         match self.box_type {
-            BoxType::TextNode(_) => Some(self),
-            _ => {
+            BoxType::TextNode(ref text) => match self.node.data {
+                NodeType::Text(ref s) => {
+                    justification_opportunities(&s[text.range.clone()], last).len()
+                }
+                _ => 0,
+            },
+            BoxType::InlineNode if !self.is_replaced() => {
+                let last_child = self.children.len().saturating_sub(1);
+                self.children
+                    .iter()
+                    .enumerate()
+                    .map(|(i, child)| child.justification_opportunities(last && i == last_child))
+                    .sum()
+            }
+            _ => 0,
+        }
+    }
+
+    /// Add `spacing` at each justification opportunity in this fragment and return how much
+    /// wider it gets.
+    fn justify(&mut self, spacing: Au, last: bool) -> Au {
+        let count = self.justification_opportunities(last);
+        let added = match self.box_type {
+            BoxType::TextNode(ref mut text) => {
+                text.justification = spacing;
+                spacing * count as i32
+            }
+            BoxType::InlineNode if !self.is_replaced() => {
+                let last_child = self.children.len().saturating_sub(1);
+                self.children
+                    .iter_mut()
+                    .enumerate()
+                    .fold(Au(0), |added, (i, child)| {
+                        added + child.justify(spacing, last && i == last_child)
+                    })
+            }
+            _ => Au(0),
+        };
+        self.dimensions.content.width += added;
+        added
+    }
+
+    /// The width of the whitespace at the end of this fragment.
+    fn trailing_whitespace_width(&self) -> Au {
+        match self.box_type {
+            BoxType::TextNode(ref text) => match self.node.data {
+                NodeType::Text(ref s) => {
+                    let s = &s[text.range.clone()];
+                    let style = &self.property.computed;
+                    Au::from_f64_px(text_font(style).text_width(&s[s.trim_right().len()..]))
+                }
+                _ => Au(0),
+            },
+            BoxType::InlineNode if !self.is_replaced() => self.children
+                .last()
+                .map_or(Au(0), |child| child.trailing_whitespace_width()),
+            _ => Au(0),
+        }
+    }
+}
+
+// Bidirectional text. The lines are broken in the logical order, and then the fragments in each
+// line are split at the changes of the bidi level and reordered.
+// ref. https://www.w3.org/TR/css-writing-modes-3/#text-direction
+impl LineMaker {
+    /// The level of the paragraph: `direction` of the block container, or its first strong
+    /// character if `unicode-bidi` is `plaintext`.
+    fn base_level(&self, paragraph: &str) -> u8 {
+        match (self.root.unicode_bidi, self.root.direction) {
+            (UnicodeBidi::Plaintext, _) => bidi::first_strong_level(paragraph).unwrap_or(0),
+            (_, Direction::Rtl) => 1,
+            (_, Direction::Ltr) => 0,
+        }
+    }
+
+    /// Reorder the fragments of each line by the bidi levels of their text.
+    fn reorder_lines(&mut self) {
+        // `bidi-override` on a block container overrides its inline content.
+        let override_content = match self.root.unicode_bidi {
+            UnicodeBidi::BidiOverride | UnicodeBidi::IsolateOverride => true,
+            _ => false,
+        };
+
+        // The text of the paragraph, and where each fragment starts in it.
+        let mut paragraph = String::new();
+        if override_content {
+            paragraph.push(if self.root.direction == Direction::Rtl {
+                bidi::RLO
+            } else {
+                bidi::LRO
+            });
+        }
+        let mut starts = vec![];
+        for new_box in &self.new_boxes {
+            starts.push(paragraph.len());
+            new_box.push_bidi_text(&mut paragraph);
+        }
+        starts.push(paragraph.len());
+        if override_content {
+            paragraph.push(bidi::PDF);
+        }
+
+        let base_level = self.base_level(&paragraph);
+        if base_level == 0 && !bidi::has_right_to_left(&paragraph) {
+            return;
+        }
+        let levels = bidi::resolve_levels(&paragraph, base_level);
+
+        let mut boxes: Vec<Option<LayoutBox>> = self.new_boxes.drain(..).map(Some).collect();
+        for line in &mut self.lines {
+            let (line_start, line_end) = (starts[line.range.start], starts[line.range.end]);
+            let mut line_levels = levels[line_start..line_end].to_vec();
+            bidi::reset_whitespace_levels(
+                &paragraph[line_start..line_end],
+                &mut line_levels,
+                base_level,
+            );
+
+            let mut pieces = vec![];
+            for i in line.range.clone() {
+                let fragment = boxes[i].take().unwrap();
+                let fragment_levels =
+                    &line_levels[starts[i] - line_start..starts[i + 1] - line_start];
+                let default_level = pieces.last().map_or(base_level, |&(_, level)| level);
+                fragment.split_by_bidi_level(fragment_levels, default_level, &mut pieces);
+            }
+
+            let piece_levels: Vec<u8> = pieces.iter().map(|&(_, level)| level).collect();
+            let mut pieces: Vec<Option<LayoutBox>> =
+                pieces.into_iter().map(|(piece, _)| Some(piece)).collect();
+            let start = self.new_boxes.len();
+            for i in bidi::visual_order(&piece_levels) {
+                self.new_boxes.push(pieces[i].take().unwrap());
+            }
+            line.range = start..self.new_boxes.len();
+            line.width = self.new_boxes[line.range.clone()]
+                .iter()
+                .fold(Au(0), |acc, piece| acc + piece.dimensions.margin_box().width);
+        }
+    }
+}
+
+/// The bidi control characters that `unicode-bidi` of an inline box puts around its content.
+/// ref. https://www.w3.org/TR/css-writing-modes-3/#bidi-control-codes-injection-table
+fn bidi_controls(style: &ComputedStyle) -> (&'static str, &'static str) {
+    let rtl = style.direction == Direction::Rtl;
+    match (style.unicode_bidi, rtl) {
+        (UnicodeBidi::Normal, _) => ("", ""),
+        (UnicodeBidi::Embed, false) => ("\u{202A}", "\u{202C}"),
+        (UnicodeBidi::Embed, true) => ("\u{202B}", "\u{202C}"),
+        (UnicodeBidi::Isolate, false) => ("\u{2066}", "\u{2069}"),
+        (UnicodeBidi::Isolate, true) => ("\u{2067}", "\u{2069}"),
+        (UnicodeBidi::BidiOverride, false) => ("\u{202D}", "\u{202C}"),
+        (UnicodeBidi::BidiOverride, true) => ("\u{202E}", "\u{202C}"),
+        (UnicodeBidi::IsolateOverride, false) => ("\u{2066}\u{202D}", "\u{202C}\u{2069}"),
+        (UnicodeBidi::IsolateOverride, true) => ("\u{2067}\u{202E}", "\u{202C}\u{2069}"),
+        (UnicodeBidi::Plaintext, _) => ("\u{2068}", "\u{2069}"),
+    }
+}
+
+impl LayoutBox {
+    /// Push the text of this fragment to the bidi paragraph. Atomic inlines count as U+FFFC
+    /// OBJECT REPLACEMENT CHARACTER.
+    fn push_bidi_text(&self, paragraph: &mut String) {
+        match self.box_type {
+            BoxType::TextNode(ref text) => if let NodeType::Text(ref s) = self.node.data {
+                paragraph.push_str(&s[text.range.clone()]);
+            },
+            BoxType::InlineNode if !self.is_replaced() => {
+                let (open, close) = bidi_controls(&self.property.computed);
+                paragraph.push_str(open);
                 for child in &self.children {
-                    if let Some(node) = child.get_first_text_node() {
-                        return Some(node);
+                    child.push_bidi_text(paragraph);
+                }
+                paragraph.push_str(close);
+            }
+            BoxType::Absolute => {}
+            _ => paragraph.push('\u{FFFC}'),
+        }
+    }
+
+    /// Split this fragment where the bidi level of its text changes, and push the pieces with
+    /// their levels to `pieces`. `levels` are the levels of the bytes of its bidi text.
+    /// A fragment of an inline box is split along with the fragment it wraps, and only the first
+    /// and the last pieces keep the padding, the border and the margin of its sides.
+    fn split_by_bidi_level(
+        mut self,
+        levels: &[u8],
+        default_level: u8,
+        pieces: &mut Vec<(LayoutBox, u8)>,
+    ) {
+        match self.box_type {
+            BoxType::TextNode(_) => {
+                let (range, font) = match self.box_type {
+                    BoxType::TextNode(ref text) => (text.range.clone(), text.font),
+                    _ => unreachable!(),
+                };
+                let text = match self.node.data {
+                    NodeType::Text(ref s) => s[range.clone()].to_string(),
+                    _ => return,
+                };
+                let mut start = 0;
+                while start < text.len() {
+                    let level = levels[start];
+                    let end = (start..text.len())
+                        .find(|&i| levels[i] != level)
+                        .unwrap_or(text.len());
+                    let mut piece = self.clone();
+                    if start > 0 || end < text.len() {
+                        let piece_font = text_font(&self.property.computed);
+                        piece.dimensions.content.width =
+                            Au::from_f64_px(piece_font.text_width(&text[start..end]));
+                        piece.set_text_info(font, range.start + start..range.start + end);
+                    }
+                    if let BoxType::TextNode(ref mut text) = piece.box_type {
+                        text.bidi_level = level;
+                    }
+                    pieces.push((piece, level));
+                    start = end;
+                }
+            }
+            BoxType::InlineNode if !self.is_replaced() && !self.children.is_empty() => {
+                let (open, _) = bidi_controls(&self.property.computed);
+                let mut child_pieces = vec![];
+                let mut start = open.len();
+                for child in self.children.drain(..) {
+                    let mut child_text = String::new();
+                    child.push_bidi_text(&mut child_text);
+                    let child_levels = &levels[start..start + child_text.len()];
+                    start += child_text.len();
+                    child.split_by_bidi_level(child_levels, default_level, &mut child_pieces);
+                }
+                let count = child_pieces.len();
+                for (i, (piece, level)) in child_pieces.into_iter().enumerate() {
+                    let mut wrapper = self.clone();
+                    if count > 1 {
+                        let d = &mut wrapper.dimensions;
+                        if i > 0 {
+                            d.padding.left = Au(0);
+                            d.border.left = Au(0);
+                            d.margin.left = Au(0);
+                        }
+                        if i < count - 1 {
+                            d.padding.right = Au(0);
+                            d.border.right = Au(0);
+                            d.margin.right = Au(0);
+                        }
+                        d.content.width = piece.dimensions.content.width;
                     }
+                    wrapper.children.push(piece);
+                    pieces.push((wrapper, level));
                 }
-                None
+            }
+            BoxType::Absolute => pieces.push((self, default_level)),
+            _ => {
+                let level = levels.first().cloned().unwrap_or(default_level);
+                pieces.push((self, level));
             }
         }
     }
+}
+
+impl LineMetrics {
+    /// Extend the metrics to cover `extent` raised by `shift`.
+    fn add(&mut self, extent: LineMetrics, shift: Au) {
+        self.above_baseline = max(self.above_baseline, extent.above_baseline + shift);
+        self.under_baseline = max(self.under_baseline, extent.under_baseline - shift);
+    }
+}
 
-    pub fn content_inline_ascent(&mut self) -> Au {
-        let height = self.dimensions.content.height;
-        match self.get_first_text_node() {
-            Some(node) => match node.box_type {
-                BoxType::TextNode(Text { font, .. }) => font.get_ascent_descent().0,
-                _ => unreachable!(),
+/// The font of the text in `style`, with the glyphs set in the orientation of its writing mode.
+pub fn text_font(style: &ComputedStyle) -> Font {
+    Font::new(style.font_size, style.font_weight, style.font_style)
+        .with_orientation(style.glyph_orientation())
+}
+
+/// The ascent and descent of the font of `style`.
+fn font_metrics(style: &ComputedStyle) -> (Au, Au) {
+    text_font(style).get_ascent_descent()
+}
+
+/// The extent of an inline box of `style` around its baseline: the content area with the
+/// half-leading added above and under it.
+/// ref. https://www.w3.org/TR/CSS2/visudet.html#leading
+fn leading_box(style: &ComputedStyle) -> LineMetrics {
+    let (ascent, descent) = font_metrics(style);
+    let half_leading = (style.line_height() - (ascent + descent)) / 2;
+    LineMetrics::new(ascent + half_leading, descent + half_leading)
+}
+
+// The vertical alignment of inline-level boxes in a line box. A fragment of an inline box wraps
+// a single fragment of its content, which is aligned to the baseline of the inline box.
+// ref. https://www.w3.org/TR/CSS2/visudet.html#propdef-vertical-align
+impl LayoutBox {
+    /// Whether this box makes a line box non-empty.
+    fn has_inline_content(&self) -> bool {
+        match self.box_type {
+            BoxType::TextNode(ref text) => match self.node.data {
+                NodeType::Text(ref s) => !s[text.range.clone()].chars().all(char::is_whitespace),
+                _ => false,
             },
-            None => height,
+            BoxType::Absolute => false,
+            BoxType::InlineNode if !self.is_replaced() => {
+                let d = &self.dimensions;
+                d.left_offset() + d.right_offset() > Au(0)
+                    || self.children.iter().any(|child| child.has_inline_content())
+            }
+            _ => true,
+        }
+    }
+
+    /// The distance from the top of the content area to the baseline. The baseline of an
+    /// inline-block is that of its last line box, and the bottom margin edge if it has none or
+    /// its overflow isn't visible, as is the baseline of a replaced element.
+    /// ref. https://www.w3.org/TR/CSS2/visudet.html#propdef-vertical-align
+    fn inline_baseline(&self) -> Au {
+        let d = &self.dimensions;
+        let bottom_margin_edge = d.content.height + d.padding.bottom + d.border.bottom
+            + d.margin.bottom;
+        match self.box_type {
+            BoxType::TextNode(_) => font_metrics(&self.property.computed).0,
+            BoxType::InlineBlockNode if self.property.computed.display == Display::Ruby => {
+                self.ruby_baseline().unwrap_or(bottom_margin_edge)
+            }
+            BoxType::InlineBlockNode if !self.property.computed.clips_overflow() => {
+                self.last_baseline().unwrap_or(bottom_margin_edge)
+            }
+            _ => bottom_margin_edge,
+        }
+    }
+
+    /// The extent of this box around its baseline, i.e. the leading box of an inline box or
+    /// text, and the margin box of the others.
+    fn own_inline_extent(&self) -> LineMetrics {
+        let d = &self.dimensions;
+        match self.box_type {
+            BoxType::TextNode(_) => leading_box(&self.property.computed),
+            BoxType::InlineNode if !self.is_replaced() => leading_box(&self.property.computed),
+            BoxType::Absolute => LineMetrics::new(Au(0), Au(0)),
+            _ => {
+                let above = d.margin.top + d.border.top + d.padding.top + self.inline_baseline();
+                LineMetrics::new(above, d.margin_box().height - above)
+            }
+        }
+    }
+
+    /// The extent of this box and its inline descendants around its baseline. Descendants
+    /// aligned to the line box by `top` or `bottom` aren't included.
+    fn inline_extent(&self) -> LineMetrics {
+        let mut extent = self.own_inline_extent();
+        if self.box_type == BoxType::InlineNode && !self.is_replaced() {
+            for child in &self.children {
+                if let Some(shift) = child.baseline_shift(&self.property.computed) {
+                    extent.add(child.inline_extent(), shift);
+                }
+            }
+        }
+        extent
+    }
+
+    /// How far `vertical-align` raises the baseline of this box above the baseline of its
+    /// parent, whose style is `parent`. None if the box is aligned to the line box instead.
+    fn baseline_shift(&self, parent: &ComputedStyle) -> Option<Au> {
+        // Text is aligned by its parent inline box.
+        if let BoxType::TextNode(_) = self.box_type {
+            return Some(Au(0));
+        }
+        let style = &self.property.computed;
+        let extent = || self.own_inline_extent();
+        Some(match style.vertical_align {
+            VerticalAlign::Baseline => Au(0),
+            VerticalAlign::Sub => -parent.font_size / 5,
+            VerticalAlign::Super => parent.font_size / 3,
+            VerticalAlign::TextTop => font_metrics(parent).0 - extent().above_baseline,
+            VerticalAlign::TextBottom => extent().under_baseline - font_metrics(parent).1,
+            // The midpoint of the box is aligned to half the x-height, taken as 0.5em, above the
+            // baseline of the parent.
+            VerticalAlign::Middle => {
+                let extent = extent();
+                parent.font_size / 4 - (extent.above_baseline - extent.under_baseline) / 2
+            }
+            VerticalAlign::Length(length) => length.to_au(style.line_height()),
+            VerticalAlign::Top | VerticalAlign::Bottom => return None,
+        })
+    }
+
+    /// The position of the baseline of this box raised by `shift` from `parent_baseline`, or
+    /// aligned to the top or the bottom of the line box if `shift` is None.
+    fn aligned_baseline(
+        &self,
+        shift: Option<Au>,
+        parent_baseline: Au,
+        line_top: Au,
+        line_bottom: Au,
+    ) -> Au {
+        match shift {
+            Some(shift) => parent_baseline - shift,
+            None => {
+                let extent = self.inline_extent();
+                if self.property.computed.vertical_align == VerticalAlign::Top {
+                    line_top + extent.above_baseline
+                } else {
+                    line_bottom - extent.under_baseline
+                }
+            }
+        }
+    }
+
+    /// Push the inline descendants aligned by `top` or `bottom` to `boxes`.
+    fn collect_line_aligned_descendants<'a>(&'a self, boxes: &mut Vec<&'a LayoutBox>) {
+        if self.box_type != BoxType::InlineNode || self.is_replaced() {
+            return;
+        }
+        for child in &self.children {
+            if child.baseline_shift(&self.property.computed).is_none() {
+                boxes.push(child);
+            }
+            child.collect_line_aligned_descendants(boxes);
+        }
+    }
+
+    /// Place the inline descendants of this box, whose baseline is at `baseline`, and return the
+    /// top of the content area. The fragment of an inline box follows the content it wraps.
+    /// All the positions are relative to the line boxes.
+    fn align_inline_box(&mut self, baseline: Au, line_top: Au, line_bottom: Au) -> Au {
+        match self.box_type {
+            // The static position of an absolutely positioned box is the top of the line.
+            BoxType::Absolute => line_top,
+            BoxType::InlineNode if !self.is_replaced() => {
+                let shifts: Vec<Option<Au>> = self.children
+                    .iter()
+                    .map(|child| child.baseline_shift(&self.property.computed))
+                    .collect();
+                let mut tops = vec![];
+                for (child, shift) in self.children.iter_mut().zip(shifts) {
+                    let child_baseline =
+                        child.aligned_baseline(shift, baseline, line_top, line_bottom);
+                    tops.push(child.align_inline_box(child_baseline, line_top, line_bottom));
+                }
+                let top = tops.first()
+                    .cloned()
+                    .unwrap_or(baseline - font_metrics(&self.property.computed).0);
+                for (child, child_top) in self.children.iter_mut().zip(tops) {
+                    child.dimensions.content.y = child_top - top;
+                }
+                top
+            }
+            _ => baseline - self.inline_baseline(),
+        }
+    }
+
+    /// The baseline of the first line box in this box, relative to the top of its content
+    /// area. None if there are no line boxes in normal flow.
+    pub fn first_baseline(&self) -> Option<Au> {
+        match self.box_type {
+            BoxType::AnonymousBlock => self.line_baselines.first().cloned(),
+            _ => self.children
+                .iter()
+                .filter(|child| child.in_normal_flow())
+                .filter_map(|child| {
+                    child
+                        .first_baseline()
+                        .map(|baseline| child.dimensions.content.y + baseline)
+                })
+                .next(),
+        }
+    }
+
+    /// The baseline of the last line box in this box, relative to the top of its content area.
+    /// None if there are no line boxes in normal flow.
+    pub fn last_baseline(&self) -> Option<Au> {
+        match self.box_type {
+            BoxType::AnonymousBlock => self.line_baselines.last().cloned(),
+            _ => self.children
+                .iter()
+                .rev()
+                .filter(|child| child.in_normal_flow())
+                .filter_map(|child| {
+                    child
+                        .last_baseline()
+                        .map(|baseline| child.dimensions.content.y + baseline)
+                })
+                .next(),
         }
     }
 }
@@ -574,40 +1208,228 @@ impl LayoutBox {
     pub fn layout_inline_block(
         &mut self,
         _floats: &mut Floats,
-        _last_margin_bottom: Au,
+        _margins: &mut CollapsibleMargins,
         containing_block: Dimensions,
-        _saved_block: Dimensions,
+        saved_block: Dimensions,
         viewport: Dimensions,
     ) {
+        if self.property.computed.display == Display::Ruby {
+            self.layout_ruby(containing_block, viewport);
+            return;
+        }
+
         // Child width can depend on parent width, so we need to calculate this box's width before
         // laying out its children.
         self.calculate_inline_block_width(containing_block);
 
-        self.assign_padding();
+        self.assign_padding(containing_block);
         self.assign_border_width();
-        self.assign_margin();
+        self.assign_margin(containing_block);
         // self.calculate_block_position(last_margin_bottom, containing_block);
 
-        self.layout_block_children(viewport);
+        let definite_block = self.definite_block(saved_block);
+        self.layout_children(definite_block, viewport);
 
         // Parent height can depend on child height, so `calculate_height` must be called after the
         // children are laid out.
-        self.calculate_block_height();
+        self.calculate_block_height(saved_block);
     }
 
-    /// Calculate the width of a block-level non-replaced element in normal flow.
-    /// Sets the horizontal margin/padding/border dimensions, and the `width`.
+    /// Calculate the width of an inline-block non-replaced element.
+    /// Sets the `width`. If `width` is auto, the used value is the shrink-to-fit width. Either is
+    /// limited by `min-width` and `max-width`.
     /// ref. https://www.w3.org/TR/CSS2/visudet.html#inlineblock-width
-    pub fn calculate_inline_block_width(&mut self, _containing_block: Dimensions) {
+    pub fn calculate_inline_block_width(&mut self, containing_block: Dimensions) {
+        let cb_width = containing_block.content.width;
         // `width` has initial value `auto`.
-        // TODO: Implement calculating shrink-to-fit width
-        if let Some(x) = self.property.value("width") {
-            self.dimensions.content.width = Au::from_f64_px(x[0].to_px().unwrap());
-        } else {
-            // TODO
-            // width == auto
-            panic!("calculating shrink-to-fit width is unsupported.");
+        let width = match self.specified_width(cb_width) {
+            Some(width) => width,
+            None => self.shrink_to_fit_width(containing_block),
         };
+        self.dimensions.content.width = self.clamp_width(width, cb_width);
+    }
+}
+
+/// The widths a box's content takes without overflowing.
+/// ref. https://www.w3.org/TR/CSS2/visudet.html#shrink-to-fit-float
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IntrinsicWidths {
+    // The width without breaking lines.
+    pub preferred: Au,
+    // The width with all possible line breaks.
+    pub minimum: Au,
+}
+
+impl LayoutBox {
+    /// Calculate the shrink-to-fit width of the content in `containing_block`, i.e.
+    /// min(max(preferred minimum width, available width), preferred width).
+    /// If the width of `containing_block` is negative, there is no limit on the width like
+    /// `LineMaker::run` with a negative `max_width`, and the preferred width is used.
+    pub fn shrink_to_fit_width(&self, containing_block: Dimensions) -> Au {
+        let widths = self.intrinsic_widths(containing_block);
+        let cb_width = containing_block.content.width;
+        if cb_width < Au(0) {
+            return widths.preferred;
+        }
+        let available_width = cb_width - self.horizontal_offset(cb_width);
+        min(max(widths.minimum, available_width), widths.preferred)
+    }
+
+    /// Calculate the preferred and preferred minimum widths of the content box.
+    pub fn intrinsic_widths(&self, containing_block: Dimensions) -> IntrinsicWidths {
+        let mut widths = IntrinsicWidths::default();
+        let mut floats_width = Au(0);
+
+        if self.box_type == BoxType::AnonymousBlock {
+            let mut linemaker =
+                LineMaker::new(self.children.clone(), Floats::new(), &self.property.computed);
+            linemaker.run(Au(-1), containing_block);
+            linemaker.end_of_lines();
+            widths.preferred = linemaker.intrinsic_width();
+
+            for inline in &self.children {
+                widths.minimum = max(widths.minimum, inline.minimum_inline_width(containing_block));
+            }
+            return widths;
+        }
+        if self.property.computed.display.is_flex() {
+            return self.flex_intrinsic_widths(containing_block);
+        }
+        if self.property.computed.display.is_grid() {
+            return self.grid_intrinsic_widths(containing_block);
+        }
+        if self.property.computed.display.is_table() {
+            return self.table_intrinsic_widths(containing_block);
+        }
+        if self.property.computed.display == Display::Ruby {
+            return self.ruby_intrinsic_widths(containing_block);
+        }
+
+        for child in &self.children {
+            match child.box_type {
+                BoxType::AnonymousBlock => {
+                    let child_widths = child.intrinsic_widths(containing_block);
+                    widths.preferred = max(widths.preferred, child_widths.preferred);
+                    widths.minimum = max(widths.minimum, child_widths.minimum);
+                }
+                BoxType::Float => {
+                    // Floats are placed side by side if possible.
+                    let child_widths = child.outer_intrinsic_widths(containing_block);
+                    floats_width += child_widths.preferred;
+                    widths.minimum = max(widths.minimum, child_widths.minimum);
+                }
+                BoxType::BlockNode
+                | BoxType::GridNode
+                | BoxType::TableNode
+                | BoxType::InlineBlockNode => {
+                    let child_widths = child.outer_intrinsic_widths(containing_block);
+                    widths.preferred = max(widths.preferred, child_widths.preferred);
+                    widths.minimum = max(widths.minimum, child_widths.minimum);
+                }
+                // Outside markers don't take space in their list items.
+                _ => {}
+            }
+        }
+
+        widths.preferred = max(widths.preferred + floats_width, widths.minimum);
+        widths
+    }
+
+    /// The intrinsic widths of the margin box. Auto margins are treated as 0.
+    pub fn outer_intrinsic_widths(&self, containing_block: Dimensions) -> IntrinsicWidths {
+        let cb_width = containing_block.content.width;
+        let offset = self.horizontal_offset(cb_width);
+
+        if let LayoutInfo::Image(_) = self.info {
+            let mut image = self.clone();
+            image.load_image(containing_block);
+            image.assign_replaced_width_if_necessary();
+            let width = image.dimensions.content.width + offset;
+            return IntrinsicWidths {
+                preferred: width,
+                minimum: width,
+            };
+        }
+
+        let widths = match self.specified_width(cb_width) {
+            Some(width) => IntrinsicWidths {
+                preferred: width,
+                minimum: width,
+            },
+            None => self.intrinsic_widths(containing_block),
+        };
+        IntrinsicWidths {
+            preferred: self.clamp_width(widths.preferred, cb_width) + offset,
+            minimum: self.clamp_width(widths.minimum, cb_width) + offset,
+        }
+    }
+
+    /// The width of the widest piece of this inline-level box that can't be broken.
+    fn minimum_inline_width(&self, containing_block: Dimensions) -> Au {
+        self.unbreakable_inline_widths(containing_block)
+            .into_iter()
+            .max()
+            .unwrap_or(Au(0))
+    }
+
+    /// The widths of the pieces of this inline-level box that can't be broken. Like the first
+    /// and the last fragments of an inline box in lines, the first and the last pieces include
+    /// the margin, the border and the padding of its sides.
+    fn unbreakable_inline_widths(&self, containing_block: Dimensions) -> Vec<Au> {
+        match self.box_type {
+            BoxType::TextNode(_) => {
+                let text = match self.node.data {
+                    NodeType::Text(ref text) => text,
+                    NodeType::Element(_) => return vec![],
+                };
+                let style = &self.property.computed;
+                let font = text_font(style);
+                // `overflow-wrap: anywhere` considers its breaks for the min-content size, but
+                // `break-word` doesn't.
+                let breaks = if style.overflow_wrap == OverflowWrap::Anywhere {
+                    cluster_boundaries(text)
+                } else {
+                    break_opportunities(text, None, style.word_break, style.line_break)
+                };
+                font.unbreakable_widths(text, &breaks)
+                    .into_iter()
+                    .map(Au::from_f64_px)
+                    .collect()
+            }
+            BoxType::InlineNode => match self.info {
+                LayoutInfo::Image(_) => vec![self.outer_intrinsic_widths(containing_block).minimum],
+                _ => {
+                    let mut widths: Vec<Au> = self.children
+                        .iter()
+                        .flat_map(|child| child.unbreakable_inline_widths(containing_block))
+                        .collect();
+                    if widths.is_empty() {
+                        widths.push(Au(0));
+                    }
+                    let cb_width = containing_block.content.width;
+                    let style = &self.property.computed;
+                    let last = widths.len() - 1;
+                    widths[0] += style.margin.left.to_au(cb_width).unwrap_or(Au(0))
+                        + style.border_width.left
+                        + style.padding.left.to_au(cb_width);
+                    widths[last] += style.margin.right.to_au(cb_width).unwrap_or(Au(0))
+                        + style.border_width.right
+                        + style.padding.right.to_au(cb_width);
+                    widths
+                }
+            },
+            BoxType::InlineBlockNode => vec![self.outer_intrinsic_widths(containing_block).minimum],
+            _ => vec![],
+        }
+    }
+
+    /// The sum of the horizontal margins, borders and paddings. Auto margins are treated as 0.
+    pub fn horizontal_offset(&self, cb_width: Au) -> Au {
+        let style = &self.property.computed;
+        style.margin.left.to_au(cb_width).unwrap_or(Au(0))
+            + style.margin.right.to_au(cb_width).unwrap_or(Au(0))
+            + style.border_width.left + style.border_width.right
+            + style.padding.left.to_au(cb_width) + style.padding.right.to_au(cb_width)
     }
 }
 
@@ -669,3 +1491,160 @@ pub fn get_pixbuf(node: &Node) -> gdk_pixbuf::Pixbuf {
             .clone()
     })
 }
+
+#[test]
+fn test_shrink_to_fit() {
+    use layout::layout_html;
+
+    // Each character is 8px wide in tests.
+    let css = "div { display: block; }
+               span { display: inline-block; padding: 1px; }
+               .f { float: left; }";
+    let widths = |width| {
+        let tree = layout_html(
+            "<div><div><span>aaa bbbb</span></div><div class='f'>a bb<span>ccc</span></div></div>",
+            css,
+            width,
+        );
+        let inline_block = &tree.children[0].children[0].children[0];
+        let float = &tree.children[1];
+        assert_eq!(float.box_type, BoxType::Float);
+        (
+            inline_block.dimensions.content.width.to_px(),
+            float.dimensions.content.width.to_px(),
+        )
+    };
+
+    // The preferred widths.
+    assert_eq!(widths(1000), (64, 58));
+    // The available widths.
+    assert_eq!(widths(50), (48, 50));
+    // The preferred minimum widths.
+    assert_eq!(widths(10), (32, 26));
+
+    // The padding of inline boxes is added to their first and last pieces.
+    let tree = layout_html(
+        "<div><div class='f'><em>aa bbb</em></div></div>",
+        "div { display: block; } .f { float: left; } em { padding: 0 4px 0 6px; }",
+        10,
+    );
+    assert_eq!(tree.children[0].dimensions.content.width.to_px(), 28);
+}
+
+#[test]
+fn test_vertical_align() {
+    use layout::layout_html;
+
+    let css = "div { display: block; }
+               .ib { display: inline-block; width: 10px; height: 30px; }
+               .super { vertical-align: super; }
+               .top { vertical-align: top; }
+               .middle { vertical-align: middle; }
+               .length { vertical-align: -2px; }";
+    let tree = layout_html(
+        "<div><div>x<span class='ib'></span></div>\
+         <div>x<span class='ib'>y</span></div>\
+         <div>x<span class='super'>y</span></div>\
+         <div>x<span class='ib top'></span></div>\
+         <div>x<span class='ib middle'></span></div>\
+         <div>x<span class='length'>y</span></div>\
+         <div><span style='position: absolute'></span></div></div>",
+        css,
+        400,
+    );
+    let px = Au::from_f64_px;
+    // The line box of each block, and the top of the content area of its items.
+    let line = |i: usize| {
+        let anonymous_block = &tree.children[i].children[0];
+        (
+            anonymous_block.dimensions.content.height,
+            anonymous_block
+                .children
+                .iter()
+                .map(|item| item.dimensions.content.y)
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    // The font is 12.8px above and 3.2px under the baseline, and `line-height` adds 1.6px to
+    // both sides. An inline-block without line boxes sits on the baseline of the strut.
+    assert_eq!(line(0), (px(30.0 + 4.8), vec![px(30.0 - 12.8), px(0.0)]));
+
+    // The baseline of an inline-block is that of its last line box.
+    assert_eq!(line(1), (px(30.0), vec![px(1.6), px(0.0)]));
+    assert_eq!(tree.children[1].children[0].line_baselines, vec![px(14.4)]);
+
+    // `super` raises the baseline by a third of the parent's font size.
+    let raised = px(14.4) + px(16.0) / 3;
+    assert_eq!(line(2), (raised + px(4.8), vec![raised - px(12.8), px(1.6)]));
+
+    // `top` aligns the box with the top of the line box, and the line box grows downwards.
+    assert_eq!(line(3), (px(30.0), vec![px(1.6), px(0.0)]));
+
+    // `middle` aligns the midpoint of the box with half the x-height above the baseline.
+    assert_eq!(line(4), (px(30.0), vec![px(19.0 - 12.8), px(0.0)]));
+
+    // A negative length lowers the box.
+    assert_eq!(line(5), (px(14.4 + 6.8), vec![px(1.6), px(3.6)]));
+
+    // A line box with nothing in flow has no height.
+    assert_eq!(line(6).0, Au(0));
+}
+
+#[test]
+fn test_text_align() {
+    use font::{FontSlant, FontWeight};
+    use layout::layout_html;
+
+    assert_eq!(justification_opportunities("ab cd ", true), vec![3]);
+    assert_eq!(justification_opportunities("ab cd ", false), vec![3, 6]);
+    assert_eq!(justification_opportunities("漢字 ab", false), vec![3, 7]);
+
+    let css = "div { display: block; width: 100px; }
+               .justify { text-align: justify; }
+               .last { text-align-last: end; }
+               .rtl { direction: rtl; }";
+    let text = "aaaa bbbb cccc dddd";
+    let tree = layout_html(
+        &format!(
+            "<div><div class='justify'>{0}</div>\
+             <div class='justify last'>{0}</div>\
+             <div style='text-align: end'>aaaa</div>\
+             <div class='rtl'>aaaa</div></div>",
+            text
+        ),
+        css,
+        400,
+    );
+    let items = |i: usize| &tree.children[i].children[0].children;
+    let right_edge = |item: &LayoutBox| item.dimensions.content.x + item.dimensions.content.width;
+    let justification = |item: &LayoutBox| match item.box_type {
+        BoxType::TextNode(ref text) => Some(text.justification),
+        _ => None,
+    };
+    let width = |s: &str| {
+        Au::from_f64_px(
+            Font::new(Au::from_px(16), FontWeight::Normal, FontSlant::Normal).text_width(s),
+        )
+    };
+
+    // The lines but the last are filled up to the right edge, but for the spacing rounded down.
+    let lines = items(0);
+    assert!(lines.len() > 1);
+    let first_line = &lines[0];
+    assert_eq!(first_line.dimensions.content.x, Au(0));
+    assert!(Au::from_px(100) - right_edge(first_line) < Au(10));
+    assert!(justification(first_line).unwrap() > Au(0));
+
+    // The last line is start-aligned unless `text-align-last` says otherwise.
+    let last_line = lines.last().unwrap();
+    assert_eq!(last_line.dimensions.content.x, Au(0));
+    assert_eq!(justification(last_line), Some(Au(0)));
+    let last_line = items(1).last().unwrap();
+    assert_eq!(right_edge(last_line), Au::from_px(100));
+
+    // `start` and `end` depend on `direction`.
+    let end = Au::from_px(100) - width("aaaa");
+    assert_eq!(items(2)[0].dimensions.content.x, end);
+    assert_eq!(items(3)[0].dimensions.content.x, end);
+}
@@ -10,7 +10,7 @@ use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
 extern crate gtk;
-use gtk::WidgetExt;
+use gtk::{AdjustmentExt, Cast, ScrollableExt, WidgetExt};
 
 extern crate app_units;
 use app_units::Au;
@@ -87,8 +87,9 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 thread_local!(
-    static LAYOUT_SAVER: RefCell<(Au, Au, painter::DisplayList)> =
-        { RefCell::new((Au(0), Au(0), vec![])) };
+    // (viewport width, viewport height, scroll position, layout tree, display list)
+    static LAYOUT_SAVER: RefCell<(Au, Au, Au, Option<layout::LayoutBox>, painter::DisplayList)> =
+        { RefCell::new((Au(0), Au(0), Au(0), None, vec![])) };
     static HTML_SRC_URL: RefCell<Option<String>> = { RefCell::new(None) };
     static HTML_TREE: Rc<RefCell<Option<dom::Node>>> = { Rc::new(RefCell::new(None)) };
     static STYLESHEET: Rc<RefCell<Option<css::Stylesheet>>> = { Rc::new(RefCell::new(None)) };
@@ -149,17 +150,44 @@ pub fn run_with_url(html_src: String) {
         window::render(move |widget| {
             let mut viewport: layout::Dimensions = ::std::default::Default::default();
             viewport.content.width = Au::from_f64_px(widget.get_allocated_width() as f64);
-            viewport.content.height = Au::from_f64_px(widget.get_allocated_height() as f64);
+            // The drawing area grows with the content, so take the visible height and the scroll
+            // position from the gtk::Viewport that the overlay is put in.
+            let scrollport = widget
+                .get_parent()
+                .and_then(|overlay| overlay.get_parent())
+                .and_then(|viewport| viewport.downcast::<gtk::Viewport>().ok());
+            let visible_height = scrollport
+                .as_ref()
+                .map_or(widget.get_allocated_height(), |viewport| {
+                    viewport.get_allocated_height()
+                });
+            viewport.content.height = Au::from_f64_px(visible_height as f64);
+            let scroll_position = Au::from_f64_px(
+                scrollport
+                    .and_then(|viewport| viewport.get_vadjustment())
+                    .map_or(0.0, |adjustment| adjustment.get_value()),
+            );
 
             LAYOUT_SAVER.with(|x| {
-                let (ref mut last_width, ref mut last_height, ref mut last_displays) =
-                    *x.borrow_mut();
-                if *last_width == viewport.content.width
-                    && *last_height == viewport.content.height
-                    && unsafe { !SRC_UPDATED }
+                let (
+                    ref mut last_width,
+                    ref mut last_height,
+                    ref mut last_scroll_position,
+                    ref mut last_layout_tree,
+                    ref mut last_displays,
+                ) = *x.borrow_mut();
+                let needs_layout = *last_width != viewport.content.width
+                    || *last_height != viewport.content.height
+                    || unsafe { SRC_UPDATED } || last_layout_tree.is_none();
+
+                if !needs_layout && !painter::BOX_SCROLLED.with(|s| s.get())
+                    && (*last_scroll_position == scroll_position
+                        || !painter::SCROLL_DEPENDENT.with(|s| s.get()))
                 {
-                    last_displays.clone()
-                } else {
+                    return last_displays.clone();
+                }
+
+                if needs_layout {
                     unsafe {
                         SRC_UPDATED = false;
                     }
@@ -169,16 +197,23 @@ pub fn run_with_url(html_src: String) {
 
                     let html_tree = HTML_TREE.with(|h| (*h.borrow()).clone().unwrap());
                     let stylesheet = STYLESHEET.with(|s| (*s.borrow()).clone().unwrap());
-                    let mut layout_tree = layout::layout_tree(&html_tree, &stylesheet, viewport);
+                    *last_layout_tree =
+                        Some(layout::layout_tree(&html_tree, &stylesheet, viewport));
                     // debug_println!("LAYOUT:\n{}", layout_tree);
+                }
+                *last_scroll_position = scroll_position;
 
-                    let display_command = painter::build_display_list(&mut layout_tree);
-                    // debug_println!("DISPLAY:\n{:?}", display_command);
+                let visible_area = layout::Rect {
+                    y: scroll_position,
+                    ..viewport.content
+                };
+                let display_command =
+                    painter::build_display_list(last_layout_tree.as_mut().unwrap(), visible_area);
+                // debug_println!("DISPLAY:\n{:?}", display_command);
 
-                    *last_displays = display_command.clone();
+                *last_displays = display_command.clone();
 
-                    display_command
-                }
+                display_command
             })
         });
     })
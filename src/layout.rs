@@ -1,17 +1,22 @@
-use style::{Display, Style};
+use style::{Display, Style, WritingMode};
+use block::CollapsibleMargins;
 use dom::{ElementData, LayoutType, Node, NodeType};
 use float::Floats;
 use font::{Font, FontSlant, FontWeight};
 use inline::LineMaker;
 use style;
 use default_style;
-use css::{parse_attr_style, Declaration, Rule, Selector, SimpleSelector, Specificity, Stylesheet,
-          Value};
+use css::{parse_attr_style, Declaration, LengthContext, PseudoElement, Rule, Selector,
+          SimpleSelector, Specificity, Stylesheet, Value};
+use content::{ContentContext, ContentItem};
 
+use std::cell::Cell;
+use std::cmp::{max, min};
 use std::collections::HashMap;
 use std::default::Default;
 use std::fmt;
 use std::ops::Range;
+use std::rc::Rc;
 
 use cairo;
 use pango;
@@ -30,6 +35,8 @@ pub struct Rect {
     pub height: Au,
 }
 
+// During layout, the dimensions of the boxes in vertical text are the logical ones, where `x` and
+// `width` are along the lines. See `writing_mode`.
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Dimensions {
     // Position of the content area relative to the document origin:
@@ -74,9 +81,17 @@ pub enum BoxType {
     BlockNode,
     InlineNode,
     InlineBlockNode,
+    // A block-level grid container.
+    GridNode,
+    // A block-level table, which contains the captions and the rows.
+    TableNode,
     Float,
+    // A box with `position: absolute` or `fixed`, which is laid out by its containing block.
+    Absolute,
     TextNode(Text),
     AnonymousBlock,
+    // An outside list marker, which is placed to the left of the list item's first line.
+    Marker,
     None, // TODO: Is this really needed?
 }
 
@@ -90,6 +105,11 @@ pub struct LayoutBox {
     pub box_type: BoxType,
     pub info: LayoutInfo,
     pub floats: Floats,
+    // The scroll offset of a scroll container. It is shared by the clones of this box, so it
+    // survives relayout of the cached layout tree.
+    pub scroll_offset: Rc<Cell<(Au, Au)>>,
+    // The baselines of the line boxes in an anonymous block, relative to its content area.
+    pub line_baselines: Vec<Au>,
     pub children: Vec<LayoutBox>,
 }
 
@@ -97,6 +117,10 @@ pub struct LayoutBox {
 pub struct Text {
     pub font: Font,
     pub range: Range<usize>,
+    // The space added at each justification opportunity by `text-align: justify`.
+    pub justification: Au,
+    // The bidi embedding level. The text is drawn right to left if it's odd.
+    pub bidi_level: u8,
 }
 
 impl ImageData {
@@ -123,14 +147,18 @@ impl ImageMetaData {
 
 impl LayoutBox {
     pub fn new(box_type: BoxType, node: Node, property: Style, info: LayoutInfo) -> LayoutBox {
+        // `z-index` has initial value `auto`.
+        let z_index = property.computed.z_index.unwrap_or(0);
         LayoutBox {
             node: node,
             property: property,
             box_type: box_type,
             info: info,
-            z_index: 0,
+            z_index: z_index,
             floats: Floats::new(),
             dimensions: Default::default(),
+            scroll_offset: Rc::new(Cell::new((Au(0), Au(0)))),
+            line_baselines: vec![],
             children: Vec::with_capacity(16),
         }
     }
@@ -147,67 +175,169 @@ impl LayoutBox {
     }
 
     pub fn in_normal_flow(&self) -> bool {
-        self.box_type != BoxType::Float
+        match self.box_type {
+            BoxType::Float | BoxType::Absolute | BoxType::Marker => false,
+            _ => true,
+        }
     }
 }
 
 /// Build the tree of LayoutBoxes, but don't perform any layout calculations yet.
 fn build_layout_tree(
     node: &Node,
-    stylesheet: &Stylesheet,
-    default_style: &Stylesheet,
+    stylesheets: &OriginStylesheets,
     inherited_property: &Style,
     parent_specified_values: &Style,
     appeared_elements: &Vec<SimpleSelector>,
+    ancestor_filter: &AncestorFilter,
+    length_context: &LengthContext,
+    style_sharing_cache: &mut StyleSharingCache,
+    content_context: &mut ContentContext,
     // id: &mut usize,
 ) -> LayoutBox {
+    let depth = appeared_elements.len();
     let mut appeared_elements = appeared_elements.clone();
-    let specified_values = match node.data {
+    let mut ancestor_filter = *ancestor_filter;
+    let mut length_context = *length_context;
+    let mut pseudo_element_styles = vec![];
+    let mut specified_values = match node.data {
         NodeType::Element(ref elem) => {
-            let values = specified_values(
-                elem,
-                default_style,
-                stylesheet,
-                inherited_property,
-                &appeared_elements,
-            );
+            let key = StyleSharingKey::new(elem);
+            let values = match key.as_ref().and_then(|key| style_sharing_cache.get(key)) {
+                Some(&(ref values, ref shared_length_context, ref shared_pseudo_styles)) => {
+                    #[cfg(test)]
+                    STYLE_SHARING_HITS.with(|hits| hits.set(hits.get() + 1));
+                    length_context = *shared_length_context;
+                    pseudo_element_styles = shared_pseudo_styles.clone();
+                    values.clone()
+                }
+                None => {
+                    let mut values = specified_values(
+                        elem,
+                        None,
+                        stylesheets,
+                        inherited_property,
+                        parent_specified_values,
+                        &appeared_elements,
+                        &ancestor_filter,
+                    );
+                    length_context = values.resolve_relative_lengths(&length_context);
+                    if appeared_elements.is_empty() {
+                        // `rem` refers to the font size of the root element.
+                        length_context.root_font_size = length_context.font_size;
+                    }
+
+                    // `::before` and `::after` inherit from the element.
+                    let inherited_property = inherit_peoperties(&values);
+                    for &pseudo_element in &[
+                        PseudoElement::Marker,
+                        PseudoElement::Before,
+                        PseudoElement::After,
+                    ] {
+                        // Every list item has a marker, and `::before` and `::after` only have a
+                        // box if a rule gives them `content`.
+                        let generated = if pseudo_element == PseudoElement::Marker {
+                            values.computed.display == Display::ListItem
+                        } else {
+                            stylesheets.have_pseudo_element(pseudo_element)
+                        };
+                        if !generated {
+                            continue;
+                        }
+                        let mut pseudo_values = specified_values(
+                            elem,
+                            Some(pseudo_element),
+                            stylesheets,
+                            &inherited_property,
+                            &values,
+                            &appeared_elements,
+                            &ancestor_filter,
+                        );
+                        if pseudo_element == PseudoElement::Marker {
+                            // Markers are always inline.
+                            pseudo_values = Style::new_with(
+                                pseudo_values
+                                    .property
+                                    .into_iter()
+                                    .filter(|&(ref name, _)| name != "display" && name != "float")
+                                    .collect(),
+                            );
+                        } else if pseudo_values.value("content").is_none() {
+                            // No box is generated without `content`.
+                            continue;
+                        }
+                        pseudo_values.resolve_relative_lengths(&length_context);
+                        pseudo_element_styles.push((pseudo_element, pseudo_values));
+                    }
+
+                    if let Some(key) = key {
+                        style_sharing_cache.insert(
+                            key,
+                            (
+                                values.clone(),
+                                length_context,
+                                pseudo_element_styles.clone(),
+                            ),
+                        );
+                    }
+                    values
+                }
+            };
             appeared_elements.push(SimpleSelector {
                 tag_name: Some(elem.tag_name.clone()),
                 id: elem.id().and_then(|id| Some(id.clone())),
                 class: elem.classes().iter().map(|x| x.to_string()).collect(),
+                pseudo_element: None,
             });
+            ancestor_filter.insert(appeared_elements.last().unwrap());
             values
         }
-        NodeType::Text(_) => {
-            Style::new_with(
-                if let Some(display) = parent_specified_values.property.get("display") {
-                    match display[0] {
-                        // If the parent element is an inline element, inherites the parent's properties.
-                        Value::Keyword(ref k) if k == "inline" => parent_specified_values.clone(),
-                        _ => inherited_property.clone(),
-                    }
-                } else {
-                    inherited_property.clone()
-                }.property
-                    .into_iter()
-                    .filter(|&(ref name, _)| name != "float")
-                    .collect(),
-            )
-        }
+        NodeType::Text(_) => text_node_style(parent_specified_values, inherited_property),
     };
 
+    // The children of a flex or grid container are blockified and don't float.
+    // ref. https://www.w3.org/TR/css-flexbox-1/#flex-items
+    if let NodeType::Element(_) = node.data {
+        let parent_display = parent_specified_values.computed.display;
+        if parent_display.is_flex() || parent_display.is_grid() {
+            let computed = &mut specified_values.computed;
+            computed.display = computed.display.blockify();
+            computed.float = style::FloatType::None;
+        }
+    }
+
     // Create the root box.
     let mut root = LayoutBox::new(
-        match specified_values.display() {
-            Display::Block => BoxType::BlockNode,
-            Display::Inline => match node.data {
+        match specified_values.computed.display {
+            Display::Block | Display::ListItem | Display::Flex => BoxType::BlockNode,
+            Display::Grid => BoxType::GridNode,
+            Display::Table => BoxType::TableNode,
+            // The internal table boxes are laid out by the table.
+            Display::TableRowGroup
+            | Display::TableHeaderGroup
+            | Display::TableFooterGroup
+            | Display::TableRow
+            | Display::TableColumnGroup
+            | Display::TableColumn
+            | Display::TableCell
+            | Display::TableCaption => BoxType::BlockNode,
+            // The annotations are laid out by the ruby container, which is set in the line as a
+            // whole like an inline-block.
+            Display::RubyText => BoxType::BlockNode,
+            Display::Inline | Display::RubyBase => match node.data {
                 NodeType::Element(_) => BoxType::InlineNode,
                 NodeType::Text(ref s) => BoxType::TextNode(Text {
                     font: Font::new_empty(),
                     range: 0..s.len(),
+                    justification: Au(0),
+                    bidi_level: 0,
                 }),
             },
-            Display::InlineBlock => match node.data {
+            Display::InlineBlock
+            | Display::InlineFlex
+            | Display::InlineGrid
+            | Display::InlineTable
+            | Display::Ruby => match node.data {
                 NodeType::Element(_) => BoxType::InlineBlockNode,
                 NodeType::Text(_) => panic!(),
             },
@@ -228,132 +358,549 @@ fn build_layout_tree(
         return root;
     }
 
-    match specified_values.float() {
+    // Elements with `display: none` don't affect counters.
+    content_context.update_counters(&specified_values, depth);
+
+    match specified_values.computed.float {
         style::FloatType::None => {}
         style::FloatType::Left | style::FloatType::Right => root.box_type = BoxType::Float,
     }
+    if specified_values.computed.position.is_absolute() {
+        root.box_type = BoxType::Absolute;
+    }
 
-    let inherited_property = inherit_peoperties(
-        &specified_values,
-        vec![
-            "font-size",
-            "line-height",
-            "font-weight",
-            "font-style",
-            "text-align",
-            "color",
-        ],
-    );
+    let inherited_property = inherit_peoperties(&specified_values);
+
+    let pseudo_element_box = |pseudo_element, content_context: &mut ContentContext| {
+        let elem = match node.data {
+            NodeType::Element(ref elem) => elem,
+            NodeType::Text(_) => return None,
+        };
+        pseudo_element_styles
+            .iter()
+            .find(|&&(p, _)| p == pseudo_element)
+            .and_then(|&(_, ref style)| {
+                if pseudo_element == PseudoElement::Marker {
+                    let items = content_context.marker_items(style, elem)?;
+                    let mut marker = build_pseudo_element_box(style, &inherited_property, items)?;
+                    let position = style.value("list-style-position");
+                    if position != Some(vec![Value::Keyword("inside".to_string())]) {
+                        marker.box_type = BoxType::Marker;
+                    }
+                    return Some(marker);
+                }
+                // The pseudo-element is the first or last child of the element.
+                content_context.update_counters(style, depth + 1);
+                let items = content_context.content_items(style, elem)?;
+                build_pseudo_element_box(style, &inherited_property, items)
+            })
+    };
 
     // Create the descendant boxes.
+    match pseudo_element_box(PseudoElement::Marker, content_context) {
+        Some(marker) if marker.box_type == BoxType::Marker => root.children.push(marker),
+        Some(marker) => root.add_child(marker),
+        None => {}
+    }
+    if let Some(before) = pseudo_element_box(PseudoElement::Before, content_context) {
+        root.add_child(before);
+    }
+    let mut style_sharing_cache = StyleSharingCache::new();
     for child in node.children.iter() {
         // *id += 1;
         let child = build_layout_tree(
             child,
-            stylesheet,
-            default_style,
+            stylesheets,
             &inherited_property,
             &specified_values,
             &appeared_elements,
+            &ancestor_filter,
+            &length_context,
+            &mut style_sharing_cache,
+            content_context,
             // id,
         );
+        root.add_child(child);
+    }
+    if let Some(after) = pseudo_element_box(PseudoElement::After, content_context) {
+        root.add_child(after);
+    }
+    content_context.leave_children(depth);
 
-        match (child.property.display(), child.property.float()) {
-            (Display::Block, style::FloatType::None) => {
-                root.children.push(child);
-            }
-            (Display::Inline, style::FloatType::None)
-            | (Display::InlineBlock, style::FloatType::None) => {
-                root.get_inline_container().children.push(child);
-            }
-            (_, style::FloatType::Left) | (_, style::FloatType::Right) => {
-                root.children.push(child);
+    root
+}
+
+/// Builds the box of a `::before`, `::after` or `::marker` with `style`, whose content is
+/// `items`.
+/// `inherited_property` is the inherited properties of the originating element.
+fn build_pseudo_element_box(
+    style: &Style,
+    inherited_property: &Style,
+    items: Vec<ContentItem>,
+) -> Option<LayoutBox> {
+    let mut root = LayoutBox::new(
+        match style.computed.display {
+            Display::Block | Display::ListItem | Display::Flex => BoxType::BlockNode,
+            Display::Grid => BoxType::GridNode,
+            Display::Table => BoxType::TableNode,
+            // The internal table boxes are laid out by the table.
+            Display::TableRowGroup
+            | Display::TableHeaderGroup
+            | Display::TableFooterGroup
+            | Display::TableRow
+            | Display::TableColumnGroup
+            | Display::TableColumn
+            | Display::TableCell
+            | Display::TableCaption
+            | Display::RubyText => BoxType::BlockNode,
+            Display::Inline | Display::RubyBase => BoxType::InlineNode,
+            Display::InlineBlock
+            | Display::InlineFlex
+            | Display::InlineGrid
+            | Display::InlineTable
+            | Display::Ruby => BoxType::InlineBlockNode,
+            Display::None => return None,
+        },
+        Node::elem("".to_string(), HashMap::new(), vec![]),
+        style.clone(),
+        LayoutInfo::Generic,
+    );
+    match style.computed.float {
+        style::FloatType::None => {}
+        style::FloatType::Left | style::FloatType::Right => root.box_type = BoxType::Float,
+    }
+    if style.computed.position.is_absolute() {
+        root.box_type = BoxType::Absolute;
+    }
+
+    // The same as the children of an element.
+    let text_style = text_node_style(style, inherited_property);
+    let image_style = inherit_peoperties(style);
+    for item in items {
+        root.add_child(match item {
+            ContentItem::Text(text) => LayoutBox::new(
+                BoxType::TextNode(Text {
+                    font: Font::new_empty(),
+                    range: 0..text.len(),
+                    justification: Au(0),
+                    bidi_level: 0,
+                }),
+                Node::text(text),
+                text_style.clone(),
+                LayoutInfo::Text,
+            ),
+            ContentItem::Image(url) => {
+                let mut attrs = HashMap::new();
+                attrs.insert("src".to_string(), url);
+                LayoutBox::new(
+                    BoxType::InlineNode,
+                    Node::elem("img".to_string(), attrs, vec![]),
+                    image_style.clone(),
+                    LayoutInfo::Image(ImageData::new_empty()),
+                )
             }
-            (Display::None, _) => {} // Don't lay out nodes with `display: none;`
+        });
+    }
+    Some(root)
+}
+
+/// Returns the style of a text node whose parent has `parent_specified_values`.
+fn text_node_style(parent_specified_values: &Style, inherited_property: &Style) -> Style {
+    Style::new_with(
+        // If the parent element is an inline element, inherites the parent's properties.
+        if parent_specified_values.computed.display == Display::Inline {
+            parent_specified_values.clone()
+        } else {
+            inherited_property.clone()
+        }.property
+            .into_iter()
+            .filter(|&(ref name, _)| name != "float")
+            .collect(),
+    )
+}
+
+/// Styles computed for the previous siblings. Selectors only look at tag names, ids, classes and
+/// ancestors, so siblings with the same tag name, classes and `style` attribute get the same style
+/// and can share it without matching the rules again.
+type StyleSharingCache =
+    HashMap<StyleSharingKey, (Style, LengthContext, Vec<(PseudoElement, Style)>)>;
+
+// The number of elements whose style is taken from the style sharing cache, for tests.
+#[cfg(test)]
+thread_local!(static STYLE_SHARING_HITS: Cell<usize> = Cell::new(0));
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct StyleSharingKey {
+    tag_name: String,
+    classes: Vec<String>,
+    style: Option<String>,
+}
+
+impl StyleSharingKey {
+    /// Returns None if the style of `elem` shouldn't be shared. Elements with an id are unique,
+    /// so caching their styles would only waste memory. Presentational hints depend on other
+    /// attributes, so elements with them aren't shared either.
+    fn new(elem: &ElementData) -> Option<StyleSharingKey> {
+        if elem.id().is_some() || !presentational_hints(elem).is_empty() {
+            return None;
         }
+        let mut classes: Vec<String> = elem.classes().iter().map(|x| x.to_string()).collect();
+        classes.sort();
+        Some(StyleSharingKey {
+            tag_name: elem.tag_name.clone(),
+            classes: classes,
+            style: elem.attrs.get("style").cloned(),
+        })
     }
+}
 
-    root
+fn inherit_peoperties(specified_values: &Style) -> Style {
+    Style::new_with(
+        specified_values
+            .property
+            .iter()
+            .filter(|&(name, _)| style::is_inherited_property(name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+    )
+}
+
+/// The cascade origins in ascending order of precedence of normal declarations.
+/// ref. https://www.w3.org/TR/css-cascade-4/#cascading-origins
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CascadeOrigin {
+    UserAgent,
+    User,
+    Author,
 }
 
-fn inherit_peoperties(specified_values: &Style, property_list: Vec<&str>) -> Style {
-    let mut inherited_property = HashMap::new();
-    let specified_values = &specified_values.property;
-    for property in property_list {
-        if let Some(value) = specified_values.get(property) {
-            inherited_property.insert(property.to_string(), value.clone());
+/// The stylesheets of each cascade origin.
+pub struct OriginStylesheets<'a> {
+    pub user_agent: &'a Stylesheet,
+    pub user: &'a Stylesheet,
+    pub author: &'a Stylesheet,
+}
+
+impl<'a> OriginStylesheets<'a> {
+    /// Whether any rule in the stylesheets may match `pseudo_element`.
+    fn have_pseudo_element(&self, pseudo_element: PseudoElement) -> bool {
+        [self.user_agent, self.user, self.author]
+            .iter()
+            .any(|stylesheet| stylesheet.pseudo_elements.contains(&pseudo_element))
+    }
+}
+
+impl CascadeOrigin {
+    /// Important declarations win over normal ones, and reverse the order of origins.
+    /// ref. https://www.w3.org/TR/css-cascade-4/#cascade-origin
+    fn precedence(self, important: bool) -> u8 {
+        match (important, self) {
+            (false, CascadeOrigin::UserAgent) => 0,
+            (false, CascadeOrigin::User) => 1,
+            (false, CascadeOrigin::Author) => 2,
+            (true, CascadeOrigin::Author) => 3,
+            (true, CascadeOrigin::User) => 4,
+            (true, CascadeOrigin::UserAgent) => 5,
         }
     }
-    Style::new_with(inherited_property)
 }
 
+/// The cascaded values with the origin of the declaration each comes from.
+type CascadedValues = HashMap<String, (CascadeOrigin, Vec<Value>)>;
+
+/// Returns the specified values of `elem`, or of its `pseudo_element` if any.
 fn specified_values(
     elem: &ElementData,
-    default_style: &Stylesheet,
-    stylesheet: &Stylesheet,
+    pseudo_element: Option<PseudoElement>,
+    stylesheets: &OriginStylesheets,
     inherited_property: &Style,
+    parent_specified_values: &Style,
     appeared_elements: &Vec<SimpleSelector>,
+    ancestor_filter: &AncestorFilter,
 ) -> Style {
-    let mut values = HashMap::with_capacity(16);
-
-    let mut rules = matching_rules(elem, &default_style, appeared_elements);
-    rules.append(&mut matching_rules(elem, stylesheet, appeared_elements));
+    let hints = match pseudo_element {
+        None => presentational_hints(elem),
+        Some(_) => vec![],
+    };
+    let mut declarations = vec![];
+    for &(origin, stylesheet) in &[
+        (CascadeOrigin::UserAgent, stylesheets.user_agent),
+        (CascadeOrigin::User, stylesheets.user),
+        (CascadeOrigin::Author, stylesheets.author),
+    ] {
+        // Presentational hints precede the author rules with specificity 0.
+        if origin == CascadeOrigin::Author {
+            for declaration in &hints {
+                declarations.push((origin, (0, 0, 0), declaration));
+            }
+        }
+        for (specificity, rule) in
+            matching_rules(elem, pseudo_element, stylesheet, appeared_elements, ancestor_filter)
+        {
+            for declaration in &rule.declarations {
+                declarations.push((origin, specificity, declaration));
+            }
+        }
+    }
 
-    // Insert inherited properties
-    inherited_property
-        .property
-        .iter()
-        .for_each(|(name, value)| {
-            values.insert(name.clone(), value.clone());
-        });
+    // Declarations in the style attribute win over any selector in the author origin.
+    let attr_style = match elem.attrs.get("style") {
+        Some(attr_style) if pseudo_element.is_none() => parse_attr_style(attr_style.clone()),
+        _ => vec![],
+    };
+    for declaration in &attr_style {
+        let specificity = (usize::max_value(), 0, 0);
+        declarations.push((CascadeOrigin::Author, specificity, declaration));
+    }
 
-    // Go through the rules from lowest to highest specificity.
-    rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
-    rules.iter().for_each(|&(_, rule)| {
-        rule.declarations.iter().for_each(|declaration| {
-            values.insert(declaration.name.clone(), declaration.values.clone());
-        })
+    // Go through the declarations from lowest to highest precedence. The sort is stable, so the
+    // later declaration wins if both origin and specificity are the same.
+    declarations.sort_by_key(|&(origin, specificity, declaration)| {
+        (origin.precedence(declaration.important), specificity)
     });
 
-    if let Some(attr_style) = elem.attrs.get("style") {
-        let decls = parse_attr_style(attr_style.clone());
-        for Declaration { name, values: vals } in decls {
-            values.insert(name, vals);
+    // `rollback[o]` holds the cascaded values of the origins lower than `o`, which `revert` in
+    // the origin `o` rolls back to.
+    let mut cascaded = CascadedValues::new();
+    let mut rollback = [
+        CascadedValues::new(),
+        CascadedValues::new(),
+        CascadedValues::new(),
+    ];
+    for (origin, _, declaration) in declarations {
+        let value = (origin, declaration.values.clone());
+        for lower in &mut rollback[origin as usize + 1..] {
+            lower.insert(declaration.name.clone(), value.clone());
         }
+        cascaded.insert(declaration.name.clone(), value);
+    }
+
+    // Insert inherited properties
+    let mut values = inherited_property.property.clone();
+
+    // Apply `inherit`, `initial`, `unset` and `revert`.
+    for (name, (origin, vals)) in cascaded {
+        match apply_css_wide_keyword(&name, origin, vals, parent_specified_values, &rollback) {
+            Some(vals) => values.insert(name, vals),
+            None => values.remove(&name),
+        };
+    }
+
+    // `color: currentColor` is the same as `color: inherit`.
+    if values
+        .get("color")
+        .and_then(|color| color.first().cloned())
+        .map_or(false, |color| color.is_current_color())
+    {
+        match inherited_property.value("color") {
+            Some(color) => values.insert("color".to_string(), color),
+            None => values.remove("color"),
+        };
     }
 
     Style::new_with(values)
 }
 
+/// Returns the declarations for the presentational attributes of `elem`, which are `start` of
+/// `ol`, `value` of `li` and `type` of lists.
+/// ref. https://html.spec.whatwg.org/multipage/rendering.html#lists
+fn presentational_hints(elem: &ElementData) -> Vec<Declaration> {
+    let declaration = |name: &str, values: Vec<Value>| Declaration {
+        name: name.to_string(),
+        values: values,
+        important: false,
+    };
+    let list_item = || Value::Keyword("list-item".to_string());
+    let integer = |name: &str| {
+        elem.attrs
+            .get(name)
+            .and_then(|value| value.trim().parse::<i32>().ok())
+    };
+
+    let mut hints = vec![];
+    match elem.tag_name.as_str() {
+        "ol" => if let Some(start) = integer("start") {
+            let reset = vec![list_item(), Value::Num(start as f64 - 1.0)];
+            hints.push(declaration("counter-reset", reset));
+        },
+        "li" => if let Some(value) = integer("value") {
+            let set = vec![list_item(), Value::Num(value as f64)];
+            hints.push(declaration("counter-set", set));
+        },
+        _ => {}
+    }
+    let list_style_type = match elem.attrs.get("type").map(|t| t.as_str()) {
+        Some("1") => "decimal",
+        Some("a") => "lower-alpha",
+        Some("A") => "upper-alpha",
+        Some("i") => "lower-roman",
+        Some("I") => "upper-roman",
+        Some(t) if t.eq_ignore_ascii_case("disc") => "disc",
+        Some(t) if t.eq_ignore_ascii_case("circle") => "circle",
+        Some(t) if t.eq_ignore_ascii_case("square") => "square",
+        _ => return hints,
+    };
+    match elem.tag_name.as_str() {
+        "ol" | "ul" | "li" => hints.push(declaration(
+            "list-style-type",
+            vec![Value::Keyword(list_style_type.to_string())],
+        )),
+        _ => {}
+    }
+    hints
+}
+
+/// Returns the value of the property `name` after applying the CSS-wide keyword in `values` if
+/// any. Returns None if the property should have no value, i.e. its initial value is unknown.
+/// `origin` is the origin of the declaration `values` comes from.
+/// ref. https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+fn apply_css_wide_keyword(
+    name: &str,
+    origin: CascadeOrigin,
+    values: Vec<Value>,
+    parent_specified_values: &Style,
+    rollback: &[CascadedValues; 3],
+) -> Option<Vec<Value>> {
+    let keyword = match values.first() {
+        Some(&Value::Keyword(ref k)) if values.len() == 1 => k.clone(),
+        _ => return Some(values),
+    };
+    let inherit = || {
+        parent_specified_values
+            .value(name)
+            .or_else(|| style::initial_value(name))
+    };
+    let unset = || {
+        if style::is_inherited_property(name) {
+            inherit()
+        } else {
+            style::initial_value(name)
+        }
+    };
+    match keyword.as_str() {
+        "inherit" => inherit(),
+        "initial" => style::initial_value(name),
+        "unset" => unset(),
+        // Rolls back to the value from the lower origins. Reverting the user agent origin is the
+        // same as unset.
+        "revert" => match rollback[origin as usize].get(name) {
+            Some(&(lower_origin, ref lower_values)) => apply_css_wide_keyword(
+                name,
+                lower_origin,
+                lower_values.clone(),
+                parent_specified_values,
+                rollback,
+            ),
+            None => unset(),
+        },
+        _ => Some(values),
+    }
+}
+
 type MatchedRule<'a> = (Specificity, &'a Rule);
 
 fn matching_rules<'a>(
     elem: &ElementData,
+    pseudo_element: Option<PseudoElement>,
     stylesheet: &'a Stylesheet,
     appeared_elements: &Vec<SimpleSelector>,
+    ancestor_filter: &AncestorFilter,
 ) -> Vec<MatchedRule<'a>> {
-    // For now, we just do a linear scan of all the rules.  For large
-    // documents, it would be more efficient to store the rules in hash tables
-    // based on tag name, id, class, etc.
+    // Only the rules in the buckets for the element's id, classes and tag name, and the
+    // universal bucket can match.
     stylesheet
-        .rules
-        .iter()
-        .filter_map(|rule| match_rule(elem, rule, appeared_elements))
+        .candidate_rules(&elem.tag_name, elem.id(), &elem.classes())
+        .into_iter()
+        .filter_map(|rule| {
+            match_rule(elem, pseudo_element, rule, appeared_elements, ancestor_filter)
+        })
         .collect()
 }
 
 fn match_rule<'a>(
     elem: &ElementData,
+    pseudo_element: Option<PseudoElement>,
     rule: &'a Rule,
     appeared_elements: &Vec<SimpleSelector>,
+    ancestor_filter: &AncestorFilter,
 ) -> Option<MatchedRule<'a>> {
     // Find the first (most specific) matching selector.
     rule.selectors
         .iter()
-        .find(|selector| matches(elem, *selector, appeared_elements))
+        .find(|selector| {
+            selector.rightmost().pseudo_element == pseudo_element
+                && ancestor_filter.may_match(*selector)
+                && matches(elem, *selector, appeared_elements)
+        })
         .map(|selector| (selector.specificity(), rule))
 }
 
+/// A Bloom filter of the tag names, ids and classes of the ancestors of an element.
+/// It rejects descendant and child selectors whose ancestor compounds can't match any ancestor
+/// without walking `appeared_elements`. False positives are possible, false negatives are not.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AncestorFilter {
+    bits: [u64; 4],
+}
+
+impl AncestorFilter {
+    pub fn insert(&mut self, ancestor: &SimpleSelector) {
+        for hash in simple_selector_hashes(ancestor) {
+            for bit in AncestorFilter::bit_positions(hash).iter() {
+                self.bits[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+    }
+
+    fn might_contain(&self, hash: u64) -> bool {
+        AncestorFilter::bit_positions(hash)
+            .iter()
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    fn bit_positions(hash: u64) -> [usize; 2] {
+        [(hash & 0xff) as usize, ((hash >> 8) & 0xff) as usize]
+    }
+
+    /// Returns false if `selector` can't match because an ancestor compound of it isn't in the
+    /// filter.
+    pub fn may_match(&self, selector: &Selector) -> bool {
+        match *selector {
+            Selector::Simple(_) => true,
+            Selector::Descendant(ref a, ref b) | Selector::Child(ref a, ref b) => {
+                simple_selector_hashes(a)
+                    .into_iter()
+                    .all(|hash| self.might_contain(hash))
+                    && self.may_match(b)
+            }
+        }
+    }
+}
+
+fn simple_selector_hashes(simple: &SimpleSelector) -> Vec<u64> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let hash = |kind: u8, name: &str| {
+        let mut hasher = DefaultHasher::new();
+        kind.hash(&mut hasher);
+        name.hash(&mut hasher);
+        hasher.finish()
+    };
+
+    let mut hashes = vec![];
+    if let Some(ref tag_name) = simple.tag_name {
+        hashes.push(hash(0, tag_name));
+    }
+    if let Some(ref id) = simple.id {
+        hashes.push(hash(1, id));
+    }
+    for class in &simple.class {
+        hashes.push(hash(2, class));
+    }
+    hashes
+}
+
 fn matches(
     elem: &ElementData,
     selector: &Selector,
@@ -433,7 +980,12 @@ fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> boo
 }
 
 use std::cell::RefCell;
-thread_local!(pub static LAYOUTBOX: RefCell<Option<LayoutBox>> = { RefCell::new(None) };);
+thread_local!(
+    pub static LAYOUTBOX: RefCell<Option<LayoutBox>> = { RefCell::new(None) };
+    // The viewport size the styles in LAYOUTBOX were computed with. Viewport-relative lengths
+    // are resolved while building the layout tree, so the tree must be rebuilt on resize.
+    static LAYOUTBOX_VIEWPORT: Cell<(Au, Au)> = { Cell::new((Au(0), Au(0))) };
+);
 
 /// Transform a style tree into a layout tree.
 pub fn layout_tree(
@@ -442,20 +994,41 @@ pub fn layout_tree(
     mut containing_block: Dimensions,
 ) -> LayoutBox {
     let mut first_construction_of_layout_tree = false;
+    let viewport_size = (
+        containing_block.content.width,
+        containing_block.content.height,
+    );
     let mut root_box = LAYOUTBOX.with(|layoutbox| {
+        LAYOUTBOX_VIEWPORT.with(|styled_viewport| {
+            if styled_viewport.replace(viewport_size) != viewport_size {
+                *layoutbox.borrow_mut() = None;
+            }
+        });
         layoutbox
             .borrow_mut()
             .get_or_insert_with(|| {
                 first_construction_of_layout_tree = true;
                 // let mut id = 0;
-                let default_style = default_style::default_style();
+                let user_agent = default_style::default_style();
+                let user = default_style::user_style();
                 build_layout_tree(
                     root,
-                    &stylesheet,
-                    &default_style,
+                    &OriginStylesheets {
+                        user_agent: &user_agent,
+                        user: &user,
+                        author: &stylesheet,
+                    },
                     &style::Style::new(),
                     &style::Style::new(),
                     &vec![],
+                    &AncestorFilter::default(),
+                    &LengthContext {
+                        viewport_width: viewport_size.0.to_f64_px(),
+                        viewport_height: viewport_size.1.to_f64_px(),
+                        ..Default::default()
+                    },
+                    &mut StyleSharingCache::new(),
+                    &mut ContentContext::new(),
                     // &mut id,
                 )
             })
@@ -468,13 +1041,23 @@ pub fn layout_tree(
     // The layout algorithm expects the container height to start at 0.
     containing_block.content.height = Au::from_f64_px(0.0);
 
-    root_box.layout(
-        &mut Floats::new(),
-        Au(0),
-        containing_block,
-        saved_block,
-        viewport,
-    );
+    if root_box.starts_vertical_flow(WritingMode::HorizontalTb) {
+        root_box.layout_vertical_flow(
+            &mut CollapsibleMargins::default(),
+            containing_block,
+            saved_block,
+            viewport,
+        );
+    } else {
+        root_box.layout(
+            &mut Floats::new(),
+            &mut CollapsibleMargins::default(),
+            containing_block,
+            saved_block,
+            viewport,
+        );
+    }
+    root_box.layout_root_positioned_descendants(viewport);
 
     if first_construction_of_layout_tree {
         LAYOUTBOX.with(|layoutbox| {
@@ -504,7 +1087,7 @@ impl LayoutBox {
     pub fn layout(
         &mut self,
         floats: &mut Floats,
-        last_margin_bottom: Au,
+        margins: &mut CollapsibleMargins,
         containing_block: Dimensions,
         saved_block: Dimensions,
         viewport: Dimensions,
@@ -512,21 +1095,35 @@ impl LayoutBox {
         match self.box_type {
             BoxType::BlockNode => self.layout_block(
                 floats,
-                last_margin_bottom,
+                margins,
                 containing_block,
                 saved_block,
                 viewport,
             ),
             BoxType::InlineBlockNode => self.layout_inline_block(
                 floats,
-                last_margin_bottom,
+                margins,
+                containing_block,
+                saved_block,
+                viewport,
+            ),
+            BoxType::GridNode => self.layout_grid(
+                floats,
+                margins,
+                containing_block,
+                saved_block,
+                viewport,
+            ),
+            BoxType::TableNode => self.layout_table(
+                floats,
+                margins,
                 containing_block,
                 saved_block,
                 viewport,
             ),
             BoxType::Float => self.layout_float(
                 floats,
-                last_margin_bottom,
+                margins,
                 containing_block,
                 saved_block,
                 viewport,
@@ -534,38 +1131,170 @@ impl LayoutBox {
             BoxType::AnonymousBlock => {
                 self.dimensions.content.x = Au::from_f64_px(0.0);
                 self.dimensions.content.y = containing_block.content.height;
+                // Line boxes separate the margins above from the margins below.
+                if !self.collapses_through(containing_block.content.width) {
+                    self.dimensions.content.y += margins.collapse();
+                    *margins = CollapsibleMargins::default();
+                }
 
-                let mut linemaker = LineMaker::new(self.children.clone(), floats.clone());
+                let mut linemaker = LineMaker::new(
+                    self.children.clone(),
+                    floats.clone(),
+                    &self.property.computed,
+                );
                 linemaker.run(containing_block.content.width, containing_block);
                 linemaker.end_of_lines();
                 linemaker.assign_position();
 
                 self.dimensions.content.width = linemaker.intrinsic_width();
                 self.dimensions.content.height = linemaker.cur_height;
+                self.line_baselines = linemaker.baselines;
+                self.children = linemaker.new_boxes;
+
+                position_inline_boxes(&mut self.children, containing_block, saved_block, viewport);
+            }
+            BoxType::Absolute => {
+                // Record the static position below the margins above. The box is laid out by its
+                // containing block.
+                self.dimensions.content.x = Au(0);
+                self.dimensions.content.y = containing_block.content.height + margins.collapse();
+            }
+            BoxType::Marker => {
+                let mut linemaker =
+                    LineMaker::new(self.children.clone(), Floats::new(), &self.property.computed);
+                // Markers don't wrap.
+                linemaker.run(Au(-1), containing_block);
+                linemaker.end_of_lines();
+                linemaker.assign_position();
+
+                // The marker takes no space in the list item.
+                self.dimensions.content.width = linemaker.intrinsic_width();
+                self.dimensions.content.height = linemaker.cur_height;
+                self.dimensions.content.x = -self.dimensions.content.width;
+                self.dimensions.content.y = containing_block.content.height;
                 self.children = linemaker.new_boxes;
             }
             // InlineNode and TextNode is contained in AnonymousBlock.
             BoxType::InlineNode | BoxType::TextNode(_) => unreachable!(),
             BoxType::None => {}
         }
+
+        // Inline-level boxes are positioned by `LineMaker` later.
+        match self.box_type {
+            BoxType::BlockNode | BoxType::GridNode | BoxType::TableNode | BoxType::Float => {
+                self.apply_relative_offset(containing_block, saved_block)
+            }
+            _ => {}
+        }
+        if self.is_positioned() && self.box_type != BoxType::Absolute {
+            self.layout_absolute_descendants(viewport);
+        }
+    }
+
+    /// Appends `child` to the children, or to the anonymous block for inline children.
+    /// Anonymous table boxes are generated around the children that don't fit in the structure
+    /// of a table.
+    fn add_child(&mut self, child: LayoutBox) {
+        let display = self.property.computed.display;
+        match display {
+            // Column groups contain only columns, and columns contain nothing.
+            Display::TableColumnGroup => {
+                if child.property.computed.display == Display::TableColumn {
+                    self.children.push(child);
+                }
+                return;
+            }
+            Display::TableColumn => return,
+            _ => {}
+        }
+        if let Some(parent_display) = anonymous_table_parent(display, &child) {
+            // Whitespace between the parts of a table is ignored.
+            if display.is_table() || display.is_table_row_group() || display == Display::TableRow
+            {
+                if let NodeType::Text(ref text) = child.node.data {
+                    if text.chars().all(char::is_whitespace) {
+                        return;
+                    }
+                }
+            }
+            let reuse = self.children.last().map_or(false, |last| {
+                last.is_anonymous() && last.property.computed.display == parent_display
+            });
+            if !reuse {
+                let anonymous_box = anonymous_table_box(parent_display, &self.property);
+                self.children.push(anonymous_box);
+            }
+            self.children.last_mut().unwrap().add_child(child);
+            return;
+        }
+
+        match (child.property.computed.display, child.property.computed.float) {
+            (Display::Block, style::FloatType::None)
+            | (Display::ListItem, style::FloatType::None)
+            | (Display::Flex, style::FloatType::None)
+            | (Display::Grid, style::FloatType::None)
+            | (Display::Table, style::FloatType::None)
+            | (Display::TableRowGroup, style::FloatType::None)
+            | (Display::TableHeaderGroup, style::FloatType::None)
+            | (Display::TableFooterGroup, style::FloatType::None)
+            | (Display::TableRow, style::FloatType::None)
+            | (Display::TableColumnGroup, style::FloatType::None)
+            | (Display::TableColumn, style::FloatType::None)
+            | (Display::TableCell, style::FloatType::None)
+            | (Display::TableCaption, style::FloatType::None)
+            | (Display::RubyText, style::FloatType::None) => {
+                self.children.push(child);
+            }
+            (Display::Inline, style::FloatType::None)
+            | (Display::InlineBlock, style::FloatType::None)
+            | (Display::InlineFlex, style::FloatType::None)
+            | (Display::InlineGrid, style::FloatType::None)
+            | (Display::InlineTable, style::FloatType::None)
+            | (Display::Ruby, style::FloatType::None)
+            | (Display::RubyBase, style::FloatType::None) => {
+                self.get_inline_container().children.push(child);
+            }
+            (_, style::FloatType::Left) | (_, style::FloatType::Right) => {
+                self.children.push(child);
+            }
+            (Display::None, _) => {} // Don't lay out nodes with `display: none;`
+        }
+    }
+
+    /// Whether this box is generated by the layout, not by an element or a text node.
+    fn is_anonymous(&self) -> bool {
+        match (&self.node.data, &self.box_type) {
+            (&NodeType::Text(_), &BoxType::TextNode(_)) => false,
+            (&NodeType::Text(_), _) => true,
+            (&NodeType::Element(_), _) => false,
+        }
     }
 
     /// Where a new inline child should go.
     fn get_inline_container(&mut self) -> &mut LayoutBox {
         match self.box_type {
-            BoxType::InlineNode | BoxType::AnonymousBlock => self,
-            BoxType::Float | BoxType::BlockNode | BoxType::InlineBlockNode => {
+            BoxType::InlineNode | BoxType::AnonymousBlock | BoxType::Marker => self,
+            BoxType::Float
+            | BoxType::Absolute
+            | BoxType::BlockNode
+            | BoxType::GridNode
+            | BoxType::TableNode
+            | BoxType::InlineBlockNode => {
                 match self.children.last() {
                     Some(&LayoutBox {
                         box_type: BoxType::AnonymousBlock,
                         ..
                     }) => {}
-                    _ => self.children.push(LayoutBox::new(
-                        BoxType::AnonymousBlock,
-                        Node::text("".to_string()),
-                        Style::new(),
-                        LayoutInfo::Generic,
-                    )),
+                    // The anonymous block inherits the font and `line-height` of the strut.
+                    _ => {
+                        let style = inherit_peoperties(&self.property);
+                        self.children.push(LayoutBox::new(
+                            BoxType::AnonymousBlock,
+                            Node::text("".to_string()),
+                            style,
+                            LayoutInfo::Generic,
+                        ))
+                    }
                 }
                 self.children.last_mut().unwrap()
             }
@@ -574,34 +1303,130 @@ impl LayoutBox {
         }
     }
 
-    pub fn assign_padding(&mut self) {
-        let (padding_top, padding_right, padding_bottom, padding_left) = self.property.padding();
+    /// Percentages refer to the width of the containing block.
+    pub fn assign_padding(&mut self, containing_block: Dimensions) {
+        let cb_width = containing_block.content.width;
+        let padding = self.property.computed.padding;
 
         let d = &mut self.dimensions;
-        d.padding.left = Au::from_f64_px(padding_left.to_px().unwrap());
-        d.padding.top = Au::from_f64_px(padding_top.to_px().unwrap());
-        d.padding.bottom = Au::from_f64_px(padding_bottom.to_px().unwrap());
-        d.padding.right = Au::from_f64_px(padding_right.to_px().unwrap());
+        d.padding.left = padding.left.to_au(cb_width);
+        d.padding.top = padding.top.to_au(cb_width);
+        d.padding.bottom = padding.bottom.to_au(cb_width);
+        d.padding.right = padding.right.to_au(cb_width);
     }
 
-    pub fn assign_margin(&mut self) {
-        let (margin_top, margin_right, margin_bottom, margin_left) = self.property.margin();
+    /// Percentages refer to the width of the containing block, and `auto` is treated as 0.
+    pub fn assign_margin(&mut self, containing_block: Dimensions) {
+        let cb_width = containing_block.content.width;
+        let margin = self.property
+            .computed
+            .margin
+            .map(|m| m.to_au(cb_width).unwrap_or(Au(0)));
 
         let d = &mut self.dimensions;
-        d.margin.left = Au::from_f64_px(margin_left.to_px().unwrap());
-        d.margin.top = Au::from_f64_px(margin_top.to_px().unwrap());
-        d.margin.bottom = Au::from_f64_px(margin_bottom.to_px().unwrap());
-        d.margin.right = Au::from_f64_px(margin_right.to_px().unwrap());
+        d.margin.left = margin.left;
+        d.margin.top = margin.top;
+        d.margin.bottom = margin.bottom;
+        d.margin.right = margin.right;
     }
 
     pub fn assign_border_width(&mut self) {
-        let (border_top, border_right, border_bottom, border_left) = self.property.border_width();
+        let border = self.property.computed.border_width;
 
         let d = &mut self.dimensions;
-        d.border.left = Au::from_f64_px(border_left.to_px().unwrap());
-        d.border.top = Au::from_f64_px(border_top.to_px().unwrap());
-        d.border.bottom = Au::from_f64_px(border_bottom.to_px().unwrap());
-        d.border.right = Au::from_f64_px(border_right.to_px().unwrap());
+        d.border.left = border.left;
+        d.border.top = border.top;
+        d.border.bottom = border.bottom;
+        d.border.right = border.right;
+    }
+}
+
+/// The display of the anonymous table box to generate between a box with `parent` display and
+/// `child`, or None if `child` can be a child of the box as is.
+/// ref. https://www.w3.org/TR/CSS2/tables.html#anonymous-boxes
+fn anonymous_table_parent(parent: Display, child: &LayoutBox) -> Option<Display> {
+    // Floats and absolutely positioned boxes aren't a part of tables.
+    let display = if child.in_normal_flow() {
+        child.property.computed.display
+    } else {
+        Display::Block
+    };
+    if parent.is_table() {
+        match display {
+            Display::TableCaption | Display::TableColumnGroup | Display::TableColumn => None,
+            Display::TableRow => None,
+            display if display.is_table_row_group() => None,
+            _ => Some(Display::TableRow),
+        }
+    } else if parent.is_table_row_group() {
+        match display {
+            Display::TableRow => None,
+            _ => Some(Display::TableRow),
+        }
+    } else if parent == Display::TableRow {
+        match display {
+            Display::TableCell => None,
+            _ => Some(Display::TableCell),
+        }
+    } else if display.is_table_internal() {
+        match parent {
+            Display::Inline => Some(Display::InlineTable),
+            _ => Some(Display::Table),
+        }
+    } else {
+        None
+    }
+}
+
+/// An anonymous table box with `display`, which inherits from `parent_style`.
+fn anonymous_table_box(display: Display, parent_style: &Style) -> LayoutBox {
+    let (box_type, keyword) = match display {
+        Display::Table => (BoxType::TableNode, "table"),
+        Display::InlineTable => (BoxType::InlineBlockNode, "inline-table"),
+        Display::TableRow => (BoxType::BlockNode, "table-row"),
+        Display::TableCell => (BoxType::BlockNode, "table-cell"),
+        _ => unreachable!(),
+    };
+    let mut property = inherit_peoperties(parent_style).property;
+    property.insert(
+        "display".to_string(),
+        vec![Value::Keyword(keyword.to_string())],
+    );
+    LayoutBox::new(
+        box_type,
+        Node::text("".to_string()),
+        Style::new_with(property),
+        LayoutInfo::Generic,
+    )
+}
+
+/// Apply relative offsets to the inline-level boxes laid out by `LineMaker`, and lay out the
+/// absolutely positioned descendants of positioned inline boxes.
+fn position_inline_boxes(
+    boxes: &mut Vec<LayoutBox>,
+    containing_block: Dimensions,
+    saved_block: Dimensions,
+    viewport: Dimensions,
+) {
+    for layout_box in boxes {
+        match layout_box.box_type {
+            BoxType::InlineNode => {
+                layout_box.apply_relative_offset(containing_block, saved_block);
+                if layout_box.is_positioned() {
+                    layout_box.layout_absolute_descendants(viewport);
+                }
+                position_inline_boxes(
+                    &mut layout_box.children,
+                    containing_block,
+                    saved_block,
+                    viewport,
+                );
+            }
+            BoxType::InlineBlockNode => {
+                layout_box.apply_relative_offset(containing_block, saved_block)
+            }
+            _ => {}
+        }
     }
 }
 
@@ -611,6 +1436,11 @@ impl LayoutBox {
         match &mut self.info {
             &mut LayoutInfo::Image(ref mut imgdata) => {
                 inline::get_image(&self.node, imgdata, containing_block);
+                // Vertical text is laid out in the logical coordinates. See `writing_mode`.
+                if self.property.computed.writing_mode.is_vertical() {
+                    let metadata = &mut imgdata.metadata;
+                    metadata.width = ::std::mem::replace(&mut metadata.height, metadata.width);
+                }
             }
             _ => {}
         }
@@ -697,6 +1527,20 @@ impl Rect {
             height: self.height,
         }
     }
+    // The area covered by both rectangles, which is empty if they don't overlap.
+    pub fn intersection(self, other: Rect) -> Rect {
+        let x = max(self.x, other.x);
+        let y = max(self.y, other.y);
+        Rect {
+            x: x,
+            y: y,
+            width: max(Au(0), min(self.x + self.width, other.x + other.width) - x),
+            height: max(Au(0), min(self.y + self.height, other.y + other.height) - y),
+        }
+    }
+    pub fn contains(self, x: Au, y: Au) -> bool {
+        self.x <= x && x <= self.x + self.width && self.y <= y && y <= self.y + self.height
+    }
 }
 
 impl Dimensions {
@@ -756,3 +1600,494 @@ impl fmt::Display for LayoutBox {
         Ok(())
     }
 }
+
+#[test]
+fn test_css_wide_keywords() {
+    let keyword = |k: &str| vec![Value::Keyword(k.to_string())];
+    let parent = Style::new_with(
+        parse_attr_style("color: red; margin-top: 10px; font-family: monospace".to_string())
+            .into_iter()
+            .map(|d| (d.name, d.values))
+            .collect(),
+    );
+    let mut below_user = CascadedValues::new();
+    below_user.insert(
+        "display".to_string(),
+        (CascadeOrigin::UserAgent, keyword("block")),
+    );
+    below_user.insert(
+        "color".to_string(),
+        (CascadeOrigin::UserAgent, keyword("revert")),
+    );
+    let mut below_author = below_user.clone();
+    below_author.insert(
+        "cursor".to_string(),
+        (CascadeOrigin::User, keyword("pointer")),
+    );
+    let rollback = [CascadedValues::new(), below_user, below_author];
+    let apply = |name: &str, value: &str| {
+        apply_css_wide_keyword(
+            name,
+            CascadeOrigin::Author,
+            keyword(value),
+            &parent,
+            &rollback,
+        )
+    };
+
+    assert_eq!(apply("margin-top", "inherit"), parent.value("margin-top"));
+    assert_eq!(
+        apply("margin-top", "unset"),
+        style::initial_value("margin-top")
+    );
+    assert_eq!(apply("font-family", "unset"), Some(keyword("monospace")));
+    assert_eq!(apply("font-family", "initial"), Some(keyword("serif")));
+    assert_eq!(apply("display", "revert"), Some(keyword("block")));
+    assert_eq!(apply("color", "revert"), Some(keyword("red")));
+    assert_eq!(apply("width", "revert"), Some(keyword("auto")));
+    assert_eq!(apply("cursor", "revert"), Some(keyword("pointer")));
+    assert_eq!(apply("unknown-property", "initial"), None);
+    assert_eq!(apply("display", "inline"), Some(keyword("inline")));
+}
+
+#[cfg(test)]
+fn example_pages() -> Vec<(Node, Stylesheet)> {
+    use css;
+    use html;
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::Path;
+
+    let read = |name: &str| {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("example");
+        let mut source = String::new();
+        File::open(path.join(name))
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .unwrap();
+        source
+    };
+
+    ["test.html", "rainbow.html", "top.html"]
+        .iter()
+        .map(|name| {
+            let dom = html::parse(read(name), Path::new(name).to_path_buf());
+            let css_source = match dom.find_stylesheet_path() {
+                Some(path) => read(path.to_str().unwrap()),
+                None => dom.find_stylesheet_in_style_tag().unwrap_or_default(),
+            };
+            (dom, css::parse(css_source))
+        })
+        .collect()
+}
+
+/// Calls `f` for every element in `node` with its ancestors.
+#[cfg(test)]
+fn for_each_element<F>(
+    node: &Node,
+    appeared_elements: &Vec<SimpleSelector>,
+    ancestor_filter: &AncestorFilter,
+    f: &mut F,
+) where
+    F: FnMut(&ElementData, &Vec<SimpleSelector>, &AncestorFilter),
+{
+    if let NodeType::Element(ref elem) = node.data {
+        f(elem, appeared_elements, ancestor_filter);
+
+        let mut appeared_elements = appeared_elements.clone();
+        let mut ancestor_filter = *ancestor_filter;
+        appeared_elements.push(SimpleSelector {
+            tag_name: Some(elem.tag_name.clone()),
+            id: elem.id().cloned(),
+            class: elem.classes().iter().map(|x| x.to_string()).collect(),
+            pseudo_element: None,
+        });
+        ancestor_filter.insert(appeared_elements.last().unwrap());
+        for child in &node.children {
+            for_each_element(child, &appeared_elements, &ancestor_filter, f);
+        }
+    }
+}
+
+#[cfg(test)]
+fn linear_matching_rules<'a>(
+    elem: &ElementData,
+    stylesheet: &'a Stylesheet,
+    appeared_elements: &Vec<SimpleSelector>,
+) -> Vec<MatchedRule<'a>> {
+    stylesheet
+        .rules
+        .iter()
+        .filter_map(|rule| {
+            rule.selectors
+                .iter()
+                .find(|selector| {
+                    selector.rightmost().pseudo_element.is_none()
+                        && matches(elem, *selector, appeared_elements)
+                })
+                .map(|selector| (selector.specificity(), rule))
+        })
+        .collect()
+}
+
+#[test]
+fn test_indexed_matching_rules() {
+    for (dom, stylesheet) in example_pages() {
+        assert!(!stylesheet.rules.is_empty());
+        for_each_element(
+            &dom,
+            &vec![],
+            &AncestorFilter::default(),
+            &mut |elem, appeared_elements, ancestor_filter| {
+                assert_eq!(
+                    matching_rules(elem, None, &stylesheet, appeared_elements, ancestor_filter),
+                    linear_matching_rules(elem, &stylesheet, appeared_elements)
+                );
+            },
+        );
+    }
+}
+
+/// Compares the indexed selector matching with a linear scan of all rules on the example pages,
+/// whose stylesheets are repeated to get thousands of rules.
+/// Run with `cargo test --release bench_matching_rules -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn bench_matching_rules() {
+    use std::time::Instant;
+
+    for (dom, stylesheet) in example_pages() {
+        let mut rules = vec![];
+        for _ in 0..200 {
+            rules.extend(stylesheet.rules.iter().cloned());
+        }
+        let stylesheet = Stylesheet::new(rules);
+
+        let start = Instant::now();
+        let mut linear = 0;
+        for_each_element(
+            &dom,
+            &vec![],
+            &AncestorFilter::default(),
+            &mut |elem, appeared_elements, _| {
+                linear += linear_matching_rules(elem, &stylesheet, appeared_elements).len();
+            },
+        );
+        let linear_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut indexed = 0;
+        for_each_element(
+            &dom,
+            &vec![],
+            &AncestorFilter::default(),
+            &mut |elem, appeared_elements, ancestor_filter| {
+                indexed +=
+                    matching_rules(elem, None, &stylesheet, appeared_elements, ancestor_filter)
+                        .len();
+            },
+        );
+        let indexed_time = start.elapsed();
+
+        assert_eq!(linear, indexed);
+        println!(
+            "{} rules: linear scan {:?}, indexed {:?}",
+            stylesheet.rules.len(),
+            linear_time,
+            indexed_time
+        );
+    }
+}
+
+#[test]
+fn test_style_sharing() {
+    use css;
+
+    STYLE_SHARING_HITS.with(|hits| hits.set(0));
+    let tree = build_html(
+        "<ul>
+           <li class='a b'>1</li>
+           <li class='b a'>2</li>
+           <li class='c'>3</li>
+           <li class='a b' id='x'>4</li>
+           <li class='a b' style='color: green'>5</li>
+         </ul>",
+        &OriginStylesheets {
+            user_agent: &default_style::default_style(),
+            user: &Stylesheet::new(vec![]),
+            author: &css::parse(".c { color: red; } #x { color: blue; }".to_string()),
+        },
+    );
+    // Only the second item shares the style of the first one.
+    assert_eq!(STYLE_SHARING_HITS.with(|hits| hits.get()), 1);
+
+    let items: Vec<&ElementData> = tree.children
+        .iter()
+        .filter(|child| child.box_type == BoxType::BlockNode)
+        .map(|child| match child.node.data {
+            NodeType::Element(ref elem) => elem,
+            NodeType::Text(_) => unreachable!(),
+        })
+        .collect();
+    let key = |i: usize| StyleSharingKey::new(items[i]);
+    assert_eq!(key(0), key(1));
+    assert_ne!(key(0), key(2));
+    assert_eq!(key(3), None);
+    assert_ne!(key(0), key(4));
+
+    let colors: Vec<css::Color> = tree.children
+        .iter()
+        .filter(|child| child.box_type == BoxType::BlockNode)
+        .map(|child| child.property.computed.color)
+        .collect();
+    assert_eq!(
+        colors,
+        vec![
+            css::BLACK,
+            css::BLACK,
+            css::named_color("red").unwrap(),
+            css::named_color("blue").unwrap(),
+            css::named_color("green").unwrap(),
+        ]
+    );
+}
+
+#[test]
+fn test_cascade_origins() {
+    use css;
+
+    let user_agent = css::parse("p { color: red !important; margin-top: 1px; }".to_string());
+    let user = css::parse(
+        "p { color: green; margin-top: 2px !important; width: 3px; height: 7px; }".to_string(),
+    );
+    let author = css::parse(
+        "p { color: blue !important; margin-top: 4px ! important; width: 5px; height: 8px; }"
+            .to_string(),
+    );
+    let tree = build_html(
+        "<p style='width: 6px; height: revert'></p>",
+        &OriginStylesheets {
+            user_agent: &user_agent,
+            user: &user,
+            author: &author,
+        },
+    );
+    let style = &tree.property;
+
+    let px = |f| Some(vec![Value::Length(f, css::Unit::Px)]);
+    // Important declarations in lower origins win.
+    assert_eq!(style.value("color"), Some(vec![Value::Keyword("red".to_string())]));
+    assert_eq!(style.value("margin-top"), px(2.0));
+    // The style attribute wins over author rules, and `revert` rolls back to the user origin.
+    assert_eq!(style.value("width"), px(6.0));
+    assert_eq!(style.value("height"), px(7.0));
+}
+
+#[test]
+fn test_empty_declarations() {
+    let tree = layout_html(
+        "<div style='color:; font-size:'><p>a</p></div>",
+        "div, p { display: block; } p { color:; content:; }",
+        100,
+    );
+    let p = &tree.children[0];
+    assert_eq!(tree.property.value("color"), None);
+    assert_eq!(p.property.value("color"), None);
+    assert_eq!(p.property.value("content"), None);
+    assert_eq!(p.property.computed.font_size, tree.property.computed.font_size);
+}
+
+#[test]
+fn test_default_style() {
+    let tree = build_html(
+        "<html><head><title>t</title></head>
+         <body><p>a</p><h4>b</h4><ul><li>c</li></ul></body></html>",
+        &OriginStylesheets {
+            user_agent: &default_style::default_style(),
+            user: &Stylesheet::new(vec![]),
+            author: &Stylesheet::new(vec![]),
+        },
+    );
+
+    // `head` isn't rendered, so `body` is the only child of `html`.
+    assert_eq!(tree.children.len(), 1);
+    let body = &tree.children[0];
+    assert_eq!(body.property.computed.margin.left.to_au(Au(0)), Some(Au::from_px(8)));
+
+    let (p, h4, ul) = (&body.children[0], &body.children[1], &body.children[2]);
+    assert_eq!(p.property.computed.margin.top.to_au(Au(0)), Some(Au::from_px(16)));
+    assert_eq!(h4.property.computed.font_weight, FontWeight::Bold);
+    assert_eq!(h4.property.computed.margin.bottom.to_au(Au(0)), Some(Au::from_f64_px(21.28)));
+    assert_eq!(ul.property.computed.padding.left.to_au(Au(0)), Au::from_px(40));
+    assert_eq!(ul.children[0].property.computed.display, Display::ListItem);
+}
+
+#[test]
+fn test_user_style_across_threads() {
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+    use std::thread;
+
+    let _lock = default_style::USER_STYLE_TEST_LOCK.lock().unwrap();
+    // The stylesheet is loaded on another thread, like the one given on the command line.
+    let path = env::temp_dir().join(format!("naglfar-user-{}.css", ::std::process::id()));
+    File::create(&path)
+        .unwrap()
+        .write_all(b".user-style-test { margin-top: 3px; }")
+        .unwrap();
+    let load_path = path.clone();
+    thread::spawn(move || default_style::load_user_style(&load_path).unwrap())
+        .join()
+        .unwrap();
+
+    let tree = build_html(
+        "<div class='user-style-test'>a</div>",
+        &OriginStylesheets {
+            user_agent: &Stylesheet::new(vec![]),
+            user: &default_style::user_style(),
+            author: &Stylesheet::new(vec![]),
+        },
+    );
+    File::create(&path).unwrap();
+    default_style::load_user_style(&path).unwrap();
+    ::std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tree.property.computed.margin.top.to_au(Au(0)), Some(Au::from_px(3)));
+}
+
+#[test]
+fn test_pseudo_elements() {
+    use css;
+
+    let tree = build_html(
+        "<div><p title='t'>x</p><p>y</p></div>",
+        &OriginStylesheets {
+            user_agent: &default_style::default_style(),
+            user: &Stylesheet::new(vec![]),
+            author: &css::parse(
+                "div { counter-reset: n 2; }
+                 p { counter-increment: n; }
+                 p::before { content: '[' attr(title) counter(n) ']'; }
+                 p:after { content: url(a.png); display: block; }"
+                    .to_string(),
+            ),
+        },
+    );
+
+    let text = |layout_box: &LayoutBox| match layout_box.node.data {
+        NodeType::Text(ref text) => text.clone(),
+        NodeType::Element(_) => panic!(),
+    };
+    for (p, before) in tree.children.iter().zip(vec!["[t3]", "[4]"]) {
+        // The ::before is inline, and the ::after is a block.
+        assert_eq!(p.children.len(), 2);
+        let inline_container = &p.children[0];
+        assert_eq!(inline_container.box_type, BoxType::AnonymousBlock);
+        assert_eq!(inline_container.children[0].box_type, BoxType::InlineNode);
+        assert_eq!(text(&inline_container.children[0].children[0]), before);
+
+        let after = &p.children[1];
+        assert_eq!(after.box_type, BoxType::BlockNode);
+        let image = &after.children[0].children[0];
+        match image.node.data {
+            NodeType::Element(ref elem) => assert_eq!(elem.attrs["src"], "a.png"),
+            NodeType::Text(_) => panic!(),
+        }
+    }
+}
+
+#[test]
+fn test_list_markers() {
+    let tree = build_html(
+        "<div>
+           <ol start='3' type='i'><li>a</li><li value='10'>b</li><li>c</li></ol>
+           <ul style='list-style-position: inside'><li>d</li></ul>
+         </div>",
+        &OriginStylesheets {
+            user_agent: &default_style::default_style(),
+            user: &Stylesheet::new(vec![]),
+            author: &Stylesheet::new(vec![]),
+        },
+    );
+
+    let text = |marker: &LayoutBox| match marker.children[0].node.data {
+        NodeType::Text(ref text) => text.clone(),
+        NodeType::Element(_) => panic!(),
+    };
+    let (ol, ul) = (&tree.children[0], &tree.children[1]);
+    let markers: Vec<String> = ol.children
+        .iter()
+        .map(|li| {
+            assert_eq!(li.children[0].box_type, BoxType::Marker);
+            text(&li.children[0])
+        })
+        .collect();
+    assert_eq!(markers, vec!["iii. ", "x. ", "xi. "]);
+
+    // An inside marker is the first inline box of the list item.
+    let marker = &ul.children[0].children[0].children[0];
+    assert_eq!(marker.box_type, BoxType::InlineNode);
+    assert_eq!(text(marker), "\u{2022} ");
+}
+
+/// Builds the layout tree of `html` styled with `stylesheets` without laying it out.
+#[cfg(test)]
+pub fn build_html(html: &str, stylesheets: &OriginStylesheets) -> LayoutBox {
+    use html;
+    use std::path::Path;
+
+    let dom = html::parse(html.to_string(), Path::new("a.html").to_path_buf());
+    build_layout_tree(
+        &dom,
+        stylesheets,
+        &Style::new(),
+        &Style::new(),
+        &vec![],
+        &AncestorFilter::default(),
+        &LengthContext::default(),
+        &mut StyleSharingCache::new(),
+        &mut ContentContext::new(),
+    )
+}
+
+/// Builds the layout tree of `html` styled with `css` and lays it out in a viewport of `width`
+/// by 600px.
+#[cfg(test)]
+pub fn layout_html(html: &str, css: &str, width: i32) -> LayoutBox {
+    use css;
+
+    let mut tree = build_html(
+        html,
+        &OriginStylesheets {
+            user_agent: &Stylesheet::new(vec![]),
+            user: &Stylesheet::new(vec![]),
+            author: &css::parse(css.to_string()),
+        },
+    );
+    let mut viewport: Dimensions = Default::default();
+    viewport.content.width = Au::from_px(width);
+    viewport.content.height = Au::from_px(600);
+    let mut containing_block = viewport;
+    containing_block.content.height = Au(0);
+    tree.layout(
+        &mut Floats::new(),
+        &mut CollapsibleMargins::default(),
+        containing_block,
+        viewport,
+        viewport,
+    );
+    tree.layout_root_positioned_descendants(viewport);
+    tree
+}
+
+/// The content box of `layout_box` as (x, y, width, height) in px, for tests.
+#[cfg(test)]
+pub fn rect(layout_box: &LayoutBox) -> (i32, i32, i32, i32) {
+    let content = layout_box.dimensions.content;
+    (
+        content.x.to_px(),
+        content.y.to_px(),
+        content.width.to_px(),
+        content.height.to_px(),
+    )
+}
@@ -4,9 +4,19 @@ pub mod default_style;
 pub mod html;
 pub mod dom;
 pub mod font;
+pub mod bidi;
+pub mod line_break;
 pub mod inline;
 pub mod block;
 pub mod float;
+pub mod position;
+pub mod overflow;
+pub mod writing_mode;
+pub mod flex;
+pub mod grid;
+pub mod table;
+pub mod ruby;
+pub mod content;
 pub mod layout;
 pub mod painter;
 pub mod window;
@@ -18,5 +28,7 @@ extern crate gdk;
 extern crate gdk_pixbuf;
 extern crate glib;
 extern crate gtk;
+#[macro_use]
+extern crate lazy_static;
 extern crate pango;
 extern crate pangocairo;
@@ -0,0 +1,485 @@
+//! Line breaking: the soft wrap opportunities in text, found with the pair rules of the Unicode
+//! line breaking algorithm and tailored by `word-break` and `line-break`.
+//! ref. https://www.unicode.org/reports/tr14/
+//! ref. https://www.w3.org/TR/css-text-3/#line-breaking
+
+use style::{LineBreak, WordBreak};
+
+/// The line breaking classes. Complex context dependent characters (SA), ambiguous (AI) and
+/// unknown ones (XX) are resolved to `Alphabetic`, and Hebrew letters (HL) and emoji (EB, EM)
+/// are approximated by `Alphabetic`, `Ideographic` and `CombiningMark`.
+/// ref. https://www.unicode.org/reports/tr14/#Table1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BreakClass {
+    MandatoryBreak,             // BK
+    CarriageReturn,             // CR
+    LineFeed,                   // LF
+    NextLine,                   // NL
+    Space,                      // SP
+    ZeroWidthSpace,             // ZW
+    ZeroWidthJoiner,            // ZWJ
+    CombiningMark,              // CM
+    WordJoiner,                 // WJ
+    Glue,                       // GL
+    BreakAfter,                 // BA
+    BreakBefore,                // BB
+    BreakBoth,                  // B2
+    Hyphen,                     // HY
+    ContingentBreak,            // CB
+    ClosePunctuation,           // CL
+    CloseParenthesis,           // CP
+    Exclamation,                // EX
+    Inseparable,                // IN
+    Nonstarter,                 // NS
+    OpenPunctuation,            // OP
+    Quotation,                  // QU
+    InfixSeparator,             // IS
+    Numeric,                    // NU
+    PostfixNumeric,             // PO
+    PrefixNumeric,              // PR
+    Symbol,                     // SY
+    Alphabetic,                 // AL
+    Ideographic,                // ID
+    HangulLvSyllable,           // H2
+    HangulLvtSyllable,          // H3
+    HangulLJamo,                // JL
+    HangulVJamo,                // JV
+    HangulTJamo,                // JT
+    RegionalIndicator,          // RI
+    ConditionalJapaneseStarter, // CJ
+}
+
+use self::BreakClass::*;
+
+/// The line breaking class of `c`.
+pub fn break_class(c: char) -> BreakClass {
+    match c {
+        '\n' => LineFeed,
+        '\r' => CarriageReturn,
+        '\u{0B}' | '\u{0C}' | '\u{2028}' | '\u{2029}' => MandatoryBreak,
+        '\u{85}' => NextLine,
+        ' ' => Space,
+        '\t' | '|' | '\u{AD}' | '\u{2000}'...'\u{2006}' | '\u{2008}'...'\u{200A}' => BreakAfter,
+        '\u{2010}' | '\u{2012}' | '\u{2013}' | '\u{3000}' => BreakAfter,
+        '\u{200B}' => ZeroWidthSpace,
+        '\u{200D}' => ZeroWidthJoiner,
+        '\u{2060}' | '\u{FEFF}' => WordJoiner,
+        '\u{A0}' | '\u{2007}' | '\u{2011}' | '\u{202F}' => Glue,
+        '\u{B4}' => BreakBefore,
+        '\u{2014}' => BreakBoth,
+        '-' => Hyphen,
+        '\u{FFFC}' => ContingentBreak,
+        '!' | '?' | '\u{FF01}' | '\u{FF1F}' => Exclamation,
+        '"' | '\'' | '\u{AB}' | '\u{BB}' | '\u{2018}'...'\u{201F}' | '\u{2039}' | '\u{203A}' => {
+            Quotation
+        }
+        '(' | '[' | '{' | '\u{A1}' | '\u{BF}' => OpenPunctuation,
+        ')' | ']' | '\u{FF09}' | '\u{FF3D}' => CloseParenthesis,
+        '}' => ClosePunctuation,
+        ',' | '.' | ':' | ';' | '\u{2044}' => InfixSeparator,
+        '/' => Symbol,
+        '0'...'9' => Numeric,
+        '%' | '\u{A2}' | '\u{B0}' | '\u{2030}'...'\u{2037}' => PostfixNumeric,
+        '$' | '+' | '\\' | '\u{A3}'...'\u{A5}' | '\u{B1}' | '\u{20A0}'...'\u{20CF}' => {
+            PrefixNumeric
+        }
+        '\u{2024}'...'\u{2026}' => Inseparable,
+        '\u{203C}' | '\u{203D}' => Nonstarter,
+        '\u{0300}'...'\u{036F}' | '\u{1AB0}'...'\u{1AFF}' | '\u{1DC0}'...'\u{1DFF}' => {
+            CombiningMark
+        }
+        '\u{20D0}'...'\u{20FF}' | '\u{FE00}'...'\u{FE0F}' | '\u{FE20}'...'\u{FE2F}' => {
+            CombiningMark
+        }
+        '\u{3099}' | '\u{309A}' | '\u{1F3FB}'...'\u{1F3FF}' => CombiningMark,
+        '\u{1F1E6}'...'\u{1F1FF}' => RegionalIndicator,
+        _ => break_class_east_asian(c),
+    }
+}
+
+/// The line breaking class of the characters of Chinese, Japanese and Korean, and of emoji.
+fn break_class_east_asian(c: char) -> BreakClass {
+    match c {
+        // Kinsoku: the punctuation that can't start a line...
+        '\u{3001}' | '\u{3002}' | '\u{FF0C}' | '\u{FF0E}' | '\u{FF61}' | '\u{FF64}' => {
+            ClosePunctuation
+        }
+        '\u{3009}' | '\u{300B}' | '\u{300D}' | '\u{300F}' | '\u{3011}' | '\u{3015}' => {
+            ClosePunctuation
+        }
+        '\u{3017}' | '\u{3019}' | '\u{301B}' | '\u{301E}' | '\u{301F}' => ClosePunctuation,
+        '\u{FF5D}' | '\u{FF60}' | '\u{FF63}' => ClosePunctuation,
+        '\u{3005}' | '\u{301C}' | '\u{303B}' | '\u{309B}'...'\u{309E}' | '\u{30A0}' => Nonstarter,
+        '\u{30FB}' | '\u{30FD}' | '\u{30FE}' | '\u{FF1A}' | '\u{FF1B}' => Nonstarter,
+        // ... and the punctuation that can't end one.
+        '\u{3008}' | '\u{300A}' | '\u{300C}' | '\u{300E}' | '\u{3010}' | '\u{3014}' => {
+            OpenPunctuation
+        }
+        '\u{3016}' | '\u{3018}' | '\u{301A}' | '\u{301D}' => OpenPunctuation,
+        '\u{FF08}' | '\u{FF3B}' | '\u{FF5B}' | '\u{FF5F}' | '\u{FF62}' => OpenPunctuation,
+        // Small kana and the prolonged sound mark, whose kinsoku depends on `line-break`.
+        '\u{3041}' | '\u{3043}' | '\u{3045}' | '\u{3047}' | '\u{3049}' | '\u{3063}' => {
+            ConditionalJapaneseStarter
+        }
+        '\u{3083}' | '\u{3085}' | '\u{3087}' | '\u{308E}' | '\u{3095}' | '\u{3096}' => {
+            ConditionalJapaneseStarter
+        }
+        '\u{30A1}' | '\u{30A3}' | '\u{30A5}' | '\u{30A7}' | '\u{30A9}' | '\u{30C3}' => {
+            ConditionalJapaneseStarter
+        }
+        '\u{30E3}' | '\u{30E5}' | '\u{30E7}' | '\u{30EE}' | '\u{30F5}' | '\u{30F6}' => {
+            ConditionalJapaneseStarter
+        }
+        '\u{30FC}' | '\u{31F0}'...'\u{31FF}' | '\u{FF67}'...'\u{FF70}' => {
+            ConditionalJapaneseStarter
+        }
+        '\u{AC00}'...'\u{D7A3}' => if (c as u32 - 0xAC00) % 28 == 0 {
+            HangulLvSyllable
+        } else {
+            HangulLvtSyllable
+        },
+        '\u{1100}'...'\u{115F}' | '\u{A960}'...'\u{A97F}' => HangulLJamo,
+        '\u{1160}'...'\u{11A7}' | '\u{D7B0}'...'\u{D7C6}' => HangulVJamo,
+        '\u{11A8}'...'\u{11FF}' | '\u{D7CB}'...'\u{D7FB}' => HangulTJamo,
+        '\u{2E80}'...'\u{2FFF}' | '\u{3000}'...'\u{303F}' | '\u{3040}'...'\u{30FF}' => Ideographic,
+        '\u{3400}'...'\u{4DBF}' | '\u{4E00}'...'\u{9FFF}' | '\u{F900}'...'\u{FAFF}' => Ideographic,
+        '\u{FF00}'...'\u{FFEF}' | '\u{20000}'...'\u{3FFFD}' => Ideographic,
+        '\u{2600}'...'\u{27BF}' | '\u{1F300}'...'\u{1F64F}' | '\u{1F680}'...'\u{1F6FF}' => {
+            Ideographic
+        }
+        '\u{1F900}'...'\u{1F9FF}' => Ideographic,
+        _ => Alphabetic,
+    }
+}
+
+/// Whether `c` is one of the iteration marks or the hyphens that `line-break: loose` allows at
+/// the start of a line.
+fn is_loose_nonstarter(c: char) -> bool {
+    match c {
+        '\u{3005}' | '\u{303B}' | '\u{309D}' | '\u{309E}' | '\u{30FD}' | '\u{30FE}' => true,
+        '\u{301C}' | '\u{30A0}' | '\u{30FB}' => true,
+        _ => false,
+    }
+}
+
+/// The class of `c` tailored by `word-break` and `line-break`.
+/// ref. https://www.w3.org/TR/css-text-3/#word-break-property
+/// ref. https://www.w3.org/TR/css-text-3/#line-break-property
+fn tailored_break_class(c: char, word_break: WordBreak, line_break: LineBreak) -> BreakClass {
+    let class = match (break_class(c), line_break) {
+        // `auto` follows the default of UAX #14, which is strict.
+        (ConditionalJapaneseStarter, LineBreak::Auto)
+        | (ConditionalJapaneseStarter, LineBreak::Strict) => Nonstarter,
+        (ConditionalJapaneseStarter, _) => Ideographic,
+        (Nonstarter, LineBreak::Loose) if is_loose_nonstarter(c) => Ideographic,
+        (Inseparable, LineBreak::Loose) => Ideographic,
+        (class, _) => class,
+    };
+    match (class, word_break) {
+        (Alphabetic, WordBreak::BreakAll) | (Numeric, WordBreak::BreakAll) => Ideographic,
+        (Ideographic, WordBreak::KeepAll)
+        | (HangulLvSyllable, WordBreak::KeepAll)
+        | (HangulLvtSyllable, WordBreak::KeepAll)
+        | (HangulLJamo, WordBreak::KeepAll)
+        | (HangulVJamo, WordBreak::KeepAll)
+        | (HangulTJamo, WordBreak::KeepAll) => Alphabetic,
+        (class, _) => class,
+    }
+}
+
+/// The byte offsets in `text` before which a line can break. `before` is the character laid
+/// out just before `text` if any, which decides if a line can break at the offset 0.
+/// The end of `text` isn't included.
+pub fn break_opportunities(
+    text: &str,
+    before: Option<char>,
+    word_break: WordBreak,
+    line_break: LineBreak,
+) -> Vec<usize> {
+    if line_break == LineBreak::Anywhere {
+        let mut opportunities = cluster_boundaries(text);
+        if before.is_some() && break_class(text.chars().next().unwrap_or(' ')) != CombiningMark {
+            opportunities.insert(0, 0);
+        }
+        return opportunities;
+    }
+
+    let mut chars: Vec<(usize, char)> = before.into_iter().map(|c| (0, c)).collect();
+    chars.extend(text.char_indices());
+    let first = if before.is_some() { 1 } else { 0 };
+
+    let mut opportunities = vec![];
+    // The class of the last character, with the combining marks taking the class of their base.
+    let mut prev = Alphabetic;
+    // The class of the last character but spaces.
+    let mut before_spaces = Alphabetic;
+    let mut prev_is_zwj = false;
+    let mut regional_indicators = 0;
+
+    for (i, &(offset, c)) in chars.iter().enumerate() {
+        let mut class = tailored_break_class(c, word_break, line_break);
+        let is_zwj = class == ZeroWidthJoiner;
+        let attached = (class == CombiningMark || is_zwj) && i > 0 && match prev {
+            MandatoryBreak | CarriageReturn | LineFeed | NextLine | Space | ZeroWidthSpace => false,
+            _ => true,
+        };
+        if attached {
+            class = prev;
+        } else if class == CombiningMark || is_zwj {
+            // LB10
+            class = Alphabetic;
+        }
+
+        if i > 0 && !attached && !prev_is_zwj && can_break(prev, before_spaces, class) {
+            // LB30a: Regional indicators are paired.
+            let between_pair = prev == RegionalIndicator && class == RegionalIndicator
+                && regional_indicators % 2 == 1;
+            if !between_pair && i >= first {
+                opportunities.push(offset);
+            }
+        }
+
+        regional_indicators = match class {
+            RegionalIndicator if attached => regional_indicators,
+            RegionalIndicator => regional_indicators + 1,
+            _ => 0,
+        };
+        prev_is_zwj = is_zwj;
+        prev = class;
+        if class != Space {
+            before_spaces = class;
+        }
+    }
+    opportunities
+}
+
+/// Whether a line can break between a character of the class `prev` and one of `class`.
+/// `before_spaces` is the class of the last character before `prev` and the spaces before it.
+/// ref. https://www.unicode.org/reports/tr14/#Algorithm
+fn can_break(prev: BreakClass, before_spaces: BreakClass, class: BreakClass) -> bool {
+    match (prev, class) {
+        // LB4, LB5
+        (MandatoryBreak, _) | (NextLine, _) | (LineFeed, _) => return true,
+        (CarriageReturn, LineFeed) => return false,
+        (CarriageReturn, _) => return true,
+        // LB6, LB7
+        (_, MandatoryBreak) | (_, CarriageReturn) | (_, LineFeed) | (_, NextLine) => return false,
+        (_, Space) | (_, ZeroWidthSpace) => return false,
+        _ => {}
+    }
+    // LB8
+    if before_spaces == ZeroWidthSpace {
+        return true;
+    }
+    match (prev, class) {
+        // LB11, LB12, LB12a
+        (_, WordJoiner) | (WordJoiner, _) | (Glue, _) => return false,
+        (Space, Glue) | (BreakAfter, Glue) | (Hyphen, Glue) => {}
+        (_, Glue) => return false,
+        // LB13
+        (_, ClosePunctuation)
+        | (_, CloseParenthesis)
+        | (_, Exclamation)
+        | (_, InfixSeparator)
+        | (_, Symbol) => return false,
+        _ => {}
+    }
+    match (before_spaces, class) {
+        // LB14, LB15, LB16, LB17
+        (OpenPunctuation, _)
+        | (Quotation, OpenPunctuation)
+        | (ClosePunctuation, Nonstarter)
+        | (CloseParenthesis, Nonstarter)
+        | (BreakBoth, BreakBoth) => return false,
+        _ => {}
+    }
+    match (prev, class) {
+        // LB18
+        (Space, _) => true,
+        // LB19, LB20
+        (_, Quotation) | (Quotation, _) => false,
+        (_, ContingentBreak) | (ContingentBreak, _) => true,
+        // LB21, LB22
+        (_, BreakAfter) | (_, Hyphen) | (_, Nonstarter) | (BreakBefore, _) => false,
+        (_, Inseparable) => false,
+        // LB23, LB23a, LB24
+        (Alphabetic, Numeric) | (Numeric, Alphabetic) => false,
+        (PrefixNumeric, Ideographic) | (Ideographic, PostfixNumeric) => false,
+        (PrefixNumeric, Alphabetic) | (PostfixNumeric, Alphabetic) => false,
+        (Alphabetic, PrefixNumeric) | (Alphabetic, PostfixNumeric) => false,
+        // LB25
+        (ClosePunctuation, PostfixNumeric)
+        | (CloseParenthesis, PostfixNumeric)
+        | (ClosePunctuation, PrefixNumeric)
+        | (CloseParenthesis, PrefixNumeric)
+        | (Numeric, PostfixNumeric)
+        | (Numeric, PrefixNumeric)
+        | (PostfixNumeric, OpenPunctuation)
+        | (PostfixNumeric, Numeric)
+        | (PrefixNumeric, OpenPunctuation)
+        | (PrefixNumeric, Numeric)
+        | (Hyphen, Numeric)
+        | (InfixSeparator, Numeric)
+        | (Numeric, Numeric)
+        | (Symbol, Numeric) => false,
+        // LB26, LB27
+        (HangulLJamo, HangulLJamo)
+        | (HangulLJamo, HangulVJamo)
+        | (HangulLJamo, HangulLvSyllable)
+        | (HangulLJamo, HangulLvtSyllable)
+        | (HangulVJamo, HangulVJamo)
+        | (HangulVJamo, HangulTJamo)
+        | (HangulLvSyllable, HangulVJamo)
+        | (HangulLvSyllable, HangulTJamo)
+        | (HangulTJamo, HangulTJamo)
+        | (HangulLvtSyllable, HangulTJamo) => false,
+        (HangulLJamo, PostfixNumeric)
+        | (HangulVJamo, PostfixNumeric)
+        | (HangulTJamo, PostfixNumeric)
+        | (HangulLvSyllable, PostfixNumeric)
+        | (HangulLvtSyllable, PostfixNumeric)
+        | (PrefixNumeric, HangulLJamo)
+        | (PrefixNumeric, HangulVJamo)
+        | (PrefixNumeric, HangulTJamo)
+        | (PrefixNumeric, HangulLvSyllable)
+        | (PrefixNumeric, HangulLvtSyllable) => false,
+        // LB28, LB29, LB30
+        (Alphabetic, Alphabetic) | (InfixSeparator, Alphabetic) => false,
+        (Alphabetic, OpenPunctuation) | (Numeric, OpenPunctuation) => false,
+        (CloseParenthesis, Alphabetic) | (CloseParenthesis, Numeric) => false,
+        // LB30a is handled by the caller.
+        (RegionalIndicator, RegionalIndicator) => true,
+        // LB31
+        _ => true,
+    }
+}
+
+/// The boundaries between the characters of `text` but before combining marks, where
+/// `overflow-wrap` and `line-break: anywhere` can break. The offsets 0 and the end of `text`
+/// aren't included.
+pub fn cluster_boundaries(text: &str) -> Vec<usize> {
+    let mut after_zwj = false;
+    text.char_indices()
+        .filter(|&(offset, c)| {
+            let class = break_class(c);
+            let boundary = offset > 0 && !after_zwj && class != CombiningMark
+                && class != ZeroWidthJoiner;
+            after_zwj = class == ZeroWidthJoiner;
+            boundary
+        })
+        .map(|(offset, _)| offset)
+        .collect()
+}
+
+#[test]
+fn test_break_opportunities() {
+    fn breaks(text: &str) -> Vec<&str> {
+        let opportunities = break_opportunities(text, None, WordBreak::Normal, LineBreak::Auto);
+        let mut pieces = vec![];
+        let mut start = 0;
+        for end in opportunities.into_iter().chain(Some(text.len())) {
+            pieces.push(&text[start..end]);
+            start = end;
+        }
+        pieces
+    }
+
+    assert_eq!(breaks("hello  world"), vec!["hello  ", "world"]);
+    assert_eq!(breaks("a (b) c."), vec!["a ", "(b) ", "c."]);
+    assert_eq!(
+        breaks("x-ray 1,000.5 $5 10%"),
+        vec!["x-", "ray ", "1,000.5 ", "$5 ", "10%"]
+    );
+    assert_eq!(
+        breaks("http://a.example/path?q"),
+        vec!["http://", "a.example/", "path?", "q"]
+    );
+    assert_eq!(breaks("e\u{301}e\u{A0}e"), vec!["e\u{301}e\u{A0}e"]);
+
+    // Kinsoku: no line starts with a closing bracket, a full stop or a small kana, and no
+    // line ends with an opening bracket.
+    assert_eq!(
+        breaks("「日本語。」ちょっと"),
+        vec!["「日", "本", "語。」", "ちょっ", "と"]
+    );
+    let normal = break_opportunities("ちょっと", None, WordBreak::Normal, LineBreak::Normal);
+    assert_eq!(normal, vec![3, 6, 9]);
+    let loose = break_opportunities("日々", None, WordBreak::Normal, LineBreak::Loose);
+    assert_eq!(loose, vec![3]);
+    let normal = break_opportunities("日々", None, WordBreak::Normal, LineBreak::Normal);
+    assert_eq!(normal, vec![] as Vec<usize>);
+
+    // `word-break`
+    let break_all = break_opportunities("ab cd.", None, WordBreak::BreakAll, LineBreak::Auto);
+    assert_eq!(break_all, vec![1, 3, 4]);
+    let keep_all = break_opportunities("日本 語", None, WordBreak::KeepAll, LineBreak::Auto);
+    assert_eq!(keep_all, vec![7]);
+
+    // The character before the text.
+    let after_space = break_opportunities("ab", Some(' '), WordBreak::Normal, LineBreak::Auto);
+    assert_eq!(after_space, vec![0]);
+    let after_letter = break_opportunities("ab", Some('x'), WordBreak::Normal, LineBreak::Auto);
+    assert_eq!(after_letter, vec![] as Vec<usize>);
+
+    let anywhere =
+        break_opportunities("a\u{301}b", Some('x'), WordBreak::Normal, LineBreak::Anywhere);
+    assert_eq!(anywhere, vec![0, 3]);
+}
+
+#[test]
+fn test_line_breaking() {
+    use app_units::Au;
+    use dom::NodeType;
+    use font::{Font, FontSlant, FontWeight};
+    use layout::{layout_html, BoxType};
+
+    let width = |s: &str| {
+        Font::new(Au::from_px(16), FontWeight::Normal, FontSlant::Normal)
+            .text_width(s)
+            .ceil()
+    };
+    let css = "div { display: block; }
+               .break-word { overflow-wrap: break-word; }
+               .break-all { word-break: break-all; }";
+    let tree = layout_html(
+        &format!(
+            "<div><div style='width: {}px'>あいうえお。</div>\
+             <div style='width: {1}px'>abcdefghij klm</div>\
+             <div class='break-word' style='width: {1}px'>abcdefghij klm</div>\
+             <div class='break-all' style='width: {1}px'>abcdefghij</div>\
+             <div style='width: {2}px'>aaaa <span>bbbb</span></div></div>",
+            width("あいうえお"),
+            width("abcde"),
+            width("aaaa bb"),
+        ),
+        css,
+        400,
+    );
+    // The text of the fragments in the line boxes of each block.
+    let lines = |i: usize| {
+        tree.children[i].children[0]
+            .children
+            .iter()
+            .map(|item| match (&item.box_type, &item.node.data) {
+                (&BoxType::TextNode(ref text), &NodeType::Text(ref s)) => {
+                    s[text.range.clone()].to_string()
+                }
+                _ => String::new(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // A line doesn't start with a full stop, so the character before it goes with it.
+    assert_eq!(lines(0), vec!["あいうえ", "お。"]);
+
+    // A word longer than the line overflows, unless `overflow-wrap` or `word-break` allows
+    // breaking it.
+    assert_eq!(lines(1), vec!["abcdefghij ", "klm"]);
+    assert_eq!(lines(2)[0], "abcde");
+    assert_eq!(lines(3)[0], "abcde");
+
+    // Text that doesn't fit moves to the next line if it can break before it.
+    assert_eq!(lines(4)[0], "aaaa ");
+    let items = &tree.children[4].children[0].children;
+    assert!(items[1].dimensions.content.y > items[0].dimensions.content.y);
+}
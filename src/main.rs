@@ -1,9 +1,12 @@
 extern crate naglfar;
-use naglfar::interface;
+use naglfar::{default_style, interface};
 
 extern crate clap;
 use clap::{App, Arg};
 
+use std::path::PathBuf;
+use std::process;
+
 const VERSION_STR: &'static str = env!("CARGO_PKG_VERSION");
 
 fn main() {
@@ -15,9 +18,48 @@ fn main() {
             Arg::with_name("URL")
                 .help("Set URL (starts with http(s):// or file://)")
                 .index(1),
+        )
+        .arg(
+            Arg::with_name("user-stylesheet")
+                .long("user-stylesheet")
+                .value_name("FILE")
+                .help("Set user stylesheet (default: ~/.config/naglfar/user.css)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ua-stylesheet")
+                .long("ua-stylesheet")
+                .value_name("FILE")
+                .help("Set user agent stylesheet (default: ~/.config/naglfar/ua.css)")
+                .takes_value(true),
         );
     let app_matches = app.clone().get_matches();
 
+    // A stylesheet given on the command line that can't be loaded is an error, and ones in the
+    // config directory are optional.
+    let stylesheet_path = |name: &str, file_name: &str| match app_matches.value_of(name) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_style::config_dir()
+            .map(|dir| dir.join(file_name))
+            .filter(|path| path.exists()),
+    };
+    if let Some(path) = stylesheet_path("ua-stylesheet", "ua.css") {
+        if let Err(e) = default_style::load_default_style(&path) {
+            eprintln!("cannot load user agent stylesheet {}: {}", path.display(), e);
+            if app_matches.is_present("ua-stylesheet") {
+                process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = stylesheet_path("user-stylesheet", "user.css") {
+        if let Err(e) = default_style::load_user_style(&path) {
+            eprintln!("cannot load user stylesheet {}: {}", path.display(), e);
+            if app_matches.is_present("user-stylesheet") {
+                process::exit(1);
+            }
+        }
+    }
+
     interface::run_with_url(if let Some(url) = app_matches.value_of("URL") {
         url.to_string()
     } else {
@@ -0,0 +1,125 @@
+use layout::{LayoutBox, Rect};
+use style::{Overflow, Position};
+
+use std::cmp::{max, min};
+
+use app_units::Au;
+
+// Large enough to cover any document on an axis that isn't clipped.
+const UNCLIPPED: Au = Au(1 << 29);
+
+impl LayoutBox {
+    /// The area the content of this box is clipped to, relative to the parent's content area.
+    /// It is the padding box on the clipped axes, and unlimited on the others.
+    /// ref. https://www.w3.org/TR/css-overflow-3/#overflow-properties
+    pub fn overflow_clip_rect(&self) -> Rect {
+        let style = &self.property.computed;
+        let padding_box = self.dimensions.padding_box();
+        let (x, width) = if style.overflow_x == Overflow::Visible {
+            (-UNCLIPPED, UNCLIPPED * 2)
+        } else {
+            (padding_box.x, padding_box.width)
+        };
+        let (y, height) = if style.overflow_y == Overflow::Visible {
+            (-UNCLIPPED, UNCLIPPED * 2)
+        } else {
+            (padding_box.y, padding_box.height)
+        };
+        Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+        }
+    }
+
+    /// How far the content of this box can be scrolled to the right and down. The scrollable
+    /// area covers the descendants overflowing the content box, and the padding after them.
+    /// ref. https://www.w3.org/TR/css-overflow-3/#scrollable
+    pub fn max_scroll_offset(&self) -> (Au, Au) {
+        let (right, bottom) = self.overflow_extent();
+        let content = self.dimensions.content;
+        (
+            max(Au(0), right - content.width),
+            max(Au(0), bottom - content.height),
+        )
+    }
+
+    /// The scroll offset clamped to `max_scroll_offset`, which may have shrunk since the box was
+    /// scrolled. Boxes other than scroll containers are never scrolled.
+    pub fn used_scroll_offset(&self) -> (Au, Au) {
+        if !self.property.computed.is_scroll_container() {
+            return (Au(0), Au(0));
+        }
+        let (x, y) = self.scroll_offset.get();
+        let (max_x, max_y) = self.max_scroll_offset();
+        (min(x, max_x), min(y, max_y))
+    }
+
+    /// The right and bottom edges of the margin boxes of the descendants, relative to the content
+    /// area of this box. Descendants of the boxes clipping their content aren't included.
+    fn overflow_extent(&self) -> (Au, Au) {
+        let content = self.dimensions.content;
+        let (mut right, mut bottom) = (content.width, content.height);
+        for child in &self.children {
+            // Fixed boxes belong to the viewport.
+            if child.property.computed.position == Position::Fixed {
+                continue;
+            }
+            let margin_box = child.dimensions.margin_box();
+            right = max(right, margin_box.x + margin_box.width);
+            bottom = max(bottom, margin_box.y + margin_box.height);
+            if !child.property.computed.clips_overflow() {
+                let (child_right, child_bottom) = child.overflow_extent();
+                right = max(right, child.dimensions.content.x + child_right);
+                bottom = max(bottom, child.dimensions.content.y + child_bottom);
+            }
+        }
+        (right, bottom)
+    }
+}
+
+#[test]
+fn test_overflow() {
+    use layout::layout_html;
+
+    let css = "div { display: block; }
+               .hidden { overflow: hidden; }
+               .float { float: left; width: 10px; height: 30px; }
+               .child { margin-top: 10px; height: 5px; }
+               .scroll { overflow-y: scroll; height: 20px; padding: 0 5px 5px 0; }
+               .tall { height: 50px; }
+               .wide { width: 150px; height: 5px; }";
+    let tree = layout_html(
+        "<div style='padding: 1px 0'><div class='hidden'><div class='float'></div></div>\
+         <div class='hidden'><div class='child'></div></div>\
+         <div class='scroll'><div class='tall'></div><div class='wide'></div></div></div>",
+        css,
+        100,
+    );
+    let root = &tree;
+
+    // A block formatting context contains its floats.
+    assert_eq!(root.children[0].dimensions.content.height.to_px(), 30);
+
+    // The margins of the children don't collapse with the block formatting context's.
+    let parent = &root.children[1];
+    assert_eq!(parent.dimensions.content.y.to_px(), 30);
+    assert_eq!(parent.dimensions.margin.top, Au(0));
+    assert_eq!(parent.children[0].dimensions.content.y.to_px(), 10);
+    assert_eq!(parent.dimensions.content.height.to_px(), 15);
+
+    // `overflow-x: visible` becomes `auto` next to a scrollable `overflow-y`.
+    let scroll = &root.children[2];
+    assert_eq!(scroll.property.computed.overflow_x, Overflow::Auto);
+    assert_eq!(scroll.dimensions.content.height.to_px(), 20);
+    assert_eq!(
+        scroll.max_scroll_offset(),
+        (Au::from_px(55), Au::from_px(35))
+    );
+
+    // The offset is clamped to the scrollable area.
+    scroll.scroll_offset.set((Au(0), Au::from_px(100)));
+    assert_eq!(scroll.used_scroll_offset(), (Au(0), Au::from_px(35)));
+    assert_eq!(root.children[0].used_scroll_offset(), (Au(0), Au(0)));
+}
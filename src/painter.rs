@@ -1,9 +1,16 @@
 use layout::{BoxType, ImageMetaData, LayoutBox, LayoutInfo, Rect};
+use bidi;
 use font::Font;
+use inline::{justification_opportunities, text_font};
 use dom::{ElementData, LayoutType, NodeType};
-use css::{Color, TextDecoration, BLACK};
+use css::{Color, TextDecoration};
+use style::{Overflow, Position};
 use app_units::Au;
 
+use std::cell::{Cell, RefCell};
+use std::cmp::{max, min};
+use std::rc::Rc;
+
 use gdk_pixbuf;
 use gtk;
 
@@ -15,6 +22,9 @@ pub enum DisplayCommand {
     Image(gdk_pixbuf::Pixbuf, ImageMetaData, Rect),
     Text(String, Rect, Color, Vec<TextDecoration>, Font),
     Button(gtk::Button, Rect),
+    // Clips the commands until the matching `PopClip` to the rectangle. Clips nest.
+    PushClip(Rect),
+    PopClip,
 }
 
 #[derive(Debug, Clone)]
@@ -30,18 +40,161 @@ impl DisplayCommandInfo {
 
 pub type DisplayList = Vec<DisplayCommandInfo>;
 
-pub fn build_display_list(layout_root: &mut LayoutBox) -> DisplayList {
+thread_local!(
+    // Whether the last display list depends on the scroll position, i.e. it has fixed or sticky
+    // boxes. If so, the display list must be rebuilt when scrolled.
+    pub static SCROLL_DEPENDENT: Cell<bool> = { Cell::new(false) };
+    // Whether a scroll container has been scrolled since the last display list was built.
+    pub static BOX_SCROLLED: Cell<bool> = { Cell::new(false) };
+    // The scroll containers in the last display list, in painting order.
+    pub static SCROLL_CONTAINERS: RefCell<Vec<ScrollContainer>> = { RefCell::new(vec![]) };
+);
+
+/// A box whose content the user can scroll.
+#[derive(Clone, Debug)]
+pub struct ScrollContainer {
+    // The visible part of the padding box in the document.
+    pub rect: Rect,
+    // Shared with the layout box.
+    pub offset: Rc<Cell<(Au, Au)>>,
+    pub max_offset: (Au, Au),
+}
+
+impl ScrollContainer {
+    /// Scroll the content by (`dx`, `dy`) within the scrollable area. Returns false if it can't
+    /// be scrolled in the direction any more.
+    pub fn scroll_by(&self, dx: Au, dy: Au) -> bool {
+        let (x, y) = self.offset.get();
+        let (max_x, max_y) = self.max_offset;
+        // The offset may exceed the maximum if the content has shrunk since.
+        let old = (min(x, max_x), min(y, max_y));
+        let new = (
+            max(Au(0), min(old.0 + dx, max_x)),
+            max(Au(0), min(old.1 + dy, max_y)),
+        );
+        if new == old {
+            return false;
+        }
+        self.offset.set(new);
+        BOX_SCROLLED.with(|box_scrolled| box_scrolled.set(true));
+        true
+    }
+}
+
+/// The clip rectangles in effect, in the document coordinates, relative to the clip of the
+/// stacking context being painted. None if not clipped.
+#[derive(Clone, Copy, Debug, Default)]
+struct Clip {
+    // For boxes in normal flow, floats, and relatively positioned or sticky boxes.
+    flow: Option<Rect>,
+    // For absolutely positioned boxes, which are clipped only by the ancestors of their
+    // containing blocks.
+    absolute: Option<Rect>,
+}
+
+/// `viewport` is the visible area of the document. Its `x` and `y` are the scroll position.
+pub fn build_display_list(layout_root: &mut LayoutBox, viewport: Rect) -> DisplayList {
     let mut list = Vec::new();
-    render_layout_box(
+    SCROLL_DEPENDENT.with(|scroll_dependent| scroll_dependent.set(false));
+    BOX_SCROLLED.with(|box_scrolled| box_scrolled.set(false));
+    SCROLL_CONTAINERS.with(|containers| containers.borrow_mut().clear());
+    render_stacking_context(
         &mut list,
         Au::from_f64_px(0.0),
         Au::from_f64_px(0.0),
         layout_root,
+        viewport,
+        None,
     );
     list
 }
 
-fn render_layout_box(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutBox) {
+// A positioned box to be painted after the normal flow: (z-index, x, y, box, clip).
+type PositionedBox<'a> = (i32, Au, Au, &'a LayoutBox, Option<Rect>);
+
+/// Paint a box which establishes a stacking context, in the order below:
+/// positioned descendants with negative `z-index`, the normal flow, and the rest of positioned
+/// descendants in `z-index` order.
+/// Positioned boxes with `z-index: auto` are painted as if they had `z-index: 0`.
+/// `clip` is the clip of the ancestors which clip their content, if any.
+/// ref. https://www.w3.org/TR/CSS2/zindex.html
+fn render_stacking_context(
+    list: &mut DisplayList,
+    x: Au,
+    y: Au,
+    layout_box: &LayoutBox,
+    viewport: Rect,
+    clip: Option<Rect>,
+) {
+    if let Some(clip) = clip {
+        list.push(DisplayCommandInfo::new(DisplayCommand::PushClip(clip)));
+    }
+
+    // The background is painted below everything in the stacking context.
+    render_background(list, x, y, layout_box);
+    render_borders(list, x, y, layout_box);
+
+    let mut buf = DisplayList::new();
+    let mut positioned = vec![];
+    render_contents(
+        &mut buf,
+        x,
+        y,
+        layout_box,
+        viewport,
+        Clip::default(),
+        &mut positioned,
+    );
+
+    // A stable sort keeps the tree order among the same `z-index`.
+    positioned.sort_by_key(|&(z_index, _, _, _, _)| z_index);
+
+    let mut positioned = positioned.into_iter().peekable();
+    while let Some(&(z_index, x, y, child, clip)) = positioned.peek() {
+        if z_index >= 0 {
+            break;
+        }
+        render_stacking_context(list, x, y, child, viewport, clip);
+        positioned.next();
+    }
+    list.append(&mut buf);
+    for (_, x, y, child, clip) in positioned {
+        render_stacking_context(list, x, y, child, viewport, clip);
+    }
+
+    if clip.is_some() {
+        list.push(DisplayCommandInfo::new(DisplayCommand::PopClip));
+    }
+}
+
+/// Paint a box in normal flow. Positioned descendants are pushed to `positioned` instead, to be
+/// painted by the stacking context.
+fn render_layout_box<'a>(
+    list: &mut DisplayList,
+    x: Au,
+    y: Au,
+    layout_box: &'a LayoutBox,
+    viewport: Rect,
+    clip: Clip,
+    positioned: &mut Vec<PositionedBox<'a>>,
+) {
+    render_background(list, x, y, layout_box);
+    render_borders(list, x, y, layout_box);
+    render_contents(list, x, y, layout_box, viewport, clip, positioned);
+}
+
+/// Paint the descendants, text and images of a box.
+/// The descendants of a box with `overflow` other than `visible` are clipped to its padding box
+/// and shifted by its scroll offset.
+fn render_contents<'a>(
+    list: &mut DisplayList,
+    x: Au,
+    y: Au,
+    layout_box: &'a LayoutBox,
+    viewport: Rect,
+    clip: Clip,
+    positioned: &mut Vec<PositionedBox<'a>>,
+) {
     let is_input_elem = match layout_box.info {
         LayoutInfo::Button(_, _) => true,
         _ => false,
@@ -49,33 +202,66 @@ fn render_layout_box(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut Layo
 
     let mut buf = DisplayList::new();
 
-    render_background(&mut buf, x, y, layout_box);
-    render_borders(&mut buf, x, y, layout_box);
+    let style = &layout_box.property.computed;
+    let mut clip = clip;
+    if style.clips_overflow() {
+        let rect = layout_box.overflow_clip_rect().add_parent_coordinate(x, y);
+        buf.push(DisplayCommandInfo::new(DisplayCommand::PushClip(rect)));
+        clip.flow = Some(clip.flow.map_or(rect, |flow| flow.intersection(rect)));
+        if style.is_scroll_container() {
+            register_scroll_container(layout_box, clip.flow.unwrap());
+        }
+    }
+    // This box is the containing block of its absolutely positioned descendants.
+    if layout_box.is_positioned() {
+        clip.absolute = clip.flow;
+    }
 
-    let mut children = layout_box.children.clone();
-    children.sort_by(|&LayoutBox { z_index: a, .. }, &LayoutBox { z_index: b, .. }| a.cmp(&b));
+    let (scroll_x, scroll_y) = layout_box.used_scroll_offset();
+    let content_box = layout_box
+        .dimensions
+        .content
+        .add_parent_coordinate(x - scroll_x, y - scroll_y);
 
-    for mut child in children
-        .iter_mut()
+    // Floats are painted after blocks.
+    let children = layout_box
+        .children
+        .iter()
         .filter(|child| child.box_type != BoxType::Float)
-    {
-        render_layout_box(
-            &mut buf,
-            x + layout_box.dimensions.content.x,
-            y + layout_box.dimensions.content.y,
-            &mut child,
+        .chain(
+            layout_box
+                .children
+                .iter()
+                .filter(|child| child.box_type == BoxType::Float),
         );
+    for child in children {
+        let (x, y) = match child.property.computed.position {
+            Position::Fixed => {
+                SCROLL_DEPENDENT.with(|scroll_dependent| scroll_dependent.set(true));
+                // Fixed boxes are laid out with the viewport at the document origin.
+                (content_box.x + viewport.x, content_box.y + viewport.y)
+            }
+            Position::Sticky => {
+                SCROLL_DEPENDENT.with(|scroll_dependent| scroll_dependent.set(true));
+                let (dx, dy) = sticky_offset(child, content_box, viewport);
+                (content_box.x + dx, content_box.y + dy)
+            }
+            _ => (content_box.x, content_box.y),
+        };
+        if child.is_positioned() {
+            let child_clip = match child.property.computed.position {
+                Position::Absolute => clip.absolute,
+                Position::Fixed => None,
+                _ => clip.flow,
+            };
+            positioned.push((child.z_index, x, y, child, child_clip));
+        } else {
+            render_layout_box(&mut buf, x, y, child, viewport, clip, positioned);
+        }
     }
-    for mut child in children
-        .iter_mut()
-        .filter(|child| child.box_type == BoxType::Float)
-    {
-        render_layout_box(
-            &mut buf,
-            x + layout_box.dimensions.content.x,
-            y + layout_box.dimensions.content.y,
-            &mut child,
-        );
+
+    if style.clips_overflow() {
+        buf.push(DisplayCommandInfo::new(DisplayCommand::PopClip));
     }
 
     render_text(&mut buf, x, y, layout_box);
@@ -91,12 +277,70 @@ fn render_layout_box(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut Layo
     }
 }
 
+/// The offset of a sticky box in its parent's content area `containing_block`.
+/// The box is shifted to stay in `viewport` inset by `top`/`right`/`bottom`/`left`, but not
+/// beyond `containing_block`.
+/// ref. https://www.w3.org/TR/css-position-3/#stickypos-insets
+fn sticky_offset(layout_box: &LayoutBox, containing_block: Rect, viewport: Rect) -> (Au, Au) {
+    let offsets = layout_box.property.computed.offsets;
+    let margin_box = layout_box
+        .dimensions
+        .margin_box()
+        .add_parent_coordinate(containing_block.x, containing_block.y);
+
+    // How far the box moves along an axis. `view_start` and `view_end` are the inset edges of
+    // the viewport.
+    let offset = |start: Au,
+                  end: Au,
+                  cb_start: Au,
+                  cb_end: Au,
+                  view_start: Option<Au>,
+                  view_end: Option<Au>| {
+        match (view_start, view_end) {
+            (Some(view_start), _) if start < view_start => {
+                max(Au(0), min(view_start - start, cb_end - end))
+            }
+            (_, Some(view_end)) if end > view_end => {
+                min(Au(0), max(view_end - end, cb_start - start))
+            }
+            _ => Au(0),
+        }
+    };
+
+    let (left, right) = (
+        offsets.left.to_au(viewport.width),
+        offsets.right.to_au(viewport.width),
+    );
+    let (top, bottom) = (
+        offsets.top.to_au(viewport.height),
+        offsets.bottom.to_au(viewport.height),
+    );
+    (
+        offset(
+            margin_box.x,
+            margin_box.x + margin_box.width,
+            containing_block.x,
+            containing_block.x + containing_block.width,
+            left.map(|left| viewport.x + left),
+            right.map(|right| viewport.x + viewport.width - right),
+        ),
+        offset(
+            margin_box.y,
+            margin_box.y + margin_box.height,
+            containing_block.y,
+            containing_block.y + containing_block.height,
+            top.map(|top| viewport.y + top),
+            bottom.map(|bottom| viewport.y + viewport.height - bottom),
+        ),
+    )
+}
+
 fn render_button(
     list: &mut DisplayList,
     _children: &mut DisplayList,
     x: Au,
     y: Au,
-    layout_box: &mut LayoutBox,
+    layout_box: &LayoutBox,
 ) {
     if let &LayoutInfo::Button(ref btn, _) = &layout_box.info {
         list.push(DisplayCommandInfo::new(DisplayCommand::Button(
@@ -106,27 +350,93 @@ fn render_button(
     }
 }
 
-fn render_text(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutBox) {
-    if let &mut BoxType::TextNode(ref text_info) = &mut layout_box.box_type {
+fn render_text(list: &mut DisplayList, x: Au, y: Au, layout_box: &LayoutBox) {
+    if let BoxType::TextNode(ref text_info) = layout_box.box_type {
         let text = if let NodeType::Text(ref text) = layout_box.node.data {
             &text.as_str()[text_info.range.clone()]
         } else {
             unreachable!()
         };
-        list.push(DisplayCommandInfo::new(DisplayCommand::Text(
-            text.to_string(),
-            layout_box.dimensions.content.add_parent_coordinate(x, y),
-            match layout_box.property.value("color") {
-                Some(maybe_color) => maybe_color[0].to_color(),
-                _ => None,
-            }.unwrap_or(BLACK),
-            layout_box.property.text_decoration(),
-            text_info.font,
-        )));
+        let style = &layout_box.property.computed;
+        let rect = layout_box.dimensions.content.add_parent_coordinate(x, y);
+
+        // Text at an odd bidi level is a right-to-left run. The override keeps Pango from
+        // reordering it again, and makes it draw the mirrored glyphs of brackets and the like.
+        let rtl = text_info.bidi_level % 2 == 1;
+        let run = |piece: &str| {
+            if rtl {
+                format!("{}{}{}", bidi::RLO, piece, bidi::PDF)
+            } else {
+                piece.to_string()
+            }
+        };
+
+        if text_info.justification == Au(0) {
+            list.push(DisplayCommandInfo::new(DisplayCommand::Text(
+                run(text),
+                rect,
+                style.color,
+                style.text_decoration.clone(),
+                text_info.font,
+            )));
+            return;
+        }
+
+        // Justified text is drawn piece by piece, split at the justification opportunities.
+        // The pieces go along the line, which is vertical in vertical text, and from the end to
+        // the start in a right-to-left run.
+        let font = text_font(style);
+        let vertical = font.orientation.is_vertical();
+        let (line_start, line_length) = if vertical {
+            (rect.y, rect.height)
+        } else {
+            (rect.x, rect.width)
+        };
+        let mut position = if rtl {
+            line_start + line_length
+        } else {
+            line_start
+        };
+        let mut start = 0;
+        let mut ends = justification_opportunities(text, true);
+        ends.push(text.len());
+        for end in ends {
+            let piece = &text[start..end];
+            let length = Au::from_f64_px(font.text_width(piece));
+            if rtl {
+                position -= length;
+            }
+            let piece_rect = if vertical {
+                Rect {
+                    y: position,
+                    height: length,
+                    ..rect
+                }
+            } else {
+                Rect {
+                    x: position,
+                    width: length,
+                    ..rect
+                }
+            };
+            list.push(DisplayCommandInfo::new(DisplayCommand::Text(
+                run(piece),
+                piece_rect,
+                style.color,
+                style.text_decoration.clone(),
+                text_info.font,
+            )));
+            if rtl {
+                position -= text_info.justification;
+            } else {
+                position += length + text_info.justification;
+            }
+            start = end;
+        }
     }
 }
 
-fn render_image(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutBox) {
+fn render_image(list: &mut DisplayList, x: Au, y: Au, layout_box: &LayoutBox) {
     if let NodeType::Element(ElementData {
         ref layout_type, ..
     }) = layout_box.node.data
@@ -143,7 +453,36 @@ fn render_image(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutBox
     }
 }
 
-fn register_anker(x: Au, y: Au, layout_box: &mut LayoutBox) {
+/// Register a scroll container for the mouse wheel, if it has anything to scroll. `rect` is the
+/// visible part of its padding box.
+fn register_scroll_container(layout_box: &LayoutBox, rect: Rect) {
+    let style = &layout_box.property.computed;
+    let (max_x, max_y) = layout_box.max_scroll_offset();
+    // `hidden` is scrollable only programmatically.
+    let user_scrollable = |overflow, max_offset| {
+        if overflow == Overflow::Hidden {
+            Au(0)
+        } else {
+            max_offset
+        }
+    };
+    let max_offset = (
+        user_scrollable(style.overflow_x, max_x),
+        user_scrollable(style.overflow_y, max_y),
+    );
+    if max_offset == (Au(0), Au(0)) || rect.width == Au(0) || rect.height == Au(0) {
+        return;
+    }
+    SCROLL_CONTAINERS.with(|containers| {
+        containers.borrow_mut().push(ScrollContainer {
+            rect: rect,
+            offset: layout_box.scroll_offset.clone(),
+            max_offset: max_offset,
+        })
+    });
+}
+
+fn register_anker(x: Au, y: Au, layout_box: &LayoutBox) {
     match layout_box.info {
         LayoutInfo::Anker => {
             if let Some(url) = layout_box.node.anker_url() {
@@ -163,7 +502,7 @@ fn register_anker(x: Au, y: Au, layout_box: &mut LayoutBox) {
     }
 }
 
-fn register_url_fragment(x: Au, y: Au, layout_box: &mut LayoutBox) {
+fn register_url_fragment(x: Au, y: Au, layout_box: &LayoutBox) {
     if let NodeType::Element(ref e) = layout_box.node.data {
         if let Some(id) = e.id() {
             URL_FRAGMENTS.with(|url_fragments| {
@@ -181,8 +520,9 @@ fn register_url_fragment(x: Au, y: Au, layout_box: &mut LayoutBox) {
     }
 }
 
-fn render_background(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutBox) {
-    lookup_color(layout_box, "background-color", "background").map(|color| {
+fn render_background(list: &mut DisplayList, x: Au, y: Au, layout_box: &LayoutBox) {
+    let color = layout_box.property.computed.background_color;
+    if color.a > 0 {
         list.push(DisplayCommandInfo::new(DisplayCommand::SolidColor(
             color,
             layout_box
@@ -190,19 +530,19 @@ fn render_background(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut Layo
                 .border_box()
                 .add_parent_coordinate(x, y),
         )))
-    });
+    }
 }
 
-fn render_borders(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutBox) {
+fn render_borders(list: &mut DisplayList, x: Au, y: Au, layout_box: &LayoutBox) {
     let d = &layout_box.dimensions;
     let border_box = d.border_box().add_parent_coordinate(x, y);
 
-    let (top_color, right_color, bottom_color, left_color) = layout_box.property.border_color();
+    let color = layout_box.property.computed.border_color;
 
     // Left border
-    if let Some(left_color) = left_color {
+    if d.border.left > Au(0) {
         list.push(DisplayCommandInfo::new(DisplayCommand::SolidColor(
-            left_color,
+            color.left,
             Rect {
                 x: border_box.x,
                 y: border_box.y,
@@ -213,9 +553,9 @@ fn render_borders(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutB
     }
 
     // Right border
-    if let Some(right_color) = right_color {
+    if d.border.right > Au(0) {
         list.push(DisplayCommandInfo::new(DisplayCommand::SolidColor(
-            right_color,
+            color.right,
             Rect {
                 x: border_box.x + border_box.width - d.border.right,
                 y: border_box.y,
@@ -226,9 +566,9 @@ fn render_borders(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutB
     }
 
     // Top border
-    if let Some(top_color) = top_color {
+    if d.border.top > Au(0) {
         list.push(DisplayCommandInfo::new(DisplayCommand::SolidColor(
-            top_color,
+            color.top,
             Rect {
                 x: border_box.x,
                 y: border_box.y,
@@ -239,9 +579,9 @@ fn render_borders(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutB
     }
 
     // Bottom border
-    if let Some(bottom_color) = bottom_color {
+    if d.border.bottom > Au(0) {
         list.push(DisplayCommandInfo::new(DisplayCommand::SolidColor(
-            bottom_color,
+            color.bottom,
             Rect {
                 x: border_box.x,
                 y: border_box.y + border_box.height - d.border.bottom,
@@ -252,13 +592,96 @@ fn render_borders(list: &mut DisplayList, x: Au, y: Au, layout_box: &mut LayoutB
     }
 }
 
-/// Return the specified color for CSS property `name` or `fallback_name`, or None if no color was specified.
-fn lookup_color(layout_box: &mut LayoutBox, name: &str, fallback_name: &str) -> Option<Color> {
-    match layout_box
-        .property
-        .lookup_without_default(name, fallback_name)
-    {
-        Some(maybe_color) => maybe_color[0].to_color(),
-        _ => None,
-    }
+#[test]
+fn test_stacking_order() {
+    use layout::layout_html;
+
+    let css = "div { display: block; height: 10px; }
+               .root { height: 100px; }
+               .a { position: relative; z-index: 2; background-color: red; }
+               .b { position: absolute; z-index: -1; background-color: blue; }
+               .c { background-color: lime; }
+               .d { position: relative; background-color: yellow; }
+               .sticky { position: sticky; top: 0; background-color: black; }";
+    let mut tree = layout_html(
+        "<div class='root'><div class='a'></div><div class='b'></div><div class='c'></div>\
+         <div class='d'></div><div class='sticky'></div></div>",
+        css,
+        100,
+    );
+    let viewport = Rect {
+        x: Au(0),
+        y: Au::from_px(35),
+        width: Au::from_px(100),
+        height: Au::from_px(50),
+    };
+    let rects: Vec<(Color, Rect)> = build_display_list(&mut tree, viewport)
+        .into_iter()
+        .filter_map(|item| match item.command {
+            DisplayCommand::SolidColor(color, rect) => Some((color, rect)),
+            _ => None,
+        })
+        .collect();
+
+    // Negative z-index, the normal flow, and then positioned boxes in z-index order.
+    let colors: Vec<(u8, u8, u8)> = rects.iter().map(|&(ref c, _)| (c.r, c.g, c.b)).collect();
+    assert_eq!(
+        colors,
+        vec![(0, 0, 255), (0, 255, 0), (255, 255, 0), (0, 0, 0), (255, 0, 0)]
+    );
+
+    // The sticky box sticks to the top of the viewport.
+    assert_eq!(rects[3].1.y, Au::from_px(35));
+    assert!(SCROLL_DEPENDENT.with(|scroll_dependent| scroll_dependent.get()));
+}
+
+#[test]
+fn test_overflow_clip() {
+    use layout::layout_html;
+
+    let css = "div { display: block; }
+               .scroller { overflow: auto; height: 20px; padding: 5px; }
+               .inner { height: 50px; background-color: red; }
+               .rel { position: relative; height: 10px; background-color: blue; }";
+    let mut tree = layout_html(
+        "<div><div class='scroller'><div class='inner'></div><div class='rel'></div></div></div>",
+        css,
+        100,
+    );
+    let viewport = Rect {
+        x: Au(0),
+        y: Au(0),
+        width: Au::from_px(100),
+        height: Au::from_px(100),
+    };
+    let commands = |tree: &mut LayoutBox| -> Vec<String> {
+        build_display_list(tree, viewport)
+            .into_iter()
+            .map(|item| match item.command {
+                DisplayCommand::SolidColor(_, rect) => format!("fill {}", rect.y.to_px()),
+                DisplayCommand::PushClip(rect) => format!("push {}", rect.height.to_px()),
+                DisplayCommand::PopClip => "pop".to_string(),
+                _ => "other".to_string(),
+            })
+            .collect()
+    };
+
+    // The content is clipped to the padding box, and so is the positioned descendant painted
+    // after the normal flow.
+    assert_eq!(
+        commands(&mut tree),
+        vec!["push 30", "fill 5", "pop", "push 30", "fill 55", "pop"]
+    );
+
+    let container = SCROLL_CONTAINERS.with(|containers| containers.borrow()[0].clone());
+    assert_eq!(container.max_offset, (Au(0), Au::from_px(40)));
+    assert!(container.scroll_by(Au(0), Au::from_px(100)));
+    assert!(!container.scroll_by(Au(0), Au::from_px(10)));
+    assert!(BOX_SCROLLED.with(|box_scrolled| box_scrolled.get()));
+
+    // The scroll offset held by the box shifts its content.
+    assert_eq!(
+        commands(&mut tree),
+        vec!["push 30", "fill -35", "pop", "push 30", "fill 15", "pop"]
+    );
 }
@@ -0,0 +1,378 @@
+use float::Floats;
+use layout::{BoxType, Dimensions, LayoutBox, LayoutInfo, Rect};
+use style::{LengthOrPercentageOrAuto, Position};
+
+use app_units::Au;
+
+impl LayoutBox {
+    /// Whether `position` is other than `static`.
+    pub fn is_positioned(&self) -> bool {
+        self.property.computed.position != Position::Static
+    }
+
+    /// Shift a relatively positioned box by `top`/`right`/`bottom`/`left`.
+    /// If both `left` and `right` are specified, `left` wins. So does `top` over `bottom`.
+    /// `saved_block` is the block percentage `top` and `bottom` refer to. See `definite_block`.
+    /// ref. https://www.w3.org/TR/CSS2/visuren.html#relative-positioning
+    pub fn apply_relative_offset(&mut self, containing_block: Dimensions, saved_block: Dimensions) {
+        let style = &self.property.computed;
+        if style.position != Position::Relative {
+            return;
+        }
+
+        let cb_width = containing_block.content.width;
+        let offsets = style.offsets;
+        let horizontal = match (offsets.left.to_au(cb_width), offsets.right.to_au(cb_width)) {
+            (Some(left), _) => left,
+            (None, Some(right)) => -right,
+            (None, None) => Au(0),
+        };
+        // Vertical percentages are treated as `auto` if the height of the containing block isn't
+        // definite.
+        let cb_height = saved_block.content.height;
+        let length = |offset: LengthOrPercentageOrAuto| match offset {
+            LengthOrPercentageOrAuto::Percentage(_) if cb_height < Au(0) => None,
+            _ => offset.to_au(cb_height),
+        };
+        let vertical = match (length(offsets.top), length(offsets.bottom)) {
+            (Some(top), _) => top,
+            (None, Some(bottom)) => -bottom,
+            (None, None) => Au(0),
+        };
+
+        self.dimensions.content.x += horizontal;
+        self.dimensions.content.y += vertical;
+    }
+
+    /// Lay out the absolutely positioned descendants whose containing block is the padding box
+    /// of this (positioned) box.
+    pub fn layout_absolute_descendants(&mut self, viewport: Dimensions) {
+        let d = self.dimensions;
+        let padding_box = Rect {
+            x: -d.padding.left,
+            y: -d.padding.top,
+            width: d.padding_box().width,
+            height: d.padding_box().height,
+        };
+        self.layout_positioned_descendants(Some(padding_box), None, viewport);
+    }
+
+    /// Lay out the fixed descendants, and the absolutely positioned descendants that have no
+    /// positioned ancestor. Their containing block is the viewport, which is placed at the
+    /// document origin here. The painter moves fixed boxes with the scroll position.
+    /// Must be called on the root box after the whole tree is laid out.
+    pub fn layout_root_positioned_descendants(&mut self, viewport: Dimensions) {
+        let initial_containing_block = Rect {
+            x: -self.dimensions.content.x,
+            y: -self.dimensions.content.y,
+            width: viewport.content.width,
+            height: viewport.content.height,
+        };
+        let absolute_cb = if self.is_positioned() {
+            // This box has already laid out its absolutely positioned descendants.
+            None
+        } else {
+            Some(initial_containing_block)
+        };
+        self.layout_positioned_descendants(absolute_cb, Some(initial_containing_block), viewport);
+    }
+
+    /// `absolute_cb` and `fixed_cb` are the containing blocks of `absolute` and `fixed` boxes in
+    /// the coordinates of this box's content area, or None if they are laid out elsewhere.
+    fn layout_positioned_descendants(
+        &mut self,
+        absolute_cb: Option<Rect>,
+        fixed_cb: Option<Rect>,
+        viewport: Dimensions,
+    ) {
+        for child in &mut self.children {
+            let position = child.property.computed.position;
+
+            if child.box_type == BoxType::Absolute {
+                let containing_block = match position {
+                    Position::Fixed => fixed_cb,
+                    _ => absolute_cb,
+                };
+                if let Some(containing_block) = containing_block {
+                    child.layout_absolute(containing_block, viewport);
+                }
+            }
+
+            // Positioned boxes lay out their own absolutely positioned descendants.
+            let absolute_cb = if child.is_positioned() {
+                None
+            } else {
+                absolute_cb
+            };
+            if absolute_cb.is_none() && fixed_cb.is_none() {
+                continue;
+            }
+
+            let (x, y) = (child.dimensions.content.x, child.dimensions.content.y);
+            let to_child_coordinates = |rect: Rect| Rect {
+                x: rect.x - x,
+                y: rect.y - y,
+                ..rect
+            };
+            child.layout_positioned_descendants(
+                absolute_cb.map(&to_child_coordinates),
+                fixed_cb.map(&to_child_coordinates),
+                viewport,
+            );
+        }
+    }
+
+    /// Lay out an absolutely positioned box and its descendants in `containing_block`, which
+    /// is in the coordinates of the parent's content area.
+    /// `dimensions.content.x/y` must hold the static position, i.e. the top left corner of the
+    /// margin box if this box were in normal flow. `LayoutBox::layout` records it.
+    pub fn layout_absolute(&mut self, containing_block: Rect, viewport: Dimensions) {
+        let static_position = (self.dimensions.content.x, self.dimensions.content.y);
+        let mut cb: Dimensions = Default::default();
+        cb.content = containing_block;
+
+        self.load_image(cb);
+        self.assign_padding(cb);
+        self.assign_border_width();
+
+        // Child width can depend on parent width, so we need to calculate this box's width before
+        // laying out its children.
+        self.calculate_absolute_width(cb, static_position.0 - containing_block.x);
+
+        self.floats = Floats::new();
+        let definite_block = self.definite_block(cb);
+        self.layout_children(definite_block, viewport);
+
+        self.calculate_absolute_height(cb, static_position.1 - containing_block.y);
+
+        self.dimensions.content.x += containing_block.x;
+        self.dimensions.content.y += containing_block.y;
+
+        self.layout_absolute_descendants(viewport);
+    }
+
+    /// Calculate the width and the horizontal position of an absolutely positioned element.
+    /// Sets the horizontal margins, the `width` and the `x` relative to `containing_block`.
+    /// ref. https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+    fn calculate_absolute_width(&mut self, containing_block: Dimensions, static_left: Au) {
+        let cb_width = containing_block.content.width;
+        let (left, right, width, margin_left, margin_right) = {
+            let style = &self.property.computed;
+            (
+                style.offsets.left.to_au(cb_width),
+                style.offsets.right.to_au(cb_width),
+                self.replaced_width()
+                    .or_else(|| style.width.to_au(cb_width)),
+                style.margin.left.to_au(cb_width),
+                style.margin.right.to_au(cb_width),
+            )
+        };
+        let edges = self.dimensions.border.left + self.dimensions.border.right
+            + self.dimensions.padding.left + self.dimensions.padding.right;
+
+        let constraint = solve_absolute_constraint(
+            cb_width,
+            static_left,
+            (left, right),
+            width,
+            (margin_left, margin_right),
+            edges,
+            |available_width| {
+                let mut cb = containing_block;
+                cb.content.width = available_width;
+                // The margins and edges are subtracted in `shrink_to_fit_width`.
+                cb.content.width +=
+                    margin_left.unwrap_or(Au(0)) + margin_right.unwrap_or(Au(0)) + edges;
+                self.shrink_to_fit_width(cb)
+            },
+        );
+
+        let d = &mut self.dimensions;
+        d.margin.left = constraint.margin_start;
+        d.margin.right = constraint.margin_end;
+        d.content.width = constraint.size;
+        d.content.x = constraint.start + d.margin.left + d.border.left + d.padding.left;
+    }
+
+    /// Calculate the height and the vertical position of an absolutely positioned element.
+    /// Must be called after the children are laid out.
+    /// Sets the vertical margins, the `height` and the `y` relative to `containing_block`.
+    /// ref. https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-height
+    fn calculate_absolute_height(&mut self, containing_block: Dimensions, static_top: Au) {
+        let cb_width = containing_block.content.width;
+        let cb_height = containing_block.content.height;
+        let content_height = self.dimensions.content.height;
+        let (top, bottom, height, margin_top, margin_bottom) = {
+            let style = &self.property.computed;
+            (
+                style.offsets.top.to_au(cb_height),
+                style.offsets.bottom.to_au(cb_height),
+                self.replaced_height()
+                    .or_else(|| style.height.to_au(cb_height)),
+                // Vertical percentages refer to the width of the containing block.
+                style.margin.top.to_au(cb_width),
+                style.margin.bottom.to_au(cb_width),
+            )
+        };
+        let edges = self.dimensions.border.top + self.dimensions.border.bottom
+            + self.dimensions.padding.top + self.dimensions.padding.bottom;
+
+        let constraint = solve_absolute_constraint(
+            cb_height,
+            static_top,
+            (top, bottom),
+            height,
+            (margin_top, margin_bottom),
+            edges,
+            |_| content_height,
+        );
+
+        let d = &mut self.dimensions;
+        d.margin.top = constraint.margin_start;
+        d.margin.bottom = constraint.margin_end;
+        d.content.height = constraint.size;
+        d.content.y = constraint.start + d.margin.top + d.border.top + d.padding.top;
+    }
+
+    pub fn replaced_width(&self) -> Option<Au> {
+        match self.info {
+            LayoutInfo::Image(ref imgdata) => Some(imgdata.metadata.width),
+            _ => None,
+        }
+    }
+
+    pub fn replaced_height(&self) -> Option<Au> {
+        match self.info {
+            LayoutInfo::Image(ref imgdata) => Some(imgdata.metadata.height),
+            _ => None,
+        }
+    }
+}
+
+/// The used values along an axis of an absolutely positioned box.
+struct AbsoluteConstraint {
+    // The distance from the containing block's start edge to the margin box's start edge.
+    start: Au,
+    margin_start: Au,
+    size: Au,
+    margin_end: Au,
+}
+
+/// Solve `start + margin_start + edges + size + margin_end + end = cb_size` for the auto values
+/// along an axis. None means `auto`.
+/// `edges` is the sum of the borders and paddings, and `auto_size` returns the size of the
+/// content for an available size when `size` is auto.
+/// ref. https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+fn solve_absolute_constraint<F>(
+    cb_size: Au,
+    static_start: Au,
+    (start, end): (Option<Au>, Option<Au>),
+    size: Option<Au>,
+    (margin_start, margin_end): (Option<Au>, Option<Au>),
+    edges: Au,
+    auto_size: F,
+) -> AbsoluteConstraint
+where
+    F: FnOnce(Au) -> Au,
+{
+    let solve = |start, margin_start, size, margin_end| AbsoluteConstraint {
+        start: start,
+        margin_start: margin_start,
+        size: size,
+        margin_end: margin_end,
+    };
+
+    if let (Some(start), Some(end), Some(size)) = (start, end, size) {
+        let remaining = cb_size - start - end - size - edges;
+        return match (margin_start, margin_end) {
+            // Auto margins center the box.
+            (None, None) => solve(start, remaining / 2, size, remaining - remaining / 2),
+            (None, Some(margin_end)) => solve(start, remaining - margin_end, size, margin_end),
+            (Some(margin_start), None) => {
+                solve(start, margin_start, size, remaining - margin_start)
+            }
+            // If the values are overconstrained, ignore `end`.
+            (Some(margin_start), Some(margin_end)) => solve(start, margin_start, size, margin_end),
+        };
+    }
+
+    // Otherwise, auto margins are 0.
+    let margin_start = margin_start.unwrap_or(Au(0));
+    let margin_end = margin_end.unwrap_or(Au(0));
+    let margins = margin_start + margin_end + edges;
+
+    match (start, end) {
+        (Some(start), Some(end)) => {
+            // `size` is auto, so the box is stretched.
+            let size = ::std::cmp::max(Au(0), cb_size - start - end - margins);
+            solve(start, margin_start, size, margin_end)
+        }
+        (None, Some(end)) => {
+            let size = size.unwrap_or_else(|| auto_size(cb_size - end - margins));
+            solve(cb_size - end - margins - size, margin_start, size, margin_end)
+        }
+        (Some(start), None) => {
+            let size = size.unwrap_or_else(|| auto_size(cb_size - start - margins));
+            solve(start, margin_start, size, margin_end)
+        }
+        // The box stays at the static position.
+        (None, None) => {
+            let size = size.unwrap_or_else(|| auto_size(cb_size - static_start - margins));
+            solve(static_start, margin_start, size, margin_end)
+        }
+    }
+}
+
+#[test]
+fn test_positioning() {
+    use layout::{layout_html, rect};
+
+    let css = "div { display: block; }
+               .rel { position: relative; left: 10px; top: 5px; height: 100px; padding: 2px; }
+               .abs { position: absolute; right: 0; bottom: 10px; width: 20px; height: 30px; }
+               .fixed { position: fixed; top: 0; left: 0; right: 0; }
+               span { position: absolute; }
+               .percent { position: relative; top: 10%; }";
+    let tree = layout_html(
+        "<div><div class='rel'><div><div class='abs'></div></div><div class='percent'></div></div>\
+         <div class='fixed'>x<span>y</span></div><div class='percent'></div></div>",
+        css,
+        200,
+    );
+    let (relative, fixed) = (&tree.children[0], &tree.children[1]);
+    assert_eq!(rect(relative), (12, 7, 196, 100));
+
+    // The containing block is the padding box of the relatively positioned box.
+    let static_block = &relative.children[0];
+    assert_eq!(rect(static_block), (0, 0, 196, 0));
+    let absolute = &static_block.children[0];
+    assert_eq!(absolute.box_type, BoxType::Absolute);
+    assert_eq!(rect(absolute), (178, 62, 20, 30));
+
+    // A percentage `top` refers to the height of the containing block if it's definite, and is
+    // treated as `auto` otherwise.
+    assert_eq!(rect(&relative.children[1]), (0, 10, 196, 0));
+    assert_eq!(rect(&tree.children[2]), (0, 104, 200, 0));
+
+    // The fixed box is stretched between `left` and `right` of the viewport.
+    assert_eq!(fixed.box_type, BoxType::Absolute);
+    assert_eq!(rect(fixed), (0, 0, 200, 19));
+
+    // An absolutely positioned box without offsets stays at its static position. It's
+    // blockified, so it's placed below the line.
+    let span = &fixed.children[1];
+    assert_eq!(span.box_type, BoxType::Absolute);
+    assert_eq!(rect(span), (0, 19, 8, 19));
+}
+
+#[test]
+fn test_computed_position_style() {
+    use style::{computed_style, Display, FloatType};
+
+    let computed = &computed_style("display: inline; float: left; position: fixed; left: 10%");
+    assert_eq!(computed.position, Position::Fixed);
+    assert_eq!(computed.offsets.left, LengthOrPercentageOrAuto::Percentage(10.0));
+    // Absolutely positioned boxes are blockified and don't float.
+    assert_eq!(computed.display, Display::Block);
+    assert_eq!(computed.float, FloatType::None);
+}
@@ -0,0 +1,179 @@
+use block::{indefinite_block, CollapsibleMargins};
+use float::Floats;
+use inline::IntrinsicWidths;
+use layout::{BoxType, Dimensions, LayoutBox};
+use style::Display;
+
+use std::cmp::max;
+
+use app_units::Au;
+
+// The indices of the base and the annotation of a ruby segment in the children of the container.
+type Segment = (Option<usize>, Option<usize>);
+
+impl LayoutBox {
+    /// Lay out a ruby container. Each annotation is centered over the base before it, and the
+    /// container is set in the line like an inline-block whose baseline is that of the bases.
+    /// The annotations make the line box taller, so they don't overlap the line above.
+    /// ref. https://www.w3.org/TR/css-ruby-1/#ruby-layout
+    pub fn layout_ruby(&mut self, containing_block: Dimensions, viewport: Dimensions) {
+        self.assign_padding(containing_block);
+        self.assign_border_width();
+        self.assign_margin(containing_block);
+
+        // The bases and the annotations are as wide as their content.
+        let mut unlimited = Dimensions::default();
+        unlimited.content.width = Au(-1);
+        let saved_block = indefinite_block(unlimited);
+        for child in &mut self.children {
+            let (mut floats, mut margins) = (Floats::new(), CollapsibleMargins::default());
+            match child.box_type {
+                BoxType::AnonymousBlock | BoxType::Absolute => {
+                    child.layout(&mut floats, &mut margins, unlimited, saved_block, viewport)
+                }
+                _ => child.layout_inline_block(
+                    &mut floats,
+                    &mut margins,
+                    unlimited,
+                    saved_block,
+                    viewport,
+                ),
+            }
+        }
+
+        let segments = self.ruby_segments();
+        let children = &mut self.children;
+        let height = |i: usize| children[i].dimensions.margin_box().height;
+        let annotation_height = segments
+            .iter()
+            .filter_map(|&(_, text)| text.map(&height))
+            .max()
+            .unwrap_or(Au(0));
+        let base_height = segments
+            .iter()
+            .filter_map(|&(base, _)| base.map(&height))
+            .max()
+            .unwrap_or(Au(0));
+
+        let mut x = Au(0);
+        for (base, text) in segments {
+            let width = segment_width((base, text), |i| children[i].dimensions.margin_box().width);
+            for &(i, top) in &[(base, annotation_height), (text, Au(0))] {
+                if let Some(i) = i {
+                    let d = &mut children[i].dimensions;
+                    d.content.x = x + (width - d.margin_box().width) / 2 + d.left_offset();
+                    d.content.y = top + d.top_offset();
+                }
+            }
+            x += width;
+        }
+        self.dimensions.content.width = x;
+        self.dimensions.content.height = annotation_height + base_height;
+    }
+
+    /// The ruby segments, each of which is an annotation and the base before it.
+    /// ref. https://www.w3.org/TR/css-ruby-1/#pairing
+    fn ruby_segments(&self) -> Vec<Segment> {
+        let mut segments = vec![];
+        let mut base = None;
+        for (i, child) in self.children.iter().enumerate() {
+            if child.property.computed.display == Display::RubyText {
+                segments.push((base.take(), Some(i)));
+            } else if child.is_ruby_base() {
+                if let Some(previous) = base.replace(i) {
+                    segments.push((Some(previous), None));
+                }
+            }
+        }
+        segments.extend(base.map(|base| (Some(base), None)));
+        segments
+    }
+
+    /// Whether this child of a ruby container is a base. Whitespace between the bases and the
+    /// annotations is ignored.
+    fn is_ruby_base(&self) -> bool {
+        self.in_normal_flow() && self.property.computed.display != Display::RubyText
+            && !self.collapses_through(Au(0))
+    }
+
+    /// The baseline of a ruby container, which is that of its first base, relative to the top
+    /// of its content area.
+    pub fn ruby_baseline(&self) -> Option<Au> {
+        self.children
+            .iter()
+            .filter(|child| child.is_ruby_base())
+            .filter_map(|child| {
+                child
+                    .first_baseline()
+                    .map(|baseline| child.dimensions.content.y + baseline)
+            })
+            .next()
+    }
+
+    /// A ruby container is laid out as a whole, so the widths are the same.
+    pub fn ruby_intrinsic_widths(&self, containing_block: Dimensions) -> IntrinsicWidths {
+        let width = self.ruby_segments()
+            .into_iter()
+            .fold(Au(0), |width, segment| {
+                width + segment_width(segment, |i| {
+                    self.children[i]
+                        .outer_intrinsic_widths(containing_block)
+                        .preferred
+                })
+            });
+        IntrinsicWidths {
+            preferred: width,
+            minimum: width,
+        }
+    }
+}
+
+/// The width of a ruby segment, which is the wider of the base and the annotation.
+fn segment_width<F: Fn(usize) -> Au>((base, text): Segment, width: F) -> Au {
+    max(base.map_or(Au(0), &width), text.map_or(Au(0), &width))
+}
+
+#[test]
+fn test_ruby() {
+    use layout::layout_html;
+
+    let css = "div { display: block; }
+               ruby { display: ruby; }
+               rt { display: ruby-text; font-size: 8px; }
+               rp { display: none; }";
+    let tree = layout_html(
+        "<div><div>a <ruby>漢字<rp>(</rp><rt>かんじ</rt><rp>)</rp>x<rt>abcdefgh</rt></ruby> b</div>\
+         </div>",
+        css,
+        400,
+    );
+    let px = Au::from_f64_px;
+    let line = &tree.children[0].children[0];
+    let ruby = line.children
+        .iter()
+        .find(|item| item.box_type == BoxType::InlineBlockNode)
+        .unwrap();
+
+    // `rp` is hidden, and each annotation is centered over the base before it. The second
+    // annotation is wider than its base.
+    let positions: Vec<(Au, Au)> = ruby.children
+        .iter()
+        .map(|child| (child.dimensions.content.x, child.dimensions.content.y))
+        .collect();
+    assert_eq!(
+        positions,
+        vec![
+            (px(0.0), px(9.6)),
+            (px(2.0), px(0.0)),
+            (px(28.0), px(9.6)),
+            (px(16.0), px(0.0)),
+        ]
+    );
+    assert_eq!(ruby.dimensions.content.width, px(48.0));
+    assert_eq!(ruby.dimensions.content.height, px(28.8));
+
+    // The line box is taller by the annotations, and the text is on the baseline of the bases.
+    assert_eq!(line.dimensions.content.height, px(28.8));
+    assert_eq!(line.line_baselines, vec![px(24.0)]);
+    assert_eq!(line.children[0].dimensions.content.y, px(11.2));
+}
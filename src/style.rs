@@ -1,6 +1,8 @@
-use css::{Color, TextDecoration, Unit, Value, pt2px};
+use css::{parse_attr_style, Color, LengthContext, TextDecoration, Unit, Value, BLACK,
+          TRANSPARENT};
 use font::{FontSlant, FontWeight};
 
+use std::cmp::max;
 use std::collections::HashMap;
 
 use app_units::Au;
@@ -8,60 +10,396 @@ use app_units::Au;
 #[derive(Clone, Debug)]
 pub struct Style {
     pub property: HashMap<String, Vec<Value>>,
-    pub cached: CachedProperties,
+    pub computed: ComputedStyle,
 }
 
 // pub struct Style(pub HashMap<String, Vec<Value>>);
 
 impl Style {
     pub fn new() -> Style {
-        Style {
-            property: HashMap::new(),
-            cached: CachedProperties::new(),
-        }
+        Style::new_with(HashMap::new())
     }
 
     pub fn new_with(hashmap: HashMap<String, Vec<Value>>) -> Style {
+        let computed = ComputedStyle::compute(&hashmap);
         Style {
             property: hashmap,
-            cached: CachedProperties::new(),
+            computed: computed,
         }
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct CachedProperties {
-    // Option<(top, right, bottom, left)>
-    margin: (Option<Value>, Option<Value>, Option<Value>, Option<Value>),
-    padding: (Option<Value>, Option<Value>, Option<Value>, Option<Value>),
-    border_width: (Option<Value>, Option<Value>, Option<Value>, Option<Value>),
-    border_color: (Option<Color>, Option<Color>, Option<Color>, Option<Color>),
-
-    font_size: Option<Value>,
-    line_height: Option<Value>,
-}
-
-impl CachedProperties {
-    pub fn new() -> CachedProperties {
-        CachedProperties {
-            margin: (None, None, None, None),
-            padding: (None, None, None, None),
-            border_width: (None, None, None, None),
-            border_color: (None, None, None, None),
-            font_size: None,
-            line_height: None,
-        }
-    }
+/// The computed values of the supported properties. Layout and painting read these fields
+/// instead of the specified values in `Style::property`.
+/// Invalid specified values are replaced with the initial values here.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComputedStyle {
+    pub display: Display,
+    pub position: Position,
+    // `top`, `right`, `bottom` and `left`.
+    pub offsets: Sides<LengthOrPercentageOrAuto>,
+    pub float: FloatType,
+    pub clear: Option<ClearType>,
+    pub width: LengthOrPercentageOrAuto,
+    pub height: LengthOrPercentageOrAuto,
+    // None if `auto`, which is 0 except for flex items.
+    pub min_width: Option<LengthOrPercentage>,
+    pub min_height: Option<LengthOrPercentage>,
+    // None if `none`.
+    pub max_width: Option<LengthOrPercentage>,
+    pub max_height: Option<LengthOrPercentage>,
+    pub box_sizing: BoxSizing,
+    pub overflow_x: Overflow,
+    pub overflow_y: Overflow,
+    pub margin: Sides<LengthOrPercentageOrAuto>,
+    pub padding: Sides<LengthOrPercentage>,
+    // Used widths, i.e. 0 if the border style is `none` or `hidden`.
+    pub border_width: Sides<Au>,
+    pub border_style: Sides<BorderStyle>,
+    pub border_color: Sides<Color>,
+    pub color: Color,
+    pub background_color: Color,
+    pub font_size: Au,
+    pub font_weight: FontWeight,
+    pub font_style: FontSlant,
+    pub line_height: LineHeight,
+    pub text_align: TextAlign,
+    // None if `auto`.
+    pub text_align_last: Option<TextAlign>,
+    pub direction: Direction,
+    pub unicode_bidi: UnicodeBidi,
+    pub writing_mode: WritingMode,
+    pub text_orientation: TextOrientation,
+    pub text_combine_upright: TextCombineUpright,
+    pub word_break: WordBreak,
+    // `word-break: break-word` computes to `anywhere`.
+    pub overflow_wrap: OverflowWrap,
+    pub line_break: LineBreak,
+    pub text_decoration: Vec<TextDecoration>,
+    // None if `auto`.
+    pub z_index: Option<i32>,
+    pub flex_direction: FlexDirection,
+    pub flex_wrap: FlexWrap,
+    pub flex_grow: f64,
+    pub flex_shrink: f64,
+    // `content` is treated as `auto`.
+    pub flex_basis: LengthOrPercentageOrAuto,
+    pub order: i32,
+    pub justify_content: Alignment,
+    pub align_content: Alignment,
+    pub align_items: Alignment,
+    // None if `auto`.
+    pub align_self: Option<Alignment>,
+    // `normal` is 0.
+    pub row_gap: LengthOrPercentage,
+    pub column_gap: LengthOrPercentage,
+    pub grid_template_columns: TrackList,
+    pub grid_template_rows: TrackList,
+    pub grid_template_areas: GridTemplateAreas,
+    pub grid_auto_columns: Vec<TrackSize>,
+    pub grid_auto_rows: Vec<TrackSize>,
+    pub grid_auto_flow: GridAutoFlow,
+    pub grid_auto_flow_dense: bool,
+    pub grid_row: (GridLine, GridLine),
+    pub grid_column: (GridLine, GridLine),
+    pub justify_items: Alignment,
+    // None if `auto`.
+    pub justify_self: Option<Alignment>,
+    pub border_collapse: BorderCollapse,
+    // The horizontal and vertical spacing.
+    pub border_spacing: (Au, Au),
+    pub caption_side: CaptionSide,
+    pub table_layout: TableLayout,
+    pub vertical_align: VerticalAlign,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sides<T> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LengthOrPercentage {
+    Length(Au),
+    Percentage(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LengthOrPercentageOrAuto {
+    Length(Au),
+    Percentage(f64),
+    Auto,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineHeight {
+    Normal,
+    Number(f64),
+    Length(Au),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlign {
+    // The start and the end of a line depend on `direction`.
+    Start,
+    End,
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnicodeBidi {
+    Normal,
+    Embed,
+    Isolate,
+    BidiOverride,
+    IsolateOverride,
+    Plaintext,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WritingMode {
+    HorizontalTb,
+    // The lines are vertical, and stacked from the right to the left.
+    VerticalRl,
+    // The lines are vertical, and stacked from the left to the right.
+    VerticalLr,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextOrientation {
+    // CJK characters are upright, and the others are set sideways.
+    Mixed,
+    Upright,
+    Sideways,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextCombineUpright {
+    None,
+    // The text is set horizontally in a single character space of the vertical line.
+    All,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WordBreak {
+    Normal,
+    BreakAll,
+    KeepAll,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowWrap {
+    Normal,
+    BreakWord,
+    Anywhere,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineBreak {
+    Auto,
+    Loose,
+    Normal,
+    Strict,
+    Anywhere,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BorderStyle {
+    None,
+    Hidden,
+    Dotted,
+    Dashed,
+    Solid,
+    Double,
+    Groove,
+    Ridge,
+    Inset,
+    Outset,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Display {
     Inline,
     Block,
     InlineBlock,
+    // A block with a marker.
+    ListItem,
+    Flex,
+    InlineFlex,
+    Grid,
+    InlineGrid,
+    Table,
+    InlineTable,
+    TableRowGroup,
+    TableHeaderGroup,
+    TableFooterGroup,
+    TableRow,
+    TableColumnGroup,
+    TableColumn,
+    TableCell,
+    TableCaption,
+    // A ruby container, whose annotations are set over the base text.
+    Ruby,
+    RubyBase,
+    RubyText,
     None,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Position {
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FlexDirection {
+    Row,
+    RowReverse,
+    Column,
+    ColumnReverse,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FlexWrap {
+    NoWrap,
+    Wrap,
+    WrapReverse,
+}
+
+/// The values of `justify-content`, `align-content`, `align-items` and `align-self`.
+/// `flex-start`/`self-start` are `Start`, and `flex-end`/`self-end` are `End`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Alignment {
+    Normal,
+    Stretch,
+    Start,
+    End,
+    Center,
+    Baseline,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+/// A sizing function of grid tracks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrackBreadth {
+    Length(LengthOrPercentage),
+    // `fr`
+    Flex(f64),
+    Auto,
+    MinContent,
+    MaxContent,
+}
+
+/// `minmax(min, max)`. Other sizes are `minmax(auto, <flex>)` for `fr`, and the same breadth for
+/// both otherwise.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TrackSize {
+    pub min: TrackBreadth,
+    pub max: TrackBreadth,
+}
+
+/// The tracks of `grid-template-columns` or `grid-template-rows`. Line names are ignored.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct TrackList {
+    pub tracks: Vec<TrackSize>,
+    // `repeat(auto-fill, ...)` or `repeat(auto-fit, ...)`, and the index in `tracks` where the
+    // repeated tracks are inserted.
+    pub auto_repeat: Option<(usize, Vec<TrackSize>)>,
+}
+
+/// The named areas of `grid-template-areas`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GridTemplateAreas {
+    pub rows: usize,
+    pub columns: usize,
+    // (name, row lines, column lines), where the lines are 0-based.
+    pub areas: Vec<(String, (usize, usize), (usize, usize))>,
+}
+
+/// A value of `grid-row-start`, `grid-row-end`, `grid-column-start` or `grid-column-end`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum GridLine {
+    Auto,
+    // A line number, which is negative if counted from the end.
+    Line(i32),
+    Span(usize),
+    // A named area, or its `-start` or `-end` line.
+    Name(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BorderCollapse {
+    Separate,
+    Collapse,
+}
+
+/// Whether `width` and `height` include the padding and the border.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BoxSizing {
+    ContentBox,
+    BorderBox,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Overflow {
+    Visible,
+    Hidden,
+    Scroll,
+    Auto,
+    Clip,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CaptionSide {
+    Top,
+    Bottom,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TableLayout {
+    Auto,
+    Fixed,
+}
+
+/// The values of `vertical-align`.
+/// ref. https://www.w3.org/TR/CSS2/visudet.html#propdef-vertical-align
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VerticalAlign {
+    Baseline,
+    Sub,
+    Super,
+    TextTop,
+    TextBottom,
+    Middle,
+    Top,
+    Bottom,
+    // Raises the box by the length, or by the percentage of its `line-height`.
+    Length(LengthOrPercentage),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GridAutoFlow {
+    Row,
+    Column,
+}
+
 #[derive(Clone, PartialEq, Debug, Copy)]
 pub enum FloatType {
     Left,
@@ -79,6 +417,146 @@ pub enum ClearType {
 pub const DEFAULT_FONT_SIZE: f64 = 16.0f64;
 pub const DEFAULT_LINE_HEIGHT_SCALE: f64 = 1.2f64;
 
+/// The longhand properties this engine knows about: (name, inherited, initial value).
+/// Sorted by name for binary search.
+/// ref. https://www.w3.org/TR/CSS2/propidx.html
+static PROPERTIES: &'static [(&'static str, bool, &'static str)] = &[
+    ("align-content", false, "normal"),
+    ("align-items", false, "normal"),
+    ("align-self", false, "auto"),
+    ("background-attachment", false, "scroll"),
+    ("background-color", false, "transparent"),
+    ("background-image", false, "none"),
+    ("background-position", false, "0% 0%"),
+    ("background-repeat", false, "repeat"),
+    ("background-size", false, "auto"),
+    ("border-bottom-color", false, "currentcolor"),
+    ("border-bottom-left-radius", false, "0"),
+    ("border-bottom-right-radius", false, "0"),
+    ("border-bottom-style", false, "none"),
+    ("border-bottom-width", false, "medium"),
+    ("border-collapse", true, "separate"),
+    ("border-left-color", false, "currentcolor"),
+    ("border-left-style", false, "none"),
+    ("border-left-width", false, "medium"),
+    ("border-right-color", false, "currentcolor"),
+    ("border-right-style", false, "none"),
+    ("border-right-width", false, "medium"),
+    ("border-spacing", true, "0"),
+    ("border-top-color", false, "currentcolor"),
+    ("border-top-left-radius", false, "0"),
+    ("border-top-right-radius", false, "0"),
+    ("border-top-style", false, "none"),
+    ("border-top-width", false, "medium"),
+    ("bottom", false, "auto"),
+    ("box-sizing", false, "content-box"),
+    ("caption-side", true, "top"),
+    ("clear", false, "none"),
+    ("color", true, "black"),
+    ("column-gap", false, "normal"),
+    ("content", false, "normal"),
+    ("counter-increment", false, "none"),
+    ("counter-reset", false, "none"),
+    ("counter-set", false, "none"),
+    ("cursor", true, "auto"),
+    ("direction", true, "ltr"),
+    ("display", false, "inline"),
+    ("empty-cells", true, "show"),
+    ("flex-basis", false, "auto"),
+    ("flex-direction", false, "row"),
+    ("flex-grow", false, "0"),
+    ("flex-shrink", false, "1"),
+    ("flex-wrap", false, "nowrap"),
+    ("float", false, "none"),
+    ("font-family", true, "serif"),
+    ("font-size", true, "medium"),
+    ("font-style", true, "normal"),
+    ("font-variant", true, "normal"),
+    ("font-weight", true, "normal"),
+    ("grid-auto-columns", false, "auto"),
+    ("grid-auto-flow", false, "row"),
+    ("grid-auto-rows", false, "auto"),
+    ("grid-column-end", false, "auto"),
+    ("grid-column-start", false, "auto"),
+    ("grid-row-end", false, "auto"),
+    ("grid-row-start", false, "auto"),
+    ("grid-template-areas", false, "none"),
+    ("grid-template-columns", false, "none"),
+    ("grid-template-rows", false, "none"),
+    ("height", false, "auto"),
+    ("justify-content", false, "normal"),
+    ("justify-items", false, "normal"),
+    ("justify-self", false, "auto"),
+    ("left", false, "auto"),
+    ("letter-spacing", true, "normal"),
+    ("line-break", true, "auto"),
+    ("line-height", true, "normal"),
+    ("list-style-image", true, "none"),
+    ("list-style-position", true, "outside"),
+    ("list-style-type", true, "disc"),
+    ("margin-bottom", false, "0"),
+    ("margin-left", false, "0"),
+    ("margin-right", false, "0"),
+    ("margin-top", false, "0"),
+    ("max-height", false, "none"),
+    ("max-width", false, "none"),
+    ("min-height", false, "auto"),
+    ("min-width", false, "auto"),
+    ("opacity", false, "1"),
+    ("order", false, "0"),
+    ("overflow-wrap", true, "normal"),
+    ("overflow-x", false, "visible"),
+    ("overflow-y", false, "visible"),
+    ("padding-bottom", false, "0"),
+    ("padding-left", false, "0"),
+    ("padding-right", false, "0"),
+    ("padding-top", false, "0"),
+    ("position", false, "static"),
+    ("quotes", true, "auto"),
+    ("right", false, "auto"),
+    ("row-gap", false, "normal"),
+    ("table-layout", false, "auto"),
+    ("text-align", true, "start"),
+    ("text-align-last", true, "auto"),
+    ("text-combine-upright", true, "none"),
+    ("text-decoration", false, "none"),
+    ("text-indent", true, "0"),
+    ("text-orientation", true, "mixed"),
+    ("text-transform", true, "none"),
+    ("top", false, "auto"),
+    ("unicode-bidi", false, "normal"),
+    ("vertical-align", false, "baseline"),
+    ("visibility", true, "visible"),
+    ("white-space", true, "normal"),
+    ("width", false, "auto"),
+    ("word-break", true, "normal"),
+    ("word-spacing", true, "normal"),
+    ("writing-mode", true, "horizontal-tb"),
+    ("z-index", false, "auto"),
+];
+
+fn property_entry(name: &str) -> Option<&'static (&'static str, bool, &'static str)> {
+    PROPERTIES
+        .binary_search_by(|&(property_name, _, _)| property_name.cmp(name))
+        .ok()
+        .map(|i| &PROPERTIES[i])
+}
+
+/// Returns if the property `name` is inherited by default. Unknown properties aren't inherited.
+pub fn is_inherited_property(name: &str) -> bool {
+    property_entry(name).map_or(false, |&(_, inherited, _)| inherited)
+}
+
+/// Returns the initial value of the property `name`, or None if the property is unknown.
+pub fn initial_value(name: &str) -> Option<Vec<Value>> {
+    property_entry(name).and_then(|&(_, _, initial)| {
+        parse_attr_style(format!("{}: {}", name, initial))
+            .into_iter()
+            .next()
+            .map(|declaration| declaration.values)
+    })
+}
+
 impl Style {
     pub fn value(&self, name: &str) -> Option<Vec<Value>> {
         self.property.get(name).cloned()
@@ -96,507 +574,841 @@ impl Style {
     pub fn lookup_without_default(&self, name: &str, fallback_name: &str) -> Option<Vec<Value>> {
         self.value(name).or_else(|| self.value(fallback_name))
     }
+}
 
-    pub fn display(&self) -> Display {
-        match self.value("display") {
-            Some(x) => match x[0] {
-                Value::Keyword(ref s) => match &**s {
-                    "block" => Display::Block,
-                    "inline-block" => Display::InlineBlock,
-                    "none" => Display::None,
-                    "inline" | _ => Display::Inline,
-                },
+impl ComputedStyle {
+    /// Computes the values from the specified values, whose relative lengths must have been
+    /// resolved by `Style::resolve_relative_lengths` if necessary.
+    pub fn compute(property: &HashMap<String, Vec<Value>>) -> ComputedStyle {
+        let value = |name: &str| property.get(name).and_then(|values| values.first());
+        let keyword = |name: &str| match value(name) {
+            Some(&Value::Keyword(ref k)) => Some(k.as_str()),
+            _ => None,
+        };
+        let sides = |prefix: &str, suffix: &str| Sides {
+            top: value(format!("{}top{}", prefix, suffix).as_str()),
+            right: value(format!("{}right{}", prefix, suffix).as_str()),
+            bottom: value(format!("{}bottom{}", prefix, suffix).as_str()),
+            left: value(format!("{}left{}", prefix, suffix).as_str()),
+        };
+
+        let color = value("color").and_then(|c| c.to_color()).unwrap_or(BLACK);
+        // `currentColor` refers to the computed `color`.
+        let resolve_color = |c: Option<&Value>| match c {
+            Some(c) if c.is_current_color() => Some(color),
+            Some(c) => c.to_color(),
+            None => None,
+        };
+
+        let font_size = value("font-size")
+            .and_then(|size| size.maybe_percent_to_px(DEFAULT_FONT_SIZE))
+            .map_or(Au::from_f64_px(DEFAULT_FONT_SIZE), Au::from_f64_px);
+
+        // margin and padding have initial value 0.
+        let zero = LengthOrPercentageOrAuto::Length(Au(0));
+        let margin = sides("margin-", "").map(|m| {
+            m.and_then(|m| m.to_length_or_percentage_or_auto())
+                .unwrap_or(zero)
+        });
+        let padding = sides("padding-", "").map(|p| {
+            p.and_then(|p| p.to_length_or_percentage())
+                .unwrap_or(LengthOrPercentage::Length(Au(0)))
+        });
+
+        // border-style has initial value `none`, and border-width has initial value `medium`.
+        let border_style = sides("border-", "-style").map(|s| {
+            s.and_then(|s| s.to_border_style())
+                .unwrap_or(BorderStyle::None)
+        });
+        let border_width = sides("border-", "-width")
+            .zip(border_style)
+            .map(|(w, s)| match s {
+                BorderStyle::None | BorderStyle::Hidden => Au(0),
+                _ => w
+                    .and_then(|w| w.to_border_width())
+                    .unwrap_or(Au::from_f64_px(3.0)),
+            });
+        // border-color has initial value `currentColor`.
+        let border_color = sides("border-", "-color").map(|c| resolve_color(c).unwrap_or(color));
+
+        let grid_auto_tracks = |name: &str| {
+            let tracks = property.get(name).map_or(vec![], |values| {
+                values.iter().filter_map(|value| value.to_track_size()).collect()
+            });
+            if tracks.is_empty() {
+                vec![TrackSize {
+                    min: TrackBreadth::Auto,
+                    max: TrackBreadth::Auto,
+                }]
+            } else {
+                tracks
+            }
+        };
+        let grid_line = |name: &str| match property.get(name).map(|values| values.as_slice()) {
+            Some([Value::Num(n)]) if *n != 0.0 => GridLine::Line(*n as i32),
+            Some([Value::Keyword(ref span), Value::Num(n)])
+            | Some([Value::Num(n), Value::Keyword(ref span)])
+                if span == "span" && *n > 0.0 =>
+            {
+                GridLine::Span(*n as usize)
+            }
+            Some([Value::Keyword(ref span)]) if span == "span" => GridLine::Span(1),
+            Some([Value::Keyword(ref name)]) if name != "auto" => GridLine::Name(name.clone()),
+            _ => GridLine::Auto,
+        };
+
+        let position = match keyword("position") {
+            Some("relative") => Position::Relative,
+            Some("absolute") => Position::Absolute,
+            Some("fixed") => Position::Fixed,
+            Some("sticky") => Position::Sticky,
+            _ => Position::Static,
+        };
+
+        let mut computed = ComputedStyle {
+            display: match keyword("display") {
+                Some("block") => Display::Block,
+                Some("list-item") => Display::ListItem,
+                Some("table") => Display::Table,
+                Some("inline-table") => Display::InlineTable,
+                Some("table-row-group") => Display::TableRowGroup,
+                Some("table-header-group") => Display::TableHeaderGroup,
+                Some("table-footer-group") => Display::TableFooterGroup,
+                Some("table-row") => Display::TableRow,
+                Some("table-column-group") => Display::TableColumnGroup,
+                Some("table-column") => Display::TableColumn,
+                Some("table-cell") => Display::TableCell,
+                Some("table-caption") => Display::TableCaption,
+                Some("ruby") => Display::Ruby,
+                Some("ruby-base") => Display::RubyBase,
+                Some("ruby-text") => Display::RubyText,
+                Some("inline-block") => Display::InlineBlock,
+                Some("flex") => Display::Flex,
+                Some("inline-flex") => Display::InlineFlex,
+                Some("grid") => Display::Grid,
+                Some("inline-grid") => Display::InlineGrid,
+                Some("none") => Display::None,
                 _ => Display::Inline,
             },
-            _ => Display::Inline,
-        }
-    }
-
-    pub fn float(&self) -> FloatType {
-        match self.value("float") {
-            Some(x) => match x[0] {
-                Value::Keyword(ref s) => match &**s {
-                    "left" => FloatType::Left,
-                    "right" => FloatType::Right,
-                    "none" => FloatType::None,
-                    _ => FloatType::None,
-                },
+            position: position,
+            offsets: sides("", "").map(|o| {
+                o.and_then(|o| o.to_length_or_percentage_or_auto())
+                    .unwrap_or(LengthOrPercentageOrAuto::Auto)
+            }),
+            float: match keyword("float") {
+                Some("left") => FloatType::Left,
+                Some("right") => FloatType::Right,
                 _ => FloatType::None,
             },
-            _ => FloatType::None,
-        }
-    }
-
-    pub fn clear(&self) -> Option<ClearType> {
-        match self.value("clear") {
-            Some(x) => match x[0] {
-                Value::Keyword(ref s) => match &**s {
-                    "left" => Some(ClearType::Left),
-                    "right" => Some(ClearType::Right),
-                    "both" => Some(ClearType::Both),
-                    _ => None,
-                },
+            clear: match keyword("clear") {
+                Some("left") => Some(ClearType::Left),
+                Some("right") => Some(ClearType::Right),
+                Some("both") => Some(ClearType::Both),
                 _ => None,
             },
-            _ => None,
+            width: value("width")
+                .and_then(|w| w.to_length_or_percentage_or_auto())
+                .unwrap_or(LengthOrPercentageOrAuto::Auto),
+            height: value("height")
+                .and_then(|h| h.to_length_or_percentage_or_auto())
+                .unwrap_or(LengthOrPercentageOrAuto::Auto),
+            min_width: value("min-width").and_then(|w| w.to_length_or_percentage()),
+            min_height: value("min-height").and_then(|h| h.to_length_or_percentage()),
+            max_width: value("max-width").and_then(|w| w.to_length_or_percentage()),
+            max_height: value("max-height").and_then(|h| h.to_length_or_percentage()),
+            box_sizing: match keyword("box-sizing") {
+                Some("border-box") => BoxSizing::BorderBox,
+                _ => BoxSizing::ContentBox,
+            },
+            overflow_x: to_overflow(keyword("overflow-x")),
+            overflow_y: to_overflow(keyword("overflow-y")),
+            margin: margin,
+            padding: padding,
+            border_width: border_width,
+            border_style: border_style,
+            border_color: border_color,
+            color: color,
+            background_color: resolve_color(value("background-color")).unwrap_or(TRANSPARENT),
+            font_size: font_size,
+            font_weight: value("font-weight").map_or(FontWeight::Normal, |w| w.to_font_weight()),
+            font_style: value("font-style").map_or(FontSlant::Normal, |s| s.to_font_slant()),
+            line_height: match value("line-height") {
+                Some(&Value::Num(f)) => LineHeight::Number(f),
+                Some(&Value::Length(f, Unit::Percent)) => {
+                    LineHeight::Length(Au::from_f64_px(font_size.to_f64_px() * f / 100.0))
+                }
+                Some(l) => l
+                    .to_px()
+                    .map_or(LineHeight::Normal, |l| LineHeight::Length(Au::from_f64_px(l))),
+                None => LineHeight::Normal,
+            },
+            text_align: keyword("text-align")
+                .and_then(to_text_align)
+                .unwrap_or(TextAlign::Start),
+            text_align_last: keyword("text-align-last").and_then(to_text_align),
+            direction: match keyword("direction") {
+                Some("rtl") => Direction::Rtl,
+                _ => Direction::Ltr,
+            },
+            unicode_bidi: match keyword("unicode-bidi") {
+                Some("embed") => UnicodeBidi::Embed,
+                Some("isolate") => UnicodeBidi::Isolate,
+                Some("bidi-override") => UnicodeBidi::BidiOverride,
+                Some("isolate-override") => UnicodeBidi::IsolateOverride,
+                Some("plaintext") => UnicodeBidi::Plaintext,
+                _ => UnicodeBidi::Normal,
+            },
+            writing_mode: match keyword("writing-mode") {
+                Some("vertical-rl") => WritingMode::VerticalRl,
+                Some("vertical-lr") => WritingMode::VerticalLr,
+                _ => WritingMode::HorizontalTb,
+            },
+            text_orientation: match keyword("text-orientation") {
+                Some("upright") => TextOrientation::Upright,
+                // `sideways-right` is the old name of `sideways`.
+                Some("sideways") | Some("sideways-right") => TextOrientation::Sideways,
+                _ => TextOrientation::Mixed,
+            },
+            text_combine_upright: match keyword("text-combine-upright") {
+                Some("all") => TextCombineUpright::All,
+                _ => TextCombineUpright::None,
+            },
+            word_break: match keyword("word-break") {
+                Some("break-all") => WordBreak::BreakAll,
+                Some("keep-all") => WordBreak::KeepAll,
+                _ => WordBreak::Normal,
+            },
+            overflow_wrap: match (keyword("word-break"), keyword("overflow-wrap")) {
+                (Some("break-word"), _) | (_, Some("anywhere")) => OverflowWrap::Anywhere,
+                (_, Some("break-word")) => OverflowWrap::BreakWord,
+                _ => OverflowWrap::Normal,
+            },
+            line_break: match keyword("line-break") {
+                Some("loose") => LineBreak::Loose,
+                Some("normal") => LineBreak::Normal,
+                Some("strict") => LineBreak::Strict,
+                Some("anywhere") => LineBreak::Anywhere,
+                _ => LineBreak::Auto,
+            },
+            text_decoration: property.get("text-decoration").map_or(vec![], |decorations| {
+                decorations
+                    .iter()
+                    .filter_map(|decoration| decoration.to_text_decoration())
+                    .collect()
+            }),
+            z_index: match value("z-index") {
+                Some(&Value::Num(z)) => Some(z as i32),
+                _ => None,
+            },
+            flex_direction: match keyword("flex-direction") {
+                Some("row-reverse") => FlexDirection::RowReverse,
+                Some("column") => FlexDirection::Column,
+                Some("column-reverse") => FlexDirection::ColumnReverse,
+                _ => FlexDirection::Row,
+            },
+            flex_wrap: match keyword("flex-wrap") {
+                Some("wrap") => FlexWrap::Wrap,
+                Some("wrap-reverse") => FlexWrap::WrapReverse,
+                _ => FlexWrap::NoWrap,
+            },
+            flex_grow: match value("flex-grow") {
+                Some(&Value::Num(f)) if f >= 0.0 => f,
+                _ => 0.0,
+            },
+            flex_shrink: match value("flex-shrink") {
+                Some(&Value::Num(f)) if f >= 0.0 => f,
+                _ => 1.0,
+            },
+            flex_basis: value("flex-basis")
+                .and_then(|b| b.to_length_or_percentage_or_auto())
+                .unwrap_or(LengthOrPercentageOrAuto::Auto),
+            order: match value("order") {
+                Some(&Value::Num(order)) => order as i32,
+                _ => 0,
+            },
+            justify_content: keyword("justify-content").map_or(Alignment::Normal, to_alignment),
+            align_content: keyword("align-content").map_or(Alignment::Normal, to_alignment),
+            align_items: keyword("align-items").map_or(Alignment::Normal, to_alignment),
+            align_self: match keyword("align-self") {
+                Some("auto") | None => None,
+                Some(align) => Some(to_alignment(align)),
+            },
+            row_gap: value("row-gap")
+                .and_then(|g| g.to_length_or_percentage())
+                .unwrap_or(LengthOrPercentage::Length(Au(0))),
+            column_gap: value("column-gap")
+                .and_then(|g| g.to_length_or_percentage())
+                .unwrap_or(LengthOrPercentage::Length(Au(0))),
+            grid_template_columns: property
+                .get("grid-template-columns")
+                .map_or(TrackList::default(), |values| to_track_list(values)),
+            grid_template_rows: property
+                .get("grid-template-rows")
+                .map_or(TrackList::default(), |values| to_track_list(values)),
+            grid_template_areas: property
+                .get("grid-template-areas")
+                .and_then(|values| to_grid_template_areas(values))
+                .unwrap_or_default(),
+            grid_auto_columns: grid_auto_tracks("grid-auto-columns"),
+            grid_auto_rows: grid_auto_tracks("grid-auto-rows"),
+            grid_auto_flow: match property.get("grid-auto-flow") {
+                Some(values) if values.contains(&Value::Keyword("column".to_string())) => {
+                    GridAutoFlow::Column
+                }
+                _ => GridAutoFlow::Row,
+            },
+            grid_auto_flow_dense: property.get("grid-auto-flow").map_or(false, |values| {
+                values.contains(&Value::Keyword("dense".to_string()))
+            }),
+            grid_row: (grid_line("grid-row-start"), grid_line("grid-row-end")),
+            grid_column: (grid_line("grid-column-start"), grid_line("grid-column-end")),
+            justify_items: keyword("justify-items").map_or(Alignment::Normal, to_alignment),
+            justify_self: match keyword("justify-self") {
+                Some("auto") | None => None,
+                Some(justify) => Some(to_alignment(justify)),
+            },
+            border_collapse: match keyword("border-collapse") {
+                Some("collapse") => BorderCollapse::Collapse,
+                _ => BorderCollapse::Separate,
+            },
+            // One length applies to both directions.
+            border_spacing: match property.get("border-spacing").map(|values| values.as_slice()) {
+                Some([h]) => {
+                    let h = h.to_px().map_or(Au(0), Au::from_f64_px);
+                    (h, h)
+                }
+                Some([h, v]) => (
+                    h.to_px().map_or(Au(0), Au::from_f64_px),
+                    v.to_px().map_or(Au(0), Au::from_f64_px),
+                ),
+                _ => (Au(0), Au(0)),
+            },
+            caption_side: match keyword("caption-side") {
+                Some("bottom") => CaptionSide::Bottom,
+                _ => CaptionSide::Top,
+            },
+            table_layout: match keyword("table-layout") {
+                Some("fixed") => TableLayout::Fixed,
+                _ => TableLayout::Auto,
+            },
+            vertical_align: match keyword("vertical-align") {
+                Some("sub") => VerticalAlign::Sub,
+                Some("super") => VerticalAlign::Super,
+                Some("text-top") => VerticalAlign::TextTop,
+                Some("text-bottom") => VerticalAlign::TextBottom,
+                Some("middle") => VerticalAlign::Middle,
+                Some("top") => VerticalAlign::Top,
+                Some("bottom") => VerticalAlign::Bottom,
+                _ => value("vertical-align")
+                    .and_then(|v| v.to_length_or_percentage())
+                    .map_or(VerticalAlign::Baseline, VerticalAlign::Length),
+            },
+        };
+
+        // Absolutely positioned boxes are blockified and don't float.
+        // ref. https://www.w3.org/TR/CSS2/visuren.html#dis-pos-flo
+        if position.is_absolute() {
+            computed.float = FloatType::None;
+            computed.display = computed.display.blockify();
+        }
+
+        // `visible` and `clip` can't be combined with a scrollable value on the other axis.
+        // ref. https://www.w3.org/TR/css-overflow-3/#overflow-properties
+        let (x, y) = (computed.overflow_x, computed.overflow_y);
+        if x.is_scrollable() != y.is_scrollable() {
+            computed.overflow_x = x.to_scrollable();
+            computed.overflow_y = y.to_scrollable();
         }
+
+        computed
+    }
+
+    /// Whether the box is a scroll container, i.e. `overflow` is not `visible` or `clip`.
+    pub fn is_scroll_container(&self) -> bool {
+        self.overflow_x.is_scrollable()
     }
 
-    pub fn padding(&mut self) -> (Value, Value, Value, Value) {
-        match (
-            self.cached.padding.0.clone(),
-            self.cached.padding.1.clone(),
-            self.cached.padding.2.clone(),
-            self.cached.padding.3.clone(),
-        ) {
-            (Some(top), Some(right), Some(bottom), Some(left)) => return (top, right, bottom, left),
-            _ => {}
+    /// Whether the box clips its content on either axis.
+    pub fn clips_overflow(&self) -> bool {
+        self.overflow_x != Overflow::Visible || self.overflow_y != Overflow::Visible
+    }
+
+    /// The used value of `line-height`.
+    pub fn line_height(&self) -> Au {
+        match self.line_height {
+            LineHeight::Normal => {
+                Au::from_f64_px(self.font_size.to_f64_px() * DEFAULT_LINE_HEIGHT_SCALE)
+            }
+            LineHeight::Number(f) => Au::from_f64_px(self.font_size.to_f64_px() * f),
+            LineHeight::Length(l) => l,
         }
+    }
+}
 
-        // padding has initial value 0.
-        let zero = Value::Length(0.0, Unit::Px);
-
-        let mut padding_top = self.value("padding-top").and_then(|x| Some(x[0].clone()));
-        let mut padding_bottom = self.value("padding-bottom")
-            .and_then(|x| Some(x[0].clone()));
-        let mut padding_left = self.value("padding-left").and_then(|x| Some(x[0].clone()));
-        let mut padding_right = self.value("padding-right").and_then(|x| Some(x[0].clone()));
-
-        if let Some(padding) = self.value("padding") {
-            match padding.len() {
-                1 => {
-                    padding_top.get_or_insert_with(|| padding[0].clone());
-                    padding_bottom.get_or_insert_with(|| padding[0].clone());
-                    padding_left.get_or_insert_with(|| padding[0].clone());
-                    padding_right.get_or_insert_with(|| padding[0].clone());
-                }
-                2 => {
-                    padding_top.get_or_insert_with(|| padding[0].clone());
-                    padding_bottom.get_or_insert_with(|| padding[0].clone());
-                    padding_left.get_or_insert_with(|| padding[1].clone());
-                    padding_right.get_or_insert_with(|| padding[1].clone());
-                }
-                3 => {
-                    padding_top.get_or_insert_with(|| padding[0].clone());
-                    padding_left.get_or_insert_with(|| padding[1].clone());
-                    padding_right.get_or_insert_with(|| padding[1].clone());
-                    padding_bottom.get_or_insert_with(|| padding[2].clone());
-                }
-                4 => {
-                    padding_top.get_or_insert_with(|| padding[0].clone());
-                    padding_right.get_or_insert_with(|| padding[1].clone());
-                    padding_bottom.get_or_insert_with(|| padding[2].clone());
-                    padding_left.get_or_insert_with(|| padding[3].clone());
+/// Converts a keyword of `text-align` or `text-align-last`. None if `auto` or invalid.
+fn to_text_align(keyword: &str) -> Option<TextAlign> {
+    match keyword {
+        "start" => Some(TextAlign::Start),
+        "end" => Some(TextAlign::End),
+        "left" => Some(TextAlign::Left),
+        "right" => Some(TextAlign::Right),
+        "center" => Some(TextAlign::Center),
+        "justify" => Some(TextAlign::Justify),
+        _ => None,
+    }
+}
+
+fn to_overflow(keyword: Option<&str>) -> Overflow {
+    match keyword {
+        Some("hidden") => Overflow::Hidden,
+        Some("scroll") => Overflow::Scroll,
+        Some("auto") => Overflow::Auto,
+        Some("clip") => Overflow::Clip,
+        _ => Overflow::Visible,
+    }
+}
+
+/// Converts a keyword of `justify-content`, `align-content`, `align-items` or `align-self`.
+fn to_alignment(keyword: &str) -> Alignment {
+    match keyword {
+        "stretch" => Alignment::Stretch,
+        "start" | "flex-start" | "self-start" | "left" => Alignment::Start,
+        "end" | "flex-end" | "self-end" | "right" => Alignment::End,
+        "center" => Alignment::Center,
+        "baseline" | "first" | "last" => Alignment::Baseline,
+        "space-between" => Alignment::SpaceBetween,
+        "space-around" => Alignment::SpaceAround,
+        "space-evenly" => Alignment::SpaceEvenly,
+        _ => Alignment::Normal,
+    }
+}
+
+/// Converts the values of `grid-template-columns` or `grid-template-rows`.
+/// ref. https://www.w3.org/TR/css-grid-1/#track-sizing
+fn to_track_list(values: &[Value]) -> TrackList {
+    let mut list = TrackList::default();
+    for value in values {
+        match *value {
+            Value::Function(ref name, ref arguments) if name == "repeat" => {
+                let tracks: Vec<TrackSize> = arguments
+                    .iter()
+                    .skip(1)
+                    .filter_map(|argument| argument.to_track_size())
+                    .collect();
+                match arguments.first() {
+                    Some(&Value::Num(count)) if count >= 1.0 => {
+                        for _ in 0..count as usize {
+                            list.tracks.extend(tracks.iter().cloned());
+                        }
+                    }
+                    Some(&Value::Keyword(ref k)) if k == "auto-fill" || k == "auto-fit" => {
+                        list.auto_repeat = Some((list.tracks.len(), tracks));
+                    }
+                    _ => {}
                 }
-                0 | _ => unreachable!(),
             }
+            // Line names are parsed as keywords, and skipped here.
+            _ => list.tracks.extend(value.to_track_size()),
         }
+    }
+    list
+}
 
-        padding_top.get_or_insert_with(|| zero.clone());
-        padding_right.get_or_insert_with(|| zero.clone());
-        padding_bottom.get_or_insert_with(|| zero.clone());
-        padding_left.get_or_insert_with(|| zero.clone());
-
-        self.cached.padding.0 = padding_top.clone();
-        self.cached.padding.1 = padding_right.clone();
-        self.cached.padding.2 = padding_bottom.clone();
-        self.cached.padding.3 = padding_left.clone();
-
-        (
-            padding_top.unwrap(),
-            padding_right.unwrap(),
-            padding_bottom.unwrap(),
-            padding_left.unwrap(),
-        )
-    }
-
-    pub fn margin(&mut self) -> (Value, Value, Value, Value) {
-        match (
-            self.cached.margin.0.clone(),
-            self.cached.margin.1.clone(),
-            self.cached.margin.2.clone(),
-            self.cached.margin.3.clone(),
-        ) {
-            (Some(top), Some(right), Some(bottom), Some(left)) => return (top, right, bottom, left),
-            _ => {}
-        }
+/// Converts the strings of `grid-template-areas`. Returns None if they don't make rectangles.
+/// ref. https://www.w3.org/TR/css-grid-1/#grid-template-areas-property
+fn to_grid_template_areas(values: &[Value]) -> Option<GridTemplateAreas> {
+    let rows: Vec<Vec<&str>> = values
+        .iter()
+        .map(|value| match *value {
+            Value::Str(ref row) => Some(row.split_whitespace().collect()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let columns = rows.first().map_or(0, |row| row.len());
+    if columns == 0 || rows.iter().any(|row| row.len() != columns) {
+        return None;
+    }
 
-        // margin has initial value 0.
-        let zero = Value::Length(0.0, Unit::Px);
-
-        let mut margin_top = self.value("margin-top").and_then(|x| Some(x[0].clone()));
-        let mut margin_bottom = self.value("margin-bottom").and_then(|x| Some(x[0].clone()));
-        let mut margin_left = self.value("margin-left").and_then(|x| Some(x[0].clone()));
-        let mut margin_right = self.value("margin-right").and_then(|x| Some(x[0].clone()));
-
-        if let Some(margin) = self.value("margin") {
-            match margin.len() {
-                1 => {
-                    margin_top.get_or_insert_with(|| margin[0].clone());
-                    margin_bottom.get_or_insert_with(|| margin[0].clone());
-                    margin_left.get_or_insert_with(|| margin[0].clone());
-                    margin_right.get_or_insert_with(|| margin[0].clone());
-                }
-                2 => {
-                    margin_top.get_or_insert_with(|| margin[0].clone());
-                    margin_bottom.get_or_insert_with(|| margin[0].clone());
-                    margin_left.get_or_insert_with(|| margin[1].clone());
-                    margin_right.get_or_insert_with(|| margin[1].clone());
-                }
-                3 => {
-                    margin_top.get_or_insert_with(|| margin[0].clone());
-                    margin_left.get_or_insert_with(|| margin[1].clone());
-                    margin_right.get_or_insert_with(|| margin[1].clone());
-                    margin_bottom.get_or_insert_with(|| margin[2].clone());
-                }
-                4 => {
-                    margin_top.get_or_insert_with(|| margin[0].clone());
-                    margin_right.get_or_insert_with(|| margin[1].clone());
-                    margin_bottom.get_or_insert_with(|| margin[2].clone());
-                    margin_left.get_or_insert_with(|| margin[3].clone());
+    let mut areas: Vec<(String, (usize, usize), (usize, usize))> = vec![];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, &name) in row.iter().enumerate() {
+            // A sequence of `.` is a null cell.
+            if name.chars().all(|c| c == '.') {
+                continue;
+            }
+            match areas.iter_mut().find(|area| area.0 == name) {
+                Some(area) => {
+                    (area.1).1 = max((area.1).1, y + 1);
+                    (area.2).1 = max((area.2).1, x + 1);
                 }
-                0 | _ => unreachable!(),
+                None => areas.push((name.to_string(), (y, y + 1), (x, x + 1))),
             }
         }
+    }
 
-        margin_top.get_or_insert_with(|| zero.clone());
-        margin_right.get_or_insert_with(|| zero.clone());
-        margin_bottom.get_or_insert_with(|| zero.clone());
-        margin_left.get_or_insert_with(|| zero.clone());
-
-        self.cached.margin.0 = margin_top.clone();
-        self.cached.margin.1 = margin_right.clone();
-        self.cached.margin.2 = margin_bottom.clone();
-        self.cached.margin.3 = margin_left.clone();
-
-        (
-            margin_top.unwrap(),
-            margin_right.unwrap(),
-            margin_bottom.unwrap(),
-            margin_left.unwrap(),
-        )
-    }
-
-    pub fn border_width(&mut self) -> (Value, Value, Value, Value) {
-        match (
-            self.cached.border_width.0.clone(),
-            self.cached.border_width.1.clone(),
-            self.cached.border_width.2.clone(),
-            self.cached.border_width.3.clone(),
-        ) {
-            (Some(top), Some(right), Some(bottom), Some(left)) => return (top, right, bottom, left),
-            _ => {}
+    // Each area must be filled with its name.
+    for &(ref name, (row_start, row_end), (column_start, column_end)) in &areas {
+        let count = rows.iter().flat_map(|row| row.iter()).filter(|&&n| n == name).count();
+        if count != (row_end - row_start) * (column_end - column_start) {
+            return None;
         }
+    }
 
-        // border has initial value 0.
-        let zero = Value::Length(0.0, Unit::Px);
-
-        let mut border_top = self.value("border-top-width")
-            .and_then(|x| Some(x[0].clone()));
-        let mut border_bottom = self.value("border-bottom-width")
-            .and_then(|x| Some(x[0].clone()));
-        let mut border_left = self.value("border-left-width")
-            .and_then(|x| Some(x[0].clone()));
-        let mut border_right = self.value("border-right-width")
-            .and_then(|x| Some(x[0].clone()));
-
-        macro_rules! return_if_possible {
-            () => {
-                if border_top.is_some() && border_bottom.is_some()
-                    && border_left.is_some() && border_right.is_some() {
-                    return (
-                        border_top.unwrap(), border_right.unwrap(),
-                        border_bottom.unwrap(), border_left.unwrap(),
-                    );
-                }
-            }
+    Some(GridTemplateAreas {
+        rows: rows.len(),
+        columns: columns,
+        areas: areas,
+    })
+}
+
+impl Overflow {
+    /// Whether the content can be scrolled, even if only programmatically as with `hidden`.
+    pub fn is_scrollable(self) -> bool {
+        self != Overflow::Visible && self != Overflow::Clip
+    }
+
+    fn to_scrollable(self) -> Overflow {
+        match self {
+            Overflow::Visible => Overflow::Auto,
+            Overflow::Clip => Overflow::Hidden,
+            overflow => overflow,
+        }
+    }
+}
+
+impl Display {
+    /// The block-level counterpart of an inline-level display.
+    /// ref. https://www.w3.org/TR/css-display-3/#blockify
+    pub fn blockify(self) -> Display {
+        match self {
+            Display::Inline | Display::InlineBlock => Display::Block,
+            Display::InlineFlex => Display::Flex,
+            Display::InlineGrid => Display::Grid,
+            Display::InlineTable => Display::Table,
+            Display::Ruby | Display::RubyBase | Display::RubyText => Display::Block,
+            display if display.is_table_internal() => Display::Block,
+            display => display,
         }
+    }
 
-        return_if_possible!();
+    /// Whether this box is a table wrapper box.
+    pub fn is_table(self) -> bool {
+        self == Display::Table || self == Display::InlineTable
+    }
 
-        if let Some(border) = self.value("border-width") {
-            match border.len() {
-                1 => {
-                    border_top.get_or_insert_with(|| border[0].clone());
-                    border_bottom.get_or_insert_with(|| border[0].clone());
-                    border_left.get_or_insert_with(|| border[0].clone());
-                    border_right.get_or_insert_with(|| border[0].clone());
-                }
-                2 => {
-                    border_top.get_or_insert_with(|| border[0].clone());
-                    border_bottom.get_or_insert_with(|| border[0].clone());
-                    border_left.get_or_insert_with(|| border[1].clone());
-                    border_right.get_or_insert_with(|| border[1].clone());
-                }
-                3 => {
-                    border_top.get_or_insert_with(|| border[0].clone());
-                    border_left.get_or_insert_with(|| border[1].clone());
-                    border_right.get_or_insert_with(|| border[1].clone());
-                    border_bottom.get_or_insert_with(|| border[2].clone());
-                }
-                4 => {
-                    border_top.get_or_insert_with(|| border[0].clone());
-                    border_right.get_or_insert_with(|| border[1].clone());
-                    border_bottom.get_or_insert_with(|| border[2].clone());
-                    border_left.get_or_insert_with(|| border[3].clone());
-                }
-                0 | _ => unreachable!(),
-            }
+    /// Whether this box is a part of a table, i.e. a row group, row, column group, column, cell
+    /// or caption.
+    /// ref. https://www.w3.org/TR/CSS2/tables.html#table-display
+    pub fn is_table_internal(self) -> bool {
+        match self {
+            Display::TableRowGroup
+            | Display::TableHeaderGroup
+            | Display::TableFooterGroup
+            | Display::TableRow
+            | Display::TableColumnGroup
+            | Display::TableColumn
+            | Display::TableCell
+            | Display::TableCaption => true,
+            _ => false,
         }
+    }
 
-        return_if_possible!();
+    /// Whether this box is a row group, i.e. `table-row-group`, `table-header-group` or
+    /// `table-footer-group`.
+    pub fn is_table_row_group(self) -> bool {
+        match self {
+            Display::TableRowGroup | Display::TableHeaderGroup | Display::TableFooterGroup => true,
+            _ => false,
+        }
+    }
 
-        macro_rules! f { ($name:expr, $var:expr) => {
-            if let Some(border_info) = self.value($name) {
-                for border in border_info {
-                    if let &Value::Length(_, _) = &border {
-                        $var.get_or_insert_with(|| border.clone());
-                        break;
-                    }
-                }
-            }
-            return_if_possible!();
-        } }
-
-        f!("border-top", border_top);
-        f!("border-bottom", border_bottom);
-        f!("border-left", border_left);
-        f!("border-right", border_right);
-
-        if let Some(border_info) = self.value("border") {
-            let mut border_width = None;
-            for border in border_info {
-                if let &Value::Length(_, _) = &border {
-                    border_width = Some(border);
-                    break;
-                }
-            }
-            if let Some(border_width) = border_width {
-                border_top.get_or_insert_with(|| border_width.clone());
-                border_right.get_or_insert_with(|| border_width.clone());
-                border_bottom.get_or_insert_with(|| border_width.clone());
-                border_left.get_or_insert_with(|| border_width.clone());
-            }
+    /// Whether this box establishes a grid formatting context.
+    pub fn is_grid(self) -> bool {
+        self == Display::Grid || self == Display::InlineGrid
+    }
+
+    /// Whether this box establishes a flex formatting context.
+    pub fn is_flex(self) -> bool {
+        self == Display::Flex || self == Display::InlineFlex
+    }
+}
+
+impl Position {
+    /// Whether this is `absolute` or `fixed`, i.e. the box is taken out of flow.
+    pub fn is_absolute(self) -> bool {
+        self == Position::Absolute || self == Position::Fixed
+    }
+}
+
+impl WritingMode {
+    pub fn is_vertical(self) -> bool {
+        self != WritingMode::HorizontalTb
+    }
+}
+
+impl<T> Sides<T> {
+    pub fn map<U, F: Fn(T) -> U>(self, f: F) -> Sides<U> {
+        Sides {
+            top: f(self.top),
+            right: f(self.right),
+            bottom: f(self.bottom),
+            left: f(self.left),
         }
+    }
 
-        border_top.get_or_insert_with(|| zero.clone());
-        border_right.get_or_insert_with(|| zero.clone());
-        border_bottom.get_or_insert_with(|| zero.clone());
-        border_left.get_or_insert_with(|| zero.clone());
-
-        self.cached.border_width.0 = border_top.clone();
-        self.cached.border_width.1 = border_right.clone();
-        self.cached.border_width.2 = border_bottom.clone();
-        self.cached.border_width.3 = border_left.clone();
-        (
-            border_top.unwrap(),
-            border_right.unwrap(),
-            border_bottom.unwrap(),
-            border_left.unwrap(),
-        )
-    }
-
-    pub fn border_color(&mut self) -> (Option<Color>, Option<Color>, Option<Color>, Option<Color>) {
-        if self.cached.border_color.0.is_some() || self.cached.border_color.1.is_some()
-            || self.cached.border_color.2.is_some()
-            || self.cached.border_color.3.is_some()
-        {
-            return (
-                self.cached.border_color.0.clone(),
-                self.cached.border_color.1.clone(),
-                self.cached.border_color.2.clone(),
-                self.cached.border_color.3.clone(),
-            );
+    pub fn zip<U>(self, other: Sides<U>) -> Sides<(T, U)> {
+        Sides {
+            top: (self.top, other.top),
+            right: (self.right, other.right),
+            bottom: (self.bottom, other.bottom),
+            left: (self.left, other.left),
         }
+    }
+}
 
-        let mut border_top = self.value("border-top-color").and_then(|x| x[0].to_color());
-        let mut border_bottom = self.value("border-bottom-color")
-            .and_then(|x| x[0].to_color());
-        let mut border_left = self.value("border-left-color")
-            .and_then(|x| x[0].to_color());
-        let mut border_right = self.value("border-right-color")
-            .and_then(|x| x[0].to_color());
-
-        macro_rules! return_if_possible {
-            () => {
-                if border_top.is_some() && border_bottom.is_some()
-                    && border_left.is_some() && border_right.is_some() {
-                    return (
-                        border_top, border_right,
-                        border_bottom, border_left,
-                    );
-                }
+impl LengthOrPercentage {
+    pub fn to_au(&self, containing_length: Au) -> Au {
+        match *self {
+            LengthOrPercentage::Length(l) => l,
+            LengthOrPercentage::Percentage(f) => {
+                Au::from_f64_px(containing_length.to_f64_px() * f / 100.0)
             }
         }
+    }
 
-        if let Some(border) = self.value("border-color") {
-            match border.len() {
-                1 => {
-                    border_top.get_or_insert_with(|| border[0].to_color().unwrap());
-                    border_bottom.get_or_insert_with(|| border[0].to_color().unwrap());
-                    border_left.get_or_insert_with(|| border[0].to_color().unwrap());
-                    border_right.get_or_insert_with(|| border[0].to_color().unwrap());
-                }
-                2 => {
-                    border_top.get_or_insert_with(|| border[0].to_color().unwrap());
-                    border_bottom.get_or_insert_with(|| border[0].to_color().unwrap());
-                    border_left.get_or_insert_with(|| border[1].to_color().unwrap());
-                    border_right.get_or_insert_with(|| border[1].to_color().unwrap());
-                }
-                3 => {
-                    border_top.get_or_insert_with(|| border[0].to_color().unwrap());
-                    border_left.get_or_insert_with(|| border[1].to_color().unwrap());
-                    border_right.get_or_insert_with(|| border[1].to_color().unwrap());
-                    border_bottom.get_or_insert_with(|| border[2].to_color().unwrap());
-                }
-                4 => {
-                    border_top.get_or_insert_with(|| border[0].to_color().unwrap());
-                    border_right.get_or_insert_with(|| border[1].to_color().unwrap());
-                    border_bottom.get_or_insert_with(|| border[2].to_color().unwrap());
-                    border_left.get_or_insert_with(|| border[3].to_color().unwrap());
-                }
-                0 | _ => unreachable!(),
+    pub fn is_zero(&self) -> bool {
+        match *self {
+            LengthOrPercentage::Length(l) => l == Au(0),
+            LengthOrPercentage::Percentage(f) => f == 0.0,
+        }
+    }
+}
+
+impl LengthOrPercentageOrAuto {
+    /// Returns None if `auto`.
+    pub fn to_au(&self, containing_length: Au) -> Option<Au> {
+        match *self {
+            LengthOrPercentageOrAuto::Length(l) => Some(l),
+            LengthOrPercentageOrAuto::Percentage(f) => {
+                Some(Au::from_f64_px(containing_length.to_f64_px() * f / 100.0))
             }
+            LengthOrPercentageOrAuto::Auto => None,
         }
+    }
 
-        return_if_possible!();
+    pub fn is_auto(&self) -> bool {
+        *self == LengthOrPercentageOrAuto::Auto
+    }
+}
 
-        macro_rules! f { ($name:expr, $var:expr) => {
-            if let Some(border_info) = self.value($name) {
-                if let Some(border_color) = (|| {
-                    for border in border_info {
-                        let color = border.to_color();
-                        if color.is_some() { return color; }
-                    }
-                    None
-                })()
-                {
-                    $var.get_or_insert_with(|| border_color.clone());
-                }
+impl Style {
+    /// Resolves font- and viewport-relative lengths into px.
+    /// `ctx.font_size` must be the parent's font size. `font-size` is resolved against it first,
+    /// and then the other properties are resolved against the element's own font size, so that
+    /// nested `em`s compound correctly.
+    /// Returns the context for the children of the element.
+    pub fn resolve_relative_lengths(&mut self, ctx: &LengthContext) -> LengthContext {
+        let parent_font_size = ctx.font_size;
+        let font_size = match self.value("font-size").and_then(|values| values.first().cloned()) {
+            Some(font_size) => resolve_font_size(&font_size, parent_font_size, ctx),
+            None => parent_font_size,
+        };
+        self.property.insert(
+            "font-size".to_string(),
+            vec![Value::Length(font_size, Unit::Px)],
+        );
+
+        let ctx = LengthContext {
+            font_size: font_size,
+            ..*ctx
+        };
+
+        for (name, values) in self.property.iter_mut() {
+            if name == "font-size" {
+                continue;
             }
-            return_if_possible!();
-        } }
-
-        f!("border-top", border_top);
-        f!("border-bottom", border_bottom);
-        f!("border-left", border_left);
-        f!("border-right", border_right);
-
-        if let Some(border_info) = self.value("border") {
-            if let Some(border_color) = (|| {
-                for border in border_info {
-                    let color = border.to_color();
-                    if color.is_some() {
-                        return color;
-                    }
+            for value in values.iter_mut() {
+                *value = value.resolve_relative_length(&ctx);
+            }
+            // A percentage of `line-height` refers to the element's font size, and its computed
+            // value is inherited as an absolute length.
+            if name == "line-height" {
+                if let Some(&Value::Length(f, Unit::Percent)) = values.first() {
+                    values[0] = Value::Length(font_size * f / 100.0, Unit::Px);
                 }
-                None
-            })()
-            {
-                border_top.get_or_insert_with(|| border_color.clone());
-                border_right.get_or_insert_with(|| border_color.clone());
-                border_bottom.get_or_insert_with(|| border_color.clone());
-                border_left.get_or_insert_with(|| border_color.clone());
             }
         }
 
-        self.cached.border_color.0 = border_top.clone();
-        self.cached.border_color.1 = border_right.clone();
-        self.cached.border_color.2 = border_bottom.clone();
-        self.cached.border_color.3 = border_left.clone();
+        self.computed = ComputedStyle::compute(&self.property);
+        ctx
+    }
+}
 
-        (border_top, border_right, border_bottom, border_left)
+/// Computes `font-size` in px from its specified value.
+/// ref. https://www.w3.org/TR/css-fonts-3/#font-size-prop
+fn resolve_font_size(font_size: &Value, parent_font_size: f64, ctx: &LengthContext) -> f64 {
+    match *font_size {
+        // `em`s and percentages in `font-size` refer to the parent's font size.
+        Value::Length(f, Unit::Percent) => parent_font_size * f / 100.0,
+        Value::Length(f, Unit::Em) => parent_font_size * f,
+        Value::Length(f, Unit::Ex) | Value::Length(f, Unit::Ch) => parent_font_size * f / 2.0,
+        Value::Length(f, ref unit) => ctx
+            .relative_unit_to_px(f, unit)
+            .or_else(|| unit.to_px(f))
+            .unwrap_or(parent_font_size),
+        Value::Num(f) => f,
+        Value::Keyword(ref k) => match k.as_str() {
+            "xx-small" => DEFAULT_FONT_SIZE * 3.0 / 5.0,
+            "x-small" => DEFAULT_FONT_SIZE * 3.0 / 4.0,
+            "small" => DEFAULT_FONT_SIZE * 8.0 / 9.0,
+            "medium" => DEFAULT_FONT_SIZE,
+            "large" => DEFAULT_FONT_SIZE * 6.0 / 5.0,
+            "x-large" => DEFAULT_FONT_SIZE * 3.0 / 2.0,
+            "xx-large" => DEFAULT_FONT_SIZE * 2.0,
+            "xxx-large" => DEFAULT_FONT_SIZE * 3.0,
+            "larger" => parent_font_size * 1.2,
+            "smaller" => parent_font_size / 1.2,
+            _ => parent_font_size,
+        },
+        _ => parent_font_size,
     }
+}
 
-    pub fn text_decoration(&self) -> Vec<TextDecoration> {
-        if let Some(text_decorations) = self.value("text-decoration") {
-            let mut decorations = vec![];
-            for text_decoration in text_decorations {
-                if let Some(decoration) = text_decoration.to_text_decoration() {
-                    decorations.push(decoration);
-                }
-            }
-            decorations
-        } else {
-            vec![]
+impl Value {
+    pub fn to_length_or_percentage(&self) -> Option<LengthOrPercentage> {
+        match *self {
+            Value::Length(f, Unit::Percent) => Some(LengthOrPercentage::Percentage(f)),
+            _ => self
+                .to_px()
+                .map(|px| LengthOrPercentage::Length(Au::from_f64_px(px))),
         }
     }
 
-    pub fn font_size(&mut self) -> Au {
-        if let Some(ref font_size) = self.cached.font_size {
-            return Au::from_f64_px(
-                font_size
-                    .clone()
-                    .maybe_percent_to_px(DEFAULT_LINE_HEIGHT_SCALE)
-                    .unwrap(),
-            );
+    pub fn to_length_or_percentage_or_auto(&self) -> Option<LengthOrPercentageOrAuto> {
+        match *self {
+            Value::Keyword(ref k) if k == "auto" => Some(LengthOrPercentageOrAuto::Auto),
+            Value::Length(f, Unit::Percent) => Some(LengthOrPercentageOrAuto::Percentage(f)),
+            _ => self
+                .to_px()
+                .map(|px| LengthOrPercentageOrAuto::Length(Au::from_f64_px(px))),
         }
-
-        let default_font_size = Value::Length(DEFAULT_FONT_SIZE, Unit::Px);
-        let font_size = &self.value_with_default("font-size", &vec![default_font_size])[0];
-        self.cached.font_size = Some(font_size.clone());
-        Au::from_f64_px(font_size.maybe_percent_to_px(DEFAULT_FONT_SIZE).unwrap())
     }
 
-    pub fn font_weight(&self) -> FontWeight {
-        let default_font_weight = Value::Keyword("normal".to_string());
-        self.value_with_default("font-weight", &vec![default_font_weight])[0].to_font_weight()
+    /// A track size of a track list, or `grid-auto-columns`/`grid-auto-rows`.
+    pub fn to_track_size(&self) -> Option<TrackSize> {
+        match *self {
+            Value::Function(ref name, ref arguments) if name == "minmax" => {
+                match (
+                    arguments.get(0).and_then(|a| a.to_track_breadth()),
+                    arguments.get(1).and_then(|a| a.to_track_breadth()),
+                ) {
+                    // The minimum can't be flexible.
+                    (Some(TrackBreadth::Flex(_)), _) | (_, None) | (None, _) => None,
+                    (Some(min), Some(max)) => Some(TrackSize { min: min, max: max }),
+                }
+            }
+            // `fit-content(limit)` is approximated with `minmax(auto, limit)`.
+            Value::Function(ref name, ref arguments) if name == "fit-content" => {
+                match arguments.get(0).and_then(|a| a.to_track_breadth()) {
+                    Some(TrackBreadth::Length(limit)) => Some(TrackSize {
+                        min: TrackBreadth::Auto,
+                        max: TrackBreadth::Length(limit),
+                    }),
+                    _ => None,
+                }
+            }
+            _ => match self.to_track_breadth()? {
+                TrackBreadth::Flex(f) => Some(TrackSize {
+                    min: TrackBreadth::Auto,
+                    max: TrackBreadth::Flex(f),
+                }),
+                breadth => Some(TrackSize {
+                    min: breadth,
+                    max: breadth,
+                }),
+            },
+        }
     }
 
-    pub fn font_style(&self) -> FontSlant {
-        let default_font_slant = Value::Keyword("normal".to_string());
-        self.lookup("font-style", "font-style", &vec![default_font_slant])[0].to_font_slant()
+    fn to_track_breadth(&self) -> Option<TrackBreadth> {
+        match *self {
+            Value::Length(f, Unit::Fr) if f >= 0.0 => Some(TrackBreadth::Flex(f)),
+            Value::Keyword(ref k) => match k.as_str() {
+                "auto" => Some(TrackBreadth::Auto),
+                "min-content" => Some(TrackBreadth::MinContent),
+                "max-content" => Some(TrackBreadth::MaxContent),
+                _ => None,
+            },
+            _ => self.to_length_or_percentage().map(TrackBreadth::Length),
+        }
     }
 
-    pub fn line_height(&mut self) -> Au {
-        let font_size = self.font_size().to_f64_px();
-        let default_line_height = Value::Length(font_size * DEFAULT_LINE_HEIGHT_SCALE, Unit::Px);
-
-        if let Some(ref line_height) = self.cached.line_height {
-            return Au::from_f64_px(match line_height {
-                &Value::Keyword(ref k) if k == "normal" => font_size * DEFAULT_LINE_HEIGHT_SCALE,
-                &Value::Length(f, Unit::Px) => f,
-                &Value::Length(f, Unit::Pt) => pt2px(f),
-                &Value::Length(_, _) => unimplemented!(),
-                &Value::Num(f) => font_size * f,
-                _ => panic!(),
-            });
+    /// ref. https://www.w3.org/TR/css-backgrounds-3/#border-width
+    pub fn to_border_width(&self) -> Option<Au> {
+        match *self {
+            Value::Keyword(ref k) => match k.as_str() {
+                "thin" => Some(Au::from_f64_px(1.0)),
+                "medium" => Some(Au::from_f64_px(3.0)),
+                "thick" => Some(Au::from_f64_px(5.0)),
+                _ => None,
+            },
+            _ => self.to_px().map(Au::from_f64_px),
         }
-
-        let line_height = &self.value_with_default("line-height", &vec![default_line_height])[0];
-        self.cached.line_height = Some(line_height.clone());
-        Au::from_f64_px(match line_height {
-            &Value::Keyword(ref k) if k == "normal" => font_size * DEFAULT_LINE_HEIGHT_SCALE,
-            &Value::Length(f, Unit::Px) => f,
-            &Value::Length(f, Unit::Pt) => pt2px(f),
-            &Value::Length(_, _) => unimplemented!(),
-            &Value::Num(f) => font_size * f,
-            _ => panic!(),
-        })
     }
 
-    pub fn text_align(&self) -> Value {
-        self.value_with_default("text-align", &vec![Value::Keyword("left".to_string())])[0].clone()
+    pub fn to_border_style(&self) -> Option<BorderStyle> {
+        match *self {
+            Value::Keyword(ref k) => match k.as_str() {
+                "none" => Some(BorderStyle::None),
+                "hidden" => Some(BorderStyle::Hidden),
+                "dotted" => Some(BorderStyle::Dotted),
+                "dashed" => Some(BorderStyle::Dashed),
+                "solid" => Some(BorderStyle::Solid),
+                "double" => Some(BorderStyle::Double),
+                "groove" => Some(BorderStyle::Groove),
+                "ridge" => Some(BorderStyle::Ridge),
+                "inset" => Some(BorderStyle::Inset),
+                "outset" => Some(BorderStyle::Outset),
+                _ => None,
+            },
+            _ => None,
+        }
     }
-}
 
-impl Value {
     pub fn to_font_weight(&self) -> FontWeight {
         match self {
             &Value::Keyword(ref k) if k.as_str() == "normal" => FontWeight::Normal,
-            &Value::Keyword(ref k) if k.as_str() == "bold" => FontWeight::Bold,
+            &Value::Keyword(ref k) if k.as_str() == "bold" || k.as_str() == "bolder" => {
+                FontWeight::Bold
+            }
+            &Value::Num(weight) if weight >= 600.0 => FontWeight::Bold,
             _ => FontWeight::Normal,
         }
     }
     pub fn to_font_slant(&self) -> FontSlant {
         match self {
             &Value::Keyword(ref k) if k.as_str() == "normal" => FontSlant::Normal,
-            &Value::Keyword(ref k) if k.as_str() == "italic" => FontSlant::Italic,
+            &Value::Keyword(ref k) if k.as_str() == "italic" || k.as_str() == "oblique" => {
+                FontSlant::Italic
+            }
             _ => FontSlant::Normal,
         }
     }
 }
 
+/// The computed style of the declarations `declarations`, e.g. `"display: block; width: 1px"`.
+#[cfg(test)]
+pub fn computed_style(declarations: &str) -> ComputedStyle {
+    use css;
+
+    Style::new_with(
+        css::parse_attr_style(declarations.to_string())
+            .into_iter()
+            .map(|d| (d.name, d.values))
+            .collect(),
+    ).computed
+}
+
 #[test]
 fn test1() {
     use html;
@@ -622,3 +1434,105 @@ fn test1() {
                a { display: inline; text-decoration: underline; }";
     css::parse(src.to_string());
 }
+
+#[test]
+fn test_nested_em_font_size() {
+    use css;
+
+    let ctx = LengthContext {
+        viewport_width: 800.0,
+        viewport_height: 600.0,
+        ..Default::default()
+    };
+
+    let mut parent = Style::new_with(
+        css::parse_attr_style("font-size: 2em; margin-left: 1em".to_string())
+            .into_iter()
+            .map(|d| (d.name, d.values))
+            .collect(),
+    );
+    let child_ctx = parent.resolve_relative_lengths(&ctx);
+    assert_eq!(parent.computed.font_size, Au::from_f64_px(32.0));
+    assert_eq!(
+        parent.value("margin-left"),
+        Some(vec![Value::Length(32.0, Unit::Px)])
+    );
+
+    let mut child = Style::new_with(
+        css::parse_attr_style("font-size: 150%; line-height: 200%; width: 10vw".to_string())
+            .into_iter()
+            .map(|d| (d.name, d.values))
+            .collect(),
+    );
+    child.resolve_relative_lengths(&child_ctx);
+    assert_eq!(child.computed.font_size, Au::from_f64_px(48.0));
+    assert_eq!(child.computed.line_height(), Au::from_f64_px(96.0));
+    assert_eq!(
+        child.value("width"),
+        Some(vec![Value::Length(80.0, Unit::Px)])
+    );
+}
+
+#[test]
+fn test_computed_style() {
+    let computed = &computed_style(
+        "display: inline-block; float: left; width: 50%; margin: 1px auto; \
+         border: thick solid; border-left-style: none; color: red; z-index: 3; \
+         line-height: 2; text-align: center; font-weight: bold; display: invalid; \
+         word-break: break-word; vertical-align: 50%",
+    );
+
+    // Invalid values fall back to the initial values.
+    assert_eq!(computed.display, Display::Inline);
+    assert_eq!(computed.float, FloatType::Left);
+    assert_eq!(computed.width, LengthOrPercentageOrAuto::Percentage(50.0));
+    assert_eq!(computed.height, LengthOrPercentageOrAuto::Auto);
+    assert_eq!(
+        computed.margin.top,
+        LengthOrPercentageOrAuto::Length(Au::from_f64_px(1.0))
+    );
+    assert!(computed.margin.left.is_auto());
+    assert_eq!(computed.border_width.top, Au::from_f64_px(5.0));
+    assert_eq!(computed.border_width.left, Au(0));
+    assert_eq!(computed.border_color.top, computed.color);
+    assert_eq!(computed.z_index, Some(3));
+    assert_eq!(
+        computed.vertical_align,
+        VerticalAlign::Length(LengthOrPercentage::Percentage(50.0))
+    );
+    assert_eq!(computed.line_height(), Au::from_f64_px(32.0));
+    assert_eq!(computed.text_align, TextAlign::Center);
+    assert_eq!(computed.text_align_last, None);
+    assert_eq!(computed.direction, Direction::Ltr);
+    assert_eq!(computed.unicode_bidi, UnicodeBidi::Normal);
+    assert_eq!(computed.writing_mode, WritingMode::HorizontalTb);
+    assert_eq!(computed.text_orientation, TextOrientation::Mixed);
+    assert_eq!(computed.word_break, WordBreak::Normal);
+    assert_eq!(computed.overflow_wrap, OverflowWrap::Anywhere);
+    assert_eq!(computed.line_break, LineBreak::Auto);
+    assert_eq!(computed.font_weight, FontWeight::Bold);
+    assert_eq!(computed.position, Position::Static);
+    assert_eq!(computed.offsets.top, LengthOrPercentageOrAuto::Auto);
+    assert_eq!(computed.min_width, None);
+    assert_eq!(computed.max_height, None);
+    assert_eq!(computed.box_sizing, BoxSizing::ContentBox);
+    assert_eq!(computed.overflow_x, Overflow::Visible);
+    assert!(!computed.clips_overflow());
+}
+
+#[test]
+fn test_property_table() {
+    assert!(PROPERTIES.windows(2).all(|w| w[0].0 < w[1].0));
+
+    assert!(is_inherited_property("font-family"));
+    assert!(is_inherited_property("visibility"));
+    assert!(!is_inherited_property("margin-top"));
+    assert!(!is_inherited_property("unknown-property"));
+
+    assert_eq!(
+        initial_value("background-color"),
+        Some(vec![Value::Keyword("transparent".to_string())])
+    );
+    assert_eq!(initial_value("padding-left"), Some(vec![Value::Num(0.0)]));
+    assert_eq!(initial_value("unknown-property"), None);
+}
@@ -0,0 +1,944 @@
+use block::{indefinite_block, CollapsibleMargins};
+use dom::NodeType;
+use float::Floats;
+use inline::IntrinsicWidths;
+use layout::{Dimensions, EdgeSizes, LayoutBox, Rect};
+use style::{BorderCollapse, BorderStyle, BoxSizing, CaptionSide, Display,
+            LengthOrPercentageOrAuto, Sides, TableLayout, VerticalAlign};
+
+use std::cmp::{max, min};
+use std::ops::Range;
+
+use app_units::Au;
+
+/// A row of the table grid.
+#[derive(Clone, Copy, Debug)]
+struct RowRef {
+    // The index of the row group in the table's children, or None if the row is a child of
+    // the table.
+    group: Option<usize>,
+    // The index of the row in the children of the row group or the table.
+    index: usize,
+}
+
+/// A cell placed in the table grid.
+#[derive(Clone, Debug)]
+struct CellSlot {
+    // The index of the row the cell starts at in `TableGrid::rows`.
+    row: usize,
+    // The index of the cell in the children of the row.
+    index: usize,
+    // The columns and the rows the cell spans.
+    columns: Range<usize>,
+    rows: Range<usize>,
+}
+
+/// The rows, columns and cells of a table.
+/// ref. https://www.w3.org/TR/CSS2/tables.html#table-layout
+#[derive(Debug, Default)]
+struct TableGrid {
+    // The rows in the visual order, i.e. the header groups first and the footer groups last.
+    rows: Vec<RowRef>,
+    // The indices of the row groups in the table's children and the rows they contain.
+    groups: Vec<(usize, Range<usize>)>,
+    cells: Vec<CellSlot>,
+    // The index of the cell occupying each slot, indexed by the rows and then the columns.
+    slots: Vec<Vec<Option<usize>>>,
+    // The widths of the columns specified by the column boxes.
+    column_widths: Vec<LengthOrPercentageOrAuto>,
+    // The column and column group boxes, i.e. (index in the table's children, index in the
+    // column group's children), and the columns they span.
+    column_boxes: Vec<((usize, Option<usize>), Range<usize>)>,
+    column_count: usize,
+}
+
+/// The widths of a column including the borders and paddings of the cells.
+#[derive(Clone, Copy, Debug, Default)]
+struct Column {
+    minimum: Au,
+    maximum: Au,
+    // Whether the width is specified by a column or a cell. The free space goes to the other
+    // columns first.
+    specified: bool,
+}
+
+impl LayoutBox {
+    /// Lay out a block-level table and its descendants.
+    /// A table establishes a new formatting context, so floats outside don't affect its content.
+    pub fn layout_table(
+        &mut self,
+        _floats: &mut Floats,
+        margins: &mut CollapsibleMargins,
+        containing_block: Dimensions,
+        _saved_block: Dimensions,
+        viewport: Dimensions,
+    ) {
+        self.calculate_table_width(containing_block);
+        self.calculate_block_position(margins, containing_block);
+
+        // The height is calculated with the rows in `layout_table_children`.
+        self.layout_table_children(viewport);
+
+        *margins = CollapsibleMargins::new(self.dimensions.margin.bottom);
+    }
+
+    /// Calculate the width of a block-level table, which is the shrink-to-fit width if `width`
+    /// is auto, and is never narrower than the minimum width of the columns.
+    /// Sets the horizontal margin/padding/border dimensions, and the `width`.
+    /// ref. https://www.w3.org/TR/CSS2/tables.html#width-layout
+    fn calculate_table_width(&mut self, containing_block: Dimensions) {
+        let cb_width = containing_block.content.width;
+        let grid = self.table_grid();
+        self.assign_padding(containing_block);
+        self.assign_border_width();
+        self.assign_table_edges(&grid);
+
+        let (width, margin_left, margin_right) = {
+            let style = &self.property.computed;
+            let minimum = self.table_intrinsic_widths(containing_block).minimum;
+            let width = match style.width.to_au(cb_width) {
+                // The edges are the ones of the table, which may be collapsed borders.
+                Some(width) if style.box_sizing == BoxSizing::BorderBox => {
+                    let d = &self.dimensions;
+                    let edges = d.border.left + d.border.right + d.padding.left + d.padding.right;
+                    max(width - edges, minimum)
+                }
+                Some(width) => max(width, minimum),
+                None => self.shrink_to_fit_width(containing_block),
+            };
+            (
+                width,
+                style.margin.left.to_au(cb_width),
+                style.margin.right.to_au(cb_width),
+            )
+        };
+        let d = &mut self.dimensions;
+        let underflow = cb_width - width - d.border.left - d.border.right - d.padding.left
+            - d.padding.right - margin_left.unwrap_or(Au(0))
+            - margin_right.unwrap_or(Au(0));
+
+        // The same as blocks with a specified width. Auto margins center the table.
+        let (margin_left, margin_right) = match (margin_left, margin_right) {
+            (Some(margin_left), Some(margin_right)) => (margin_left, margin_right + underflow),
+            (Some(margin_left), None) => (margin_left, max(Au(0), underflow)),
+            (None, Some(margin_right)) => (max(Au(0), underflow), margin_right),
+            (None, None) => (max(Au(0), underflow / 2), max(Au(0), underflow / 2)),
+        };
+        d.content.width = width;
+        d.margin.left = margin_left;
+        d.margin.right = margin_right;
+    }
+
+    /// Lay out the captions, rows, row groups, columns and cells of a table within its content
+    /// area. The width of the table must be calculated before.
+    /// Sets `self.dimensions.height` to the total content height, which is at least `height`.
+    pub fn layout_table_children(&mut self, viewport: Dimensions) {
+        let grid = self.table_grid();
+        self.assign_table_edges(&grid);
+        self.floats = Floats::new();
+
+        let mut containing_block: Dimensions = Default::default();
+        containing_block.content.width = self.dimensions.content.width;
+        let borders = self.cell_borders(&grid);
+        let spacing = self.border_spacing();
+
+        // The table is never narrower than its columns.
+        let columns = self.table_columns(&grid, &borders, containing_block);
+        let minimum = columns
+            .iter()
+            .fold(spacing_sum(spacing.0, columns.len()), |acc, c| acc + c.minimum);
+        let width = max(self.dimensions.content.width, minimum);
+        self.dimensions.content.width = width;
+        containing_block.content.width = width;
+
+        let widths = distribute_width(&columns, width - spacing_sum(spacing.0, columns.len()));
+        let mut column_x = vec![];
+        let mut x = spacing.0;
+        for &width in &widths {
+            column_x.push(x);
+            x += width + spacing.0;
+        }
+        let column_range = |columns: &Range<usize>| {
+            let start = column_x[columns.start];
+            (start, column_x[columns.end - 1] + widths[columns.end - 1] - start)
+        };
+
+        // Lay out the cells in the widths of their columns.
+        let mut baselines = vec![];
+        for (slot, borders) in grid.cells.iter().zip(&borders) {
+            let (_, width) = column_range(&slot.columns);
+            let cell = self.table_cell_mut(&grid, slot);
+            let padding = cell.property.computed.padding;
+            let offset = padding.left.to_au(containing_block.content.width)
+                + padding.right.to_au(containing_block.content.width)
+                + borders.left + borders.right;
+            cell.layout_flex_item(containing_block, max(Au(0), width - offset), viewport);
+
+            // The baseline of a cell without line boxes is the bottom of its content area.
+            let baseline = cell
+                .first_baseline()
+                .unwrap_or(cell.dimensions.content.height);
+
+            // Cells have no margins.
+            let d = &mut cell.dimensions;
+            d.margin = Default::default();
+            d.border = to_edge_sizes(*borders);
+            baselines.push(match cell.property.computed.vertical_align {
+                VerticalAlign::Top | VerticalAlign::Middle | VerticalAlign::Bottom => None,
+                _ => Some(d.border.top + d.padding.top + baseline),
+            });
+        }
+
+        // Size the rows to fit the cells. The baselines of the cells aligned to the baseline
+        // are aligned in each row.
+        let mut heights: Vec<Au> = grid.rows
+            .iter()
+            .map(|&row| match self.table_row(row).property.computed.height {
+                LengthOrPercentageOrAuto::Length(height) => height,
+                _ => Au(0),
+            })
+            .collect();
+        let mut row_baselines = vec![Au(0); grid.rows.len()];
+        for (slot, &baseline) in grid.cells.iter().zip(&baselines) {
+            if let Some(baseline) = baseline {
+                row_baselines[slot.row] = max(row_baselines[slot.row], baseline);
+            }
+        }
+        let cell_heights: Vec<Au> = grid.cells
+            .iter()
+            .zip(&baselines)
+            .map(|(slot, baseline)| {
+                let height = self.table_cell(&grid, slot).dimensions.border_box().height;
+                height + baseline.map_or(Au(0), |b| row_baselines[slot.row] - b)
+            })
+            .collect();
+        let mut spanning: Vec<usize> = (0..grid.cells.len()).collect();
+        spanning.sort_by_key(|&i| grid.cells[i].rows.len());
+        for i in spanning {
+            let rows = grid.cells[i].rows.clone();
+            let sum = heights[rows.clone()]
+                .iter()
+                .fold(spacing.1 * (rows.len() as i32 - 1), |acc, &h| acc + h);
+            if cell_heights[i] > sum {
+                distribute_extra(&mut heights[rows], cell_heights[i] - sum);
+            }
+        }
+
+        let mut captions: Vec<usize> = (0..self.children.len())
+            .filter(|&i| self.children[i].property.computed.display == Display::TableCaption)
+            .collect();
+        for &i in &captions {
+            let caption = &mut self.children[i];
+            let width = max(Au(0), width - caption.horizontal_offset(width));
+            caption.layout_flex_item(containing_block, width, viewport);
+        }
+        let caption_height = captions.iter().fold(Au(0), |acc, &i| {
+            acc + self.children[i].dimensions.margin_box().height
+        });
+
+        // A specified height of the table is distributed to the rows.
+        let grid_height = |heights: &[Au]| {
+            heights
+                .iter()
+                .fold(spacing_sum(spacing.1, heights.len()), |acc, &h| acc + h)
+        };
+        // Percentage heights are ignored.
+        if let Some(height) = self.specified_height(indefinite_block(self.dimensions)) {
+            let extra = height - caption_height - grid_height(&heights);
+            if extra > Au(0) && !heights.is_empty() {
+                distribute_extra(&mut heights, extra);
+            }
+        }
+
+        // Place the captions above and below the grid.
+        let is_bottom = |caption: &LayoutBox| {
+            caption.property.computed.caption_side == CaptionSide::Bottom
+        };
+        captions.sort_by_key(|&i| is_bottom(&self.children[i]));
+        let mut y = Au(0);
+        let mut grid_top = Au(0);
+        let mut grid_placed = false;
+        for &i in &captions {
+            if !grid_placed && is_bottom(&self.children[i]) {
+                grid_top = y;
+                y += grid_height(&heights);
+                grid_placed = true;
+            }
+            let d = &mut self.children[i].dimensions;
+            d.content.x = d.margin.left + d.border.left + d.padding.left;
+            d.content.y = y + d.margin.top + d.border.top + d.padding.top;
+            y += d.margin_box().height;
+        }
+        if !grid_placed {
+            grid_top = y;
+            y += grid_height(&heights);
+        }
+        self.dimensions.content.height = y;
+
+        let mut row_y = vec![];
+        let mut y = grid_top + spacing.1;
+        for &height in &heights {
+            row_y.push(y);
+            y += height + spacing.1;
+        }
+        let row_range = |rows: &Range<usize>| {
+            let start = row_y[rows.start];
+            (start, row_y[rows.end - 1] + heights[rows.end - 1] - start)
+        };
+
+        // The row groups and rows span the columns.
+        let inner = Dimensions {
+            content: Rect {
+                x: spacing.0,
+                y: grid_top,
+                width: max(Au(0), width - spacing.0 * 2),
+                height: Au(0),
+            },
+            ..Default::default()
+        };
+        for &(group, ref rows) in &grid.groups {
+            let mut d = inner;
+            if !rows.is_empty() {
+                let (y, height) = row_range(rows);
+                d.content.y = y;
+                d.content.height = height;
+            }
+            self.children[group].dimensions = d;
+        }
+        for (r, &row) in grid.rows.iter().enumerate() {
+            let mut d = inner;
+            d.content.y = row_y[r];
+            d.content.height = heights[r];
+            if let Some(group) = row.group {
+                let group = self.children[group].dimensions.content;
+                d.content.x -= group.x;
+                d.content.y -= group.y;
+            }
+            self.table_row_mut(row).dimensions = d;
+        }
+
+        // Stretch the cells to the rows they span, and align their content vertically.
+        for (slot, &baseline) in grid.cells.iter().zip(&baselines) {
+            let (x, _) = column_range(&slot.columns);
+            let (y, height) = row_range(&slot.rows);
+            let row_baseline = row_baselines[slot.row];
+            let row = self.table_row(grid.rows[slot.row]).dimensions.content;
+            let row_origin = match grid.rows[slot.row].group {
+                Some(group) => {
+                    let group = self.children[group].dimensions.content;
+                    (row.x + group.x, row.y + group.y)
+                }
+                None => (row.x, row.y),
+            };
+
+            let cell = self.table_cell_mut(&grid, slot);
+            let vertical_align = cell.property.computed.vertical_align;
+            let d = &mut cell.dimensions;
+            let content_height = d.content.height;
+            d.content.x = x - row_origin.0 + d.border.left + d.padding.left;
+            d.content.y = y - row_origin.1 + d.border.top + d.padding.top;
+            d.content.height = max(
+                content_height,
+                height - d.border.top - d.border.bottom - d.padding.top - d.padding.bottom,
+            );
+            let free_space = d.content.height - content_height;
+            let offset = match vertical_align {
+                VerticalAlign::Top => Au(0),
+                VerticalAlign::Middle => free_space / 2,
+                VerticalAlign::Bottom => free_space,
+                // The other values apply only to inline-level boxes.
+                _ => row_baseline - baseline.unwrap_or(row_baseline),
+            };
+            for child in &mut cell.children {
+                child.dimensions.content.y += offset;
+            }
+        }
+
+        // The columns and column groups span the rows.
+        for &((i, j), ref columns) in &grid.column_boxes {
+            let mut d: Dimensions = Default::default();
+            if !columns.is_empty() && columns.end <= column_x.len() {
+                let (x, width) = column_range(columns);
+                d.content.x = x;
+                d.content.width = width;
+            }
+            d.content.y = grid_top + spacing.1;
+            d.content.height = max(Au(0), grid_height(&heights) - spacing.1 * 2);
+            match j {
+                Some(j) => {
+                    let group = self.children[i].dimensions.content;
+                    d.content.x -= group.x;
+                    d.content.y -= group.y;
+                    self.children[i].children[j].dimensions = d;
+                }
+                None => self.children[i].dimensions = d,
+            }
+        }
+
+        // The height of a table is a minimum, so it isn't definite.
+        let container = self.dimensions;
+        for &i in &captions {
+            self.children[i].apply_relative_offset(container, indefinite_block(container));
+        }
+        for slot in &grid.cells {
+            let cell = self.table_cell_mut(&grid, slot);
+            cell.apply_relative_offset(container, indefinite_block(container));
+            if cell.is_positioned() {
+                cell.layout_absolute_descendants(viewport);
+            }
+        }
+    }
+
+    /// Calculate the preferred and preferred minimum widths of the content box of a table, i.e.
+    /// the sums of the widths of the columns and the border spacing, or the widths of the
+    /// captions if wider.
+    pub fn table_intrinsic_widths(&self, containing_block: Dimensions) -> IntrinsicWidths {
+        let grid = self.table_grid();
+        let borders = self.cell_borders(&grid);
+        let columns = self.table_columns(&grid, &borders, containing_block);
+        let spacing = spacing_sum(self.border_spacing().0, columns.len());
+
+        let mut widths = IntrinsicWidths {
+            preferred: columns.iter().fold(spacing, |acc, c| acc + c.maximum),
+            minimum: columns.iter().fold(spacing, |acc, c| acc + c.minimum),
+        };
+        for caption in &self.children {
+            if caption.property.computed.display == Display::TableCaption {
+                let caption_widths = caption.outer_intrinsic_widths(containing_block);
+                widths.minimum = max(widths.minimum, caption_widths.minimum);
+            }
+        }
+        widths.preferred = max(widths.preferred, widths.minimum);
+        widths
+    }
+
+    /// The horizontal and vertical border spacing, which is 0 if the borders collapse.
+    fn border_spacing(&self) -> (Au, Au) {
+        let style = &self.property.computed;
+        match style.border_collapse {
+            BorderCollapse::Separate => style.border_spacing,
+            BorderCollapse::Collapse => (Au(0), Au(0)),
+        }
+    }
+
+    /// In the collapsing border model, the table has no padding, and its borders are the halves
+    /// of the collapsed borders outside the cells. The margin box stays in place.
+    fn assign_table_edges(&mut self, grid: &TableGrid) {
+        if self.property.computed.border_collapse != BorderCollapse::Collapse
+            || grid.cells.is_empty()
+        {
+            return;
+        }
+        let table = self.collapsed_borders(grid).1;
+        let d = &mut self.dimensions;
+        d.content.x -= d.border.left + d.padding.left - table.left;
+        d.content.y -= d.border.top + d.padding.top - table.top;
+        d.padding = Default::default();
+        d.border = to_edge_sizes(table);
+    }
+
+    /// Find the rows, row groups, columns and cells of this table, and place the cells in the
+    /// grid with their `colspan` and `rowspan`.
+    /// ref. https://html.spec.whatwg.org/multipage/tables.html#forming-a-table
+    fn table_grid(&self) -> TableGrid {
+        let mut grid = TableGrid::default();
+
+        // The header groups come first and the footer groups last. The rows that are children
+        // of the table are grouped with the adjacent rows.
+        let mut headers = vec![];
+        let mut bodies = vec![];
+        let mut footers = vec![];
+        for (i, child) in self.children.iter().enumerate() {
+            let group = |child: &LayoutBox| {
+                (0..child.children.len())
+                    .filter(|&j| child.children[j].property.computed.display == Display::TableRow)
+                    .map(|j| RowRef {
+                        group: Some(i),
+                        index: j,
+                    })
+                    .collect::<Vec<_>>()
+            };
+            match child.property.computed.display {
+                Display::TableHeaderGroup => headers.push((Some(i), group(child))),
+                Display::TableFooterGroup => footers.push((Some(i), group(child))),
+                Display::TableRowGroup => bodies.push((Some(i), group(child))),
+                Display::TableRow => {
+                    let row = RowRef {
+                        group: None,
+                        index: i,
+                    };
+                    match bodies.last_mut() {
+                        Some(&mut (None, ref mut rows)) => rows.push(row),
+                        _ => bodies.push((None, vec![row])),
+                    }
+                }
+                Display::TableColumnGroup => {
+                    let group_start = grid.column_widths.len();
+                    for (j, column) in child.children.iter().enumerate() {
+                        let start = grid.column_widths.len();
+                        let columns = start..start + span_attribute(column, "span");
+                        let width = match column.property.computed.width {
+                            LengthOrPercentageOrAuto::Auto => child.property.computed.width,
+                            width => width,
+                        };
+                        for _ in columns.clone() {
+                            grid.column_widths.push(width);
+                        }
+                        grid.column_boxes.push(((i, Some(j)), columns));
+                    }
+                    // A column group without columns spans `span` columns.
+                    if child.children.is_empty() {
+                        for _ in 0..span_attribute(child, "span") {
+                            grid.column_widths.push(child.property.computed.width);
+                        }
+                    }
+                    let end = grid.column_widths.len();
+                    grid.column_boxes.push(((i, None), group_start..end));
+                }
+                Display::TableColumn => {
+                    let start = grid.column_widths.len();
+                    for _ in 0..span_attribute(child, "span") {
+                        grid.column_widths.push(child.property.computed.width);
+                    }
+                    let end = grid.column_widths.len();
+                    grid.column_boxes.push(((i, None), start..end));
+                }
+                _ => {}
+            }
+        }
+
+        for (group, rows) in headers.into_iter().chain(bodies).chain(footers) {
+            let start = grid.rows.len();
+            grid.rows.extend(rows);
+            let end = grid.rows.len();
+            grid.slots.resize(end, vec![]);
+            if let Some(group) = group {
+                grid.groups.push((group, start..end));
+            }
+
+            for r in start..end {
+                let row = self.table_row(grid.rows[r]);
+                let mut column = 0;
+                for (index, cell) in row.children.iter().enumerate() {
+                    if cell.property.computed.display != Display::TableCell {
+                        continue;
+                    }
+                    while grid.slots[r].get(column).map_or(false, |slot| slot.is_some()) {
+                        column += 1;
+                    }
+                    let colspan = min(span_attribute(cell, "colspan"), 1000);
+                    // `rowspan="0"` spans the rest of the row group.
+                    let rowspan = match attribute(cell, "rowspan") {
+                        Some(0) => end - r,
+                        Some(rowspan) => min(rowspan, end - r),
+                        None => 1,
+                    };
+                    let slot = CellSlot {
+                        row: r,
+                        index: index,
+                        columns: column..column + colspan,
+                        rows: r..r + rowspan,
+                    };
+                    for slots in &mut grid.slots[slot.rows.clone()] {
+                        if slots.len() < slot.columns.end {
+                            slots.resize(slot.columns.end, None);
+                        }
+                        for s in &mut slots[slot.columns.clone()] {
+                            *s = Some(grid.cells.len());
+                        }
+                    }
+                    grid.cells.push(slot);
+                    column += colspan;
+                }
+            }
+        }
+
+        grid.column_count = grid.slots
+            .iter()
+            .fold(grid.column_widths.len(), |acc, slots| max(acc, slots.len()));
+        for slots in &mut grid.slots {
+            slots.resize(grid.column_count, None);
+        }
+        grid
+    }
+
+    /// The widths of the columns. With `table-layout: fixed` and a specified width, they are
+    /// determined by the columns and the cells of the first row only. Otherwise, all the cells
+    /// contribute their intrinsic widths.
+    /// ref. https://www.w3.org/TR/CSS2/tables.html#fixed-table-layout
+    /// ref. https://www.w3.org/TR/CSS2/tables.html#auto-table-layout
+    fn table_columns(
+        &self,
+        grid: &TableGrid,
+        borders: &[Sides<Au>],
+        containing_block: Dimensions,
+    ) -> Vec<Column> {
+        let cb_width = containing_block.content.width;
+        let style = &self.property.computed;
+        let fixed =
+            style.table_layout == TableLayout::Fixed && style.width.to_au(cb_width).is_some();
+        let mut columns = vec![Column::default(); grid.column_count];
+
+        for (column, width) in columns.iter_mut().zip(&grid.column_widths) {
+            if let LengthOrPercentageOrAuto::Length(width) = *width {
+                column.minimum = if fixed { width } else { Au(0) };
+                column.maximum = width;
+                column.specified = true;
+            }
+        }
+
+        let mut cells: Vec<usize> = (0..grid.cells.len())
+            .filter(|&i| !fixed || grid.cells[i].row == 0)
+            .collect();
+        cells.sort_by_key(|&i| grid.cells[i].columns.len());
+        for i in cells {
+            let slot = &grid.cells[i];
+            let cell = self.table_cell(grid, slot);
+            let cell_style = &cell.property.computed;
+            let offset = cell_style.padding.left.to_au(cb_width)
+                + cell_style.padding.right.to_au(cb_width) + borders[i].left
+                + borders[i].right;
+            let specified = match cell_style.width {
+                LengthOrPercentageOrAuto::Length(width) => Some(width + offset),
+                _ => None,
+            };
+            let spanned = &mut columns[slot.columns.clone()];
+
+            if fixed {
+                // The width of a spanning cell is divided between the columns.
+                if let Some(width) = specified {
+                    for column in spanned.iter_mut().filter(|c| !c.specified) {
+                        column.minimum = width / slot.columns.len() as i32;
+                        column.maximum = column.minimum;
+                        column.specified = true;
+                    }
+                }
+                continue;
+            }
+
+            let content = cell.intrinsic_widths(containing_block);
+            let minimum = content.minimum + offset;
+            let maximum = max(minimum, specified.unwrap_or(content.preferred + offset));
+            if spanned.len() == 1 {
+                let column = &mut spanned[0];
+                column.minimum = max(column.minimum, minimum);
+                column.maximum = max(column.maximum, maximum);
+                column.specified |= specified.is_some();
+                continue;
+            }
+
+            // A spanning cell widens the columns equally if they are too narrow.
+            let spacing = style.border_spacing.0 * (spanned.len() as i32 - 1);
+            let extra = minimum - spanned.iter().fold(spacing, |acc, c| acc + c.minimum);
+            if extra > Au(0) {
+                let mut minimums: Vec<Au> = spanned.iter().map(|c| c.minimum).collect();
+                distribute_extra(&mut minimums, extra);
+                for (column, minimum) in spanned.iter_mut().zip(minimums) {
+                    column.minimum = minimum;
+                }
+            }
+            let extra = maximum - spanned.iter().fold(spacing, |acc, c| acc + c.maximum);
+            if extra > Au(0) {
+                let mut maximums: Vec<Au> = spanned.iter().map(|c| c.maximum).collect();
+                distribute_extra(&mut maximums, extra);
+                for (column, maximum) in spanned.iter_mut().zip(maximums) {
+                    column.maximum = maximum;
+                }
+            }
+        }
+
+        for column in &mut columns {
+            column.maximum = max(column.maximum, column.minimum);
+        }
+        columns
+    }
+
+    /// The used border widths of the cells. In the collapsing border model, they are the halves
+    /// of the collapsed borders.
+    fn cell_borders(&self, grid: &TableGrid) -> Vec<Sides<Au>> {
+        match self.property.computed.border_collapse {
+            BorderCollapse::Separate => grid.cells
+                .iter()
+                .map(|slot| self.table_cell(grid, slot).property.computed.border_width)
+                .collect(),
+            BorderCollapse::Collapse => self.collapsed_borders(grid).0,
+        }
+    }
+
+    /// The border widths of the cells and the table in the collapsing border model. A border
+    /// between two cells is the widest of the borders on both sides, unless one of them is
+    /// `hidden`, and is split between the cells. The table gets the outer halves of the borders
+    /// at its edges.
+    /// ref. https://www.w3.org/TR/CSS2/tables.html#border-conflict-resolution
+    fn collapsed_borders(&self, grid: &TableGrid) -> (Vec<Sides<Au>>, Sides<Au>) {
+        let border = |layout_box: &LayoutBox| {
+            let style = &layout_box.property.computed;
+            style.border_width.zip(style.border_style)
+        };
+        let table = border(self);
+        let cell_border =
+            |i: Option<usize>| i.map(|i| border(self.table_cell(grid, &grid.cells[i])));
+        let resolve = |borders: &[(Au, BorderStyle)]| {
+            if borders.iter().any(|&(_, style)| style == BorderStyle::Hidden) {
+                Au(0)
+            } else {
+                borders.iter().fold(Au(0), |acc, &(width, _)| max(acc, width))
+            }
+        };
+
+        let last_row = grid.rows.len();
+        let last_column = grid.column_count;
+        let mut table_borders = Sides {
+            top: Au(0),
+            right: Au(0),
+            bottom: Au(0),
+            left: Au(0),
+        };
+        let mut cells = vec![];
+        for (i, slot) in grid.cells.iter().enumerate() {
+            let own = cell_border(Some(i)).unwrap();
+
+            // The borders of the neighbors on each side, or of the table at its edges.
+            let mut left = vec![own.left];
+            let mut right = vec![own.right];
+            for r in slot.rows.clone() {
+                match slot.columns.start {
+                    0 => left.push(table.left),
+                    c => left.extend(cell_border(grid.slots[r][c - 1]).map(|b| b.right)),
+                }
+                match slot.columns.end {
+                    c if c == last_column => right.push(table.right),
+                    c => right.extend(cell_border(grid.slots[r][c]).map(|b| b.left)),
+                }
+            }
+            let mut top = vec![own.top];
+            let mut bottom = vec![own.bottom];
+            for c in slot.columns.clone() {
+                match slot.rows.start {
+                    0 => top.push(table.top),
+                    r => top.extend(cell_border(grid.slots[r - 1][c]).map(|b| b.bottom)),
+                }
+                match slot.rows.end {
+                    r if r == last_row => bottom.push(table.bottom),
+                    r => bottom.extend(cell_border(grid.slots[r][c]).map(|b| b.top)),
+                }
+            }
+
+            // The box on the left or top gets the larger half.
+            let (left, right) = (resolve(&left), resolve(&right));
+            let (top, bottom) = (resolve(&top), resolve(&bottom));
+            if slot.columns.start == 0 {
+                table_borders.left = max(table_borders.left, left - left / 2);
+            }
+            if slot.columns.end == last_column {
+                table_borders.right = max(table_borders.right, right / 2);
+            }
+            if slot.rows.start == 0 {
+                table_borders.top = max(table_borders.top, top - top / 2);
+            }
+            if slot.rows.end == last_row {
+                table_borders.bottom = max(table_borders.bottom, bottom / 2);
+            }
+            cells.push(Sides {
+                top: top / 2,
+                right: right - right / 2,
+                bottom: bottom - bottom / 2,
+                left: left / 2,
+            });
+        }
+        (cells, table_borders)
+    }
+
+    fn table_row(&self, row: RowRef) -> &LayoutBox {
+        match row.group {
+            Some(group) => &self.children[group].children[row.index],
+            None => &self.children[row.index],
+        }
+    }
+
+    fn table_row_mut(&mut self, row: RowRef) -> &mut LayoutBox {
+        match row.group {
+            Some(group) => &mut self.children[group].children[row.index],
+            None => &mut self.children[row.index],
+        }
+    }
+
+    fn table_cell(&self, grid: &TableGrid, slot: &CellSlot) -> &LayoutBox {
+        &self.table_row(grid.rows[slot.row]).children[slot.index]
+    }
+
+    fn table_cell_mut(&mut self, grid: &TableGrid, slot: &CellSlot) -> &mut LayoutBox {
+        &mut self.table_row_mut(grid.rows[slot.row]).children[slot.index]
+    }
+}
+
+/// Distribute `width`, which excludes the border spacing, to `columns`.
+/// If `width` is narrower than the maximum widths, each column gets the same share of the
+/// difference between its minimum and maximum widths. Otherwise, the columns without specified
+/// widths grow in proportion to their maximum widths.
+fn distribute_width(columns: &[Column], width: Au) -> Vec<Au> {
+    let minimum = columns.iter().fold(Au(0), |acc, c| acc + c.minimum);
+    let maximum = columns.iter().fold(Au(0), |acc, c| acc + c.maximum);
+    if width <= minimum {
+        return columns.iter().map(|c| c.minimum).collect();
+    }
+    if width <= maximum {
+        let ratio = (width - minimum).to_f64_px() / (maximum - minimum).to_f64_px();
+        return columns
+            .iter()
+            .map(|c| c.minimum + Au::from_f64_px((c.maximum - c.minimum).to_f64_px() * ratio))
+            .collect();
+    }
+
+    let mut widths: Vec<Au> = columns.iter().map(|c| c.maximum).collect();
+    let growing: Vec<usize> = if columns.iter().all(|c| c.specified) {
+        (0..columns.len()).collect()
+    } else {
+        (0..columns.len()).filter(|&i| !columns[i].specified).collect()
+    };
+    let weight = growing.iter().fold(Au(0), |acc, &i| acc + columns[i].maximum);
+    let extra = width - maximum;
+    if weight > Au(0) {
+        for &i in &growing {
+            let ratio = columns[i].maximum.to_f64_px() / weight.to_f64_px();
+            widths[i] += Au::from_f64_px(extra.to_f64_px() * ratio);
+        }
+    } else {
+        let mut grown: Vec<Au> = growing.iter().map(|&i| widths[i]).collect();
+        distribute_extra(&mut grown, extra);
+        for (&i, width) in growing.iter().zip(grown) {
+            widths[i] = width;
+        }
+    }
+    widths
+}
+
+/// Add `extra` to `sizes` equally. The last one gets the remainder.
+fn distribute_extra(sizes: &mut [Au], extra: Au) {
+    let count = sizes.len() as i32;
+    if count == 0 {
+        return;
+    }
+    for size in sizes.iter_mut() {
+        *size += extra / count;
+    }
+    sizes[sizes.len() - 1] += extra - extra / count * count;
+}
+
+/// The total border spacing around and between `count` tracks.
+fn spacing_sum(spacing: Au, count: usize) -> Au {
+    if count == 0 {
+        Au(0)
+    } else {
+        spacing * (count as i32 + 1)
+    }
+}
+
+/// The value of the integer attribute `name` of the element of `layout_box`.
+fn attribute(layout_box: &LayoutBox, name: &str) -> Option<usize> {
+    match layout_box.node.data {
+        NodeType::Element(ref elem) => elem.attrs
+            .get(name)
+            .and_then(|value| value.trim().parse::<usize>().ok()),
+        NodeType::Text(_) => None,
+    }
+}
+
+/// The value of `span`, `colspan` or `rowspan`, which is at least 1.
+fn span_attribute(layout_box: &LayoutBox, name: &str) -> usize {
+    max(attribute(layout_box, name).unwrap_or(1), 1)
+}
+
+fn to_edge_sizes(sides: Sides<Au>) -> EdgeSizes {
+    EdgeSizes {
+        left: sides.left,
+        right: sides.right,
+        top: sides.top,
+        bottom: sides.bottom,
+    }
+}
+
+#[test]
+fn test_table() {
+    use layout::{layout_html, rect, BoxType};
+
+    let css = "div { display: block; }
+               table { display: table; border-spacing: 2px; }
+               caption { display: table-caption; }
+               tbody { display: table-row-group; }
+               tfoot { display: table-footer-group; }
+               tr { display: table-row; }
+               td { display: table-cell; padding: 1px; vertical-align: middle; }
+               .fixed { table-layout: fixed; width: 100px; border-collapse: collapse;
+                        border: 2px solid; }
+               .fixed td { border: 4px solid; }
+               .bottom { vertical-align: bottom; }
+               .cell { display: table-cell; }";
+    let tree = layout_html(
+        "<div><table><caption>cap</caption><tr><td>aa</td><td>bbbb</td></tr>\
+         <tr><td colspan='2'>c</td></tr></table>\
+         <table class='fixed'><tr><td class='bottom' rowspan='2' style='width: 30px'>a</td>\
+         <td>b</td></tr><tr><td>c</td></tr></table>\
+         <div><span class='cell'>x</span><span class='cell'>yy</span></div>\
+         <table><tfoot><tr><td>f</td></tr></tfoot><tbody><tr><td>b</td></tr></tbody></table>\
+         </div>",
+        css,
+        400,
+    );
+    // The table is as wide as its columns and the border spacing. The caption is above the rows,
+    // and the spanning cell covers both columns.
+    let table = &tree.children[0];
+    assert_eq!(table.box_type, BoxType::TableNode);
+    assert_eq!(rect(table), (0, 0, 58, 67));
+    assert_eq!(rect(&table.children[0]), (0, 0, 58, 19));
+    let row = &table.children[1];
+    assert_eq!(rect(row), (2, 21, 54, 21));
+    assert_eq!(rect(&row.children[0]), (1, 1, 16, 19));
+    assert_eq!(rect(&row.children[1]), (21, 1, 32, 19));
+    assert_eq!(rect(&table.children[2].children[0]), (1, 1, 52, 19));
+
+    // The first row decides the column widths, and the borders collapse into halves.
+    let table = &tree.children[1];
+    assert_eq!(table.dimensions.border.left.to_px(), 2);
+    assert_eq!(table.dimensions.content.width.to_px(), 100);
+    let cell = &table.children[0].children[0];
+    assert_eq!(rect(cell), (3, 3, 30, 44));
+    assert_eq!(cell.dimensions.border.right.to_px(), 2);
+    // The content of the cell spanning two rows is aligned to the bottom.
+    assert_eq!(cell.children[0].dimensions.content.y.to_px(), 25);
+    assert_eq!(rect(&table.children[0].children[1]), (39, 3, 58, 19));
+    assert_eq!(rect(&table.children[1].children[0]), (39, 3, 58, 19));
+
+    // The stray cells are wrapped in an anonymous table and row.
+    let table = &tree.children[2].children[0];
+    assert_eq!(table.box_type, BoxType::TableNode);
+    assert_eq!(table.dimensions.content.width.to_px(), 24);
+    let row = &table.children[0];
+    assert_eq!(row.property.computed.display, Display::TableRow);
+    assert_eq!(row.children.len(), 2);
+    assert_eq!(row.children[1].dimensions.content.x.to_px(), 8);
+
+    // The footer group is placed after the body.
+    let table = &tree.children[3];
+    assert!(table.children[0].dimensions.content.y > table.children[1].dimensions.content.y);
+}
+
+#[test]
+fn test_computed_table_style() {
+    use style::computed_style;
+
+    let computed = &computed_style(
+        "display: table-cell; border-collapse: collapse; border-spacing: 1px 2px; \
+         caption-side: bottom; table-layout: fixed; vertical-align: top",
+    );
+    assert!(computed.display.is_table_internal());
+    assert_eq!(computed.display.blockify(), Display::Block);
+    assert_eq!(computed.border_collapse, BorderCollapse::Collapse);
+    assert_eq!(
+        computed.border_spacing,
+        (Au::from_f64_px(1.0), Au::from_f64_px(2.0))
+    );
+    assert_eq!(computed.caption_side, CaptionSide::Bottom);
+    assert_eq!(computed.table_layout, TableLayout::Fixed);
+    assert_eq!(computed.vertical_align, VerticalAlign::Top);
+}
@@ -10,17 +10,21 @@ use gtk::ContainerExt;
 use glib::prelude::*; // or `use gtk::prelude::*;`
 use glib;
 
-use gdk::{ContextExt, Cursor, CursorType, Event, EventButton, EventMask, EventMotion, RGBA};
+use gdk::{ContextExt, Cursor, CursorType, Event, EventButton, EventMask, EventMotion,
+          EventScroll, ScrollDirection, RGBA};
 use gdk_pixbuf::{InterpType, PixbufExt};
 
 use cairo::Context;
-use pango::LayoutExt;
+use pango::{ContextExt as PangoContextExt, LayoutExt};
 
 use std::{cell::RefCell, collections::HashMap};
+use std::f64::consts::PI;
+
+use app_units::Au;
 
 use layout::Rect;
-use painter::{DisplayCommand, DisplayList};
-use font::FONT_DESC;
+use painter::{DisplayCommand, DisplayList, SCROLL_CONTAINERS, SCROLL_DEPENDENT};
+use font::{GlyphOrientation, FONT_DESC};
 use css::{TextDecoration, px2pt};
 use interface::update_html_source;
 
@@ -40,6 +44,9 @@ thread_local!(
 
 static mut RESIZED: bool = false;
 
+// How far a scroll container moves per wheel step, in px.
+const SCROLL_STEP: f64 = 48.0;
+
 struct RenderingWindow {
     window: gtk::Window,
     drawing_area: gtk::DrawingArea,
@@ -124,14 +131,80 @@ impl RenderingWindow {
 
         let scrolled_window = gtk::ScrolledWindow::new(None, None);
         scrolled_window.add(&overlay);
+
+        // Fixed and sticky boxes move with the scroll position.
+        {
+            let drawing_area = drawing_area.clone();
+            scrolled_window
+                .get_vadjustment()
+                .unwrap()
+                .connect_value_changed(move |_| {
+                    if SCROLL_DEPENDENT.with(|scroll_dependent| scroll_dependent.get()) {
+                        SURFACE_CACHE.with(|sc| *sc.borrow_mut() = None);
+                        drawing_area.queue_draw();
+                    }
+                });
+        }
         vbox.pack_start(&scrolled_window, true, true, 0);
 
         window.add(&vbox);
         overlay.add_events(
             EventMask::POINTER_MOTION_MASK.bits() as i32
-                | EventMask::BUTTON_PRESS_MASK.bits() as i32,
+                | EventMask::BUTTON_PRESS_MASK.bits() as i32
+                | EventMask::SCROLL_MASK.bits() as i32,
         );
 
+        overlay
+            .connect("scroll-event", false, |args| {
+                let overlay = args[0]
+                    .clone()
+                    .downcast::<gtk::Overlay>()
+                    .unwrap()
+                    .get()
+                    .unwrap();
+                let event = args[1]
+                    .clone()
+                    .downcast::<Event>()
+                    .unwrap()
+                    .get()
+                    .unwrap()
+                    .downcast::<EventScroll>()
+                    .unwrap();
+                let (x, y) = event.get_position();
+                let (dx, dy) = match event.get_direction() {
+                    ScrollDirection::Up => (0.0, -SCROLL_STEP),
+                    ScrollDirection::Down => (0.0, SCROLL_STEP),
+                    ScrollDirection::Left => (-SCROLL_STEP, 0.0),
+                    ScrollDirection::Right => (SCROLL_STEP, 0.0),
+                    _ => {
+                        let (dx, dy) = event.get_delta();
+                        (dx * SCROLL_STEP, dy * SCROLL_STEP)
+                    }
+                };
+                let (x, y) = (Au::from_f64_px(x), Au::from_f64_px(y));
+                let (dx, dy) = (Au::from_f64_px(dx), Au::from_f64_px(dy));
+
+                // Nested scroll containers are painted after their ancestors, so the innermost
+                // one under the pointer comes last. If it can't be scrolled any further, the
+                // outer ones are.
+                let scrolled = SCROLL_CONTAINERS.with(|containers| {
+                    containers
+                        .borrow()
+                        .iter()
+                        .rev()
+                        .filter(|container| container.rect.contains(x, y))
+                        .any(|container| container.scroll_by(dx, dy))
+                });
+                if scrolled {
+                    SURFACE_CACHE.with(|sc| *sc.borrow_mut() = None);
+                    overlay.get_children()[0].queue_draw(); // [0] is DrawingArea
+                }
+
+                // Otherwise the gtk::ScrolledWindow scrolls the whole page.
+                Some(scrolled.to_value())
+            })
+            .unwrap();
+
         overlay
             .connect("motion-notify-event", false, |args| {
                 use gdk::WindowExt;
@@ -405,14 +478,53 @@ fn render_item(
                 color.b as f64 / 255.0,
                 color.a as f64 / 255.0,
             );
-            ctx.move_to(rect.x.to_f64_px(), rect.y.to_f64_px());
 
+            let (gravity, hint) = font.orientation.to_pango_gravity();
+            let pango_ctx = pango_layout.get_context().unwrap();
+            pango_ctx.set_base_gravity(gravity);
+            pango_ctx.set_gravity_hint(hint);
+            pango_layout.context_changed();
+
+            ctx.save();
+            if font.orientation.is_vertical() {
+                // Vertical text is drawn in a context turned clockwise around the top right
+                // corner of the text.
+                ctx.translate((rect.x + rect.width).to_f64_px(), rect.y.to_f64_px());
+                ctx.rotate(PI / 2.0);
+                ctx.move_to(0.0, 0.0);
+            } else if font.orientation == GlyphOrientation::Combined {
+                // Combined text is squeezed into the width of a character of the vertical line,
+                // and centered in the line.
+                let width = pango::units_to_double(pango_layout.get_size().0);
+                let scale = (rect.height.to_f64_px() / width).min(1.0);
+                ctx.translate(
+                    rect.x.to_f64_px() + (rect.width.to_f64_px() - width * scale) / 2.0,
+                    rect.y.to_f64_px(),
+                );
+                ctx.scale(scale, 1.0);
+                ctx.move_to(0.0, 0.0);
+            } else {
+                ctx.move_to(rect.x.to_f64_px(), rect.y.to_f64_px());
+            }
+            pangocairo::functions::update_layout(ctx, &pango_layout);
             pangocairo::functions::show_layout(ctx, &pango_layout);
+            ctx.restore();
         }
         &DisplayCommand::Button(ref _btn, _rect) => {
             // use gtk::LayoutExt;
             // layout.put(btn, rect.x.ceil_to_px(), rect.y.ceil_to_px());
         }
+        &DisplayCommand::PushClip(rect) => {
+            ctx.save();
+            ctx.rectangle(
+                rect.x.to_f64_px(),
+                rect.y.to_f64_px(),
+                rect.width.to_f64_px(),
+                rect.height.to_f64_px(),
+            );
+            ctx.clip();
+        }
+        &DisplayCommand::PopClip => ctx.restore(),
     }
 }
 
@@ -0,0 +1,346 @@
+//! Vertical writing modes. The layout code works in the logical coordinates of the writing mode,
+//! where `x` and `width` are along the lines (the inline axis) and `y` and `height` go across
+//! them (the block axis). A block that starts a vertical flow in a horizontal one has its subtree
+//! laid out in the logical coordinates, then turned into the physical ones.
+//! ref. https://www.w3.org/TR/css-writing-modes-3/
+
+use block::CollapsibleMargins;
+use font::GlyphOrientation;
+use layout::{BoxType, Dimensions, EdgeSizes, LayoutBox, LayoutInfo, Rect};
+use style::{ComputedStyle, Sides, TextCombineUpright, TextOrientation, WritingMode};
+
+use std::mem::swap;
+
+use app_units::Au;
+
+impl LayoutBox {
+    /// Whether this box starts a vertical flow in the flow of the writing mode `parent`.
+    /// Orthogonal flows nested in a vertical flow are laid out in the writing mode of the
+    /// vertical flow.
+    pub fn starts_vertical_flow(&self, parent: WritingMode) -> bool {
+        self.box_type == BoxType::BlockNode
+            && !parent.is_vertical()
+            && self.property.computed.writing_mode.is_vertical()
+    }
+
+    /// Lay out a block-level box whose content flows vertically in a horizontal flow.
+    /// The lines are as long as the height of the box, which shrinks to fit the content in the
+    /// height of the containing block, or of the viewport if it isn't definite. The width of the
+    /// box is the extent of its lines unless it's specified.
+    /// ref. https://www.w3.org/TR/css-writing-modes-3/#orthogonal-auto
+    pub fn layout_vertical_flow(
+        &mut self,
+        margins: &mut CollapsibleMargins,
+        containing_block: Dimensions,
+        saved_block: Dimensions,
+        viewport: Dimensions,
+    ) {
+        let mode = self.property.computed.writing_mode;
+        self.to_logical(mode);
+
+        let mut logical_block = Dimensions::default();
+        logical_block.content.width = if saved_block.content.height >= Au(0) {
+            saved_block.content.height
+        } else {
+            viewport.content.height
+        };
+        // Percentage widths, i.e. block sizes, refer to the width of the containing block.
+        let mut logical_saved_block = logical_block;
+        logical_saved_block.content.height = containing_block.content.width;
+        let logical_viewport = viewport.transposed();
+
+        // The box is laid out like an inline-block in the logical coordinates. It establishes a
+        // new block formatting context.
+        self.load_image(logical_block);
+        self.calculate_inline_block_width(logical_block);
+        self.assign_padding(logical_block);
+        self.assign_border_width();
+        self.assign_margin(logical_block);
+        let definite_block = self.definite_block(logical_saved_block);
+        self.layout_children(definite_block, logical_viewport);
+        self.calculate_block_height(logical_saved_block);
+        if self.is_positioned() {
+            self.layout_absolute_descendants(logical_viewport);
+        }
+
+        self.to_physical(mode);
+        self.dimensions = self.dimensions.to_physical(mode, Au(0));
+
+        // Place the box below the previous boxes like a block formatting context root.
+        let d = &mut self.dimensions;
+        margins.adjoin(d.margin.top);
+        d.margin.top = margins.collapse();
+        d.content.x = d.left_offset();
+        d.content.y = containing_block.content.height + d.top_offset();
+        *margins = CollapsibleMargins::new(d.margin.bottom);
+
+        self.apply_relative_offset(containing_block, saved_block);
+    }
+
+    /// Turns the styles of this box and its descendants into the logical ones of `mode`.
+    fn to_logical(&mut self, mode: WritingMode) {
+        self.property.computed.to_logical(mode);
+        for child in &mut self.children {
+            child.to_logical(mode);
+        }
+    }
+
+    /// Turns the styles of this box and its descendants back into the physical ones, and the
+    /// positions of the descendants into the physical ones relative to their parents.
+    fn to_physical(&mut self, mode: WritingMode) {
+        self.property.computed.to_physical(mode);
+        // `load_image` gives the logical size of an image in vertical text.
+        if let LayoutInfo::Image(ref mut image) = self.info {
+            swap(&mut image.metadata.width, &mut image.metadata.height);
+        }
+        let block_size = self.dimensions.content.height;
+        for child in &mut self.children {
+            child.to_physical(mode);
+            child.dimensions = child.dimensions.to_physical(mode, block_size);
+        }
+    }
+}
+
+impl Dimensions {
+    /// These logical dimensions in `mode` as the physical ones, for a box in the content area
+    /// whose logical height is `block_size`.
+    pub fn to_physical(self, mode: WritingMode, block_size: Au) -> Dimensions {
+        let content = self.content;
+        Dimensions {
+            content: Rect {
+                x: match mode {
+                    WritingMode::VerticalRl => block_size - content.y - content.height,
+                    _ => content.y,
+                },
+                y: content.x,
+                width: content.height,
+                height: content.width,
+            },
+            padding: self.padding.to_physical(mode),
+            border: self.border.to_physical(mode),
+            margin: self.margin.to_physical(mode),
+        }
+    }
+
+    /// These dimensions with the axes swapped.
+    pub fn transposed(self) -> Dimensions {
+        let mut dimensions = self;
+        {
+            let content = &mut dimensions.content;
+            swap(&mut content.x, &mut content.y);
+            swap(&mut content.width, &mut content.height);
+        }
+        dimensions
+    }
+}
+
+impl EdgeSizes {
+    /// These logical edges in `mode` as the physical ones. See `Sides::to_logical`.
+    pub fn to_physical(self, mode: WritingMode) -> EdgeSizes {
+        let sides = Sides {
+            top: self.top,
+            right: self.right,
+            bottom: self.bottom,
+            left: self.left,
+        }.to_physical(mode);
+        EdgeSizes {
+            top: sides.top,
+            right: sides.right,
+            bottom: sides.bottom,
+            left: sides.left,
+        }
+    }
+}
+
+impl<T> Sides<T> {
+    /// The sides in the logical directions of `mode`: `top` is the block start side, `bottom` the
+    /// block end, `left` the line start and `right` the line end.
+    pub fn to_logical(self, mode: WritingMode) -> Sides<T> {
+        match mode {
+            WritingMode::HorizontalTb => self,
+            WritingMode::VerticalRl => Sides {
+                top: self.right,
+                right: self.bottom,
+                bottom: self.left,
+                left: self.top,
+            },
+            WritingMode::VerticalLr => Sides {
+                top: self.left,
+                right: self.bottom,
+                bottom: self.right,
+                left: self.top,
+            },
+        }
+    }
+
+    /// The inverse of `to_logical`.
+    pub fn to_physical(self, mode: WritingMode) -> Sides<T> {
+        match mode {
+            WritingMode::HorizontalTb => self,
+            WritingMode::VerticalRl => Sides {
+                top: self.left,
+                right: self.top,
+                bottom: self.right,
+                left: self.bottom,
+            },
+            WritingMode::VerticalLr => Sides {
+                top: self.left,
+                right: self.bottom,
+                bottom: self.right,
+                left: self.top,
+            },
+        }
+    }
+}
+
+impl ComputedStyle {
+    /// Turns the box properties into the logical ones of `mode`, so that `width` is the size
+    /// along the lines, `margin-left` the margin at the line start, and so on.
+    pub fn to_logical(&mut self, mode: WritingMode) {
+        if !mode.is_vertical() {
+            return;
+        }
+        self.offsets = self.offsets.to_logical(mode);
+        self.margin = self.margin.to_logical(mode);
+        self.padding = self.padding.to_logical(mode);
+        self.border_width = self.border_width.to_logical(mode);
+        self.border_style = self.border_style.to_logical(mode);
+        self.border_color = self.border_color.to_logical(mode);
+        self.swap_axes();
+    }
+
+    /// The inverse of `to_logical`.
+    pub fn to_physical(&mut self, mode: WritingMode) {
+        if !mode.is_vertical() {
+            return;
+        }
+        self.offsets = self.offsets.to_physical(mode);
+        self.margin = self.margin.to_physical(mode);
+        self.padding = self.padding.to_physical(mode);
+        self.border_width = self.border_width.to_physical(mode);
+        self.border_style = self.border_style.to_physical(mode);
+        self.border_color = self.border_color.to_physical(mode);
+        self.swap_axes();
+    }
+
+    fn swap_axes(&mut self) {
+        swap(&mut self.width, &mut self.height);
+        swap(&mut self.min_width, &mut self.min_height);
+        swap(&mut self.max_width, &mut self.max_height);
+        swap(&mut self.overflow_x, &mut self.overflow_y);
+        let (horizontal, vertical) = self.border_spacing;
+        self.border_spacing = (vertical, horizontal);
+    }
+
+    /// How the glyphs of the text in this style are set in the line.
+    /// ref. https://www.w3.org/TR/css-writing-modes-3/#text-orientation
+    pub fn glyph_orientation(&self) -> GlyphOrientation {
+        if !self.writing_mode.is_vertical() {
+            return GlyphOrientation::Horizontal;
+        }
+        if self.text_combine_upright == TextCombineUpright::All {
+            return GlyphOrientation::Combined;
+        }
+        match self.text_orientation {
+            TextOrientation::Mixed => GlyphOrientation::Mixed,
+            TextOrientation::Upright => GlyphOrientation::Upright,
+            TextOrientation::Sideways => GlyphOrientation::Sideways,
+        }
+    }
+}
+
+#[test]
+fn test_writing_mode() {
+    use font::{Font, FontSlant, FontWeight};
+    use layout::layout_html;
+    use style::LengthOrPercentageOrAuto;
+
+    let css = "div { display: block; }
+               .rl { writing-mode: vertical-rl; height: 100px; padding: 1px 2px 3px 4px; }
+               .lr { writing-mode: vertical-lr; }
+               .tcy { text-combine-upright: all; }";
+    let tree = layout_html(
+        "<div><div class='rl'>aaaa bbbb cccc dddd</div>\
+         <div class='rl lr'>aaaa bbbb cccc dddd</div>\
+         <div class='rl' style='height: auto'>平成<span class='tcy'>30</span>年</div></div>",
+        css,
+        400,
+    );
+    let text_boxes = |i: usize| -> Vec<(Rect, GlyphOrientation)> {
+        tree.children[i].children[0]
+            .children
+            .iter()
+            .flat_map(|item| Some(item).into_iter().chain(item.children.iter()))
+            .filter_map(|item| match item.box_type {
+                BoxType::TextNode(ref text) => {
+                    Some((item.dimensions.content, text.font.orientation))
+                }
+                _ => None,
+            })
+            .collect()
+    };
+
+    // The lines are stacked from the right, and the width is the extent of the lines. A line is
+    // 19.2px wide, and the text is 16px wide with the half-leading on each side.
+    let rl = &tree.children[0];
+    let d = rl.dimensions;
+    assert_eq!((d.content.x, d.content.y), (Au::from_px(4), Au::from_px(1)));
+    assert_eq!(d.content.height, Au::from_px(100));
+    let (p, px) = (d.padding, Au::from_px);
+    assert_eq!(
+        (p.top, p.right, p.bottom, p.left),
+        (px(1), px(2), px(3), px(4))
+    );
+    assert_eq!(
+        rl.property.computed.height,
+        LengthOrPercentageOrAuto::Length(Au::from_px(100))
+    );
+    let lines = text_boxes(0);
+    assert_eq!(lines.len(), 2);
+    let (first, second) = (lines[0].0, lines[1].0);
+    assert_eq!(first.y, Au(0));
+    assert_eq!(d.content.width, Au::from_f64_px(38.4));
+    let first_right = first.x + first.width;
+    assert_eq!(first_right, d.content.width - Au::from_f64_px(1.6));
+    assert!(second.x + second.width <= first.x);
+    assert!(first.height > first.width);
+    assert_eq!(lines[0].1, GlyphOrientation::Mixed);
+
+    // `vertical-lr` stacks them from the left.
+    let lines = text_boxes(1);
+    assert_eq!(lines[0].0.x, Au::from_f64_px(1.6));
+    assert_eq!(lines[1].0.x, Au::from_f64_px(20.8));
+
+    // The height shrinks to fit, and combined text takes up a square of the font size.
+    let boxes = text_boxes(2);
+    assert_eq!(boxes[1].1, GlyphOrientation::Combined);
+    assert_eq!(boxes[1].0.height, Au::from_px(16));
+    let width = |s: &str| {
+        Au::from_f64_px(
+            Font::new(Au::from_px(16), FontWeight::Normal, FontSlant::Normal).text_width(s),
+        )
+    };
+    assert_eq!(
+        tree.children[2].dimensions.content.height,
+        width("平成年") + Au::from_px(16)
+    );
+
+    // `text-combine-upright` is inherited.
+    let tree = layout_html("<div class='tcy'><span>30</span></div>", css, 400);
+    assert_eq!(
+        tree.children[0].children[0].property.computed.text_combine_upright,
+        TextCombineUpright::All
+    );
+}
+
+#[test]
+fn test_computed_writing_mode_style() {
+    use style::computed_style;
+
+    let computed = &computed_style(
+        "writing-mode: vertical-rl; text-orientation: sideways-right; text-combine-upright: all",
+    );
+    assert_eq!(computed.writing_mode, WritingMode::VerticalRl);
+    assert_eq!(computed.text_orientation, TextOrientation::Sideways);
+    assert_eq!(computed.text_combine_upright, TextCombineUpright::All);
+}